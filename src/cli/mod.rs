@@ -0,0 +1,460 @@
+use crate::code_gen::{generator_for, CodeGenerator, GeneratorOptions, Language};
+use crate::compiler::{Compiler, SourceTree};
+use crate::parser::ParserImpl;
+use crate::validator::validate;
+
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+const USAGE: &str = "Usage: rs-proto generate --lang <LANG> --out-dir <DIR> [-I <DIR>]... [--opt <OPTIONS>]... <FILE>...
+
+Generates code for .proto files, writing one file for each in a directory named after its package.
+
+Options:
+  --lang <LANG>           The language to generate: dart
+  --out-dir <DIR>         The directory to write the generated files to
+  -I, --proto_path <DIR>  A directory to look for .proto files and their imports in, which can be given more than
+                          once (defaults to the current directory)
+  --opt <OPTIONS>         Comma-separated options for the generator, e.g. omit_comments
+  -h, --help              Print this message";
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Generate(GenerateArgs),
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GenerateArgs {
+    pub language: Language,
+    pub out_dir: PathBuf,
+    pub proto_paths: Vec<PathBuf>,
+    pub options: GeneratorOptions,
+    pub files: Vec<String>,
+}
+
+// Runs a command line (without the program name) and returns the exit code: 1 if the files can't be generated and 2 if
+// the command line itself is wrong.
+pub fn run(args: &[String], stdout: &mut dyn Write, stderr: &mut dyn Write) -> i32 {
+    let command = match parse_args(args) {
+        Ok(command) => command,
+        Err(message) => {
+            let _ = writeln!(stderr, "rs-proto: {}\n\n{}", message, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    match command {
+        Command::Help => {
+            let _ = writeln!(stdout, "{}", USAGE);
+            EXIT_SUCCESS
+        }
+        Command::Generate(args) => generate(&args, stderr),
+    }
+}
+
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    let mut args = args.iter();
+
+    match args.next().map(String::as_str) {
+        Some("generate") => {}
+        Some("help") | Some("-h") | Some("--help") => return Ok(Command::Help),
+        Some(command) => return Err(format!("Unknown command '{}'", command)),
+        None => return Err("Missing a command".to_string()),
+    }
+
+    let mut language = None;
+    let mut out_dir = None;
+    let mut proto_paths = vec![];
+    let mut options = vec![];
+    let mut files = vec![];
+
+    while let Some(arg) = args.next() {
+        // Options take their value from the next argument or after an '=' (or directly after -I, like protoc).
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ if arg.starts_with("-I") && arg.len() > 2 => ("-I", Some(arg[2..].to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| {
+            value
+                .clone()
+                .or_else(|| args.next().cloned())
+                .ok_or_else(|| format!("Missing a value for '{}'", name))
+        };
+
+        match name {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--lang" => language = Some(parse_language(&value(name)?)?),
+            "--out-dir" => out_dir = Some(PathBuf::from(value(name)?)),
+            "-I" | "--proto_path" => proto_paths.push(PathBuf::from(value(name)?)),
+            "--opt" => options.push(value(name)?),
+            "--" => files.extend(args.by_ref().cloned()),
+            _ if name.starts_with('-') && name.len() > 1 => {
+                return Err(format!("Unknown option '{}'", name));
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+
+    if files.is_empty() {
+        return Err("Missing the .proto files to generate".to_string());
+    }
+
+    Ok(Command::Generate(GenerateArgs {
+        language: language.ok_or_else(|| "Missing '--lang'".to_string())?,
+        out_dir: out_dir.ok_or_else(|| "Missing '--out-dir'".to_string())?,
+        proto_paths,
+        options: GeneratorOptions::parse(&options.join(",")),
+        files,
+    }))
+}
+
+fn parse_language(name: &str) -> Result<Language, String> {
+    match name {
+        "dart" => Ok(Language::Dart),
+        _ => Err(format!(
+            "Unknown language '{}' (the supported languages are: dart)",
+            name
+        )),
+    }
+}
+
+fn generate(args: &GenerateArgs, stderr: &mut dyn Write) -> i32 {
+    let mut report = |message: &str| {
+        let _ = writeln!(stderr, "{}", message);
+    };
+
+    let generator = match generator_for(
+        Box::new(ParserImpl::default()),
+        args.language,
+        &args.options,
+    ) {
+        Ok(generator) => generator,
+        Err(message) => {
+            report(&format!("rs-proto: {}", message));
+            return EXIT_USAGE;
+        }
+    };
+
+    // Like protoc, search the current directory when no proto paths are given.
+    let proto_paths = match args.proto_paths.as_slice() {
+        [] => vec![PathBuf::from(".")],
+        proto_paths => proto_paths.to_vec(),
+    };
+
+    let mut names = vec![];
+    let mut failed = false;
+    for file in &args.files {
+        match virtual_file_name(file, &proto_paths) {
+            Ok(name) if names.contains(&name) => {}
+            Ok(name) => names.push(name),
+            Err(message) => {
+                report(&message);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        return EXIT_FAILURE;
+    }
+
+    let compiler = Compiler::new(
+        Box::new(ParserImpl::default()),
+        SourceTree::new(proto_paths),
+    );
+    let name_refs = names.iter().map(String::as_str).collect::<Vec<&str>>();
+    let compilation = match compiler.compile(&name_refs) {
+        Ok(compilation) => compilation,
+        Err(err) => {
+            report(&err.to_string());
+            return EXIT_FAILURE;
+        }
+    };
+
+    // Nothing is written unless every file generates.
+    let mut outputs: Vec<(&str, String, String)> = vec![];
+    for name in &name_refs {
+        let file = match compilation.file(name) {
+            Some(file) => file,
+            None => continue,
+        };
+
+        // Errors come back from the generator, but warnings would otherwise go unseen.
+        for warning in validate(&file.program, &compilation.dependencies(name))
+            .iter()
+            .filter(|diagnostic| !diagnostic.is_error())
+        {
+            report(&warning.in_file(name));
+        }
+
+        let output_file_name = generator.output_file_name(file);
+        if let Some((other, _, _)) = outputs
+            .iter()
+            .find(|(_, other_output, _)| *other_output == output_file_name)
+        {
+            report(&format!(
+                "{}: error: Generates '{}', which '{}' already generates.",
+                name, output_file_name, other
+            ));
+            failed = true;
+            continue;
+        }
+
+        match generator.gen_file(&compilation, name) {
+            Ok(content) => outputs.push((name, output_file_name, content)),
+            Err(error) => {
+                report(&error.in_file(name));
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        return EXIT_FAILURE;
+    }
+
+    let runtime_files = generator.runtime_files();
+    let files = outputs
+        .iter()
+        .map(|(_, output_file_name, content)| (output_file_name, content))
+        .chain(runtime_files.iter().map(|(name, content)| (name, content)));
+
+    for (output_file_name, content) in files {
+        let path = args.out_dir.join(output_file_name);
+        let written = match path.parent() {
+            Some(dir) => fs::create_dir_all(dir).and_then(|_| fs::write(&path, content)),
+            None => fs::write(&path, content),
+        };
+
+        if let Err(err) = written {
+            report(&format!(
+                "rs-proto: Failed to write '{}': {}",
+                path.display(),
+                err
+            ));
+            return EXIT_FAILURE;
+        }
+    }
+
+    EXIT_SUCCESS
+}
+
+// The name of a file from the command line relative to the proto path it's in, which is the name imports use for it.
+// Like protoc, files that aren't on disk are assumed to already be relative to one of the proto paths.
+fn virtual_file_name(file: &str, proto_paths: &[PathBuf]) -> Result<String, String> {
+    let path = Path::new(file);
+    if !path.is_file() {
+        return Ok(file.to_string());
+    }
+
+    let path = fs::canonicalize(path).map_err(|err| format!("{}: {}", file, err))?;
+    for proto_path in proto_paths {
+        let relative = match fs::canonicalize(proto_path) {
+            Ok(proto_path) => match path.strip_prefix(proto_path) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => continue,
+            },
+            Err(_) => continue,
+        };
+
+        return relative
+            .components()
+            .map(|component| match component {
+                Component::Normal(part) => part
+                    .to_str()
+                    .ok_or_else(|| format!("{}: error: The path isn't valid UTF-8.", file)),
+                _ => Err(format!(
+                    "{}: error: The path can't be made relative to its proto path.",
+                    file
+                )),
+            })
+            .collect::<Result<Vec<&str>, String>>()
+            .map(|parts| parts.join("/"));
+    }
+
+    Err(format!(
+        "{}: error: File does not reside within any path specified using --proto_path (or -I).",
+        file
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn run_for_test(command_line: &[&str]) -> (i32, String) {
+        let mut stdout = vec![];
+        let mut stderr = vec![];
+        let code = run(&args(command_line), &mut stdout, &mut stderr);
+
+        (code, String::from_utf8(stderr).unwrap())
+    }
+
+    // A fresh output directory for each test, since tests run in parallel.
+    fn out_dir(test: &str) -> PathBuf {
+        let out_dir =
+            std::env::temp_dir().join(format!("rs-proto-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&out_dir);
+
+        out_dir
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse_args(&args(&[
+                "generate",
+                "--lang",
+                "dart",
+                "--out-dir=gen/",
+                "-I",
+                "protos/",
+                "-Ivendor",
+                "--proto_path=more",
+                "--opt",
+                "omit_comments",
+                "a.proto",
+                "--",
+                "-b.proto"
+            ])),
+            Ok(Command::Generate(GenerateArgs {
+                language: Language::Dart,
+                out_dir: PathBuf::from("gen/"),
+                proto_paths: vec![
+                    PathBuf::from("protos/"),
+                    PathBuf::from("vendor"),
+                    PathBuf::from("more")
+                ],
+                options: GeneratorOptions::parse("omit_comments"),
+                files: vec!["a.proto".to_string(), "-b.proto".to_string()],
+            }))
+        );
+        assert_eq!(parse_args(&args(&["--help"])), Ok(Command::Help));
+
+        let error = |command_line: &[&str]| parse_args(&args(command_line)).unwrap_err();
+        assert_eq!(error(&[]), "Missing a command");
+        assert_eq!(error(&["build"]), "Unknown command 'build'");
+        assert_eq!(
+            error(&["generate", "--lang", "go", "a.proto"]),
+            "Unknown language 'go' (the supported languages are: dart)"
+        );
+        assert_eq!(
+            error(&["generate", "--lang", "dart", "a.proto"]),
+            "Missing '--out-dir'"
+        );
+        assert_eq!(
+            error(&["generate", "--lang", "dart", "--out-dir", "gen"]),
+            "Missing the .proto files to generate"
+        );
+        assert_eq!(
+            error(&["generate", "--out-dir"]),
+            "Missing a value for '--out-dir'"
+        );
+        assert_eq!(
+            error(&["generate", "--verbose", "a.proto"]),
+            "Unknown option '--verbose'"
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        let out_dir = out_dir("generate");
+
+        // Files can be given relative to a proto path or as paths on disk inside one.
+        let (code, stderr) = run_for_test(&[
+            "generate",
+            "--lang",
+            "dart",
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+            "-I",
+            "test_data/cli/",
+            "greeting.proto",
+            "./test_data/cli/shared/name.proto",
+        ]);
+
+        assert_eq!((code, stderr.as_str()), (EXIT_SUCCESS, ""));
+        assert!(
+            fs::read_to_string(out_dir.join("app/greetings/greeting.pb.dart"))
+                .unwrap()
+                .starts_with(
+                    "import '../../rs_proto.dart';\nimport '../shared/name.pb.dart';\n\n\
+                     class Greeting extends ProtobufMessage {\n\tName? to;\n\tString text;\n"
+                )
+        );
+        assert!(out_dir.join("app/shared/name.pb.dart").is_file());
+
+        // The runtime the generated code imports goes at the root of the output directory.
+        assert!(out_dir.join("rs_proto.dart").is_file());
+
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn test_generate_errors() {
+        let out_dir = out_dir("generate-errors");
+        let generate = |extra_args: &[&str]| {
+            let mut command_line = vec![
+                "generate",
+                "--lang",
+                "dart",
+                "--out-dir",
+                out_dir.to_str().unwrap(),
+                "-I",
+                "test_data/cli",
+            ];
+            command_line.extend(extra_args);
+
+            run_for_test(&command_line)
+        };
+
+        assert_eq!(
+            generate(&["invalid.proto", "greeting.proto"]),
+            (
+                EXIT_FAILURE,
+                "invalid.proto: warning: No syntax specified, so proto2 is assumed. Please use 'syntax = \"proto2\";' or 'syntax = \"proto3\";'\n\
+                 invalid.proto:3:5: error: Field number 1 has already been used in \"Invalid\" by field \"a\".\n"
+                    .to_string()
+            )
+        );
+        // Nothing is written when any file fails.
+        assert!(!out_dir.exists());
+
+        assert_eq!(
+            generate(&["missing.proto"]),
+            (
+                EXIT_FAILURE,
+                "'missing.proto' was not found in any of the include directories\n".to_string()
+            )
+        );
+        assert_eq!(
+            generate(&["test_data/warnings.proto"]),
+            (
+                EXIT_FAILURE,
+                "test_data/warnings.proto: error: File does not reside within any path specified using --proto_path (or -I).\n"
+                    .to_string()
+            )
+        );
+
+        let (code, stderr) = generate(&["--opt", "fast", "greeting.proto"]);
+        assert_eq!(code, EXIT_USAGE);
+        assert_eq!(
+            stderr,
+            "rs-proto: Unknown option 'fast' for the Dart generator\n"
+        );
+
+        let (code, stderr) = run_for_test(&["generate", "a.proto"]);
+        assert_eq!(code, EXIT_USAGE);
+        assert!(stderr.starts_with("rs-proto: Missing '--lang'\n\nUsage: rs-proto generate"));
+    }
+}
@@ -0,0 +1,2153 @@
+use super::{CodeGenerator, GeneratorError, GeneratorOptions};
+use crate::code_gen::env::*;
+use crate::compiler::{Compilation, CompiledFile};
+use crate::parser::*;
+use crate::utils::{camel_case, pascal_case, CasedString};
+use crate::validator::{validate, Diagnostic};
+use names::FieldName;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+mod names;
+mod value;
+mod wire;
+
+const BASE_MESSAGE_TYPE: &str = "ProtobufMessage";
+const BASE_ENUM_TYPE: &str = "ProtobufEnum";
+const ONEOF_NOT_SET_CASE: &str = "notSet";
+const FIXNUM_IMPORT: &str = "import 'package:fixnum/fixnum.dart';";
+const FIXNUM_INT64_TYPE: &str = "Int64";
+
+// The runtime library generated code relies on, which is written to the root of the output directory.
+const RUNTIME_FILE: &str = "rs_proto.dart";
+const RUNTIME_SOURCE: &str = include_str!("runtime.dart");
+
+pub struct DartCodeGenerator {
+    parser: Box<dyn Parser>,
+    options: DartOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DartOptions {
+    // Leaves the comments from the .proto files out of the generated code.
+    pub omit_comments: bool,
+    // Generates sound null-safe code (Dart 2.12 and later). Turning it off targets the older dialect without it.
+    pub null_safety: bool,
+}
+
+impl Default for DartOptions {
+    fn default() -> Self {
+        DartOptions {
+            omit_comments: false,
+            null_safety: true,
+        }
+    }
+}
+
+impl DartOptions {
+    pub fn from_generator_options(options: &GeneratorOptions) -> Result<Self, String> {
+        let mut result = DartOptions::default();
+
+        for (key, value) in options.iter() {
+            match key {
+                "omit_comments" => result.omit_comments = GeneratorOptions::parse_flag(key, value)?,
+                "null_safety" => result.null_safety = GeneratorOptions::parse_flag(key, value)?,
+                _ => return Err(format!("Unknown option '{}' for the Dart generator", key)),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl DartCodeGenerator {
+    pub fn new(parser: Box<dyn Parser>, options: DartOptions) -> Self {
+        DartCodeGenerator { parser, options }
+    }
+
+    fn gen_type(
+        &self,
+        proto_type: &ProtoType,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<String, GeneratorError> {
+        match proto_type {
+            ProtoType::Enum(enumeration) => self.gen_enum(enumeration, env, 0),
+            ProtoType::Message(message) => self.gen_message(message, env, 0),
+        }
+    }
+
+    fn gen_message(
+        &self,
+        message: &ProtoMessage,
+        env: &mut GeneratorEnvironment,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let mut result = vec![];
+
+        let indentation = "\t".repeat(indent);
+        let inner_indentation = "\t".repeat(indent);
+
+        let message_name = env
+            .get_fully_qualified_identifier()
+            .expect("expect to generate message in the context of a proto type");
+
+        let field_names = names::field_names(message);
+
+        result.push(self.gen_doc_comment(&message.comments, indent));
+        result.push(format!(
+            "{}class {} extends {} {{\n",
+            indentation, &message_name, BASE_MESSAGE_TYPE
+        ));
+
+        for field in &message.fields {
+            result.push(format!(
+                "{}{}\n",
+                &inner_indentation,
+                self.gen_message_field(field, &field_names[&field.position].name, env, indent + 1)?
+            ));
+        }
+
+        if !message.fields.is_empty() {
+            result.push("\n".to_string());
+        }
+        result.push(self.gen_constructor(&message_name, message, env, indent + 1)?);
+        result.push(self.gen_presence_methods(message, &field_names, env, indent + 1)?);
+
+        for oneof in &message.oneofs {
+            result.push("\n".to_string());
+            result.push(self.gen_oneof(&message_name, oneof, &field_names, env, indent + 1)?);
+        }
+
+        result.push("\n".to_string());
+        result.push(self.gen_serialization(&message_name, message, env, indent + 1)?);
+
+        result.push("\n".to_string());
+        result.push(self.gen_value_methods(&message_name, message, env, indent + 1)?);
+
+        result.push(format!("{}}}", indentation));
+
+        for oneof in &message.oneofs {
+            result.push(format!(
+                "\n\n{}",
+                Self::gen_oneof_case_enum(&message_name, oneof, &field_names, indent)?
+            ));
+        }
+
+        // Queue up message ops to be written after we finish unrolling the environment.
+        for proto_type in &message.types {
+            let child_env = env.new_child(proto_type)?;
+
+            let proto_type_output = {
+                format!(
+                    "\n\n{}",
+                    self.gen_type(proto_type, &mut child_env.borrow_mut())?
+                )
+            };
+
+            child_env.borrow_mut().queue_output(proto_type_output);
+        }
+
+        Ok(result.join(""))
+    }
+
+    fn gen_message_field(
+        &self,
+        field: &ProtoMessageField,
+        name: &str,
+        env: &mut GeneratorEnvironment,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let mut result = vec![];
+
+        let indentation = "\t".repeat(indent);
+
+        let dart_type = Self::get_dart_type(&field.field_type, field.span, env)?;
+
+        // Fields with a declared default keep null in a private field while they aren't set, and read as the default.
+        if let Some(default) = Self::declared_default(field, env)? {
+            let nullable_type = self.nullable(&dart_type);
+
+            result.push(format!("{}{} _{};\n", indentation, nullable_type, name));
+            result.push(self.gen_doc_comment(&field.comments, indent));
+            result.push(format!(
+                "{}{} get {} => _{} ?? {};\n",
+                indentation, dart_type, name, name, default
+            ));
+            result.push(format!(
+                "{}set {}({} value) => _{} = value;",
+                indentation, name, nullable_type, name
+            ));
+
+            return Ok(result.join(""));
+        }
+
+        // Repeated and map fields start out empty rather than null. Fields without presence are given their defaults
+        // by the constructor.
+        result.push(self.gen_doc_comment(&field.comments, indent));
+        result.push(match (&field.modifier, &field.field_type) {
+            (Some(ProtoMessageFieldModifier::Repeated), _) => {
+                format!("{}List<{}> {} = [];", indentation, dart_type, name)
+            }
+            (_, ProtoFieldType::Primitive(ProtoPrimitiveType::Map(_, _))) => {
+                format!("{}{} {} = {{}};", indentation, dart_type, name)
+            }
+            _ if Self::tracks_presence(field, env)? => {
+                format!("{}{} {};", indentation, self.nullable(&dart_type), name)
+            }
+            _ => format!("{}{} {};", indentation, dart_type, name),
+        });
+
+        Ok(result.join(""))
+    }
+
+    // hasX and clearX for the singular fields that track whether they're set, which is when they (or their private
+    // fields, for fields with a declared default) aren't null.
+    fn gen_presence_methods(
+        &self,
+        message: &ProtoMessage,
+        field_names: &HashMap<u32, FieldName>,
+        env: &mut GeneratorEnvironment,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let mut result = vec![];
+
+        let indentation = "\t".repeat(indent);
+
+        for field in &message.fields {
+            if Self::is_collection(field) || !Self::tracks_presence(field, env)? {
+                continue;
+            }
+
+            let FieldName { name, pascal_name } = &field_names[&field.position];
+            let name = match Self::declared_default(field, env)? {
+                Some(_) => format!("_{}", name),
+                None => name.clone(),
+            };
+
+            result.push(format!(
+                "\n{}bool has{}() => {} != null;\n",
+                indentation, pascal_name, name
+            ));
+            result.push(format!(
+                "{}void clear{}() => {} = null;\n",
+                indentation, pascal_name, name
+            ));
+        }
+
+        Ok(result.join(""))
+    }
+
+    fn gen_oneof(
+        &self,
+        message_name: &str,
+        oneof: &ProtoOneof,
+        field_names: &HashMap<u32, FieldName>,
+        env: &mut GeneratorEnvironment,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let mut result = vec![];
+
+        let indentation = "\t".repeat(indent);
+        let inner_indentation = "\t".repeat(indent + 1);
+
+        let case_enum_name = Self::get_oneof_case_enum_name(message_name, oneof);
+        let oneof_name = camel_case(CasedString::SnakeCase(&oneof.name));
+        let case_field_name = format!("_{}Case", oneof_name);
+        let clear_fn_name = format!("clear{}", pascal_case(CasedString::SnakeCase(&oneof.name)));
+
+        // Backing fields for the oneof case and each of its members.
+        result.push(format!(
+            "{}{} {} = {}.{};\n",
+            indentation, case_enum_name, case_field_name, case_enum_name, ONEOF_NOT_SET_CASE
+        ));
+
+        let mut members = vec![];
+        for field in &oneof.fields {
+            let dart_type = Self::get_dart_type(&field.field_type, field.span, env)?;
+            let member_name = &field_names[&field.position].name;
+
+            result.push(format!(
+                "{}{} _{};\n",
+                indentation,
+                self.nullable(&dart_type),
+                member_name
+            ));
+
+            members.push((field, dart_type, member_name));
+        }
+
+        result.push(format!(
+            "\n{}{} get {}Case => {};\n",
+            indentation, case_enum_name, oneof_name, case_field_name
+        ));
+
+        // Setting a member clears whichever member was previously set, and setting it to null leaves none set.
+        for (field, dart_type, member_name) in &members {
+            let nullable_type = self.nullable(dart_type);
+            let pascal_name = &field_names[&field.position].pascal_name;
+
+            result.push("\n".to_string());
+            result.push(self.gen_doc_comment(&field.comments, indent));
+            result.push(format!(
+                "{}{} get {} => _{};\n",
+                indentation, nullable_type, member_name, member_name
+            ));
+            result.push(format!(
+                "{}set {}({} value) {{\n",
+                indentation, member_name, nullable_type
+            ));
+            result.push(format!("{}{}();\n", inner_indentation, clear_fn_name));
+            result.push(format!("{}if (value != null) {{\n", inner_indentation));
+            result.push(format!(
+                "{}\t_{} = value;\n",
+                inner_indentation, member_name
+            ));
+            result.push(format!(
+                "{}\t{} = {}.{};\n",
+                inner_indentation, case_field_name, case_enum_name, member_name
+            ));
+            result.push(format!("{}}}\n", inner_indentation));
+            result.push(format!("{}}}\n", indentation));
+            result.push(format!(
+                "{}bool has{}() => {} == {}.{};\n",
+                indentation, pascal_name, case_field_name, case_enum_name, member_name
+            ));
+            result.push(format!("{}void clear{}() {{\n", indentation, pascal_name));
+            result.push(format!(
+                "{}if (has{}()) {{\n",
+                inner_indentation, pascal_name
+            ));
+            result.push(format!("{}\t{}();\n", inner_indentation, clear_fn_name));
+            result.push(format!("{}}}\n", inner_indentation));
+            result.push(format!("{}}}\n", indentation));
+        }
+
+        result.push(format!("\n{}void {}() {{\n", indentation, clear_fn_name));
+        for (_, _, member_name) in &members {
+            result.push(format!("{}_{} = null;\n", inner_indentation, member_name));
+        }
+        result.push(format!(
+            "{}{} = {}.{};\n",
+            inner_indentation, case_field_name, case_enum_name, ONEOF_NOT_SET_CASE
+        ));
+        result.push(format!("{}}}\n", indentation));
+
+        Ok(result.join(""))
+    }
+
+    fn gen_oneof_case_enum(
+        message_name: &str,
+        oneof: &ProtoOneof,
+        field_names: &HashMap<u32, FieldName>,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let indentation = "\t".repeat(indent);
+        let value_indentation = "\t".repeat(indent + 1);
+
+        let cases = oneof
+            .fields
+            .iter()
+            .map(|field| field_names[&field.position].name.clone())
+            .chain(std::iter::once(ONEOF_NOT_SET_CASE.to_string()))
+            .map(|case| format!("{}{}", value_indentation, case))
+            .collect::<Vec<String>>()
+            .join(",\n");
+
+        Ok(format!(
+            "{}enum {} {{\n{}\n{}}}",
+            indentation,
+            Self::get_oneof_case_enum_name(message_name, oneof),
+            cases,
+            indentation
+        ))
+    }
+
+    fn get_oneof_case_enum_name(message_name: &str, oneof: &ProtoOneof) -> String {
+        format!(
+            "{}_{}",
+            message_name,
+            pascal_case(CasedString::SnakeCase(&oneof.name))
+        )
+    }
+
+    // Documents a declaration with its leading comment, or its trailing comment if it has no leading one.
+    fn gen_doc_comment(&self, comments: &ProtoComments, indent: usize) -> String {
+        let indentation = "\t".repeat(indent);
+
+        let text = match comments.leading.as_ref().or(comments.trailing.as_ref()) {
+            Some(text) if !self.options.omit_comments => text,
+            _ => return String::new(),
+        };
+
+        // Block comments often open and close on lines of their own, which shouldn't end up in the docs.
+        let lines = text.lines().map(str::trim_end).collect::<Vec<&str>>();
+        let (first, last) = match (
+            lines.iter().position(|line| !line.is_empty()),
+            lines.iter().rposition(|line| !line.is_empty()),
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return String::new(),
+        };
+
+        lines[first..=last]
+            .iter()
+            .map(|line| format!("{}///{}\n", indentation, line))
+            .collect::<Vec<String>>()
+            .join("")
+    }
+
+    // Whether a singular field keeps track of being set, in which case it's nullable and has hasX and clearX methods.
+    // Only proto3 fields without a label that aren't messages don't, and are never null.
+    fn tracks_presence(
+        field: &ProtoMessageField,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<bool, GeneratorError> {
+        if field.explicit_presence || env.program().syntax != Some(ProtoSyntax::Proto3) {
+            return Ok(true);
+        }
+
+        match &field.field_type {
+            ProtoFieldType::IdentifierPath(path) => {
+                let node = env.resolve_type_node(path, field.span)?;
+                let is_message = matches!(
+                    node.borrow().proto_type.as_deref(),
+                    Some(ProtoType::Message(_))
+                );
+
+                Ok(is_message)
+            }
+            ProtoFieldType::Primitive(_) => Ok(false),
+        }
+    }
+
+    fn is_collection(field: &ProtoMessageField) -> bool {
+        field.modifier == Some(ProtoMessageFieldModifier::Repeated)
+            || matches!(
+                field.field_type,
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Map(_, _))
+            )
+    }
+
+    // The type of a value that can be missing, which only has to be marked as such in null-safe code.
+    fn nullable(&self, dart_type: &str) -> String {
+        if self.options.null_safety {
+            format!("{}?", dart_type)
+        } else {
+            dart_type.to_string()
+        }
+    }
+
+    // `span` is the location of the field the type belongs to, for reporting types that can't be resolved.
+    fn get_dart_type(
+        field_type: &ProtoFieldType,
+        span: Option<Span>,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<String, GeneratorError> {
+        match field_type {
+            ProtoFieldType::IdentifierPath(identifier) => {
+                Ok(env.resolve_identifier_path(identifier, span)?)
+            }
+            ProtoFieldType::Primitive(primitive) => match primitive {
+                ProtoPrimitiveType::Double | ProtoPrimitiveType::Float => Ok("double".to_string()),
+                ProtoPrimitiveType::Int32
+                | ProtoPrimitiveType::UInt32
+                | ProtoPrimitiveType::SInt32
+                | ProtoPrimitiveType::Fixed32
+                | ProtoPrimitiveType::SFixed32 => Ok("int".to_string()),
+                ProtoPrimitiveType::Int64
+                | ProtoPrimitiveType::UInt64
+                | ProtoPrimitiveType::SInt64
+                | ProtoPrimitiveType::Fixed64
+                | ProtoPrimitiveType::SFixed64 => Ok(FIXNUM_INT64_TYPE.to_string()),
+                ProtoPrimitiveType::Boolean => Ok("bool".to_string()),
+                ProtoPrimitiveType::Str => Ok("String".to_string()),
+                ProtoPrimitiveType::Bytes => Ok("List<int>".to_string()),
+                ProtoPrimitiveType::Map(key, value) => Ok(format!(
+                    "Map<{}, {}>",
+                    Self::get_dart_type(key, span, env)?,
+                    Self::get_dart_type(value, span, env)?
+                )),
+            },
+        }
+    }
+
+    fn uses_fixnum(proto_types: &[ProtoType]) -> bool {
+        proto_types.iter().any(|proto_type| match proto_type {
+            ProtoType::Message(message) => {
+                message
+                    .fields
+                    .iter()
+                    .chain(message.oneofs.iter().flat_map(|oneof| oneof.fields.iter()))
+                    .any(|field| Self::is_fixnum_type(&field.field_type))
+                    || Self::uses_fixnum(&message.types)
+            }
+            ProtoType::Enum(_) => false,
+        })
+    }
+
+    fn is_fixnum_type(field_type: &ProtoFieldType) -> bool {
+        match field_type {
+            ProtoFieldType::Primitive(primitive) => match primitive {
+                ProtoPrimitiveType::Int64
+                | ProtoPrimitiveType::UInt64
+                | ProtoPrimitiveType::SInt64
+                | ProtoPrimitiveType::Fixed64
+                | ProtoPrimitiveType::SFixed64 => true,
+                ProtoPrimitiveType::Map(key, value) => {
+                    Self::is_fixnum_type(key) || Self::is_fixnum_type(value)
+                }
+                _ => false,
+            },
+            ProtoFieldType::IdentifierPath(_) => false,
+        }
+    }
+
+    fn gen_enum(
+        &self,
+        enumeration: &ProtoEnum,
+        env: &mut GeneratorEnvironment,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let mut result = vec![];
+
+        let indentation = "\t".repeat(indent);
+
+        let enum_name = env
+            .get_fully_qualified_identifier()
+            .expect("expect to generate message in the context of a proto type");
+
+        result.push(self.gen_doc_comment(&enumeration.comments, indent));
+        result.push(format!(
+            "{}class {} extends {} {{\n",
+            indentation, enum_name, BASE_ENUM_TYPE
+        ));
+
+        result.push(self.gen_enum_body(&enum_name, &enumeration.values, indent + 1)?);
+
+        result.push(format!("\n{}}}", indentation));
+
+        Ok(result.join(""))
+    }
+
+    fn gen_enum_body(
+        &self,
+        enum_name: &str,
+        enum_values: &[ProtoEnumValue],
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let mut result = vec![];
+
+        let value_names = names::enum_value_names(enum_values);
+        for (value, value_name) in enum_values.iter().zip(&value_names) {
+            result.push(format!(
+                "{}\n",
+                self.gen_enum_value(enum_name, value, value_name, indent)?
+            ));
+        }
+
+        result.push(format!(
+            "\n{}",
+            Self::gen_all_enum_values_list(enum_name, &value_names, indent)?
+        ));
+
+        result.push(format!("\n\n{}", self.gen_enum_lookups(enum_name, indent)?));
+
+        result.push(format!("\n\n{}", Self::gen_enum_ctor(enum_name, indent)?));
+
+        Ok(result.join(""))
+    }
+
+    fn gen_enum_value(
+        &self,
+        enum_name: &str,
+        value: &ProtoEnumValue,
+        value_name: &str,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let indentation = "\t".repeat(indent);
+
+        Ok(format!(
+            "{}{}static const {} {} = {}._({}, \"{}\");",
+            self.gen_doc_comment(&value.comments, indent),
+            indentation,
+            enum_name,
+            value_name,
+            enum_name,
+            value.position,
+            value.name,
+        ))
+    }
+
+    fn gen_all_enum_values_list(
+        enum_name: &str,
+        value_names: &[String],
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let indentation = "\t".repeat(indent);
+        let value_indentation = "\t".repeat(indent + 1);
+
+        let all_values = value_names
+            .iter()
+            .map(|value_name| format!("{}{}", value_indentation, value_name))
+            .collect::<Vec<String>>()
+            .join(",\n");
+
+        Ok(format!(
+            "{}static const List<{}> values = [\n{}\n{}];",
+            indentation, enum_name, all_values, indentation
+        ))
+    }
+
+    // Lookups by number and name. Numbers the enum doesn't declare, e.g. from a newer version of the schema, are
+    // decoded with valueOfOrUnknown so they aren't lost.
+    fn gen_enum_lookups(&self, enum_name: &str, indent: usize) -> Result<String, GeneratorError> {
+        let indentation = "\t".repeat(indent);
+
+        let mut result = vec![];
+
+        result.push(format!(
+            "{}static final Map<int, {}> _byPosition = {}.initByPosition(values);\n",
+            indentation, enum_name, BASE_ENUM_TYPE
+        ));
+        result.push(format!(
+            "{}static final Map<String, {}> _byName = {}.initByName(values);\n\n",
+            indentation, enum_name, BASE_ENUM_TYPE
+        ));
+
+        result.push(format!(
+            "{}static {} valueOf(int position) => _byPosition[position];\n",
+            indentation,
+            self.nullable(enum_name)
+        ));
+        result.push(format!(
+            "{}static {} valueOfOrUnknown(int position) => _byPosition[position] ?? {}._(position, '$position');\n",
+            indentation, enum_name, enum_name
+        ));
+        result.push(format!(
+            "{}static {} valueOfName(String name) => _byName[name];",
+            indentation,
+            self.nullable(enum_name)
+        ));
+
+        Ok(result.join(""))
+    }
+
+    fn gen_enum_ctor(enum_name: &str, indent: usize) -> Result<String, GeneratorError> {
+        let indentation = "\t".repeat(indent);
+
+        Ok(format!(
+            "{}const {}._(int position, String name) : super(position, name);",
+            indentation, enum_name
+        ))
+    }
+}
+
+impl DartCodeGenerator {
+    fn identifier_qualifier() -> IdentifierQualifier {
+        IdentifierQualifier::new(Box::new(|proto_type, parent| {
+            match parent.clone().borrow().fully_qualified_identifier.clone() {
+                Some(parent_identifier) => {
+                    format!("{}_{}", parent_identifier, &proto_type.get_name())
+                }
+                None => proto_type.get_name().to_string(),
+            }
+        }))
+    }
+
+    // Generates a file whose types can reference its own types and those of its dependencies.
+    fn gen_program<'a>(
+        &self,
+        prog: &'a Program,
+        dependencies: &[&'a Program],
+        runtime_import: &str,
+        file_imports: Vec<String>,
+    ) -> Result<String, GeneratorError> {
+        let mut result = vec![];
+
+        // Only errors stop generation; warnings are left for callers that run the validator themselves.
+        let errors = validate(prog, dependencies)
+            .into_iter()
+            .filter(Diagnostic::is_error)
+            .collect::<Vec<Diagnostic>>();
+
+        if !errors.is_empty() {
+            return Err(GeneratorError::Invalid(errors));
+        }
+
+        let mut programs = vec![prog];
+        programs.extend(dependencies);
+
+        let type_hierarchy =
+            ProtoTypeHierarchy::from_programs(&programs, Self::identifier_qualifier());
+        let mut env = GeneratorEnvironment::new(prog, Rc::new(type_hierarchy));
+
+        // 64-bit integers are represented with fixnum's Int64 since Dart's int can't hold them on the web.
+        let mut imports = vec![];
+        if Self::uses_fixnum(&prog.types) {
+            imports.push(FIXNUM_IMPORT.to_string());
+        }
+        if !prog.types.is_empty() {
+            imports.push(format!("import '{}';", runtime_import));
+        }
+
+        imports.extend(
+            file_imports
+                .iter()
+                .map(|file_import| format!("import '{}';", file_import)),
+        );
+
+        if !imports.is_empty() {
+            result.push(format!("{}\n\n", imports.join("\n")));
+        }
+
+        // Generate all the top-level types, separated by blank lines.
+        for (i, proto_type) in prog.types.iter().enumerate() {
+            let child_env = env.new_child(proto_type)?;
+
+            if i > 0 {
+                result.push("\n\n".to_string());
+            }
+            result.push(self.gen_type(proto_type, &mut child_env.borrow_mut())?);
+        }
+
+        // Generate any types that were queued up while generating top-level types.
+        result.extend(env.flush_queued_outputs_deep());
+
+        if !prog.types.is_empty() {
+            result.push("\n".to_string());
+        }
+
+        Ok(result.join(""))
+    }
+
+    // The path of the Dart file generated for a .proto file: the file's name in a directory for each part of its
+    // package, e.g. "protos/bar.proto" in package "foo.baz" becomes "foo/baz/bar.pb.dart". Files without a package
+    // stay in their own directory.
+    pub fn dart_file_name(file: &CompiledFile) -> String {
+        let (dir, file_name) = match file.name.rsplit_once('/') {
+            Some((dir, file_name)) => (dir.to_string(), file_name),
+            None => (String::new(), file.name.as_str()),
+        };
+        let dir = match &file.program.package {
+            Some(package) => package.replace('.', "/"),
+            None => dir,
+        };
+        let file_name = format!("{}.pb.dart", file_name.trim_end_matches(".proto"));
+
+        match dir.as_str() {
+            "" => file_name,
+            _ => format!("{}/{}", dir, file_name),
+        }
+    }
+
+    // The path to import the Dart file `to` by from the Dart file `from`.
+    fn relative_import(from: &str, to: &str) -> String {
+        let from_dirs = from.split('/').collect::<Vec<&str>>();
+        let from_dirs = &from_dirs[..from_dirs.len() - 1];
+        let to_parts = to.split('/').collect::<Vec<&str>>();
+
+        let common = from_dirs
+            .iter()
+            .zip(to_parts.iter())
+            .take_while(|(from_dir, to_part)| from_dir == to_part)
+            .count();
+
+        let mut parts = vec![".."; from_dirs.len() - common];
+        parts.extend(&to_parts[common..]);
+
+        parts.join("/")
+    }
+}
+
+impl CodeGenerator for DartCodeGenerator {
+    fn gen_code(&self, src: String) -> Result<String, GeneratorError> {
+        let prog = self.parser.parse(&src)?;
+
+        self.gen_program(&prog, &[], RUNTIME_FILE, vec![])
+    }
+
+    fn gen_file(&self, compilation: &Compilation, name: &str) -> Result<String, GeneratorError> {
+        let file = compilation
+            .file(name)
+            .ok_or_else(|| GeneratorError::MissingFile(name.to_string()))?;
+
+        // Types can come from the file itself or anything it can see through its imports.
+        let visible_files = compilation.visible_files(name);
+
+        // Dart doesn't re-export imports, so every visible file has to be imported directly.
+        let dart_file = Self::dart_file_name(file);
+        let file_imports = visible_files
+            .iter()
+            .filter(|visible_file| visible_file.name != name)
+            .map(|visible_file| {
+                Self::relative_import(&dart_file, &Self::dart_file_name(visible_file))
+            })
+            .collect();
+
+        self.gen_program(
+            &file.program,
+            &compilation.dependencies(name),
+            &Self::relative_import(&dart_file, RUNTIME_FILE),
+            file_imports,
+        )
+    }
+
+    fn output_file_name(&self, file: &CompiledFile) -> String {
+        Self::dart_file_name(file)
+    }
+
+    fn runtime_files(&self) -> Vec<(String, String)> {
+        vec![(RUNTIME_FILE.to_string(), RUNTIME_SOURCE.to_string())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{Compiler, SourceTree};
+    use crate::parser::ParserImpl;
+
+    macro_rules! gen_code_for_test {
+        ($test_path: expr) => {{
+            let parser = ParserImpl::default();
+            let generator = DartCodeGenerator::new(Box::new(parser), DartOptions::default());
+
+            generator
+                .gen_code(include_str!($test_path).to_string())
+                .expect("unsuccessful codegen")
+                .to_owned()
+        }};
+    }
+
+    #[test]
+    fn test_nested() {
+        let result = gen_code_for_test!("../../../test_data/nested.proto");
+
+        assert_eq!(
+            result,
+            "import 'rs_proto.dart';
+
+class Foo extends ProtobufMessage {
+\tFoo();
+
+\tstatic Foo fromBuffer(List<int> bytes) => Foo()..mergeFromBuffer(bytes);
+
+\t@override
+\tvoid writeToWriter(ProtobufWriter writer) {
+\t}
+
+\t@override
+\tvoid mergeFromReader(ProtobufReader reader) {
+\t\twhile (!reader.isAtEnd) {
+\t\t\tfinal tag = reader.readTag();
+\t\t\tswitch (tag) {
+\t\t\t\tdefault:
+\t\t\t\t\treader.skipField(tag);
+\t\t\t}
+\t\t}
+\t}
+
+\t@override
+\tbool operator ==(Object other) =>
+\t\t\tother is Foo;
+
+\t@override
+\tint get hashCode => protobufHash([]);
+
+\tFoo copyWith() => Foo();
+
+\t@override
+\tString toString() =>
+\t\t\t'Foo()';
+}
+
+class Foo_Bar extends ProtobufMessage {
+\tFoo_Bar? bar;
+
+\tFoo_Bar({
+\t\tthis.bar,
+\t});
+
+\tbool hasBar() => bar != null;
+\tvoid clearBar() => bar = null;
+
+\tstatic Foo_Bar fromBuffer(List<int> bytes) => Foo_Bar()..mergeFromBuffer(bytes);
+
+\t@override
+\tvoid writeToWriter(ProtobufWriter writer) {
+\t\tif (bar != null) {
+\t\t\twriter..writeTag(10)..writeMessage(bar!);
+\t\t}
+\t}
+
+\t@override
+\tvoid mergeFromReader(ProtobufReader reader) {
+\t\twhile (!reader.isAtEnd) {
+\t\t\tfinal tag = reader.readTag();
+\t\t\tswitch (tag) {
+\t\t\t\tcase 10:
+\t\t\t\t\treader.readMessage(bar ??= Foo_Bar());
+\t\t\t\t\tbreak;
+\t\t\t\tdefault:
+\t\t\t\t\treader.skipField(tag);
+\t\t\t}
+\t\t}
+\t}
+
+\t@override
+\tbool operator ==(Object other) =>
+\t\t\tother is Foo_Bar &&
+\t\t\tprotobufEquals(bar, other.bar);
+
+\t@override
+\tint get hashCode => protobufHash([bar]);
+
+\tFoo_Bar copyWith({
+\t\tFoo_Bar? bar,
+\t}) {
+\t\treturn Foo_Bar(
+\t\t\tbar: bar ?? this.bar,
+\t\t);
+\t}
+
+\t@override
+\tString toString() =>
+\t\t\t'Foo_Bar('
+\t\t\t'bar: $bar'
+\t\t\t')';
+}
+
+class Foo_Bar_Baz extends ProtobufEnum {
+\tstatic const Foo_Bar_Baz unknown = Foo_Bar_Baz._(0, \"UNKNOWN\");
+
+\tstatic const List<Foo_Bar_Baz> values = [
+\t\tunknown
+\t];
+
+\tstatic final Map<int, Foo_Bar_Baz> _byPosition = ProtobufEnum.initByPosition(values);
+\tstatic final Map<String, Foo_Bar_Baz> _byName = ProtobufEnum.initByName(values);
+
+\tstatic Foo_Bar_Baz? valueOf(int position) => _byPosition[position];
+\tstatic Foo_Bar_Baz valueOfOrUnknown(int position) => _byPosition[position] ?? Foo_Bar_Baz._(position, '$position');
+\tstatic Foo_Bar_Baz? valueOfName(String name) => _byName[name];
+
+\tconst Foo_Bar_Baz._(int position, String name) : super(position, name);
+}
+
+class Foo_Baz extends ProtobufMessage {
+\tFoo_Baz_Bar? bar;
+\tFoo_Baz_Bar? bar2;
+\tFoo_Bar_Baz? baz;
+
+\tFoo_Baz({
+\t\tthis.bar,
+\t\tthis.bar2,
+\t\tthis.baz,
+\t});
+
+\tbool hasBar() => bar != null;
+\tvoid clearBar() => bar = null;
+
+\tbool hasBar2() => bar2 != null;
+\tvoid clearBar2() => bar2 = null;
+
+\tbool hasBaz() => baz != null;
+\tvoid clearBaz() => baz = null;
+
+\tstatic Foo_Baz fromBuffer(List<int> bytes) => Foo_Baz()..mergeFromBuffer(bytes);
+
+\t@override
+\tvoid writeToWriter(ProtobufWriter writer) {
+\t\tif (bar != null) {
+\t\t\twriter..writeTag(8)..writeEnum(bar!);
+\t\t}
+\t\tif (bar2 != null) {
+\t\t\twriter..writeTag(16)..writeEnum(bar2!);
+\t\t}
+\t\tif (baz != null) {
+\t\t\twriter..writeTag(24)..writeEnum(baz!);
+\t\t}
+\t}
+
+\t@override
+\tvoid mergeFromReader(ProtobufReader reader) {
+\t\twhile (!reader.isAtEnd) {
+\t\t\tfinal tag = reader.readTag();
+\t\t\tswitch (tag) {
+\t\t\t\tcase 8:
+\t\t\t\t\tbar = reader.readEnum(Foo_Baz_Bar.valueOfOrUnknown);
+\t\t\t\t\tbreak;
+\t\t\t\tcase 16:
+\t\t\t\t\tbar2 = reader.readEnum(Foo_Baz_Bar.valueOfOrUnknown);
+\t\t\t\t\tbreak;
+\t\t\t\tcase 24:
+\t\t\t\t\tbaz = reader.readEnum(Foo_Bar_Baz.valueOfOrUnknown);
+\t\t\t\t\tbreak;
+\t\t\t\tdefault:
+\t\t\t\t\treader.skipField(tag);
+\t\t\t}
+\t\t}
+\t}
+
+\t@override
+\tbool operator ==(Object other) =>
+\t\t\tother is Foo_Baz &&
+\t\t\tprotobufEquals(bar, other.bar) &&
+\t\t\tprotobufEquals(bar2, other.bar2) &&
+\t\t\tprotobufEquals(baz, other.baz);
+
+\t@override
+\tint get hashCode => protobufHash([bar, bar2, baz]);
+
+\tFoo_Baz copyWith({
+\t\tFoo_Baz_Bar? bar,
+\t\tFoo_Baz_Bar? bar2,
+\t\tFoo_Bar_Baz? baz,
+\t}) {
+\t\treturn Foo_Baz(
+\t\t\tbar: bar ?? this.bar,
+\t\t\tbar2: bar2 ?? this.bar2,
+\t\t\tbaz: baz ?? this.baz,
+\t\t);
+\t}
+
+\t@override
+\tString toString() =>
+\t\t\t'Foo_Baz('
+\t\t\t'bar: $bar, '
+\t\t\t'bar2: $bar2, '
+\t\t\t'baz: $baz'
+\t\t\t')';
+}
+
+class Foo_Baz_Bar extends ProtobufEnum {
+\tstatic const Foo_Baz_Bar unknown = Foo_Baz_Bar._(0, \"UNKNOWN\");
+
+\tstatic const List<Foo_Baz_Bar> values = [
+\t\tunknown
+\t];
+
+\tstatic final Map<int, Foo_Baz_Bar> _byPosition = ProtobufEnum.initByPosition(values);
+\tstatic final Map<String, Foo_Baz_Bar> _byName = ProtobufEnum.initByName(values);
+
+\tstatic Foo_Baz_Bar? valueOf(int position) => _byPosition[position];
+\tstatic Foo_Baz_Bar valueOfOrUnknown(int position) => _byPosition[position] ?? Foo_Baz_Bar._(position, '$position');
+\tstatic Foo_Baz_Bar? valueOfName(String name) => _byName[name];
+
+\tconst Foo_Baz_Bar._(int position, String name) : super(position, name);
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_message() {
+        let result = gen_code_for_test!("../../../test_data/message.proto");
+
+        assert_eq!(
+            result,
+            "import 'package:fixnum/fixnum.dart';
+import 'rs_proto.dart';
+
+class Person extends ProtobufMessage {
+\tString? firstName;
+\tString? lastName;
+\tInt64? dateOfBirthUnixEpoch;
+
+\tPerson({
+\t\tthis.firstName,
+\t\tthis.lastName,
+\t\tthis.dateOfBirthUnixEpoch,
+\t});
+
+\tbool hasFirstName() => firstName != null;
+\tvoid clearFirstName() => firstName = null;
+
+\tbool hasLastName() => lastName != null;
+\tvoid clearLastName() => lastName = null;
+
+\tbool hasDateOfBirthUnixEpoch() => dateOfBirthUnixEpoch != null;
+\tvoid clearDateOfBirthUnixEpoch() => dateOfBirthUnixEpoch = null;
+
+\tstatic Person fromBuffer(List<int> bytes) => Person()..mergeFromBuffer(bytes);
+
+\t@override
+\tvoid writeToWriter(ProtobufWriter writer) {
+\t\tif (firstName != null) {
+\t\t\twriter..writeTag(10)..writeString(firstName!);
+\t\t}
+\t\tif (lastName != null) {
+\t\t\twriter..writeTag(18)..writeString(lastName!);
+\t\t}
+\t\tif (dateOfBirthUnixEpoch != null) {
+\t\t\twriter..writeTag(24)..writeInt64(dateOfBirthUnixEpoch!);
+\t\t}
+\t}
+
+\t@override
+\tvoid mergeFromReader(ProtobufReader reader) {
+\t\twhile (!reader.isAtEnd) {
+\t\t\tfinal tag = reader.readTag();
+\t\t\tswitch (tag) {
+\t\t\t\tcase 10:
+\t\t\t\t\tfirstName = reader.readString();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 18:
+\t\t\t\t\tlastName = reader.readString();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 24:
+\t\t\t\t\tdateOfBirthUnixEpoch = reader.readInt64();
+\t\t\t\t\tbreak;
+\t\t\t\tdefault:
+\t\t\t\t\treader.skipField(tag);
+\t\t\t}
+\t\t}
+\t}
+
+\t@override
+\tbool operator ==(Object other) =>
+\t\t\tother is Person &&
+\t\t\tprotobufEquals(firstName, other.firstName) &&
+\t\t\tprotobufEquals(lastName, other.lastName) &&
+\t\t\tprotobufEquals(dateOfBirthUnixEpoch, other.dateOfBirthUnixEpoch);
+
+\t@override
+\tint get hashCode => protobufHash([firstName, lastName, dateOfBirthUnixEpoch]);
+
+\tPerson copyWith({
+\t\tString? firstName,
+\t\tString? lastName,
+\t\tInt64? dateOfBirthUnixEpoch,
+\t}) {
+\t\treturn Person(
+\t\t\tfirstName: firstName ?? this.firstName,
+\t\t\tlastName: lastName ?? this.lastName,
+\t\t\tdateOfBirthUnixEpoch: dateOfBirthUnixEpoch ?? this.dateOfBirthUnixEpoch,
+\t\t);
+\t}
+
+\t@override
+\tString toString() =>
+\t\t\t'Person('
+\t\t\t'firstName: $firstName, '
+\t\t\t'lastName: $lastName, '
+\t\t\t'dateOfBirthUnixEpoch: $dateOfBirthUnixEpoch'
+\t\t\t')';
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_enum() {
+        let result = gen_code_for_test!("../../../test_data/enum.proto");
+
+        assert_eq!(
+            result,
+            "import 'rs_proto.dart';
+
+class RelationshipType extends ProtobufEnum {
+\tstatic const RelationshipType unknownValue = RelationshipType._(0, \"UNKNOWN_VALUE\");
+\tstatic const RelationshipType parent = RelationshipType._(1, \"PARENT\");
+\tstatic const RelationshipType sibling = RelationshipType._(2, \"SIBLING\");
+\tstatic const RelationshipType child = RelationshipType._(3, \"CHILD\");
+\tstatic const RelationshipType ancestor = RelationshipType._(4, \"ANCESTOR\");
+\tstatic const RelationshipType descendant = RelationshipType._(5, \"DESCENDANT\");
+
+\tstatic const List<RelationshipType> values = [
+\t\tunknownValue,
+\t\tparent,
+\t\tsibling,
+\t\tchild,
+\t\tancestor,
+\t\tdescendant
+\t];
+
+\tstatic final Map<int, RelationshipType> _byPosition = ProtobufEnum.initByPosition(values);
+\tstatic final Map<String, RelationshipType> _byName = ProtobufEnum.initByName(values);
+
+\tstatic RelationshipType? valueOf(int position) => _byPosition[position];
+\tstatic RelationshipType valueOfOrUnknown(int position) => _byPosition[position] ?? RelationshipType._(position, '$position');
+\tstatic RelationshipType? valueOfName(String name) => _byName[name];
+
+\tconst RelationshipType._(int position, String name) : super(position, name);
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_enum_value_names() {
+        let result = gen_code_for_test!("../../../test_data/enum_names.proto");
+
+        assert!(result.contains("\tstatic const Keyword values_ = Keyword._(0, \"VALUES\");\n"));
+        assert!(result.contains("\tstatic const Keyword name_ = Keyword._(1, \"NAME\");\n"));
+        assert!(result.contains("\tstatic const Keyword position_ = Keyword._(2, \"POSITION\");\n"));
+        assert!(result.contains("\tstatic const Keyword valueOf_ = Keyword._(3, \"VALUE_OF\");\n"));
+        assert!(result
+            .contains("\tstatic const Keyword valueOfName_ = Keyword._(4, \"VALUE_OF_NAME\");\n"));
+        assert!(result.contains("\tstatic const Keyword new_ = Keyword._(5, \"NEW\");\n"));
+        assert!(
+            result.contains("\tstatic const Keyword hashCode_ = Keyword._(6, \"HASH_CODE\");\n")
+        );
+        assert!(result.contains("\tstatic const Keyword class_ = Keyword._(7, \"CLASS\");\n"));
+        assert!(result.contains(
+            "\tstatic const List<Keyword> values = [\n\t\tvalues_,\n\t\tname_,\n\t\tposition_,\n\t\tvalueOf_,\n\t\tvalueOfName_,\n\t\tnew_,\n\t\thashCode_,\n\t\tclass_\n\t];\n"
+        ));
+        assert!(result.contains("\t\tthis.keyword = Keyword.values_,\n"));
+
+        // Top-level types are separated by a blank line, and the file ends with a newline.
+        assert!(result.contains("\n}\n\nclass Holder extends ProtobufMessage {\n"));
+        assert!(result.ends_with(")';\n}\n"));
+    }
+
+    #[test]
+    fn test_member_names() {
+        let result = gen_code_for_test!("../../../test_data/member_names.proto");
+
+        assert!(result.contains("\tint hashCode_1;\n"));
+        assert!(result.contains("\tList<int> writeToBuffer_2;\n"));
+        assert!(result.contains("\tString class_3;\n"));
+        assert!(result.contains("\tString? name;\n"));
+        assert!(result.contains("\tbool hasName_5;\n"));
+        assert!(result.contains("\tbool hasName() => name != null;\n"));
+        assert!(result.contains("\tString? get toString_6 => _toString_6;\n"));
+        assert!(result.contains("\tbool hasValues_7() => _kindCase == Members_Kind.values_7;\n"));
+        assert!(result.contains("\tvoid clearValues_7() {\n"));
+        assert!(result.contains("enum Members_Kind {\n\ttoString_6,\n\tvalues_7,\n\tnotSet\n}"));
+        assert!(result.contains("writer..writeTag(8)..writeInt32(hashCode_1);\n"));
+        assert!(result.contains("\t\t\t\t\twriteToBuffer_2 = reader.readBytes();\n"));
+        assert!(result.contains("\tint get hashCode => protobufHash([hashCode_1, writeToBuffer_2, class_3, name, hasName_5, _kindCase, _toString_6, _values_7]);\n"));
+        assert!(result.contains("\t\t\tclass_3: class_3 ?? this.class_3,\n"));
+        assert!(result.contains("\t\t\t'hashCode_1: $hashCode_1, '\n"));
+    }
+
+    #[test]
+    fn test_declared_defaults() {
+        let result = gen_code_for_test!("../../../test_data/declared_defaults.proto");
+
+        assert!(result.contains(
+            "\tint? _count;\n\tint get count => _count ?? 5;\n\tset count(int? value) => _count = value;\n"
+        ));
+        assert!(
+            result.contains("\tInt64 get big => _big ?? Int64.fromInts(0xffdfffff, 0xffffffff);\n")
+        );
+        assert!(result.contains("\tInt64 get huge => _huge ?? Int64(-1);\n"));
+        assert!(result.contains("\tdouble get ratio => _ratio ?? -double.infinity;\n"));
+        assert!(result.contains("\tdouble get scale => _scale ?? 1.5;\n"));
+        assert!(result.contains("\tbool get enabled => _enabled ?? true;\n"));
+        assert!(result.contains("\tString get label => _label ?? 'it\\'s \\$5\\n';\n"));
+        assert!(result.contains("\tList<int> get magic => _magic ?? const <int>[1, 2];\n"));
+        assert!(result.contains("\tList<int> get raw => _raw ?? const <int>[255, 0];\n"));
+        assert!(result.contains("\tDefaults_Level get level => _level ?? Defaults_Level.high;\n"));
+        assert!(result.contains("\tint? plain;\n"));
+        assert!(result.contains("\tdouble get unknown => _unknown ?? double.nan;\n"));
+
+        // Whether the field is set, and whether it's written, doesn't depend on the default.
+        assert!(result.contains(
+            "\tbool hasCount() => _count != null;\n\tvoid clearCount() => _count = null;\n"
+        ));
+        assert!(result.contains(
+            "\t\tif (_count != null) {\n\t\t\twriter..writeTag(8)..writeInt32(_count!);\n"
+        ));
+        assert!(result.contains("\t\tif (count != null) {\n\t\t\tthis.count = count;\n\t\t}\n"));
+        assert!(result.contains("\t\t\tprotobufEquals(_count, other._count) &&\n"));
+        assert!(result.contains("\t\tcopy\n\t\t\t.._count = _count\n"));
+    }
+
+    #[test]
+    fn test_oneof() {
+        let result = gen_code_for_test!("../../../test_data/oneof.proto");
+
+        assert_eq!(
+            result,
+            "import 'package:fixnum/fixnum.dart';
+import 'rs_proto.dart';
+
+class Sample extends ProtobufMessage {
+\tint? id;
+
+\tSample({
+\t\tthis.id,
+\t\tString? text,
+\t\tInt64? bigNumber,
+\t}) {
+\t\tif (text != null) {
+\t\t\tthis.text = text;
+\t\t}
+\t\tif (bigNumber != null) {
+\t\t\tthis.bigNumber = bigNumber;
+\t\t}
+\t}
+
+\tbool hasId() => id != null;
+\tvoid clearId() => id = null;
+
+\tSample_Payload _payloadCase = Sample_Payload.notSet;
+\tString? _text;
+\tInt64? _bigNumber;
+
+\tSample_Payload get payloadCase => _payloadCase;
+
+\tString? get text => _text;
+\tset text(String? value) {
+\t\tclearPayload();
+\t\tif (value != null) {
+\t\t\t_text = value;
+\t\t\t_payloadCase = Sample_Payload.text;
+\t\t}
+\t}
+\tbool hasText() => _payloadCase == Sample_Payload.text;
+\tvoid clearText() {
+\t\tif (hasText()) {
+\t\t\tclearPayload();
+\t\t}
+\t}
+
+\tInt64? get bigNumber => _bigNumber;
+\tset bigNumber(Int64? value) {
+\t\tclearPayload();
+\t\tif (value != null) {
+\t\t\t_bigNumber = value;
+\t\t\t_payloadCase = Sample_Payload.bigNumber;
+\t\t}
+\t}
+\tbool hasBigNumber() => _payloadCase == Sample_Payload.bigNumber;
+\tvoid clearBigNumber() {
+\t\tif (hasBigNumber()) {
+\t\t\tclearPayload();
+\t\t}
+\t}
+
+\tvoid clearPayload() {
+\t\t_text = null;
+\t\t_bigNumber = null;
+\t\t_payloadCase = Sample_Payload.notSet;
+\t}
+
+\tstatic Sample fromBuffer(List<int> bytes) => Sample()..mergeFromBuffer(bytes);
+
+\t@override
+\tvoid writeToWriter(ProtobufWriter writer) {
+\t\tif (id != null) {
+\t\t\twriter..writeTag(8)..writeInt32(id!);
+\t\t}
+\t\tif (_payloadCase == Sample_Payload.text) {
+\t\t\twriter..writeTag(18)..writeString(_text!);
+\t\t}
+\t\tif (_payloadCase == Sample_Payload.bigNumber) {
+\t\t\twriter..writeTag(24)..writeInt64(_bigNumber!);
+\t\t}
+\t}
+
+\t@override
+\tvoid mergeFromReader(ProtobufReader reader) {
+\t\twhile (!reader.isAtEnd) {
+\t\t\tfinal tag = reader.readTag();
+\t\t\tswitch (tag) {
+\t\t\t\tcase 8:
+\t\t\t\t\tid = reader.readInt32();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 18:
+\t\t\t\t\ttext = reader.readString();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 24:
+\t\t\t\t\tbigNumber = reader.readInt64();
+\t\t\t\t\tbreak;
+\t\t\t\tdefault:
+\t\t\t\t\treader.skipField(tag);
+\t\t\t}
+\t\t}
+\t}
+
+\t@override
+\tbool operator ==(Object other) =>
+\t\t\tother is Sample &&
+\t\t\tprotobufEquals(id, other.id) &&
+\t\t\tprotobufEquals(_payloadCase, other._payloadCase) &&
+\t\t\tprotobufEquals(_text, other._text) &&
+\t\t\tprotobufEquals(_bigNumber, other._bigNumber);
+
+\t@override
+\tint get hashCode => protobufHash([id, _payloadCase, _text, _bigNumber]);
+
+\tSample copyWith({
+\t\tint? id,
+\t\tString? text,
+\t\tInt64? bigNumber,
+\t}) {
+\t\tfinal copy = Sample(
+\t\t\tid: id ?? this.id,
+\t\t);
+\t\tcopy
+\t\t\t.._payloadCase = _payloadCase
+\t\t\t.._text = _text
+\t\t\t.._bigNumber = _bigNumber;
+\t\tif (text != null) {
+\t\t\tcopy.text = text;
+\t\t}
+\t\tif (bigNumber != null) {
+\t\t\tcopy.bigNumber = bigNumber;
+\t\t}
+\t\treturn copy;
+\t}
+
+\t@override
+\tString toString() =>
+\t\t\t'Sample('
+\t\t\t'id: $id, '
+\t\t\t'text: $text, '
+\t\t\t'bigNumber: $bigNumber'
+\t\t\t')';
+}
+
+enum Sample_Payload {
+\ttext,
+\tbigNumber,
+\tnotSet
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_scalars() {
+        let result = gen_code_for_test!("../../../test_data/scalars.proto");
+
+        assert_eq!(
+            result,
+            "import 'package:fixnum/fixnum.dart';
+import 'rs_proto.dart';
+
+class Scalars extends ProtobufMessage {
+\tdouble? aDouble;
+\tdouble? aFloat;
+\tint? anInt32;
+\tInt64? anInt64;
+\tint? aUint32;
+\tInt64? aUint64;
+\tint? aSint32;
+\tInt64? aSint64;
+\tint? aFixed32;
+\tInt64? aFixed64;
+\tint? aSfixed32;
+\tInt64? aSfixed64;
+\tbool? aBool;
+\tString? aString;
+\tList<int>? someBytes;
+\tMap<String, List<int>> aMap = {};
+\tScalars_stringy? notAScalar;
+
+\tScalars({
+\t\tthis.aDouble,
+\t\tthis.aFloat,
+\t\tthis.anInt32,
+\t\tthis.anInt64,
+\t\tthis.aUint32,
+\t\tthis.aUint64,
+\t\tthis.aSint32,
+\t\tthis.aSint64,
+\t\tthis.aFixed32,
+\t\tthis.aFixed64,
+\t\tthis.aSfixed32,
+\t\tthis.aSfixed64,
+\t\tthis.aBool,
+\t\tthis.aString,
+\t\tthis.someBytes,
+\t\tMap<String, List<int>>? aMap,
+\t\tthis.notAScalar,
+\t}) {
+\t\tif (aMap != null) {
+\t\t\tthis.aMap.addAll(aMap);
+\t\t}
+\t}
+
+\tbool hasADouble() => aDouble != null;
+\tvoid clearADouble() => aDouble = null;
+
+\tbool hasAFloat() => aFloat != null;
+\tvoid clearAFloat() => aFloat = null;
+
+\tbool hasAnInt32() => anInt32 != null;
+\tvoid clearAnInt32() => anInt32 = null;
+
+\tbool hasAnInt64() => anInt64 != null;
+\tvoid clearAnInt64() => anInt64 = null;
+
+\tbool hasAUint32() => aUint32 != null;
+\tvoid clearAUint32() => aUint32 = null;
+
+\tbool hasAUint64() => aUint64 != null;
+\tvoid clearAUint64() => aUint64 = null;
+
+\tbool hasASint32() => aSint32 != null;
+\tvoid clearASint32() => aSint32 = null;
+
+\tbool hasASint64() => aSint64 != null;
+\tvoid clearASint64() => aSint64 = null;
+
+\tbool hasAFixed32() => aFixed32 != null;
+\tvoid clearAFixed32() => aFixed32 = null;
+
+\tbool hasAFixed64() => aFixed64 != null;
+\tvoid clearAFixed64() => aFixed64 = null;
+
+\tbool hasASfixed32() => aSfixed32 != null;
+\tvoid clearASfixed32() => aSfixed32 = null;
+
+\tbool hasASfixed64() => aSfixed64 != null;
+\tvoid clearASfixed64() => aSfixed64 = null;
+
+\tbool hasABool() => aBool != null;
+\tvoid clearABool() => aBool = null;
+
+\tbool hasAString() => aString != null;
+\tvoid clearAString() => aString = null;
+
+\tbool hasSomeBytes() => someBytes != null;
+\tvoid clearSomeBytes() => someBytes = null;
+
+\tbool hasNotAScalar() => notAScalar != null;
+\tvoid clearNotAScalar() => notAScalar = null;
+
+\tstatic Scalars fromBuffer(List<int> bytes) => Scalars()..mergeFromBuffer(bytes);
+
+\t@override
+\tvoid writeToWriter(ProtobufWriter writer) {
+\t\tif (aDouble != null) {
+\t\t\twriter..writeTag(9)..writeDouble(aDouble!);
+\t\t}
+\t\tif (aFloat != null) {
+\t\t\twriter..writeTag(21)..writeFloat(aFloat!);
+\t\t}
+\t\tif (anInt32 != null) {
+\t\t\twriter..writeTag(24)..writeInt32(anInt32!);
+\t\t}
+\t\tif (anInt64 != null) {
+\t\t\twriter..writeTag(32)..writeInt64(anInt64!);
+\t\t}
+\t\tif (aUint32 != null) {
+\t\t\twriter..writeTag(40)..writeUint32(aUint32!);
+\t\t}
+\t\tif (aUint64 != null) {
+\t\t\twriter..writeTag(48)..writeUint64(aUint64!);
+\t\t}
+\t\tif (aSint32 != null) {
+\t\t\twriter..writeTag(56)..writeSint32(aSint32!);
+\t\t}
+\t\tif (aSint64 != null) {
+\t\t\twriter..writeTag(64)..writeSint64(aSint64!);
+\t\t}
+\t\tif (aFixed32 != null) {
+\t\t\twriter..writeTag(77)..writeFixed32(aFixed32!);
+\t\t}
+\t\tif (aFixed64 != null) {
+\t\t\twriter..writeTag(81)..writeFixed64(aFixed64!);
+\t\t}
+\t\tif (aSfixed32 != null) {
+\t\t\twriter..writeTag(93)..writeSfixed32(aSfixed32!);
+\t\t}
+\t\tif (aSfixed64 != null) {
+\t\t\twriter..writeTag(97)..writeSfixed64(aSfixed64!);
+\t\t}
+\t\tif (aBool != null) {
+\t\t\twriter..writeTag(104)..writeBool(aBool!);
+\t\t}
+\t\tif (aString != null) {
+\t\t\twriter..writeTag(114)..writeString(aString!);
+\t\t}
+\t\tif (someBytes != null) {
+\t\t\twriter..writeTag(122)..writeBytes(someBytes!);
+\t\t}
+\t\taMap.forEach((key, value) {
+\t\t\twriter..writeTag(130)..writeLengthDelimited((entry) => entry..writeTag(10)..writeString(key)..writeTag(18)..writeBytes(value));
+\t\t});
+\t\tif (notAScalar != null) {
+\t\t\twriter..writeTag(138)..writeMessage(notAScalar!);
+\t\t}
+\t}
+
+\t@override
+\tvoid mergeFromReader(ProtobufReader reader) {
+\t\twhile (!reader.isAtEnd) {
+\t\t\tfinal tag = reader.readTag();
+\t\t\tswitch (tag) {
+\t\t\t\tcase 9:
+\t\t\t\t\taDouble = reader.readDouble();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 21:
+\t\t\t\t\taFloat = reader.readFloat();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 24:
+\t\t\t\t\tanInt32 = reader.readInt32();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 32:
+\t\t\t\t\tanInt64 = reader.readInt64();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 40:
+\t\t\t\t\taUint32 = reader.readUint32();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 48:
+\t\t\t\t\taUint64 = reader.readUint64();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 56:
+\t\t\t\t\taSint32 = reader.readSint32();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 64:
+\t\t\t\t\taSint64 = reader.readSint64();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 77:
+\t\t\t\t\taFixed32 = reader.readFixed32();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 81:
+\t\t\t\t\taFixed64 = reader.readFixed64();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 93:
+\t\t\t\t\taSfixed32 = reader.readSfixed32();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 97:
+\t\t\t\t\taSfixed64 = reader.readSfixed64();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 104:
+\t\t\t\t\taBool = reader.readBool();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 114:
+\t\t\t\t\taString = reader.readString();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 122:
+\t\t\t\t\tsomeBytes = reader.readBytes();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 130:
+\t\t\t\t\treader.readMapEntry(aMap, 10, (reader) => reader.readString(), '', 18, (reader) => reader.readBytes(), () => <int>[]);
+\t\t\t\t\tbreak;
+\t\t\t\tcase 138:
+\t\t\t\t\treader.readMessage(notAScalar ??= Scalars_stringy());
+\t\t\t\t\tbreak;
+\t\t\t\tdefault:
+\t\t\t\t\treader.skipField(tag);
+\t\t\t}
+\t\t}
+\t}
+
+\t@override
+\tbool operator ==(Object other) =>
+\t\t\tother is Scalars &&
+\t\t\tprotobufEquals(aDouble, other.aDouble) &&
+\t\t\tprotobufEquals(aFloat, other.aFloat) &&
+\t\t\tprotobufEquals(anInt32, other.anInt32) &&
+\t\t\tprotobufEquals(anInt64, other.anInt64) &&
+\t\t\tprotobufEquals(aUint32, other.aUint32) &&
+\t\t\tprotobufEquals(aUint64, other.aUint64) &&
+\t\t\tprotobufEquals(aSint32, other.aSint32) &&
+\t\t\tprotobufEquals(aSint64, other.aSint64) &&
+\t\t\tprotobufEquals(aFixed32, other.aFixed32) &&
+\t\t\tprotobufEquals(aFixed64, other.aFixed64) &&
+\t\t\tprotobufEquals(aSfixed32, other.aSfixed32) &&
+\t\t\tprotobufEquals(aSfixed64, other.aSfixed64) &&
+\t\t\tprotobufEquals(aBool, other.aBool) &&
+\t\t\tprotobufEquals(aString, other.aString) &&
+\t\t\tprotobufEquals(someBytes, other.someBytes) &&
+\t\t\tprotobufEquals(aMap, other.aMap) &&
+\t\t\tprotobufEquals(notAScalar, other.notAScalar);
+
+\t@override
+\tint get hashCode => protobufHash([aDouble, aFloat, anInt32, anInt64, aUint32, aUint64, aSint32, aSint64, aFixed32, aFixed64, aSfixed32, aSfixed64, aBool, aString, someBytes, aMap, notAScalar]);
+
+\tScalars copyWith({
+\t\tdouble? aDouble,
+\t\tdouble? aFloat,
+\t\tint? anInt32,
+\t\tInt64? anInt64,
+\t\tint? aUint32,
+\t\tInt64? aUint64,
+\t\tint? aSint32,
+\t\tInt64? aSint64,
+\t\tint? aFixed32,
+\t\tInt64? aFixed64,
+\t\tint? aSfixed32,
+\t\tInt64? aSfixed64,
+\t\tbool? aBool,
+\t\tString? aString,
+\t\tList<int>? someBytes,
+\t\tMap<String, List<int>>? aMap,
+\t\tScalars_stringy? notAScalar,
+\t}) {
+\t\treturn Scalars(
+\t\t\taDouble: aDouble ?? this.aDouble,
+\t\t\taFloat: aFloat ?? this.aFloat,
+\t\t\tanInt32: anInt32 ?? this.anInt32,
+\t\t\tanInt64: anInt64 ?? this.anInt64,
+\t\t\taUint32: aUint32 ?? this.aUint32,
+\t\t\taUint64: aUint64 ?? this.aUint64,
+\t\t\taSint32: aSint32 ?? this.aSint32,
+\t\t\taSint64: aSint64 ?? this.aSint64,
+\t\t\taFixed32: aFixed32 ?? this.aFixed32,
+\t\t\taFixed64: aFixed64 ?? this.aFixed64,
+\t\t\taSfixed32: aSfixed32 ?? this.aSfixed32,
+\t\t\taSfixed64: aSfixed64 ?? this.aSfixed64,
+\t\t\taBool: aBool ?? this.aBool,
+\t\t\taString: aString ?? this.aString,
+\t\t\tsomeBytes: someBytes ?? this.someBytes,
+\t\t\taMap: aMap ?? this.aMap,
+\t\t\tnotAScalar: notAScalar ?? this.notAScalar,
+\t\t);
+\t}
+
+\t@override
+\tString toString() =>
+\t\t\t'Scalars('
+\t\t\t'aDouble: $aDouble, '
+\t\t\t'aFloat: $aFloat, '
+\t\t\t'anInt32: $anInt32, '
+\t\t\t'anInt64: $anInt64, '
+\t\t\t'aUint32: $aUint32, '
+\t\t\t'aUint64: $aUint64, '
+\t\t\t'aSint32: $aSint32, '
+\t\t\t'aSint64: $aSint64, '
+\t\t\t'aFixed32: $aFixed32, '
+\t\t\t'aFixed64: $aFixed64, '
+\t\t\t'aSfixed32: $aSfixed32, '
+\t\t\t'aSfixed64: $aSfixed64, '
+\t\t\t'aBool: $aBool, '
+\t\t\t'aString: $aString, '
+\t\t\t'someBytes: $someBytes, '
+\t\t\t'aMap: $aMap, '
+\t\t\t'notAScalar: $notAScalar'
+\t\t\t')';
+}
+
+class Scalars_stringy extends ProtobufMessage {
+\tScalars_stringy();
+
+\tstatic Scalars_stringy fromBuffer(List<int> bytes) => Scalars_stringy()..mergeFromBuffer(bytes);
+
+\t@override
+\tvoid writeToWriter(ProtobufWriter writer) {
+\t}
+
+\t@override
+\tvoid mergeFromReader(ProtobufReader reader) {
+\t\twhile (!reader.isAtEnd) {
+\t\t\tfinal tag = reader.readTag();
+\t\t\tswitch (tag) {
+\t\t\t\tdefault:
+\t\t\t\t\treader.skipField(tag);
+\t\t\t}
+\t\t}
+\t}
+
+\t@override
+\tbool operator ==(Object other) =>
+\t\t\tother is Scalars_stringy;
+
+\t@override
+\tint get hashCode => protobufHash([]);
+
+\tScalars_stringy copyWith() => Scalars_stringy();
+
+\t@override
+\tString toString() =>
+\t\t\t'Scalars_stringy()';
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let result = gen_code_for_test!("../../../test_data/doc_comments.proto");
+
+        assert_eq!(
+            result,
+            "import 'rs_proto.dart';
+
+/// A person we know.
+class Person extends ProtobufMessage {
+\t/// What they go by.
+\tString name;
+
+\tPerson({
+\t\tthis.name = '',
+\t\tString? email,
+\t\tString? phone,
+\t}) {
+\t\tif (email != null) {
+\t\t\tthis.email = email;
+\t\t}
+\t\tif (phone != null) {
+\t\t\tthis.phone = phone;
+\t\t}
+\t}
+
+\tPerson_Contact _contactCase = Person_Contact.notSet;
+\tString? _email;
+\tString? _phone;
+
+\tPerson_Contact get contactCase => _contactCase;
+
+\t/// Their email address,
+\t/// if they have one.
+\tString? get email => _email;
+\tset email(String? value) {
+\t\tclearContact();
+\t\tif (value != null) {
+\t\t\t_email = value;
+\t\t\t_contactCase = Person_Contact.email;
+\t\t}
+\t}
+\tbool hasEmail() => _contactCase == Person_Contact.email;
+\tvoid clearEmail() {
+\t\tif (hasEmail()) {
+\t\t\tclearContact();
+\t\t}
+\t}
+
+\tString? get phone => _phone;
+\tset phone(String? value) {
+\t\tclearContact();
+\t\tif (value != null) {
+\t\t\t_phone = value;
+\t\t\t_contactCase = Person_Contact.phone;
+\t\t}
+\t}
+\tbool hasPhone() => _contactCase == Person_Contact.phone;
+\tvoid clearPhone() {
+\t\tif (hasPhone()) {
+\t\t\tclearContact();
+\t\t}
+\t}
+
+\tvoid clearContact() {
+\t\t_email = null;
+\t\t_phone = null;
+\t\t_contactCase = Person_Contact.notSet;
+\t}
+
+\tstatic Person fromBuffer(List<int> bytes) => Person()..mergeFromBuffer(bytes);
+
+\t@override
+\tvoid writeToWriter(ProtobufWriter writer) {
+\t\tif (name.isNotEmpty) {
+\t\t\twriter..writeTag(10)..writeString(name);
+\t\t}
+\t\tif (_contactCase == Person_Contact.email) {
+\t\t\twriter..writeTag(18)..writeString(_email!);
+\t\t}
+\t\tif (_contactCase == Person_Contact.phone) {
+\t\t\twriter..writeTag(26)..writeString(_phone!);
+\t\t}
+\t}
+
+\t@override
+\tvoid mergeFromReader(ProtobufReader reader) {
+\t\twhile (!reader.isAtEnd) {
+\t\t\tfinal tag = reader.readTag();
+\t\t\tswitch (tag) {
+\t\t\t\tcase 10:
+\t\t\t\t\tname = reader.readString();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 18:
+\t\t\t\t\temail = reader.readString();
+\t\t\t\t\tbreak;
+\t\t\t\tcase 26:
+\t\t\t\t\tphone = reader.readString();
+\t\t\t\t\tbreak;
+\t\t\t\tdefault:
+\t\t\t\t\treader.skipField(tag);
+\t\t\t}
+\t\t}
+\t}
+
+\t@override
+\tbool operator ==(Object other) =>
+\t\t\tother is Person &&
+\t\t\tprotobufEquals(name, other.name) &&
+\t\t\tprotobufEquals(_contactCase, other._contactCase) &&
+\t\t\tprotobufEquals(_email, other._email) &&
+\t\t\tprotobufEquals(_phone, other._phone);
+
+\t@override
+\tint get hashCode => protobufHash([name, _contactCase, _email, _phone]);
+
+\tPerson copyWith({
+\t\tString? name,
+\t\tString? email,
+\t\tString? phone,
+\t}) {
+\t\tfinal copy = Person(
+\t\t\tname: name ?? this.name,
+\t\t);
+\t\tcopy
+\t\t\t.._contactCase = _contactCase
+\t\t\t.._email = _email
+\t\t\t.._phone = _phone;
+\t\tif (email != null) {
+\t\t\tcopy.email = email;
+\t\t}
+\t\tif (phone != null) {
+\t\t\tcopy.phone = phone;
+\t\t}
+\t\treturn copy;
+\t}
+
+\t@override
+\tString toString() =>
+\t\t\t'Person('
+\t\t\t'name: $name, '
+\t\t\t'email: $email, '
+\t\t\t'phone: $phone'
+\t\t\t')';
+}
+
+enum Person_Contact {
+\temail,
+\tphone,
+\tnotSet
+}
+
+/// How two people are related.
+class Person_Relationship extends ProtobufEnum {
+\t/// Not known yet.
+\tstatic const Person_Relationship unknown = Person_Relationship._(0, \"UNKNOWN\");
+\tstatic const Person_Relationship sibling = Person_Relationship._(1, \"SIBLING\");
+
+\tstatic const List<Person_Relationship> values = [
+\t\tunknown,
+\t\tsibling
+\t];
+
+\tstatic final Map<int, Person_Relationship> _byPosition = ProtobufEnum.initByPosition(values);
+\tstatic final Map<String, Person_Relationship> _byName = ProtobufEnum.initByName(values);
+
+\tstatic Person_Relationship? valueOf(int position) => _byPosition[position];
+\tstatic Person_Relationship valueOfOrUnknown(int position) => _byPosition[position] ?? Person_Relationship._(position, '$position');
+\tstatic Person_Relationship? valueOfName(String name) => _byName[name];
+
+\tconst Person_Relationship._(int position, String name) : super(position, name);
+}
+"
+        );
+
+        let generator = DartCodeGenerator::new(
+            Box::new(ParserImpl::default()),
+            DartOptions {
+                omit_comments: true,
+                ..DartOptions::default()
+            },
+        );
+        let result = generator
+            .gen_code(include_str!("../../../test_data/doc_comments.proto").to_string())
+            .expect("unsuccessful codegen");
+
+        assert!(result.starts_with(
+            "import 'rs_proto.dart';\n\nclass Person extends ProtobufMessage {\n\tString name;\n"
+        ));
+        assert!(!result.contains("///"));
+    }
+
+    #[test]
+    fn test_without_null_safety() {
+        let options =
+            DartOptions::from_generator_options(&GeneratorOptions::parse("null_safety=false"))
+                .expect("failed to parse options");
+        assert_eq!(
+            options,
+            DartOptions {
+                null_safety: false,
+                ..DartOptions::default()
+            }
+        );
+
+        let generator = DartCodeGenerator::new(Box::new(ParserImpl::default()), options);
+        let result = generator
+            .gen_code(include_str!("../../../test_data/message.proto").to_string())
+            .expect("unsuccessful codegen");
+
+        // Fields keep their presence methods, but nothing is marked as nullable.
+        assert!(result.contains("\tString firstName;\n"));
+        assert!(result.contains("\tbool hasFirstName() => firstName != null;\n"));
+        assert!(result.contains("..writeString(firstName);\n"));
+        assert!(result.contains("\t\tString firstName,\n"));
+    }
+
+    #[test]
+    fn test_gen_file() {
+        let include_dir =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_data/imports");
+        let compiler = Compiler::new(
+            Box::new(ParserImpl::default()),
+            SourceTree::new(vec![include_dir]),
+        );
+
+        let compilation = compiler
+            .compile(&["a.proto"])
+            .expect("failed to compile a.proto");
+
+        let generator =
+            DartCodeGenerator::new(Box::new(ParserImpl::default()), DartOptions::default());
+
+        assert_eq!(
+            generator.gen_file(&compilation, "a.proto"),
+            Ok("import '../rs_proto.dart';
+import 'b.pb.dart';
+import '../other/c.pb.dart';
+
+class A extends ProtobufMessage {
+\tB? b;
+\tC? c;
+\tB? absoluteB;
+
+\tA({
+\t\tthis.b,
+\t\tthis.c,
+\t\tthis.absoluteB,
+\t});
+
+\tbool hasB() => b != null;
+\tvoid clearB() => b = null;
+
+\tbool hasC() => c != null;
+\tvoid clearC() => c = null;
+
+\tbool hasAbsoluteB() => absoluteB != null;
+\tvoid clearAbsoluteB() => absoluteB = null;
+
+\tstatic A fromBuffer(List<int> bytes) => A()..mergeFromBuffer(bytes);
+
+\t@override
+\tvoid writeToWriter(ProtobufWriter writer) {
+\t\tif (b != null) {
+\t\t\twriter..writeTag(10)..writeMessage(b!);
+\t\t}
+\t\tif (c != null) {
+\t\t\twriter..writeTag(18)..writeMessage(c!);
+\t\t}
+\t\tif (absoluteB != null) {
+\t\t\twriter..writeTag(26)..writeMessage(absoluteB!);
+\t\t}
+\t}
+
+\t@override
+\tvoid mergeFromReader(ProtobufReader reader) {
+\t\twhile (!reader.isAtEnd) {
+\t\t\tfinal tag = reader.readTag();
+\t\t\tswitch (tag) {
+\t\t\t\tcase 10:
+\t\t\t\t\treader.readMessage(b ??= B());
+\t\t\t\t\tbreak;
+\t\t\t\tcase 18:
+\t\t\t\t\treader.readMessage(c ??= C());
+\t\t\t\t\tbreak;
+\t\t\t\tcase 26:
+\t\t\t\t\treader.readMessage(absoluteB ??= B());
+\t\t\t\t\tbreak;
+\t\t\t\tdefault:
+\t\t\t\t\treader.skipField(tag);
+\t\t\t}
+\t\t}
+\t}
+
+\t@override
+\tbool operator ==(Object other) =>
+\t\t\tother is A &&
+\t\t\tprotobufEquals(b, other.b) &&
+\t\t\tprotobufEquals(c, other.c) &&
+\t\t\tprotobufEquals(absoluteB, other.absoluteB);
+
+\t@override
+\tint get hashCode => protobufHash([b, c, absoluteB]);
+
+\tA copyWith({
+\t\tB? b,
+\t\tC? c,
+\t\tB? absoluteB,
+\t}) {
+\t\treturn A(
+\t\t\tb: b ?? this.b,
+\t\t\tc: c ?? this.c,
+\t\t\tabsoluteB: absoluteB ?? this.absoluteB,
+\t\t);
+\t}
+
+\t@override
+\tString toString() =>
+\t\t\t'A('
+\t\t\t'b: $b, '
+\t\t\t'c: $c, '
+\t\t\t'absoluteB: $absoluteB'
+\t\t\t')';
+}
+"
+            .to_string())
+        );
+
+        // The runtime is imported relative to each generated file.
+        assert!(generator
+            .gen_file(&compilation, "nested/c.proto")
+            .unwrap()
+            .starts_with("import '../rs_proto.dart';\n\nclass C extends ProtobufMessage {\n"));
+
+        // Generated files are laid out by package rather than by where the .proto files are.
+        assert_eq!(
+            compilation
+                .files
+                .iter()
+                .map(|file| generator.output_file_name(file))
+                .collect::<Vec<String>>(),
+            vec!["other/c.pb.dart", "app/b.pb.dart", "app/a.pb.dart"]
+        );
+        assert_eq!(
+            DartCodeGenerator::relative_import("app/a.pb.dart", "other/c.pb.dart"),
+            "../other/c.pb.dart"
+        );
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let generator =
+            DartCodeGenerator::new(Box::new(ParserImpl::default()), DartOptions::default());
+
+        let result = generator.gen_code(
+            "syntax = \"proto3\";\nmessage Foo {\n    int32 a = 1;\n    int32 b = 1;\n}"
+                .to_string(),
+        );
+
+        assert!(matches!(result, Err(GeneratorError::Invalid(_))));
+        assert_eq!(
+            result.map_err(|err| err.in_file("foo.proto")),
+            Err(
+                "foo.proto:4:5: error: Field number 1 has already been used in \"Foo\" by field \"a\"."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_resolution_errors() {
+        let generator =
+            DartCodeGenerator::new(Box::new(ParserImpl::default()), DartOptions::default());
+
+        let result = generator.gen_code(
+            "syntax = \"proto3\";\npackage app;\nmessage Foo {\n    message Bar {}\n    Baz baz = 1;\n}"
+                .to_string(),
+        );
+
+        assert!(matches!(result, Err(GeneratorError::Resolution(_))));
+        assert_eq!(
+            result.map_err(|err| err.in_file("foo.proto")),
+            Err("foo.proto:5:5: Unable to resolve type 'Baz' from scope 'app.Foo'. Did you mean 'app.Foo.Bar'?".to_string())
+        );
+
+        // Map values are reported at the map field.
+        let result = generator.gen_code(
+            "syntax = \"proto3\";\nmessage Foo {\n    map<string, Missing> values = 1;\n}"
+                .to_string(),
+        );
+
+        assert_eq!(
+            result.map_err(|err| err.to_string()),
+            Err("3:5: Unable to resolve type 'Missing' from scope 'Foo'".to_string())
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use super::ONEOF_NOT_SET_CASE;
+use crate::parser::{ProtoEnumValue, ProtoMessage};
+use crate::utils::{camel_case, pascal_case, CasedString};
+
+use std::collections::HashMap;
+
+// Dart's reserved words, which can't be used as identifiers at all.
+const RESERVED_WORDS: &[&str] = &[
+    "assert", "break", "case", "catch", "class", "const", "continue", "default", "do", "else",
+    "enum", "extends", "false", "final", "finally", "for", "if", "in", "is", "new", "null",
+    "rethrow", "return", "super", "switch", "this", "throw", "true", "try", "var", "void", "while",
+    "with",
+];
+
+// Members of every Dart object, which a class can't declare static members with the same name as.
+const OBJECT_MEMBERS: &[&str] = &["hashCode", "noSuchMethod", "runtimeType", "toString"];
+
+// Members of generated enums and the runtime's ProtobufEnum.
+const ENUM_MEMBERS: &[&str] = &[
+    "values",
+    "valueOf",
+    "valueOfOrUnknown",
+    "valueOfName",
+    "position",
+    "name",
+];
+
+// Members of generated messages and the runtime's ProtobufMessage.
+const MESSAGE_MEMBERS: &[&str] = &[
+    "copyWith",
+    "fromBuffer",
+    "mergeFromBuffer",
+    "mergeFromReader",
+    "writeToBuffer",
+    "writeToWriter",
+];
+
+// Members of the Dart enums generated for oneof cases, which are named after the oneof's fields.
+const ONEOF_CASE_MEMBERS: &[&str] = &["index", "values", ONEOF_NOT_SET_CASE];
+
+// The names of the members generated for a field: the field itself and its hasX and clearX methods.
+pub(super) struct FieldName {
+    pub(super) name: String,
+    pub(super) pascal_name: String,
+}
+
+// The names of a message's fields and oneof members, by field number. Like protoc-gen-dart, a field whose members
+// would be reserved or clash with an earlier field's gets a "_<number>" suffix on all of them.
+pub(super) fn field_names(message: &ProtoMessage) -> HashMap<u32, FieldName> {
+    let mut taken = message
+        .oneofs
+        .iter()
+        .flat_map(|oneof| {
+            vec![
+                format!("{}Case", camel_case(CasedString::SnakeCase(&oneof.name))),
+                format!("clear{}", pascal_case(CasedString::SnakeCase(&oneof.name))),
+            ]
+        })
+        .collect::<Vec<String>>();
+    let mut result = HashMap::new();
+
+    let oneof_fields = message.oneofs.iter().flat_map(|oneof| &oneof.fields);
+    let no_members: &[&str] = &[];
+    for (field, case_members) in message
+        .fields
+        .iter()
+        .map(|field| (field, no_members))
+        .chain(oneof_fields.map(|field| (field, ONEOF_CASE_MEMBERS)))
+    {
+        let mut name = camel_case(CasedString::SnakeCase(&field.name));
+        let mut pascal_name = pascal_case(CasedString::SnakeCase(&field.name));
+
+        let generated = [
+            name.clone(),
+            format!("has{}", pascal_name),
+            format!("clear{}", pascal_name),
+        ];
+        if is_reserved(&name, MESSAGE_MEMBERS)
+            || case_members.contains(&name.as_str())
+            || generated.iter().any(|member| taken.contains(member))
+        {
+            name = format!("{}_{}", name, field.position);
+            pascal_name = format!("{}_{}", pascal_name, field.position);
+        }
+
+        taken.push(name.clone());
+        taken.push(format!("has{}", pascal_name));
+        taken.push(format!("clear{}", pascal_name));
+        result.insert(field.position, FieldName { name, pascal_name });
+    }
+
+    result
+}
+
+// The names of the constants for an enum's values. Like protoc-gen-dart, a name that's reserved or already taken by an
+// earlier value gets a "_" suffix until it's free.
+pub(super) fn enum_value_names(values: &[ProtoEnumValue]) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+
+    for value in values {
+        let mut name = camel_case(CasedString::ScreamingSnakeCase(&value.name));
+        while is_reserved(&name, ENUM_MEMBERS) || result.contains(&name) {
+            name.push('_');
+        }
+
+        result.push(name);
+    }
+
+    result
+}
+
+fn is_reserved(name: &str, members: &[&str]) -> bool {
+    RESERVED_WORDS.contains(&name) || OBJECT_MEMBERS.contains(&name) || members.contains(&name)
+}
@@ -0,0 +1,527 @@
+use super::names::{enum_value_names, field_names};
+use super::DartCodeGenerator;
+use crate::code_gen::env::*;
+use crate::code_gen::GeneratorError;
+use crate::parser::*;
+use crate::utils::{camel_case, CasedString};
+
+// A field as the constructor and the value methods (==, hashCode, copyWith and toString) see it.
+struct ValueField {
+    name: String,
+    // The type of the field's constructor and copyWith parameter, which can always be left out.
+    dart_type: String,
+    kind: ValueKind,
+}
+
+enum ValueKind {
+    // A singular field, with the value it takes when it isn't given if it has one.
+    Singular(Option<String>),
+    // A repeated or map field, which the message keeps its own copy of.
+    Collection,
+    // A oneof member or a field with a declared default, which is set through its setter and kept in a private field.
+    Backed,
+}
+
+// The message's fields and oneofs, in declaration order.
+struct ValueFields {
+    fields: Vec<ValueField>,
+    // The private fields behind the fields with a declared default, and behind each oneof, the case first and then one
+    // for each member. They're compared and copied rather than the public members.
+    backing_fields: Vec<Vec<String>>,
+}
+
+impl DartCodeGenerator {
+    // A constructor with a named parameter for every field. proto3 scalars default to their zero values.
+    pub(super) fn gen_constructor(
+        &self,
+        message_name: &str,
+        message: &ProtoMessage,
+        env: &mut GeneratorEnvironment,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let ValueFields { fields, .. } = self.value_fields(message, env)?;
+
+        let indentation = "\t".repeat(indent);
+        let inner_indentation = "\t".repeat(indent + 1);
+
+        if fields.is_empty() {
+            return Ok(format!("{}{}();\n", indentation, message_name));
+        }
+
+        let mut result = vec![format!("{}{}({{\n", indentation, message_name)];
+        let mut body = vec![];
+        for field in &fields {
+            match &field.kind {
+                ValueKind::Singular(Some(default)) => result.push(format!(
+                    "{}this.{} = {},\n",
+                    inner_indentation, field.name, default
+                )),
+                ValueKind::Singular(None) => {
+                    result.push(format!("{}this.{},\n", inner_indentation, field.name))
+                }
+                ValueKind::Collection => {
+                    result.push(format!(
+                        "{}{} {},\n",
+                        inner_indentation, field.dart_type, field.name
+                    ));
+                    body.push(Self::gen_if_given(
+                        &field.name,
+                        &format!("this.{}.addAll({});", field.name, field.name),
+                        indent + 1,
+                    ));
+                }
+                ValueKind::Backed => {
+                    result.push(format!(
+                        "{}{} {},\n",
+                        inner_indentation, field.dart_type, field.name
+                    ));
+                    body.push(Self::gen_if_given(
+                        &field.name,
+                        &format!("this.{} = {};", field.name, field.name),
+                        indent + 1,
+                    ));
+                }
+            }
+        }
+
+        if body.is_empty() {
+            result.push(format!("{}}});\n", indentation));
+        } else {
+            result.push(format!("{}}}) {{\n", indentation));
+            result.extend(body);
+            result.push(format!("{}}}\n", indentation));
+        }
+
+        Ok(result.join(""))
+    }
+
+    // ==, hashCode, copyWith and toString, which treat the message as a value. Repeated and map fields are compared
+    // and hashed by their contents.
+    pub(super) fn gen_value_methods(
+        &self,
+        message_name: &str,
+        message: &ProtoMessage,
+        env: &mut GeneratorEnvironment,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let ValueFields {
+            fields,
+            backing_fields,
+        } = self.value_fields(message, env)?;
+
+        let indentation = "\t".repeat(indent);
+        let continuation_indentation = "\t".repeat(indent + 2);
+
+        // Oneofs are compared by their backing fields, so that the case counts too, and fields with declared defaults are
+        // too, so that whether they're set counts.
+        let compared = fields
+            .iter()
+            .filter(|field| !matches!(field.kind, ValueKind::Backed))
+            .map(|field| field.name.clone())
+            .chain(backing_fields.iter().flatten().cloned())
+            .collect::<Vec<String>>();
+
+        let mut result = vec![];
+
+        result.push(format!(
+            "{}@override\n{}bool operator ==(Object other) =>\n{}other is {}",
+            indentation, indentation, continuation_indentation, message_name
+        ));
+        for name in &compared {
+            result.push(format!(
+                " &&\n{}protobufEquals({}, other.{})",
+                continuation_indentation, name, name
+            ));
+        }
+        result.push(";\n".to_string());
+
+        result.push(format!(
+            "\n{}@override\n{}int get hashCode => protobufHash([{}]);\n",
+            indentation,
+            indentation,
+            compared.join(", ")
+        ));
+
+        result.push("\n".to_string());
+        result.push(Self::gen_copy_with(
+            message_name,
+            &fields,
+            &backing_fields,
+            indent,
+        ));
+
+        result.push(format!(
+            "\n{}@override\n{}String toString() =>\n",
+            indentation, indentation
+        ));
+        let described = fields
+            .iter()
+            .map(|field| format!("{}: ${}", field.name, field.name))
+            .collect::<Vec<String>>();
+        if described.is_empty() {
+            result.push(format!(
+                "{}'{}()';\n",
+                continuation_indentation, message_name
+            ));
+        } else {
+            result.push(format!("{}'{}('\n", continuation_indentation, message_name));
+            for (i, description) in described.iter().enumerate() {
+                let separator = if i + 1 < described.len() { ", " } else { "" };
+                result.push(format!(
+                    "{}'{}{}'\n",
+                    continuation_indentation, description, separator
+                ));
+            }
+            result.push(format!("{}')';\n", continuation_indentation));
+        }
+
+        Ok(result.join(""))
+    }
+
+    // Fields that aren't given keep their current values. Giving a oneof member replaces whichever member is set.
+    fn gen_copy_with(
+        message_name: &str,
+        fields: &[ValueField],
+        backing_fields: &[Vec<String>],
+        indent: usize,
+    ) -> String {
+        let indentation = "\t".repeat(indent);
+        let inner_indentation = "\t".repeat(indent + 1);
+        let argument_indentation = "\t".repeat(indent + 2);
+
+        if fields.is_empty() {
+            return format!(
+                "{}{} copyWith() => {}();\n",
+                indentation, message_name, message_name
+            );
+        }
+
+        let mut result = vec![format!("{}{} copyWith({{\n", indentation, message_name)];
+        for field in fields {
+            result.push(format!(
+                "{}{} {},\n",
+                inner_indentation, field.dart_type, field.name
+            ));
+        }
+        result.push(format!("{}}}) {{\n", indentation));
+
+        let arguments = fields
+            .iter()
+            .filter(|field| !matches!(field.kind, ValueKind::Backed))
+            .map(|field| {
+                format!(
+                    "{}{}: {} ?? this.{},\n",
+                    argument_indentation, field.name, field.name, field.name
+                )
+            })
+            .collect::<Vec<String>>();
+        let constructed = if arguments.is_empty() {
+            format!("{}()", message_name)
+        } else {
+            format!(
+                "{}(\n{}{})",
+                message_name,
+                arguments.join(""),
+                inner_indentation
+            )
+        };
+
+        if backing_fields.is_empty() {
+            result.push(format!("{}return {};\n", inner_indentation, constructed));
+            result.push(format!("{}}}\n", indentation));
+
+            return result.join("");
+        }
+
+        result.push(format!(
+            "{}final copy = {};\n",
+            inner_indentation, constructed
+        ));
+        for group in backing_fields {
+            result.push(format!("{}copy", inner_indentation));
+            for backing_field in group {
+                result.push(format!(
+                    "\n{}..{} = {}",
+                    argument_indentation, backing_field, backing_field
+                ));
+            }
+            result.push(";\n".to_string());
+        }
+        for field in fields
+            .iter()
+            .filter(|field| matches!(field.kind, ValueKind::Backed))
+        {
+            result.push(Self::gen_if_given(
+                &field.name,
+                &format!("copy.{} = {};", field.name, field.name),
+                indent + 1,
+            ));
+        }
+        result.push(format!("{}return copy;\n", inner_indentation));
+        result.push(format!("{}}}\n", indentation));
+
+        result.join("")
+    }
+
+    fn gen_if_given(name: &str, statement: &str, indent: usize) -> String {
+        let indentation = "\t".repeat(indent);
+
+        format!(
+            "{}if ({} != null) {{\n{}\t{}\n{}}}\n",
+            indentation, name, indentation, statement, indentation
+        )
+    }
+
+    fn value_fields(
+        &self,
+        message: &ProtoMessage,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<ValueFields, GeneratorError> {
+        let field_names = field_names(message);
+
+        let mut fields = vec![];
+        let mut default_backing_fields = vec![];
+        for field in &message.fields {
+            let name = field_names[&field.position].name.clone();
+            let dart_type = Self::get_dart_type(&field.field_type, field.span, env)?;
+
+            let (dart_type, kind) = if field.modifier == Some(ProtoMessageFieldModifier::Repeated) {
+                (format!("List<{}>", dart_type), ValueKind::Collection)
+            } else if Self::is_collection(field) {
+                (dart_type, ValueKind::Collection)
+            } else if Self::declared_default(field, env)?.is_some() {
+                default_backing_fields.push(format!("_{}", name));
+                (dart_type, ValueKind::Backed)
+            } else if Self::tracks_presence(field, env)? {
+                (dart_type, ValueKind::Singular(None))
+            } else {
+                let default = Self::default_value(&field.field_type, field.span, env)?;
+                (dart_type, ValueKind::Singular(default))
+            };
+
+            fields.push(ValueField {
+                name,
+                dart_type: self.nullable(&dart_type),
+                kind,
+            });
+        }
+
+        let mut backing_fields = vec![];
+        if !default_backing_fields.is_empty() {
+            backing_fields.push(default_backing_fields);
+        }
+        for oneof in &message.oneofs {
+            let mut oneof_backing_fields = vec![format!(
+                "_{}Case",
+                camel_case(CasedString::SnakeCase(&oneof.name))
+            )];
+
+            for field in &oneof.fields {
+                let name = field_names[&field.position].name.clone();
+                oneof_backing_fields.push(format!("_{}", name));
+
+                fields.push(ValueField {
+                    name,
+                    dart_type: self.nullable(&Self::get_dart_type(
+                        &field.field_type,
+                        field.span,
+                        env,
+                    )?),
+                    kind: ValueKind::Backed,
+                });
+            }
+
+            backing_fields.push(oneof_backing_fields);
+        }
+
+        Ok(ValueFields {
+            fields,
+            backing_fields,
+        })
+    }
+
+    // The zero value of a proto3 scalar or enum, as a constant. Messages have no default.
+    fn default_value(
+        field_type: &ProtoFieldType,
+        span: Option<Span>,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<Option<String>, GeneratorError> {
+        let primitive = match field_type {
+            ProtoFieldType::Primitive(primitive) => primitive,
+            ProtoFieldType::IdentifierPath(path) => {
+                let node = env.resolve_type_node(path, span)?;
+                let node = node.borrow();
+
+                // proto3 enums have to start with their zero value.
+                return Ok(match node.proto_type.as_deref() {
+                    Some(ProtoType::Enum(enumeration)) => enum_value_names(&enumeration.values)
+                        .first()
+                        .map(|value_name| {
+                            format!(
+                                "{}.{}",
+                                node.fully_qualified_identifier
+                                    .as_deref()
+                                    .expect("expected fully qualified identifier on non-root node"),
+                                value_name
+                            )
+                        }),
+                    _ => None,
+                });
+            }
+        };
+
+        Ok(Some(
+            match primitive {
+                ProtoPrimitiveType::Double | ProtoPrimitiveType::Float => "0.0",
+                ProtoPrimitiveType::Int64
+                | ProtoPrimitiveType::UInt64
+                | ProtoPrimitiveType::SInt64
+                | ProtoPrimitiveType::Fixed64
+                | ProtoPrimitiveType::SFixed64 => "Int64.ZERO",
+                ProtoPrimitiveType::Boolean => "false",
+                ProtoPrimitiveType::Str => "''",
+                ProtoPrimitiveType::Bytes => "const <int>[]",
+                ProtoPrimitiveType::Map(_, _) => return Ok(None),
+                _ => "0",
+            }
+            .to_string(),
+        ))
+    }
+
+    // The value a field with a proto2 [default = ...] option reads as while it isn't set. Only singular scalars and
+    // enums can have one.
+    pub(super) fn declared_default(
+        field: &ProtoMessageField,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<Option<String>, GeneratorError> {
+        let constant = match field.default_value() {
+            Some(constant) if !Self::is_collection(field) => constant,
+            _ => return Ok(None),
+        };
+
+        let primitive = match &field.field_type {
+            ProtoFieldType::Primitive(primitive) => primitive,
+            ProtoFieldType::IdentifierPath(path) => {
+                let node = env.resolve_type_node(path, field.span)?;
+                let node = node.borrow();
+
+                let (enumeration, value) = match (node.proto_type.as_deref(), constant) {
+                    (Some(ProtoType::Enum(enumeration)), ProtoConstant::Identifier(value)) => {
+                        (enumeration, value)
+                    }
+                    _ => return Ok(None),
+                };
+
+                return Ok(enumeration
+                    .values
+                    .iter()
+                    .position(|enum_value| &enum_value.name == value)
+                    .map(|index| {
+                        format!(
+                            "{}.{}",
+                            node.fully_qualified_identifier
+                                .as_deref()
+                                .expect("expected fully qualified identifier on non-root node"),
+                            enum_value_names(&enumeration.values)[index]
+                        )
+                    }));
+            }
+        };
+
+        Ok(match (primitive, constant) {
+            (ProtoPrimitiveType::Double, ProtoConstant::Float(value)) => Some(dart_double(*value)),
+            // Float fields only hold what fits in 32 bits.
+            (ProtoPrimitiveType::Float, ProtoConstant::Float(value)) => {
+                Some(dart_double(f64::from(*value as f32)))
+            }
+            (ProtoPrimitiveType::Double | ProtoPrimitiveType::Float, ProtoConstant::Int(value)) => {
+                Some(dart_double(*value as f64))
+            }
+            (
+                ProtoPrimitiveType::Double | ProtoPrimitiveType::Float,
+                ProtoConstant::UInt(value),
+            ) => Some(dart_double(*value as f64)),
+            (
+                ProtoPrimitiveType::Int64
+                | ProtoPrimitiveType::UInt64
+                | ProtoPrimitiveType::SInt64
+                | ProtoPrimitiveType::Fixed64
+                | ProtoPrimitiveType::SFixed64,
+                ProtoConstant::Int(value),
+            ) => Some(dart_int64(*value)),
+            // uint64s above the int64 range keep their bits.
+            (
+                ProtoPrimitiveType::Int64
+                | ProtoPrimitiveType::UInt64
+                | ProtoPrimitiveType::SInt64
+                | ProtoPrimitiveType::Fixed64
+                | ProtoPrimitiveType::SFixed64,
+                ProtoConstant::UInt(value),
+            ) => Some(dart_int64(*value as i64)),
+            (ProtoPrimitiveType::Boolean, ProtoConstant::Boolean(value)) => Some(value.to_string()),
+            (ProtoPrimitiveType::Str, ProtoConstant::Str(value)) => Some(dart_string(value)),
+            (ProtoPrimitiveType::Bytes, ProtoConstant::Str(_) | ProtoConstant::Bytes(_)) => {
+                Some(format!(
+                    "const <int>[{}]",
+                    constant
+                        .as_bytes()
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|byte| byte.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ))
+            }
+            (ProtoPrimitiveType::Map(_, _), _) => None,
+            (_, ProtoConstant::Int(value)) => Some(value.to_string()),
+            (_, ProtoConstant::UInt(value)) => Some(value.to_string()),
+            _ => None,
+        })
+    }
+}
+
+fn dart_double(value: f64) -> String {
+    if value.is_nan() {
+        "double.nan".to_string()
+    } else if value.is_infinite() {
+        format!("{}double.infinity", if value < 0.0 { "-" } else { "" })
+    } else {
+        // Debug formatting always keeps a decimal point or exponent, so the literal is a double.
+        format!("{:?}", value)
+    }
+}
+
+// Dart ints lose precision past 2^53 when compiled to JavaScript, so larger values are given by their halves.
+fn dart_int64(value: i64) -> String {
+    const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+    if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&value) {
+        format!("Int64({})", value)
+    } else {
+        format!(
+            "Int64.fromInts(0x{:08x}, 0x{:08x})",
+            (value as u64) >> 32,
+            value as u64 & 0xffff_ffff
+        )
+    }
+}
+
+fn dart_string(value: &str) -> String {
+    let mut result = String::from("'");
+
+    for ch in value.chars() {
+        match ch {
+            '\\' => result.push_str("\\\\"),
+            '\'' => result.push_str("\\'"),
+            '$' => result.push_str("\\$"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            ch if ch.is_control() => result.push_str(&format!("\\u{{{:x}}}", ch as u32)),
+            ch => result.push(ch),
+        }
+    }
+
+    result.push('\'');
+    result
+}
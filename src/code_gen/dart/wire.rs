@@ -0,0 +1,627 @@
+use super::names::field_names;
+use super::DartCodeGenerator;
+use crate::code_gen::env::*;
+use crate::code_gen::GeneratorError;
+use crate::parser::*;
+use crate::utils::{camel_case, CasedString};
+
+// Wire types, the low three bits of a field's tag.
+const WIRE_VARINT: u32 = 0;
+const WIRE_FIXED64: u32 = 1;
+const WIRE_LENGTH_DELIMITED: u32 = 2;
+const WIRE_FIXED32: u32 = 5;
+
+// Map entries are messages with the key as field 1 and the value as field 2.
+const MAP_KEY_FIELD: u32 = 1;
+const MAP_VALUE_FIELD: u32 = 2;
+
+// How a single value is read and written, by the suffix of the runtime's methods for it, e.g. "Sint32" for
+// writeSint32/readSint32. Enums and messages carry their generated class name.
+enum WireValue {
+    Scalar(&'static str, u32),
+    Enum(String),
+    Message(String),
+}
+
+enum WireKind {
+    Singular(WireValue),
+    Repeated { value: WireValue, packed: bool },
+    Map { key: WireValue, value: WireValue },
+}
+
+enum Presence {
+    // proto3 fields without a label are only written when they don't hold their default value.
+    Implicit,
+    Explicit,
+    // The condition under which a oneof member is set.
+    Oneof(String),
+}
+
+struct WireField {
+    number: u32,
+    // The field's (public) name.
+    name: String,
+    // The expression holding the field's value, which is the private backing field for oneof members and fields with a
+    // declared default.
+    value: String,
+    kind: WireKind,
+    presence: Presence,
+}
+
+impl WireValue {
+    fn wire_type(&self) -> u32 {
+        match self {
+            WireValue::Scalar(_, wire_type) => *wire_type,
+            WireValue::Enum(_) => WIRE_VARINT,
+            WireValue::Message(_) => WIRE_LENGTH_DELIMITED,
+        }
+    }
+
+    fn write_method(&self) -> String {
+        match self {
+            WireValue::Scalar(method, _) => format!("write{}", method),
+            WireValue::Enum(_) => "writeEnum".to_string(),
+            WireValue::Message(_) => "writeMessage".to_string(),
+        }
+    }
+
+    fn read(&self, reader: &str) -> String {
+        match self {
+            WireValue::Scalar(method, _) => format!("{}.read{}()", reader, method),
+            WireValue::Enum(name) => format!("{}.readEnum({}.valueOfOrUnknown)", reader, name),
+            WireValue::Message(name) => format!("{}.readMessage({}())", reader, name),
+        }
+    }
+
+    // Only scalars that aren't length-delimited themselves can be packed.
+    fn is_packable(&self) -> bool {
+        self.wire_type() != WIRE_LENGTH_DELIMITED
+    }
+
+    // The value a missing map key or value takes.
+    fn default_value(&self) -> String {
+        match self {
+            WireValue::Scalar(method, _) => match *method {
+                "Double" | "Float" => "0.0",
+                "Int64" | "Uint64" | "Sint64" | "Fixed64" | "Sfixed64" => "Int64.ZERO",
+                "Bool" => "false",
+                "String" => "''",
+                "Bytes" => "<int>[]",
+                _ => "0",
+            }
+            .to_string(),
+            WireValue::Enum(name) => format!("{}.values.first", name),
+            WireValue::Message(name) => format!("{}()", name),
+        }
+    }
+
+    // The condition under which a field with implicit presence is written. Only code without null safety has to allow
+    // for the field being null.
+    fn is_set(&self, value: &str, null_safety: bool) -> String {
+        if null_safety {
+            return match self {
+                WireValue::Scalar(method, _) => match *method {
+                    "Int64" | "Uint64" | "Sint64" | "Fixed64" | "Sfixed64" => {
+                        format!("!{}.isZero", value)
+                    }
+                    "Bool" => value.to_string(),
+                    "String" | "Bytes" => format!("{}.isNotEmpty", value),
+                    // -0.0 == 0 in Dart, but it isn't the default value, since its bits differ.
+                    "Double" | "Float" => format!("{} != 0 || {}.isNegative", value, value),
+                    _ => format!("{} != 0", value),
+                },
+                WireValue::Enum(_) => format!("{}.position != 0", value),
+                WireValue::Message(_) => format!("{} != null", value),
+            };
+        }
+
+        match self {
+            WireValue::Scalar(method, _) => match *method {
+                "Int64" | "Uint64" | "Sint64" | "Fixed64" | "Sfixed64" => {
+                    format!("{} != null && !{}.isZero", value, value)
+                }
+                "Bool" => format!("{} == true", value),
+                "String" | "Bytes" => format!("{} != null && {}.isNotEmpty", value, value),
+                "Double" | "Float" => format!(
+                    "{} != null && ({} != 0 || {}.isNegative)",
+                    value, value, value
+                ),
+                _ => format!("{} != null && {} != 0", value, value),
+            },
+            WireValue::Enum(_) => format!("{} != null && {}.position != 0", value, value),
+            WireValue::Message(_) => format!("{} != null", value),
+        }
+    }
+}
+
+fn tag(number: u32, wire_type: u32) -> u32 {
+    number << 3 | wire_type
+}
+
+impl DartCodeGenerator {
+    // fromBuffer, writeToWriter and mergeFromReader, which read and write the message in the binary wire format
+    // using the runtime's ProtobufReader and ProtobufWriter.
+    pub(super) fn gen_serialization(
+        &self,
+        message_name: &str,
+        message: &ProtoMessage,
+        env: &mut GeneratorEnvironment,
+        indent: usize,
+    ) -> Result<String, GeneratorError> {
+        let fields = Self::wire_fields(message_name, message, env)?;
+
+        let indentation = "\t".repeat(indent);
+        let inner_indentation = "\t".repeat(indent + 1);
+        let case_indentation = "\t".repeat(indent + 3);
+        let statement_indentation = "\t".repeat(indent + 4);
+
+        let mut result = vec![];
+
+        result.push(format!(
+            "{}static {} fromBuffer(List<int> bytes) => {}()..mergeFromBuffer(bytes);\n",
+            indentation, message_name, message_name
+        ));
+
+        result.push(format!(
+            "\n{}@override\n{}void writeToWriter(ProtobufWriter writer) {{\n",
+            indentation, indentation
+        ));
+        for field in &fields {
+            result.push(self.gen_write_field(field, indent + 1));
+        }
+        result.push(format!("{}}}\n", indentation));
+
+        result.push(format!(
+            "\n{}@override\n{}void mergeFromReader(ProtobufReader reader) {{\n",
+            indentation, indentation
+        ));
+        result.push(format!("{}while (!reader.isAtEnd) {{\n", inner_indentation));
+        result.push(format!(
+            "{}\tfinal tag = reader.readTag();\n{}\tswitch (tag) {{\n",
+            inner_indentation, inner_indentation
+        ));
+        for field in &fields {
+            for (tag, statement) in Self::gen_read_field(field) {
+                result.push(format!(
+                    "{}case {}:\n{}{};\n{}break;\n",
+                    case_indentation, tag, statement_indentation, statement, statement_indentation
+                ));
+            }
+        }
+        result.push(format!(
+            "{}default:\n{}reader.skipField(tag);\n",
+            case_indentation, statement_indentation
+        ));
+        result.push(format!(
+            "{}\t}}\n{}}}\n{}}}\n",
+            inner_indentation, inner_indentation, indentation
+        ));
+
+        Ok(result.join(""))
+    }
+
+    // The message's fields and oneof members in field number order, which is the order they're written in.
+    fn wire_fields(
+        message_name: &str,
+        message: &ProtoMessage,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<Vec<WireField>, GeneratorError> {
+        let is_proto3 = env.program().syntax == Some(ProtoSyntax::Proto3);
+
+        let field_names = field_names(message);
+
+        let mut fields = vec![];
+        for field in &message.fields {
+            let name = field_names[&field.position].name.clone();
+            let kind = Self::wire_kind(field, is_proto3, env)?;
+            let presence = match kind {
+                WireKind::Singular(_) if Self::tracks_presence(field, env)? => Presence::Explicit,
+                _ => Presence::Implicit,
+            };
+            // Fields with a declared default are only written when they're set, not when they read as the default.
+            let value = match Self::declared_default(field, env)? {
+                Some(_) => format!("_{}", name),
+                None => name.clone(),
+            };
+
+            fields.push(WireField {
+                number: field.position,
+                value,
+                name,
+                kind,
+                presence,
+            });
+        }
+
+        for oneof in &message.oneofs {
+            let case_enum_name = Self::get_oneof_case_enum_name(message_name, oneof);
+            let case_field_name =
+                format!("_{}Case", camel_case(CasedString::SnakeCase(&oneof.name)));
+
+            for field in &oneof.fields {
+                let name = field_names[&field.position].name.clone();
+
+                fields.push(WireField {
+                    number: field.position,
+                    value: format!("_{}", name),
+                    kind: Self::wire_kind(field, is_proto3, env)?,
+                    presence: Presence::Oneof(format!(
+                        "{} == {}.{}",
+                        case_field_name, case_enum_name, name
+                    )),
+                    name,
+                });
+            }
+        }
+
+        fields.sort_by_key(|field| field.number);
+
+        Ok(fields)
+    }
+
+    fn wire_kind(
+        field: &ProtoMessageField,
+        is_proto3: bool,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<WireKind, GeneratorError> {
+        if let ProtoFieldType::Primitive(ProtoPrimitiveType::Map(key, value)) = &field.field_type {
+            return Ok(WireKind::Map {
+                key: Self::wire_value(key, field.span, env)?,
+                value: Self::wire_value(value, field.span, env)?,
+            });
+        }
+
+        let value = Self::wire_value(&field.field_type, field.span, env)?;
+        if field.modifier != Some(ProtoMessageFieldModifier::Repeated) {
+            return Ok(WireKind::Singular(value));
+        }
+
+        // Repeated scalars are packed by default in proto3, but only when asked for in proto2.
+        let packed = value.is_packable()
+            && match field.packed() {
+                Some(packed) => packed,
+                None => is_proto3,
+            };
+
+        Ok(WireKind::Repeated { value, packed })
+    }
+
+    fn wire_value(
+        field_type: &ProtoFieldType,
+        span: Option<Span>,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<WireValue, GeneratorError> {
+        let primitive = match field_type {
+            ProtoFieldType::Primitive(primitive) => primitive,
+            ProtoFieldType::IdentifierPath(path) => {
+                let node = env.resolve_type_node(path, span)?;
+                let node = node.borrow();
+
+                let name = node
+                    .fully_qualified_identifier
+                    .clone()
+                    .expect("expected fully qualified identifier on non-root node");
+
+                return Ok(match node.proto_type.as_deref() {
+                    Some(ProtoType::Enum(_)) => WireValue::Enum(name),
+                    _ => WireValue::Message(name),
+                });
+            }
+        };
+
+        let (method, wire_type) = match primitive {
+            ProtoPrimitiveType::Double => ("Double", WIRE_FIXED64),
+            ProtoPrimitiveType::Float => ("Float", WIRE_FIXED32),
+            ProtoPrimitiveType::Int32 => ("Int32", WIRE_VARINT),
+            ProtoPrimitiveType::Int64 => ("Int64", WIRE_VARINT),
+            ProtoPrimitiveType::UInt32 => ("Uint32", WIRE_VARINT),
+            ProtoPrimitiveType::UInt64 => ("Uint64", WIRE_VARINT),
+            ProtoPrimitiveType::SInt32 => ("Sint32", WIRE_VARINT),
+            ProtoPrimitiveType::SInt64 => ("Sint64", WIRE_VARINT),
+            ProtoPrimitiveType::Fixed32 => ("Fixed32", WIRE_FIXED32),
+            ProtoPrimitiveType::Fixed64 => ("Fixed64", WIRE_FIXED64),
+            ProtoPrimitiveType::SFixed32 => ("Sfixed32", WIRE_FIXED32),
+            ProtoPrimitiveType::SFixed64 => ("Sfixed64", WIRE_FIXED64),
+            ProtoPrimitiveType::Boolean => ("Bool", WIRE_VARINT),
+            ProtoPrimitiveType::Str => ("String", WIRE_LENGTH_DELIMITED),
+            ProtoPrimitiveType::Bytes => ("Bytes", WIRE_LENGTH_DELIMITED),
+            ProtoPrimitiveType::Map(_, _) => {
+                return Err(GeneratorError::Unsupported(
+                    "Map keys and values can't be maps".to_string(),
+                ))
+            }
+        };
+
+        Ok(WireValue::Scalar(method, wire_type))
+    }
+
+    fn gen_write_field(&self, field: &WireField, indent: usize) -> String {
+        let indentation = "\t".repeat(indent);
+        let inner_indentation = "\t".repeat(indent + 1);
+
+        let (opening, statement) = match &field.kind {
+            WireKind::Singular(value) => {
+                let condition = match &field.presence {
+                    Presence::Implicit => value.is_set(&field.value, self.options.null_safety),
+                    Presence::Explicit => format!("{} != null", field.value),
+                    Presence::Oneof(condition) => condition.clone(),
+                };
+                // Checking a field doesn't make it non-null as far as Dart's type system is concerned.
+                let written = match field.presence {
+                    Presence::Implicit => field.value.clone(),
+                    _ if self.options.null_safety => format!("{}!", field.value),
+                    _ => field.value.clone(),
+                };
+
+                (
+                    format!("if ({})", condition),
+                    format!(
+                        "writer..writeTag({})..{}({});",
+                        tag(field.number, value.wire_type()),
+                        value.write_method(),
+                        written
+                    ),
+                )
+            }
+            WireKind::Repeated {
+                value,
+                packed: true,
+            } => (
+                format!("if ({}.isNotEmpty)", field.value),
+                format!(
+                    "writer..writeTag({})..writePacked({}, (writer, value) => writer.{}(value));",
+                    tag(field.number, WIRE_LENGTH_DELIMITED),
+                    field.value,
+                    value.write_method()
+                ),
+            ),
+            WireKind::Repeated { value, .. } => (
+                format!("for (final value in {})", field.value),
+                format!(
+                    "writer..writeTag({})..{}(value);",
+                    tag(field.number, value.wire_type()),
+                    value.write_method()
+                ),
+            ),
+            WireKind::Map { key, value } => {
+                return format!(
+                    "{}{}.forEach((key, value) {{\n{}writer..writeTag({})..writeLengthDelimited((entry) => entry..writeTag({})..{}(key)..writeTag({})..{}(value));\n{}}});\n",
+                    indentation,
+                    field.value,
+                    inner_indentation,
+                    tag(field.number, WIRE_LENGTH_DELIMITED),
+                    tag(MAP_KEY_FIELD, key.wire_type()),
+                    key.write_method(),
+                    tag(MAP_VALUE_FIELD, value.wire_type()),
+                    value.write_method(),
+                    indentation
+                );
+            }
+        };
+
+        format!(
+            "{}{} {{\n{}{}\n{}}}\n",
+            indentation, opening, inner_indentation, statement, indentation
+        )
+    }
+
+    // The statements that read a field, for each tag it can be read from.
+    fn gen_read_field(field: &WireField) -> Vec<(u32, String)> {
+        match &field.kind {
+            WireKind::Singular(WireValue::Message(message_name)) => {
+                // Messages that appear more than once are merged.
+                let existing = match field.presence {
+                    Presence::Oneof(_) => format!("{} ?? {}()", field.value, message_name),
+                    _ => format!("{} ??= {}()", field.value, message_name),
+                };
+                let statement = match field.presence {
+                    Presence::Oneof(_) => {
+                        format!("{} = reader.readMessage({})", field.name, existing)
+                    }
+                    _ => format!("reader.readMessage({})", existing),
+                };
+
+                vec![(tag(field.number, WIRE_LENGTH_DELIMITED), statement)]
+            }
+            WireKind::Singular(value) => vec![(
+                tag(field.number, value.wire_type()),
+                format!("{} = {}", field.name, value.read("reader")),
+            )],
+            // Parsers have to accept repeated scalars whether or not they're packed.
+            WireKind::Repeated { value, .. } => {
+                let mut statements = vec![(
+                    tag(field.number, value.wire_type()),
+                    format!("{}.add({})", field.name, value.read("reader")),
+                )];
+
+                if value.is_packable() {
+                    statements.push((
+                        tag(field.number, WIRE_LENGTH_DELIMITED),
+                        format!(
+                            "reader.readPacked((reader) => {}.add({}))",
+                            field.name,
+                            value.read("reader")
+                        ),
+                    ));
+                }
+
+                statements
+            }
+            WireKind::Map { key, value } => vec![(
+                tag(field.number, WIRE_LENGTH_DELIMITED),
+                format!(
+                    "reader.readMapEntry({}, {}, (reader) => {}, {}, {}, (reader) => {}, () => {})",
+                    field.name,
+                    tag(MAP_KEY_FIELD, key.wire_type()),
+                    key.read("reader"),
+                    key.default_value(),
+                    tag(MAP_VALUE_FIELD, value.wire_type()),
+                    value.read("reader"),
+                    value.default_value()
+                ),
+            )],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_gen::dart::DartOptions;
+    use crate::code_gen::CodeGenerator;
+    use crate::compiler::{Compiler, SourceTree};
+    use crate::descriptor::{Decoder, FieldValue};
+    use crate::parser::ParserImpl;
+    use std::path::PathBuf;
+
+    fn fields(bytes: &[u8]) -> Vec<(u32, FieldValue<'_>)> {
+        let mut decoder = Decoder::new(bytes);
+        let mut fields = vec![];
+        while let Some(field) = decoder.next_field().expect("failed to decode") {
+            fields.push(field);
+        }
+
+        fields
+    }
+
+    // test_data/dart is a Dart package whose tests check the generated code against the fixtures, so what's checked in
+    // there has to be what the generator currently produces.
+    #[test]
+    fn test_generated_package() {
+        let compiler = Compiler::new(
+            Box::new(ParserImpl::default()),
+            SourceTree::new(vec![
+                PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_data/dart/protos")
+            ]),
+        );
+        let compilation = compiler
+            .compile(&["wire.proto"])
+            .expect("failed to compile");
+
+        let generator =
+            DartCodeGenerator::new(Box::new(ParserImpl::default()), DartOptions::default());
+
+        assert_eq!(
+            generator.output_file_name(&compilation.files[0]),
+            "fixtures/wire.pb.dart"
+        );
+        assert_eq!(
+            generator.gen_file(&compilation, "wire.proto"),
+            Ok(include_str!("../../../test_data/dart/lib/fixtures/wire.pb.dart").to_string())
+        );
+        assert_eq!(
+            generator.runtime_files(),
+            vec![(
+                "rs_proto.dart".to_string(),
+                include_str!("../../../test_data/dart/lib/rs_proto.dart").to_string()
+            )]
+        );
+
+        // test_data/dart_legacy runs the same tests, with the same runtime, on an SDK without null safety.
+        let legacy_generator = DartCodeGenerator::new(
+            Box::new(ParserImpl::default()),
+            DartOptions {
+                null_safety: false,
+                ..DartOptions::default()
+            },
+        );
+
+        assert_eq!(
+            legacy_generator.gen_file(&compilation, "wire.proto"),
+            Ok(
+                include_str!("../../../test_data/dart_legacy/lib/fixtures/wire.pb.dart")
+                    .to_string()
+            )
+        );
+        assert_eq!(legacy_generator.runtime_files(), generator.runtime_files());
+    }
+
+    #[test]
+    fn test_fixtures() {
+        use FieldValue::*;
+
+        let point = |x: u64, y: u64| {
+            let mut bytes = vec![];
+            if x != 0 {
+                bytes.extend([0x08, x as u8]);
+            }
+            if y != 0 {
+                bytes.extend([0x10, y as u8]);
+            }
+            bytes
+        };
+        let packed = [0x01]
+            .iter()
+            .chain(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01])
+            .chain(&[0xac, 0x02])
+            .copied()
+            .collect::<Vec<u8>>();
+
+        // Points are given ZigZag encoded, which maps -1 to 1, 1 to 2, 2 to 4 and so on.
+        let everything = include_bytes!("../../../test_data/dart/test/fixtures/everything.bin");
+        let (point_1, point_2, point_3, point_4) =
+            (point(1, 4), point(2, 0), point(0, 0), point(0, 3));
+        let (points_by_id, counts_a, counts_b) = (
+            [&[0x08, 0x07, 0x12, 0x02][..], &point_4].concat(),
+            [0x0a, 0x01, b'a', 0x10, 0x01],
+            [0x0a, 0x01, b'b', 0x10, 0x00],
+        );
+        assert_eq!(
+            fields(everything),
+            vec![
+                (1, Fixed64(1.5f64.to_bits())),
+                (2, Fixed32((-2.25f32).to_bits())),
+                (3, Varint(-1i64 as u64)),
+                (4, Varint(1_234_567_890_123)),
+                (5, Varint(u64::from(u32::MAX))),
+                (6, Varint(u64::MAX)),
+                (7, Varint(5)),
+                (8, Varint(2_469_135_780_245)),
+                (9, Fixed32(4_000_000_000)),
+                (10, Fixed64(9_007_199_254_740_993)),
+                (11, Fixed32(-5i32 as u32)),
+                (12, Fixed64(-6i64 as u64)),
+                (13, Varint(1)),
+                (14, LengthDelimited("héllo ✓".as_bytes())),
+                (15, LengthDelimited(&[0, 1, 254, 255])),
+                (16, Varint(2)),
+                (17, LengthDelimited(&point_1)),
+                (18, Varint(0)),
+                (20, LengthDelimited(&packed)),
+                (21, Varint(1)),
+                (21, Varint(2)),
+                (22, LengthDelimited(b"a")),
+                (22, LengthDelimited(b"")),
+                (23, LengthDelimited(&point_2)),
+                (23, LengthDelimited(&point_3)),
+                (24, LengthDelimited(&[0x01, 0x02])),
+                (25, LengthDelimited(&counts_a)),
+                (25, LengthDelimited(&counts_b)),
+                (26, LengthDelimited(&points_by_id)),
+                (27, LengthDelimited(b"pick")),
+            ]
+        );
+
+        let lenient = include_bytes!("../../../test_data/dart/test/fixtures/lenient.bin");
+        let (point_x, point_y, origin_x, origin_y) =
+            (point(2, 0), point(0, 4), point(6, 0), point(0, 8));
+        assert_eq!(
+            fields(lenient),
+            vec![
+                (3, Varint(1)),
+                (20, Varint(1)),
+                (20, Varint(2)),
+                (21, LengthDelimited(&[0x01, 0x02])),
+                (99, Varint(5)),
+                (17, LengthDelimited(&point_x)),
+                (100, LengthDelimited(b"hi")),
+                (101, Fixed32(7)),
+                (102, Fixed64(8)),
+                (17, LengthDelimited(&point_y)),
+                (3, Varint(2)),
+                (28, LengthDelimited(&origin_x)),
+                (28, LengthDelimited(&origin_y)),
+            ]
+        );
+    }
+}
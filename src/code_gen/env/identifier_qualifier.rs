@@ -3,7 +3,7 @@ use crate::parser::ProtoType;
 use std::cell::RefCell;
 use std::rc::Rc;
 
-type IdentifierQualfifierFn = Fn(&ProtoType, Rc<RefCell<ProtoTypeHierarchyNode>>) -> String;
+type IdentifierQualfifierFn = dyn Fn(&ProtoType, Rc<RefCell<ProtoTypeHierarchyNode>>) -> String;
 
 pub enum IdentifierQualifier {
     IdentifierQualifier(Box<IdentifierQualfifierFn>),
@@ -1,19 +1,25 @@
-use crate::parser::{Program, ProtoIdentifierPath, ProtoType};
+use crate::parser::{Program, ProtoIdentifierPath, ProtoType, Span};
+use crate::utils::edit_distance;
 use std::cell::RefCell;
 use std::rc::Rc;
 
 mod identifier_qualifier;
 mod proto_type_hierarchy;
 mod proto_type_hierarchy_node;
+mod resolution_error;
 
 pub use identifier_qualifier::*;
 pub use proto_type_hierarchy::*;
 pub use proto_type_hierarchy_node::*;
+pub use resolution_error::*;
+
+// The most near-matches suggested when a type can't be resolved.
+const MAX_CANDIDATES: usize = 3;
 
 #[derive(Debug)]
 pub struct GeneratorEnvironment<'a> {
     // The Program this hierarchy is based off of.
-    program: &'a Program<'a>,
+    program: &'a Program,
 
     // Hierarchy of known proto types.
     type_hierarchy: Rc<ProtoTypeHierarchy<'a>>,
@@ -30,7 +36,19 @@ pub struct GeneratorEnvironment<'a> {
 
 impl<'a> GeneratorEnvironment<'a> {
     pub fn new(program: &'a Program, type_hierarchy: Rc<ProtoTypeHierarchy<'a>>) -> Self {
-        let type_context = type_hierarchy.clone().head.clone();
+        // Top-level references (e.g. from services and extends) are looked up from the file's package scope.
+        let mut type_context = type_hierarchy.head.clone();
+        for package_segment in program
+            .package
+            .iter()
+            .flat_map(|package| package.split('.'))
+        {
+            let child = type_context.borrow().find_child(package_segment);
+            match child {
+                Some(child) => type_context = child,
+                None => break,
+            }
+        }
 
         GeneratorEnvironment {
             program,
@@ -41,14 +59,28 @@ impl<'a> GeneratorEnvironment<'a> {
         }
     }
 
-    pub fn new_child(&mut self, proto_type: &ProtoType) -> Rc<RefCell<Self>> {
+    pub fn new_child(
+        &mut self,
+        proto_type: &ProtoType,
+    ) -> Result<Rc<RefCell<Self>>, Box<ResolutionError>> {
+        // The type is found by name among the context's children. Comparing types would miss those holding a NaN
+        // constant, which isn't equal to itself.
         let type_hierarchy = self.type_hierarchy.clone();
-        let type_context = match type_hierarchy.find_type_node(proto_type) {
+        let type_context = self
+            .type_context
+            .borrow()
+            .find_child(proto_type.get_name())
+            .filter(|child| child.borrow().proto_type.is_some());
+        let type_context = match type_context {
             Some(type_context) => type_context,
-            None => panic!(
-                "Failed to find type '{:?}' in hierarchy: {:?}",
-                proto_type, self.type_hierarchy
-            ),
+            None => {
+                return Err(Box::new(ResolutionError {
+                    path: proto_type.get_name().to_string(),
+                    scope: self.type_context.borrow().full_name.clone(),
+                    candidates: vec![],
+                    span: proto_type.get_span(),
+                }))
+            }
         };
 
         let child = Rc::new(RefCell::new(GeneratorEnvironment {
@@ -61,7 +93,26 @@ impl<'a> GeneratorEnvironment<'a> {
 
         self.children.push(child.clone());
 
-        child
+        Ok(child)
+    }
+
+    // An environment in the context of a type node found some other way, e.g. by resolving a reference to it.
+    pub fn for_type_node(
+        program: &'a Program,
+        type_hierarchy: Rc<ProtoTypeHierarchy<'a>>,
+        type_context: Rc<RefCell<ProtoTypeHierarchyNode<'a>>>,
+    ) -> Self {
+        GeneratorEnvironment {
+            program,
+            type_hierarchy,
+            type_context,
+            queued_outputs: vec![],
+            children: vec![],
+        }
+    }
+
+    pub fn program(&self) -> &'a Program {
+        self.program
     }
 
     pub fn get_fully_qualified_identifier(&self) -> Option<String> {
@@ -71,76 +122,140 @@ impl<'a> GeneratorEnvironment<'a> {
             .clone()
     }
 
+    // Resolves a type reference using protobuf's scoping rules:
+    //   * a path with a leading dot is looked up from the root scope,
+    //   * otherwise the first component of the path binds to the innermost enclosing scope that declares it, and
+    //     the rest of the path must resolve within that declaration (protoc doesn't backtrack to outer scopes).
     pub fn resolve_proto_type(
         &self,
         path: &ProtoIdentifierPath,
     ) -> Option<Rc<RefCell<ProtoTypeHierarchyNode<'a>>>> {
-        path.get_path_parts()
-            .iter()
-            .fold(None, |acc, identifier| match acc {
-                None => {
-                    let starting_context = &self.type_context;
-                    let derived_context =
-                        Self::resolve_proto_type_relative_to_context(identifier, starting_context);
+        let parts = path.get_path_parts();
+
+        if path.is_absolute() {
+            return Self::resolve_type_in_scope(&self.type_hierarchy.head, &parts);
+        }
 
-                    Some(derived_context)
+        let mut scope = Some(self.type_context.clone());
+        while let Some(node) = scope {
+            let first = node.borrow().find_child(parts[0]);
+
+            if let Some(first) = first {
+                // A lone name that matches a package (rather than a type) keeps searching outwards.
+                if parts.len() > 1 || first.borrow().proto_type.is_some() {
+                    return Self::resolve_type_in_scope(&first, &parts[1..]);
                 }
-                Some(result) => match result {
-                    None => None,
-                    Some(context) => {
-                        let derived_context =
-                            Self::resolve_proto_type_relative_to_context(identifier, &context);
-
-                        Some(derived_context)
-                    }
-                },
-            })
-            .unwrap()
+            }
+
+            scope = node.borrow().parent.clone();
+        }
+
+        None
     }
 
-    fn resolve_proto_type_relative_to_context(
-        identifier: &str,
-        type_context: &Rc<RefCell<ProtoTypeHierarchyNode<'a>>>,
+    fn resolve_type_in_scope(
+        scope: &Rc<RefCell<ProtoTypeHierarchyNode<'a>>>,
+        parts: &[&str],
     ) -> Option<Rc<RefCell<ProtoTypeHierarchyNode<'a>>>> {
-        let mut curr = Some(type_context.clone());
-
-        loop {
-            match curr.clone() {
-                Some(node) => match node.borrow().proto_type.clone() {
-                    Some(ref proto_type) if proto_type.get_name() == identifier => return curr,
-                    _ => {
-                        for child in &node.borrow().children {
-                            if let Some(proto_type) = child.borrow().proto_type.clone() {
-                                if proto_type.get_name() == identifier {
-                                    return Some(child.clone());
-                                }
-                            }
-                        }
-
-                        curr = node.borrow().parent.clone();
-                    }
-                },
-                None => return None,
-            }
+        let mut curr = scope.clone();
+        for part in parts {
+            let next = curr.borrow().find_child(part)?;
+            curr = next;
         }
-    }
 
-    pub fn resolve_identifier_path(&self, path: &ProtoIdentifierPath) -> String {
-        let resolved_type = match self.resolve_proto_type(path) {
-            Some(resolved_type) => resolved_type,
-            _ => panic!(
-                "Failed to find identifier '{:?}' relative to {:?}",
-                path, self
-            ),
-        };
+        // Paths have to name a type, not a package.
+        let is_type = curr.borrow().proto_type.is_some();
+        if is_type {
+            Some(curr)
+        } else {
+            None
+        }
+    }
 
-        let identifier = resolved_type
+    // Resolves a type reference to its generated identifier. `span` is the location of the reference, which is
+    // reported if the type can't be found.
+    pub fn resolve_identifier_path(
+        &self,
+        path: &ProtoIdentifierPath,
+        span: Option<Span>,
+    ) -> Result<String, Box<ResolutionError>> {
+        let identifier = self
+            .resolve_type_node(path, span)?
             .borrow()
             .fully_qualified_identifier
             .clone()
             .expect("expected fully qualified identifier on non-root node");
 
-        identifier.to_string()
+        Ok(identifier)
+    }
+
+    // Like `resolve_proto_type`, but reports a type that can't be found as an error at `span`.
+    pub fn resolve_type_node(
+        &self,
+        path: &ProtoIdentifierPath,
+        span: Option<Span>,
+    ) -> Result<Rc<RefCell<ProtoTypeHierarchyNode<'a>>>, Box<ResolutionError>> {
+        self.resolve_proto_type(path).ok_or_else(|| {
+            let ProtoIdentifierPath::Path(written_path) = path;
+
+            Box::new(ResolutionError {
+                path: written_path.to_string(),
+                scope: self.type_context.borrow().full_name.clone(),
+                candidates: self.find_candidates(path),
+                span,
+            })
+        })
+    }
+
+    // Finds the types whose name (or full name) is within a few edits of the path, closest first.
+    fn find_candidates(&self, path: &ProtoIdentifierPath) -> Vec<String> {
+        let parts = path.get_path_parts();
+        let name = parts[parts.len() - 1].to_lowercase();
+        let full_path = parts.join(".").to_lowercase();
+
+        // Allow roughly one edit for every three characters, so short names don't match everything.
+        let max_distance = name.len().div_ceil(3);
+
+        let mut candidates = vec![];
+        Self::collect_candidates(
+            &self.type_hierarchy.head,
+            &name,
+            &full_path,
+            max_distance,
+            &mut candidates,
+        );
+
+        candidates.sort();
+        candidates.dedup();
+
+        candidates
+            .into_iter()
+            .take(MAX_CANDIDATES)
+            .map(|(_, full_name)| full_name)
+            .collect()
+    }
+
+    fn collect_candidates(
+        node: &Rc<RefCell<ProtoTypeHierarchyNode<'a>>>,
+        name: &str,
+        full_path: &str,
+        max_distance: usize,
+        candidates: &mut Vec<(usize, String)>,
+    ) {
+        let node = node.borrow();
+
+        if let Some(proto_type) = &node.proto_type {
+            let distance = edit_distance(name, &proto_type.get_name().to_lowercase())
+                .min(edit_distance(full_path, &node.full_name.to_lowercase()));
+
+            if distance <= max_distance {
+                candidates.push((distance, node.full_name.clone()));
+            }
+        }
+
+        for child in &node.children {
+            Self::collect_candidates(child, name, full_path, max_distance, candidates);
+        }
     }
 
     pub fn queue_output(&mut self, output: String) {
@@ -162,3 +277,195 @@ impl<'a> GeneratorEnvironment<'a> {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, ParserImpl, ProtoMessage};
+
+    fn resolve(env: &GeneratorEnvironment, path: &str) -> Option<String> {
+        env.resolve_proto_type(&ProtoIdentifierPath::Path(path.to_string()))
+            .map(|node| node.borrow().full_name.clone())
+    }
+
+    fn nested_message<'p>(message: &'p ProtoMessage, name: &str) -> &'p ProtoType {
+        message
+            .types
+            .iter()
+            .find(|proto_type| proto_type.get_name() == name)
+            .expect("missing nested type")
+    }
+
+    #[test]
+    fn test_resolution() {
+        let src = include_str!("../../../test_data/resolution.proto");
+        let program = ParserImpl::default()
+            .parse(src)
+            .expect("failed to parse resolution.proto");
+
+        let type_hierarchy = ProtoTypeHierarchy::from_program(
+            &program,
+            IdentifierQualifier::new(Box::new(|proto_type, _| proto_type.get_name().to_string())),
+        );
+
+        let mut env = GeneratorEnvironment::new(&program, Rc::new(type_hierarchy));
+
+        let outer_type = &program.types[0];
+        let outer = match outer_type {
+            ProtoType::Message(outer) => outer,
+            _ => panic!("expected Outer to be a message"),
+        };
+        let inner_type = nested_message(outer, "Inner");
+        let inner = match inner_type {
+            ProtoType::Message(inner) => inner,
+            _ => panic!("expected Inner to be a message"),
+        };
+
+        let outer_env = env.new_child(outer_type).expect("missing Outer");
+        let inner_env = outer_env
+            .borrow_mut()
+            .new_child(inner_type)
+            .expect("missing Inner");
+        let deep_env = inner_env
+            .borrow_mut()
+            .new_child(nested_message(inner, "Deep"))
+            .expect("missing Deep");
+
+        let outer_env = outer_env.borrow();
+        let inner_env = inner_env.borrow();
+        let deep_env = deep_env.borrow();
+
+        let expected_full_name = |name: &str| Some(name.to_string());
+
+        // Relative names resolve innermost-first.
+        assert_eq!(
+            resolve(&outer_env, "Inner"),
+            expected_full_name("foo.bar.Outer.Inner")
+        );
+        assert_eq!(
+            resolve(&outer_env, "Inner.Deep"),
+            expected_full_name("foo.bar.Outer.Inner.Deep")
+        );
+        assert_eq!(resolve(&outer_env, "Deep"), None);
+        assert_eq!(
+            resolve(&inner_env, "Deep"),
+            expected_full_name("foo.bar.Outer.Inner.Deep")
+        );
+        assert_eq!(
+            resolve(&deep_env, "Deep"),
+            expected_full_name("foo.bar.Outer.Inner.Deep")
+        );
+        assert_eq!(
+            resolve(&deep_env, "Outer"),
+            expected_full_name("foo.bar.Outer")
+        );
+        assert_eq!(
+            resolve(&deep_env, "Sibling"),
+            expected_full_name("foo.bar.Sibling")
+        );
+
+        // Partially and fully qualified names resolve through the package's scopes.
+        assert_eq!(
+            resolve(&outer_env, "Outer.Inner"),
+            expected_full_name("foo.bar.Outer.Inner")
+        );
+        assert_eq!(
+            resolve(&inner_env, "foo.bar.Sibling"),
+            expected_full_name("foo.bar.Sibling")
+        );
+        assert_eq!(
+            resolve(&env, "foo.bar.Sibling"),
+            expected_full_name("foo.bar.Sibling")
+        );
+
+        // The top-level environment is scoped to the file's package.
+        assert_eq!(
+            resolve(&env, "Sibling"),
+            expected_full_name("foo.bar.Sibling")
+        );
+
+        // The first component binds to the innermost scope that declares it, so 'bar' means Outer.bar here and the
+        // lookup doesn't fall back to the 'bar' package.
+        assert_eq!(
+            resolve(&inner_env, "bar"),
+            expected_full_name("foo.bar.Outer.bar")
+        );
+        assert_eq!(resolve(&inner_env, "bar.Sibling"), None);
+
+        // A leading dot always resolves from the root scope.
+        assert_eq!(
+            resolve(&deep_env, ".foo.bar.Outer.Inner"),
+            expected_full_name("foo.bar.Outer.Inner")
+        );
+        assert_eq!(resolve(&deep_env, ".Outer"), None);
+        assert_eq!(resolve(&deep_env, ".bar.Sibling"), None);
+
+        // Packages and unknown names aren't types.
+        assert_eq!(resolve(&outer_env, "foo.bar"), None);
+        assert_eq!(resolve(&outer_env, "foo"), None);
+        assert_eq!(resolve(&outer_env, "Missing"), None);
+        assert_eq!(resolve(&outer_env, "Inner.Missing"), None);
+    }
+
+    #[test]
+    fn test_resolution_errors() {
+        let src = include_str!("../../../test_data/resolution.proto");
+        let program = ParserImpl::default()
+            .parse(src)
+            .expect("failed to parse resolution.proto");
+
+        let type_hierarchy = ProtoTypeHierarchy::from_program(
+            &program,
+            IdentifierQualifier::new(Box::new(|proto_type, _| proto_type.get_name().to_string())),
+        );
+
+        let mut env = GeneratorEnvironment::new(&program, Rc::new(type_hierarchy));
+        let outer_env = env.new_child(&program.types[0]).expect("missing Outer");
+        let outer_env = outer_env.borrow();
+
+        let span = program.types[1].get_span();
+        let path = ProtoIdentifierPath::Path("Inenr.Deep".to_string());
+
+        assert_eq!(
+            outer_env.resolve_identifier_path(&path, span),
+            Err(Box::new(ResolutionError {
+                path: "Inenr.Deep".to_string(),
+                scope: "foo.bar.Outer".to_string(),
+                candidates: vec!["foo.bar.Outer.Inner.Deep".to_string()],
+                span,
+            }))
+        );
+
+        // Names are compared case-insensitively, and the closest matches come first.
+        let path = ProtoIdentifierPath::Path("sibling".to_string());
+        assert_eq!(
+            outer_env
+                .resolve_identifier_path(&path, None)
+                .map_err(|err| err.candidates),
+            Err(vec!["foo.bar.Sibling".to_string()])
+        );
+
+        // Nothing is suggested when no type is close.
+        let path = ProtoIdentifierPath::Path("Unrelated".to_string());
+        assert_eq!(
+            outer_env
+                .resolve_identifier_path(&path, None)
+                .map_err(|err| err.to_string()),
+            Err("Unable to resolve type 'Unrelated' from scope 'foo.bar.Outer'".to_string())
+        );
+
+        // Types that aren't part of the hierarchy can't be used as a context.
+        let other_program = ParserImpl::default()
+            .parse("message Other {}")
+            .expect("failed to parse");
+        assert_eq!(
+            env.new_child(&other_program.types[0]).map(|_| ()),
+            Err(Box::new(ResolutionError {
+                path: "Other".to_string(),
+                scope: "foo.bar".to_string(),
+                candidates: vec![],
+                span: other_program.types[0].get_span(),
+            }))
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use super::*;
-use crate::parser::{Program, ProtoType};
+use crate::parser::Program;
 use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
@@ -17,44 +17,58 @@ impl<'a> fmt::Debug for ProtoTypeHierarchy<'a> {
 
 impl<'a> ProtoTypeHierarchy<'a> {
     pub fn from_program(program: &'a Program, identifier_qualifier: IdentifierQualifier) -> Self {
-        let head = Rc::new(RefCell::new(ProtoTypeHierarchyNode::new_head()));
+        Self::from_programs(&[program], identifier_qualifier)
+    }
 
-        for proto_type in &program.types {
-            let child = ProtoTypeHierarchyNode::new(
-                head.clone(),
-                Rc::new(proto_type.clone()),
-                &identifier_qualifier,
-            );
+    // Builds a single hierarchy out of several files, merging the scopes of files that share a package.
+    pub fn from_programs(
+        programs: &[&'a Program],
+        identifier_qualifier: IdentifierQualifier,
+    ) -> Self {
+        let head = Rc::new(RefCell::new(ProtoTypeHierarchyNode::new_head()));
 
-            head.borrow_mut().children.push(child);
-        }
+        for program in programs {
+            // Each segment of the package is a scope that the file's types are nested in.
+            let mut package_scope = head.clone();
+            for package_segment in program
+                .package
+                .iter()
+                .flat_map(|package| package.split('.'))
+            {
+                let existing = package_scope
+                    .borrow()
+                    .children
+                    .iter()
+                    .find(|child| {
+                        child.borrow().package_segment.as_deref() == Some(package_segment)
+                    })
+                    .cloned();
 
-        ProtoTypeHierarchy { head }
-    }
+                package_scope = match existing {
+                    Some(existing) => existing,
+                    None => {
+                        let child = ProtoTypeHierarchyNode::new_package(
+                            package_scope.clone(),
+                            package_segment,
+                        );
 
-    pub fn find_type_node(
-        &self,
-        proto_type: &ProtoType,
-    ) -> Option<Rc<RefCell<ProtoTypeHierarchyNode<'a>>>> {
-        Self::find_type_node_rec(self.head.clone(), proto_type)
-    }
-
-    fn find_type_node_rec(
-        node: Rc<RefCell<ProtoTypeHierarchyNode<'a>>>,
-        proto_type: &ProtoType,
-    ) -> Option<Rc<RefCell<ProtoTypeHierarchyNode<'a>>>> {
-        if let Some(node_proto_type) = node.borrow().proto_type.clone() {
-            if (*node_proto_type) == *proto_type {
-                return Some(node.clone());
+                        package_scope.borrow_mut().children.push(child.clone());
+                        child
+                    }
+                };
             }
-        }
 
-        for child in &node.borrow().children {
-            if let result @ Some(_) = Self::find_type_node_rec(child.clone(), proto_type) {
-                return result;
+            for proto_type in &program.types {
+                let child = ProtoTypeHierarchyNode::new(
+                    package_scope.clone(),
+                    Rc::new(proto_type.clone()),
+                    &identifier_qualifier,
+                );
+
+                package_scope.borrow_mut().children.push(child);
             }
         }
 
-        None
+        ProtoTypeHierarchy { head }
     }
 }
@@ -9,7 +9,13 @@ pub struct ProtoTypeHierarchyNode<'a> {
     pub parent: Option<Rc<RefCell<ProtoTypeHierarchyNode<'a>>>>,
 
     // The type represented by this node (if present).
-    pub proto_type: Option<Rc<ProtoType<'a>>>,
+    pub proto_type: Option<Rc<ProtoType>>,
+
+    // The package segment represented by this node (if this node is a package scope).
+    pub package_segment: Option<String>,
+
+    // The protobuf name of this node, e.g. "foo.bar.Baz" (empty for the root node).
+    pub full_name: String,
 
     // The fully qualified name of the type (if present).
     pub fully_qualified_identifier: Option<String>,
@@ -37,7 +43,7 @@ impl<'a> fmt::Debug for ProtoTypeHierarchyNode<'a> {
 
         let children = format!("{}", self.children.len());
 
-        f.write_fmt(format_args!("ProtoTypeHierarchyNode{{ parent: {}, proto_type: {}, fully_qualified_identifier: {:?}, children: {} }}", parent, proto_type, fully_qualified_identifier, children))
+        f.write_fmt(format_args!("ProtoTypeHierarchyNode{{ parent: {}, proto_type: {}, full_name: {:?}, fully_qualified_identifier: {:?}, children: {} }}", parent, proto_type, self.full_name, fully_qualified_identifier, children))
     }
 }
 
@@ -46,21 +52,42 @@ impl<'a> ProtoTypeHierarchyNode<'a> {
         ProtoTypeHierarchyNode {
             parent: None,
             proto_type: None,
+            package_segment: None,
+            full_name: String::new(),
             fully_qualified_identifier: None,
             children: vec![],
         }
     }
 
+    pub fn new_package(
+        parent: Rc<RefCell<ProtoTypeHierarchyNode<'a>>>,
+        package_segment: &str,
+    ) -> Rc<RefCell<Self>> {
+        let full_name = Self::join_name(&parent.borrow().full_name, package_segment);
+
+        Rc::new(RefCell::new(ProtoTypeHierarchyNode {
+            parent: Some(parent),
+            proto_type: None,
+            package_segment: Some(package_segment.to_string()),
+            full_name,
+            fully_qualified_identifier: None,
+            children: vec![],
+        }))
+    }
+
     pub fn new(
         parent: Rc<RefCell<ProtoTypeHierarchyNode<'a>>>,
-        proto_type: Rc<ProtoType<'a>>,
+        proto_type: Rc<ProtoType>,
         identifier_qualifier: &IdentifierQualifier,
     ) -> Rc<RefCell<Self>> {
         let fully_qualified_identifier = identifier_qualifier.invoke(&proto_type, parent.clone());
+        let full_name = Self::join_name(&parent.borrow().full_name, proto_type.get_name());
 
         let result = Rc::new(RefCell::new(ProtoTypeHierarchyNode {
             parent: Some(parent),
             proto_type: Some(proto_type.clone()),
+            package_segment: None,
+            full_name,
             fully_qualified_identifier: Some(fully_qualified_identifier),
             children: vec![],
         }));
@@ -82,4 +109,27 @@ impl<'a> ProtoTypeHierarchyNode<'a> {
 
         result
     }
+
+    // The name of this node within its parent scope, which is either a type name or a package segment.
+    pub fn name(&self) -> Option<&str> {
+        match (&self.proto_type, &self.package_segment) {
+            (Some(proto_type), _) => Some(proto_type.get_name()),
+            (None, Some(package_segment)) => Some(package_segment),
+            (None, None) => None,
+        }
+    }
+
+    pub fn find_child(&self, name: &str) -> Option<Rc<RefCell<ProtoTypeHierarchyNode<'a>>>> {
+        self.children
+            .iter()
+            .find(|child| child.borrow().name() == Some(name))
+            .cloned()
+    }
+
+    fn join_name(parent_name: &str, name: &str) -> String {
+        match parent_name {
+            "" => name.to_string(),
+            _ => format!("{}.{}", parent_name, name),
+        }
+    }
 }
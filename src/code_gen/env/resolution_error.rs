@@ -0,0 +1,53 @@
+use crate::parser::Span;
+use std::fmt;
+
+// A type reference that couldn't be resolved from the scope it appeared in.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResolutionError {
+    // The path as written, e.g. "Foo.Bar" or ".pkg.Foo".
+    pub path: String,
+
+    // The full name of the scope the path was looked up from (empty for the root scope).
+    pub scope: String,
+
+    // Full names of known types with names close to the path, best match first.
+    pub candidates: Vec<String>,
+
+    // Where the reference appeared in the source.
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = self.span {
+            write!(f, "{}:{}: ", span.start_line, span.start_column)?;
+        }
+
+        match self.scope.as_str() {
+            "" => write!(
+                f,
+                "Unable to resolve type '{}' from the root scope",
+                self.path
+            )?,
+            scope => write!(
+                f,
+                "Unable to resolve type '{}' from scope '{}'",
+                self.path, scope
+            )?,
+        }
+
+        match self.candidates.as_slice() {
+            [] => Ok(()),
+            [candidate] => write!(f, ". Did you mean '{}'?", candidate),
+            candidates => write!(
+                f,
+                ". Did you mean one of {}?",
+                candidates
+                    .iter()
+                    .map(|candidate| format!("'{}'", candidate))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use super::env::ResolutionError;
+use crate::parser::ParseError;
+use crate::validator::Diagnostic;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum GeneratorError {
+    // The source given to gen_code failed to parse.
+    Parse(ParseError),
+
+    // The file breaks protobuf's semantic rules. Only errors are kept, since warnings don't stop generation.
+    Invalid(Vec<Diagnostic>),
+
+    // A type reference couldn't be resolved.
+    Resolution(Box<ResolutionError>),
+
+    // gen_file was asked for a file that isn't part of the compilation.
+    MissingFile(String),
+
+    // The file uses something the generator can't represent.
+    Unsupported(String),
+}
+
+impl GeneratorError {
+    // One line per error, each prefixed with the file it's for, e.g. "a.proto:3:5: error: ..." for errors with a
+    // location and "a.proto: ..." for the rest.
+    pub fn in_file(&self, file: &str) -> String {
+        match self {
+            GeneratorError::Parse(error) => in_file(file, error.span().is_some(), error),
+            GeneratorError::Invalid(diagnostics) => diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.in_file(file))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            GeneratorError::Resolution(error) => in_file(file, error.span.is_some(), error),
+            GeneratorError::MissingFile(_) | GeneratorError::Unsupported(_) => {
+                in_file(file, false, self)
+            }
+        }
+    }
+}
+
+impl From<ParseError> for GeneratorError {
+    fn from(error: ParseError) -> Self {
+        GeneratorError::Parse(error)
+    }
+}
+
+impl From<Box<ResolutionError>> for GeneratorError {
+    fn from(error: Box<ResolutionError>) -> Self {
+        GeneratorError::Resolution(error)
+    }
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorError::Parse(error) => write!(f, "{}", error),
+            GeneratorError::Invalid(diagnostics) => f.write_str(
+                &diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic.to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            GeneratorError::Resolution(error) => write!(f, "{}", error),
+            GeneratorError::MissingFile(name) => {
+                write!(f, "'{}' is not part of the compilation", name)
+            }
+            GeneratorError::Unsupported(message) => f.write_str(message),
+        }
+    }
+}
+
+// Errors with a location already lead with "line:column: ", so they only need the file in front of them.
+fn in_file(file: &str, has_location: bool, error: &dyn fmt::Display) -> String {
+    match has_location {
+        true => format!("{}:{}", file, error),
+        false => format!("{}: {}", file, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Span;
+
+    #[test]
+    fn test_in_file() {
+        let span = Span {
+            start: 0,
+            end: 1,
+            start_line: 3,
+            start_column: 5,
+            end_line: 3,
+            end_column: 6,
+        };
+
+        assert_eq!(
+            GeneratorError::Invalid(vec![
+                Diagnostic::error(Some(span), "Bad field.".to_string()),
+                Diagnostic::error(None, "Bad file.".to_string()),
+            ])
+            .in_file("a.proto"),
+            "a.proto:3:5: error: Bad field.\na.proto: error: Bad file."
+        );
+
+        // Messages that happen to start with digits aren't mistaken for locations.
+        assert_eq!(
+            GeneratorError::Unsupported("3:5 isn't a location".to_string()).in_file("a.proto"),
+            "a.proto: 3:5 isn't a location"
+        );
+    }
+}
@@ -1,19 +1,43 @@
 mod dart;
-mod env;
+pub mod env;
+mod error;
+mod options;
 
+pub use error::*;
+pub use options::*;
+
+use crate::compiler::{Compilation, CompiledFile};
 use crate::parser::Parser;
-use dart::DartCodeGenerator;
+use dart::{DartCodeGenerator, DartOptions};
 
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Language {
     Dart,
 }
 
 pub trait CodeGenerator {
-    fn gen_code(&self, src: String) -> Result<String, String>;
+    fn gen_code(&self, src: String) -> Result<String, GeneratorError>;
+
+    // Generates the code for one file of a compilation, which may reference types from the files it imports.
+    fn gen_file(&self, compilation: &Compilation, name: &str) -> Result<String, GeneratorError>;
+
+    // The path of the file generated for a file of a compilation, relative to the output directory.
+    fn output_file_name(&self, file: &CompiledFile) -> String;
+
+    // Files the generated code depends on, as (path, contents) pairs to write to the output directory with it.
+    fn runtime_files(&self) -> Vec<(String, String)>;
 }
 
-pub fn generator_for(parser: Box<Parser>, language: Language) -> impl CodeGenerator {
+// Fails if the options include any the language's generator doesn't understand.
+pub fn generator_for(
+    parser: Box<dyn Parser>,
+    language: Language,
+    options: &GeneratorOptions,
+) -> Result<impl CodeGenerator, String> {
     match language {
-        Language::Dart => DartCodeGenerator::new(parser),
+        Language::Dart => Ok(DartCodeGenerator::new(
+            parser,
+            DartOptions::from_generator_options(options)?,
+        )),
     }
 }
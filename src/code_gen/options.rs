@@ -0,0 +1,67 @@
+// Options for a generator in protoc's plugin parameter format: comma-separated "key=value" pairs or bare "key" flags,
+// e.g. "omit_comments,foo=bar". Each generator decides which keys it understands.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GeneratorOptions {
+    options: Vec<(String, Option<String>)>,
+}
+
+impl GeneratorOptions {
+    pub fn parse(parameter: &str) -> Self {
+        let options = parameter
+            .split(',')
+            .map(str::trim)
+            .filter(|option| !option.is_empty())
+            .map(|option| match option.split_once('=') {
+                Some((key, value)) => (key.trim().to_string(), Some(value.trim().to_string())),
+                None => (option.to_string(), None),
+            })
+            .collect();
+
+        GeneratorOptions { options }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.options
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_deref()))
+    }
+
+    // Reads the value of a flag, which is on when it's given without a value.
+    pub fn parse_flag(key: &str, value: Option<&str>) -> Result<bool, String> {
+        match value {
+            None | Some("true") => Ok(true),
+            Some("false") => Ok(false),
+            Some(value) => Err(format!(
+                "Option '{}' must be true or false, not '{}'",
+                key, value
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let options = GeneratorOptions::parse("omit_comments, foo=bar,,baz=");
+
+        assert_eq!(
+            options.iter().collect::<Vec<(&str, Option<&str>)>>(),
+            vec![
+                ("omit_comments", None),
+                ("foo", Some("bar")),
+                ("baz", Some(""))
+            ]
+        );
+        assert_eq!(GeneratorOptions::parse(""), GeneratorOptions::default());
+
+        assert_eq!(GeneratorOptions::parse_flag("a", None), Ok(true));
+        assert_eq!(GeneratorOptions::parse_flag("a", Some("false")), Ok(false));
+        assert_eq!(
+            GeneratorOptions::parse_flag("a", Some("yes")),
+            Err("Option 'a' must be true or false, not 'yes'".to_string())
+        );
+    }
+}
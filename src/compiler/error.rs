@@ -0,0 +1,107 @@
+use crate::parser::{ParseError, Span};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CompileError {
+    // A file (or one of its imports) couldn't be found in any of the source tree's include directories.
+    NotFound {
+        file: String,
+        importer: Option<String>,
+        span: Option<Span>,
+    },
+
+    // A file (or one of its imports) was named by a path that could leave the include directories, e.g. "../a.proto".
+    InvalidPath {
+        file: String,
+        importer: Option<String>,
+        span: Option<Span>,
+    },
+
+    // A file was found but couldn't be read.
+    Io {
+        file: String,
+        message: String,
+    },
+
+    // A file failed to parse.
+    Parse {
+        file: String,
+        error: ParseError,
+    },
+
+    // A chain of imports leads back to a file that's already being imported, e.g. [a.proto, b.proto, a.proto].
+    ImportCycle {
+        cycle: Vec<String>,
+    },
+
+    // Two files declare the same fully qualified name.
+    DuplicateSymbol {
+        name: String,
+        file: String,
+        previous_file: String,
+    },
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::NotFound {
+                file,
+                importer,
+                span,
+            } => {
+                write_importer(f, importer, span)?;
+                write!(
+                    f,
+                    "'{}' was not found in any of the include directories",
+                    file
+                )
+            }
+            CompileError::InvalidPath {
+                file,
+                importer,
+                span,
+            } => {
+                write_importer(f, importer, span)?;
+                write!(
+                    f,
+                    "'{}' must be a relative path without backslashes, consecutive slashes, \".\" or \"..\"",
+                    file
+                )
+            }
+            CompileError::Io { message, .. } => f.write_str(message),
+            CompileError::Parse { file, error } => match error.span() {
+                Some(_) => write!(f, "{}:{}", file, error),
+                None => write!(f, "{}: {}", file, error),
+            },
+            CompileError::ImportCycle { cycle } => {
+                write!(f, "Import cycle detected: {}", cycle.join(" -> "))
+            }
+            CompileError::DuplicateSymbol {
+                name,
+                file,
+                previous_file,
+            } => write!(
+                f,
+                "{}: '{}' is already defined in '{}'",
+                file, name, previous_file
+            ),
+        }
+    }
+}
+
+fn write_importer(
+    f: &mut fmt::Formatter<'_>,
+    importer: &Option<String>,
+    span: &Option<Span>,
+) -> fmt::Result {
+    match (importer, span) {
+        (Some(importer), Some(span)) => write!(
+            f,
+            "{}:{}:{}: ",
+            importer, span.start_line, span.start_column
+        ),
+        (Some(importer), None) => write!(f, "{}: ", importer),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,335 @@
+mod error;
+mod source_tree;
+mod symbol_table;
+
+pub use error::*;
+pub use source_tree::*;
+
+use crate::parser::{Parser, Program, ProtoImport, ProtoImportModifier};
+use symbol_table::SymbolTable;
+
+// Loads .proto files and their imports from a SourceTree and parses each of them once.
+pub struct Compiler {
+    parser: Box<dyn Parser>,
+    source_tree: SourceTree,
+}
+
+#[derive(Debug)]
+pub struct Compilation {
+    // Every loaded file, with each file's dependencies ahead of it.
+    pub files: Vec<CompiledFile>,
+}
+
+#[derive(Debug)]
+pub struct CompiledFile {
+    pub name: String,
+    pub program: Program,
+}
+
+impl Compiler {
+    pub fn new(parser: Box<dyn Parser>, source_tree: SourceTree) -> Self {
+        Compiler {
+            parser,
+            source_tree,
+        }
+    }
+
+    // Loads and parses the given files and everything they import (transitively).
+    pub fn compile(&self, names: &[&str]) -> Result<Compilation, CompileError> {
+        let mut files = vec![];
+
+        for name in names {
+            self.load_file(name, None, &mut vec![], &mut files)?;
+        }
+
+        Compilation::from_files(files)
+    }
+
+    fn load_file(
+        &self,
+        name: &str,
+        importer: Option<(&str, &ProtoImport)>,
+        stack: &mut Vec<String>,
+        files: &mut Vec<CompiledFile>,
+    ) -> Result<(), CompileError> {
+        if files.iter().any(|file| file.name == name) {
+            return Ok(());
+        }
+
+        if let Some(start) = stack.iter().position(|file| file == name) {
+            let mut cycle = stack[start..].to_vec();
+            cycle.push(name.to_string());
+
+            return Err(CompileError::ImportCycle { cycle });
+        }
+
+        let source = self.source_tree.open(name).map_err(|err| match err {
+            CompileError::NotFound { file, .. } => CompileError::NotFound {
+                file,
+                importer: importer.map(|(importer, _)| importer.to_string()),
+                span: importer.and_then(|(_, import)| import.span),
+            },
+            CompileError::InvalidPath { file, .. } => CompileError::InvalidPath {
+                file,
+                importer: importer.map(|(importer, _)| importer.to_string()),
+                span: importer.and_then(|(_, import)| import.span),
+            },
+            err => err,
+        })?;
+
+        let program = self.parse(&source)?;
+
+        stack.push(name.to_string());
+
+        for import in &program.imports {
+            match self.load_file(&import.path, Some((name, import)), stack, files) {
+                // Weak imports are allowed to be missing.
+                Err(CompileError::NotFound { ref file, .. })
+                    if file == &import.path
+                        && import.modifier == Some(ProtoImportModifier::Weak) => {}
+                result => result?,
+            }
+        }
+
+        stack.pop();
+        files.push(CompiledFile {
+            name: source.name,
+            program,
+        });
+
+        Ok(())
+    }
+
+    fn parse(&self, source: &SourceFile) -> Result<Program, CompileError> {
+        self.parser
+            .parse(&source.src)
+            .map_err(|error| CompileError::Parse {
+                file: source.name.clone(),
+                error,
+            })
+    }
+}
+
+impl Compilation {
+    // Builds a compilation from files that were already parsed (or decoded from descriptors), which must be listed with
+    // their dependencies first.
+    pub fn from_files(files: Vec<CompiledFile>) -> Result<Self, CompileError> {
+        let mut symbols = SymbolTable::default();
+
+        for file in &files {
+            symbols.add_program(&file.name, &file.program)?;
+        }
+
+        Ok(Compilation { files })
+    }
+
+    pub fn file(&self, name: &str) -> Option<&CompiledFile> {
+        self.files.iter().find(|file| file.name == name)
+    }
+
+    // The files whose types can be referenced from the given file: the file itself, the files it imports, and any
+    // files those re-export with 'import public' (transitively).
+    pub fn visible_files(&self, name: &str) -> Vec<&CompiledFile> {
+        let mut visible = vec![];
+
+        if let Some(file) = self.file(name) {
+            visible.push(file);
+
+            for import in &file.program.imports {
+                self.add_public_imports(&import.path, &mut visible);
+            }
+        }
+
+        visible
+    }
+
+    // The programs of the files visible from the given file, other than the file itself.
+    pub fn dependencies(&self, name: &str) -> Vec<&Program> {
+        self.visible_files(name)
+            .into_iter()
+            .filter(|file| file.name != name)
+            .map(|file| &file.program)
+            .collect()
+    }
+
+    fn add_public_imports<'c>(&'c self, name: &str, visible: &mut Vec<&'c CompiledFile>) {
+        // Missing weak imports won't have been loaded.
+        let file = match self.file(name) {
+            Some(file) => file,
+            None => return,
+        };
+
+        if visible
+            .iter()
+            .any(|visible_file| visible_file.name == file.name)
+        {
+            return;
+        }
+
+        visible.push(file);
+
+        for import in &file.program.imports {
+            if import.modifier == Some(ProtoImportModifier::Public) {
+                self.add_public_imports(&import.path, visible);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ParserImpl, Span};
+    use std::path::PathBuf;
+
+    fn test_compiler() -> Compiler {
+        let include_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_data/imports");
+
+        Compiler::new(
+            Box::new(ParserImpl::default()),
+            SourceTree::new(vec![include_dir]),
+        )
+    }
+
+    fn names(compilation: &Compilation) -> Vec<&str> {
+        compilation
+            .files
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect()
+    }
+
+    fn compile_error(compiler: &Compiler, names: &[&str]) -> Option<CompileError> {
+        compiler.compile(names).err()
+    }
+
+    #[test]
+    fn test_load_imports() {
+        let compiler = test_compiler();
+
+        let compilation = compiler
+            .compile(&["private.proto"])
+            .expect("failed to compile private.proto");
+
+        // Dependencies come first, and the missing weak import is skipped.
+        assert_eq!(
+            names(&compilation),
+            vec!["nested/c.proto", "b.proto", "a.proto", "private.proto"]
+        );
+
+        let visible_names = |name: &str| {
+            compilation
+                .visible_files(name)
+                .iter()
+                .map(|file| file.name.as_str())
+                .collect::<Vec<&str>>()
+        };
+
+        // c.proto is re-exported by b.proto, but a.proto's imports aren't visible to private.proto.
+        assert_eq!(
+            visible_names("a.proto"),
+            vec!["a.proto", "b.proto", "nested/c.proto"]
+        );
+        assert_eq!(
+            visible_names("private.proto"),
+            vec!["private.proto", "a.proto"]
+        );
+    }
+
+    #[test]
+    fn test_virtual_files() {
+        let mut source_tree = SourceTree::new(vec![]);
+        source_tree.add_file("main.proto", "import \"dep.proto\";".to_string());
+        source_tree.add_file("dep.proto", "message Dep {}".to_string());
+
+        let compiler = Compiler::new(Box::new(ParserImpl::default()), source_tree);
+        let compilation = compiler
+            .compile(&["main.proto"])
+            .expect("failed to compile main.proto");
+
+        assert_eq!(names(&compilation), vec!["dep.proto", "main.proto"]);
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let compiler = test_compiler();
+
+        assert_eq!(
+            compile_error(&compiler, &["cycle_a.proto"]),
+            Some(CompileError::ImportCycle {
+                cycle: vec![
+                    "cycle_a.proto".to_string(),
+                    "cycle_b.proto".to_string(),
+                    "cycle_a.proto".to_string()
+                ]
+            })
+        );
+
+        assert_eq!(
+            compile_error(&compiler, &["bad_import.proto"]),
+            Some(CompileError::NotFound {
+                file: "does_not_exist.proto".to_string(),
+                importer: Some("bad_import.proto".to_string()),
+                span: Some(Span {
+                    start: 20,
+                    end: 50,
+                    start_line: 3,
+                    start_column: 1,
+                    end_line: 3,
+                    end_column: 31
+                }),
+            })
+        );
+
+        assert_eq!(
+            compile_error(&compiler, &["missing.proto"]),
+            Some(CompileError::NotFound {
+                file: "missing.proto".to_string(),
+                importer: None,
+                span: None,
+            })
+        );
+
+        assert_eq!(
+            compile_error(&compiler, &["duplicate.proto"]).map(|err| err.to_string()),
+            Some("duplicate.proto: 'app.B' is already defined in 'b.proto'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        let compiler = test_compiler();
+
+        // Paths that would leave the include directory are rejected, even when the file they point at exists.
+        for name in &[
+            "../imports/a.proto",
+            "nested/../a.proto",
+            "./a.proto",
+            "nested//c.proto",
+            "nested\\c.proto",
+        ] {
+            assert_eq!(
+                compile_error(&compiler, &[name]),
+                Some(CompileError::InvalidPath {
+                    file: name.to_string(),
+                    importer: None,
+                    span: None,
+                })
+            );
+        }
+
+        let absolute = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_data/imports/a.proto");
+        assert!(matches!(
+            compile_error(&compiler, &[absolute.to_str().unwrap()]),
+            Some(CompileError::InvalidPath { .. })
+        ));
+
+        assert_eq!(
+            compile_error(&compiler, &["escaping_import.proto"]).map(|err| err.to_string()),
+            Some(
+                "escaping_import.proto:3:1: '../descriptors/common.proto' must be a relative path without backslashes, consecutive slashes, \".\" or \"..\""
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use super::CompileError;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Maps import paths (e.g. "google/protobuf/empty.proto") to their contents.
+#[derive(Debug, Default)]
+pub struct SourceTree {
+    // Directories that import paths are resolved against, in order of precedence.
+    include_dirs: Vec<PathBuf>,
+
+    // Files that were added directly rather than read from disk; these take precedence over the include dirs.
+    virtual_files: HashMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceFile {
+    // The path the file is imported by.
+    pub name: String,
+
+    // Where the file was loaded from (the same as `name` for virtual files).
+    pub location: PathBuf,
+
+    pub src: String,
+}
+
+impl SourceTree {
+    pub fn new(include_dirs: Vec<PathBuf>) -> Self {
+        SourceTree {
+            include_dirs,
+            virtual_files: HashMap::new(),
+        }
+    }
+
+    pub fn add_file(&mut self, name: &str, src: String) {
+        self.virtual_files.insert(name.to_string(), src);
+    }
+
+    pub fn open(&self, name: &str) -> Result<SourceFile, CompileError> {
+        if !is_virtual_path(name) {
+            return Err(CompileError::InvalidPath {
+                file: name.to_string(),
+                importer: None,
+                span: None,
+            });
+        }
+
+        if let Some(src) = self.virtual_files.get(name) {
+            return Ok(SourceFile {
+                name: name.to_string(),
+                location: PathBuf::from(name),
+                src: src.clone(),
+            });
+        }
+
+        for include_dir in &self.include_dirs {
+            let location = include_dir.join(name);
+            if !location.is_file() {
+                continue;
+            }
+
+            return match fs::read_to_string(&location) {
+                Ok(src) => Ok(SourceFile {
+                    name: name.to_string(),
+                    location,
+                    src,
+                }),
+                Err(err) => Err(CompileError::Io {
+                    file: name.to_string(),
+                    message: format!("Failed to read '{}': {}", location.display(), err),
+                }),
+            };
+        }
+
+        Err(CompileError::NotFound {
+            file: name.to_string(),
+            importer: None,
+            span: None,
+        })
+    }
+}
+
+// Like protoc's DiskSourceTree, import paths have to stay inside of the include directories: absolute paths, backslashes,
+// empty segments and "." or ".." segments aren't allowed.
+fn is_virtual_path(name: &str) -> bool {
+    !Path::new(name).is_absolute()
+        && !name.contains('\\')
+        && name
+            .split('/')
+            .all(|segment| !matches!(segment, "" | "." | ".."))
+}
@@ -0,0 +1,107 @@
+use super::CompileError;
+use crate::parser::{Program, ProtoType};
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SymbolKind {
+    Package,
+    Message,
+    Enum,
+    Service,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+
+    // The file that declared the symbol (or the first file that declared the package).
+    pub file: String,
+}
+
+// Every fully qualified name declared across a compilation, e.g. "foo.bar.Baz". This is only used to reject names that
+// are declared more than once; references are resolved through the code generator's environment.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<String, Symbol>,
+}
+
+impl SymbolTable {
+    pub fn add_program(&mut self, file: &str, program: &Program) -> Result<(), CompileError> {
+        let mut scope = String::new();
+        for package_segment in program
+            .package
+            .iter()
+            .flat_map(|package| package.split('.'))
+        {
+            scope = Self::join_name(&scope, package_segment);
+            self.add(&scope, SymbolKind::Package, file)?;
+        }
+
+        self.add_types(&scope, &program.types, file)?;
+
+        for service in &program.services {
+            self.add(
+                &Self::join_name(&scope, &service.name),
+                SymbolKind::Service,
+                file,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn add_types(
+        &mut self,
+        scope: &str,
+        types: &[ProtoType],
+        file: &str,
+    ) -> Result<(), CompileError> {
+        for proto_type in types {
+            let full_name = Self::join_name(scope, proto_type.get_name());
+
+            match proto_type {
+                ProtoType::Message(message) => {
+                    self.add(&full_name, SymbolKind::Message, file)?;
+                    self.add_types(&full_name, &message.types, file)?;
+                }
+                ProtoType::Enum(_) => self.add(&full_name, SymbolKind::Enum, file)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add(&mut self, full_name: &str, kind: SymbolKind, file: &str) -> Result<(), CompileError> {
+        match self.symbols.get(full_name) {
+            // Any number of files can share a package.
+            Some(existing)
+                if existing.kind == SymbolKind::Package && kind == SymbolKind::Package =>
+            {
+                Ok(())
+            }
+            Some(existing) => Err(CompileError::DuplicateSymbol {
+                name: full_name.to_string(),
+                file: file.to_string(),
+                previous_file: existing.file.clone(),
+            }),
+            None => {
+                self.symbols.insert(
+                    full_name.to_string(),
+                    Symbol {
+                        kind,
+                        file: file.to_string(),
+                    },
+                );
+
+                Ok(())
+            }
+        }
+    }
+
+    fn join_name(scope: &str, name: &str) -> String {
+        match scope {
+            "" => name.to_string(),
+            _ => format!("{}.{}", scope, name),
+        }
+    }
+}
@@ -0,0 +1,655 @@
+use super::lower::{full_name_qualifier, primitive_field_type};
+use super::types::{FieldType, UnknownField};
+use super::wire::{Decoder, Encoder, RawValue};
+use super::DescriptorError;
+use crate::code_gen::env::*;
+use crate::compiler::Compilation;
+use crate::parser::*;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type TypeNode<'a> = Rc<RefCell<ProtoTypeHierarchyNode<'a>>>;
+
+// Custom options are extensions of the *Options messages in descriptor.proto. Like protoc, they're interpreted against
+// the extend declarations visible from the file, and stored as the extension fields they set.
+pub(super) struct CustomOptions<'a> {
+    program: &'a Program,
+
+    // Every type of the compilation, since the messages used in options can have fields of types that the file
+    // setting the option doesn't import.
+    type_hierarchy: Rc<ProtoTypeHierarchy<'a>>,
+
+    extensions: Vec<Extension<'a>>,
+}
+
+struct Extension<'a> {
+    // The full name of the extended message, e.g. "google.protobuf.FieldOptions".
+    extendee: String,
+    field: ValueField<'a>,
+}
+
+// A field that an option sets (directly, through a field path or inside an aggregate), with its type resolved.
+struct ValueField<'a> {
+    // The full name of the field, e.g. "foo.my_option", which protoc's errors refer to it by.
+    full_name: String,
+    number: u32,
+    repeated: bool,
+    field_type: FieldType,
+
+    // The enum or message named by the field's type.
+    type_node: Option<TypeNode<'a>>,
+}
+
+// Where an interpreted option is in its *Options message: the numbers of the fields its name goes through, e.g.
+// [50000, 1] for "(my_option).foo", and whether the last of them is repeated. SourceCodeInfo locates options by these.
+pub(super) struct OptionPath {
+    pub(super) numbers: Vec<i32>,
+    pub(super) repeated: bool,
+}
+
+impl<'a> CustomOptions<'a> {
+    pub(super) fn new(compilation: &'a Compilation, name: &str) -> Result<Self, DescriptorError> {
+        let program = &compilation
+            .file(name)
+            .ok_or_else(|| DescriptorError::UnknownFile {
+                file: name.to_string(),
+            })?
+            .program;
+
+        let type_hierarchy = ProtoTypeHierarchy::from_programs(
+            &compilation
+                .files
+                .iter()
+                .map(|file| &file.program)
+                .collect::<Vec<&Program>>(),
+            full_name_qualifier(),
+        );
+
+        let mut result = CustomOptions {
+            program,
+            type_hierarchy: Rc::new(type_hierarchy),
+            extensions: vec![],
+        };
+
+        for file in compilation.visible_files(name) {
+            let mut env = GeneratorEnvironment::new(&file.program, result.type_hierarchy.clone());
+            result
+                .add_extensions(&file.program.extends, &file.program.types, &mut env)
+                .map_err(|error| DescriptorError::Resolution {
+                    file: file.name.to_string(),
+                    error,
+                })?;
+        }
+
+        Ok(result)
+    }
+
+    fn add_extensions(
+        &mut self,
+        extends: &'a [ProtoExtend],
+        types: &'a [ProtoType],
+        env: &mut GeneratorEnvironment<'a>,
+    ) -> Result<(), Box<ResolutionError>> {
+        for extend in extends {
+            let extendee = env
+                .resolve_type_node(&extend.extendee, extend.span)?
+                .borrow()
+                .full_name
+                .clone();
+
+            for field in &extend.fields {
+                self.extensions.push(Extension {
+                    extendee: extendee.clone(),
+                    field: Self::value_field(field, env)?,
+                });
+            }
+        }
+
+        for proto_type in types {
+            if let ProtoType::Message(message) = proto_type {
+                let child_env = env.new_child(proto_type)?;
+                self.add_extensions(
+                    &message.extends,
+                    &message.types,
+                    &mut child_env.borrow_mut(),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Interprets a custom option of an `options_message` (e.g. "FieldOptions") that's set in `scope`, the full name of
+    // the message or package the option's declaration is in. Errors are worded like protoc's.
+    pub(super) fn interpret(
+        &self,
+        option: &ProtoOption,
+        scope: Option<&str>,
+        options_message: &str,
+    ) -> Result<(UnknownField, OptionPath), String> {
+        let name = format!("({})", option.name);
+
+        let extension = self
+            .find_extension(&option.name, scope)
+            .ok_or_else(|| unknown_option(&name))?;
+        if extension.extendee != format!("google.protobuf.{}", options_message) {
+            return Err(format!(
+                "Option field \"{}\" is not a field or extension of message \"{}\".",
+                name, options_message
+            ));
+        }
+
+        let field_path = match &option.field_path {
+            Some(field_path) => field_path.split('.').collect(),
+            None => vec![],
+        };
+
+        let mut path = OptionPath {
+            numbers: vec![],
+            repeated: false,
+        };
+        let value = self.field_path_value(
+            &extension.field,
+            &field_path,
+            &option.value,
+            name,
+            &mut path,
+        )?;
+
+        Ok((
+            UnknownField {
+                number: extension.field.number,
+                value,
+            },
+            path,
+        ))
+    }
+
+    // Like protoc, which parses the options into their message before serializing it, the extensions set on an options
+    // message are ordered by number, and the values set on the same singular message field are merged into one.
+    pub(super) fn canonicalize(
+        &self,
+        fields: Vec<UnknownField>,
+        options_message: &str,
+    ) -> Result<Vec<UnknownField>, String> {
+        let extendee = format!("google.protobuf.{}", options_message);
+        let fields = fields
+            .into_iter()
+            .map(|field| (field.number, field.value))
+            .collect();
+
+        let merged = self.merge_fields(fields, |number| {
+            Ok(self
+                .extensions
+                .iter()
+                .find(|extension| {
+                    extension.extendee == extendee && extension.field.number == number
+                })
+                .map(|extension| Self::merge_kind(&extension.field)))
+        })?;
+
+        Ok(merged
+            .into_iter()
+            .map(|(number, value)| UnknownField { number, value })
+            .collect())
+    }
+
+    fn canonicalize_message(&self, node: &TypeNode<'a>, bytes: &[u8]) -> Result<Vec<u8>, String> {
+        let mut fields = vec![];
+        let mut decoder = Decoder::new(bytes);
+        while let Some((number, value)) = decoder.next_field().map_err(|err| err.to_string())? {
+            fields.push((number, value.into()));
+        }
+
+        let merged = self.merge_fields(fields, |number| {
+            Ok(self
+                .find_message_field(node, |field| field.position == number)?
+                .map(|field| Self::merge_kind(&field)))
+        })?;
+
+        let mut encoder = Encoder::new();
+        for (number, value) in &merged {
+            encoder.raw(*number, value);
+        }
+
+        Ok(encoder.into_bytes())
+    }
+
+    // Orders `fields` by number and merges the values of each singular field, where `field_kind` gives whether the field
+    // with a number is repeated, and its type if it's a message. Fields it doesn't know are kept as they are.
+    fn merge_fields(
+        &self,
+        mut fields: Vec<(u32, RawValue)>,
+        field_kind: impl Fn(u32) -> Result<Option<(bool, Option<TypeNode<'a>>)>, String>,
+    ) -> Result<Vec<(u32, RawValue)>, String> {
+        fields.sort_by_key(|(number, _)| *number);
+
+        let mut merged: Vec<(u32, RawValue, Option<TypeNode<'a>>)> = vec![];
+        for (number, value) in fields {
+            let (repeated, message) = field_kind(number)?.unwrap_or((true, None));
+
+            match merged.last_mut() {
+                Some((last, last_value, _)) if *last == number && !repeated => {
+                    match (last_value, value) {
+                        // Messages are merged by concatenating their encodings.
+                        (RawValue::LengthDelimited(bytes), RawValue::LengthDelimited(more))
+                            if message.is_some() =>
+                        {
+                            bytes.extend(more)
+                        }
+                        (last_value, value) => *last_value = value,
+                    }
+                }
+                _ => merged.push((number, value, message)),
+            }
+        }
+
+        merged
+            .into_iter()
+            .map(|(number, value, message)| match (message, value) {
+                (Some(node), RawValue::LengthDelimited(bytes)) => Ok((
+                    number,
+                    RawValue::LengthDelimited(self.canonicalize_message(&node, &bytes)?),
+                )),
+                (_, value) => Ok((number, value)),
+            })
+            .collect()
+    }
+
+    fn merge_kind(field: &ValueField<'a>) -> (bool, Option<TypeNode<'a>>) {
+        let message = match field.field_type {
+            FieldType::Message => field.type_node.clone(),
+            _ => None,
+        };
+
+        (field.repeated, message)
+    }
+
+    // Like type references, option names bind to the innermost enclosing scope that declares them.
+    fn find_extension(&self, name: &str, scope: Option<&str>) -> Option<&Extension<'a>> {
+        let find = |full_name: &str| {
+            self.extensions
+                .iter()
+                .find(|extension| extension.field.full_name == full_name)
+        };
+
+        if let Some(full_name) = name.strip_prefix('.') {
+            return find(full_name);
+        }
+
+        let mut scope = scope;
+        loop {
+            let found = match scope {
+                Some(scope) => find(&format!("{}.{}", scope, name)),
+                None => find(name),
+            };
+            if found.is_some() {
+                return found;
+            }
+
+            scope = match scope {
+                Some(scope) => scope.rsplit_once('.').map(|(parent, _)| parent),
+                None => return None,
+            };
+        }
+    }
+
+    // The value of `field` when `path` (e.g. ["foo", "bar"] for "(my_option).foo.bar") is set to `value`. `name` is
+    // the option name up to `field`, as written. The fields along the way are added to `option_path`.
+    fn field_path_value(
+        &self,
+        field: &ValueField<'a>,
+        path: &[&str],
+        value: &ProtoConstant,
+        name: String,
+        option_path: &mut OptionPath,
+    ) -> Result<RawValue, String> {
+        option_path.numbers.push(field.number as i32);
+
+        let (first, rest) = match path.split_first() {
+            Some(split) => split,
+            None => {
+                option_path.repeated = field.repeated;
+                return self.value(field, value);
+            }
+        };
+
+        let node = match (field.field_type, &field.type_node) {
+            (FieldType::Message, Some(node)) => node,
+            _ => {
+                return Err(format!(
+                    "Option \"{}\" is an atomic type, not a message.",
+                    name
+                ))
+            }
+        };
+        if field.repeated {
+            return Err(format!(
+                "Option field \"{}\" is a repeated message. Repeated message options must be initialized using an aggregate value.",
+                name
+            ));
+        }
+
+        let subfield = self.message_field(node, first)?.ok_or_else(|| {
+            format!(
+                "Option field \"{}.{}\" is not a field or extension of message \"{}\".",
+                name,
+                first,
+                type_name(node)
+            )
+        })?;
+
+        let mut encoder = Encoder::new();
+        encoder.raw(
+            subfield.number,
+            &self.field_path_value(
+                &subfield,
+                rest,
+                value,
+                format!("{}.{}", name, first),
+                option_path,
+            )?,
+        );
+
+        Ok(RawValue::LengthDelimited(encoder.into_bytes()))
+    }
+
+    fn value(&self, field: &ValueField<'a>, value: &ProtoConstant) -> Result<RawValue, String> {
+        let integer = |min: i128, max: i128| {
+            let integer = match value {
+                ProtoConstant::Int(_) if min >= 0 => {
+                    return Err(format!(
+                        "Value must be non-negative integer for {} option \"{}\".",
+                        scalar_name(field.field_type),
+                        field.full_name
+                    ))
+                }
+                ProtoConstant::Int(value) => i128::from(*value),
+                ProtoConstant::UInt(value) => i128::from(*value),
+                _ => {
+                    return Err(format!(
+                        "Value must be integer for {} option \"{}\".",
+                        scalar_name(field.field_type),
+                        field.full_name
+                    ))
+                }
+            };
+
+            match min <= integer && integer <= max {
+                true => Ok(integer),
+                false => Err(format!(
+                    "Value out of range for {} option \"{}\".",
+                    scalar_name(field.field_type),
+                    field.full_name
+                )),
+            }
+        };
+        let number = || match value {
+            ProtoConstant::Int(value) => Ok(*value as f64),
+            ProtoConstant::UInt(value) => Ok(*value as f64),
+            ProtoConstant::Float(value) => Ok(*value),
+            ProtoConstant::Identifier(value) if value == "inf" => Ok(f64::INFINITY),
+            ProtoConstant::Identifier(value) if value == "nan" => Ok(f64::NAN),
+            _ => Err(format!(
+                "Value must be number for {} option \"{}\".",
+                scalar_name(field.field_type),
+                field.full_name
+            )),
+        };
+        let string = || match (value, field.field_type) {
+            (ProtoConstant::Str(value), _) => {
+                Ok(RawValue::LengthDelimited(value.as_bytes().to_vec()))
+            }
+            (ProtoConstant::Bytes(value), FieldType::Bytes) => {
+                Ok(RawValue::LengthDelimited(value.clone()))
+            }
+            (ProtoConstant::Bytes(_), _) => Err(format!(
+                "Value for string option \"{}\" is not valid UTF-8.",
+                field.full_name
+            )),
+            _ => Err(format!(
+                "Value must be quoted string for {} option \"{}\".",
+                scalar_name(field.field_type),
+                field.full_name
+            )),
+        };
+
+        let (min_i32, max_i32) = (i128::from(i32::MIN), i128::from(i32::MAX));
+        let (min_i64, max_i64) = (i128::from(i64::MIN), i128::from(i64::MAX));
+
+        // Negative int32s are sign extended like int64s, and the sint types are zigzag encoded.
+        Ok(match field.field_type {
+            FieldType::Int32 => RawValue::Varint(integer(min_i32, max_i32)? as i64 as u64),
+            FieldType::Int64 => RawValue::Varint(integer(min_i64, max_i64)? as i64 as u64),
+            FieldType::UInt32 => RawValue::Varint(integer(0, i128::from(u32::MAX))? as u64),
+            FieldType::UInt64 => RawValue::Varint(integer(0, i128::from(u64::MAX))? as u64),
+            FieldType::SInt32 => {
+                let value = integer(min_i32, max_i32)? as i32;
+                RawValue::Varint(u64::from(((value << 1) ^ (value >> 31)) as u32))
+            }
+            FieldType::SInt64 => {
+                let value = integer(min_i64, max_i64)? as i64;
+                RawValue::Varint(((value << 1) ^ (value >> 63)) as u64)
+            }
+            FieldType::Fixed32 => RawValue::Fixed32(integer(0, i128::from(u32::MAX))? as u32),
+            FieldType::Fixed64 => RawValue::Fixed64(integer(0, i128::from(u64::MAX))? as u64),
+            FieldType::SFixed32 => RawValue::Fixed32(integer(min_i32, max_i32)? as i32 as u32),
+            FieldType::SFixed64 => RawValue::Fixed64(integer(min_i64, max_i64)? as i64 as u64),
+            FieldType::Float => RawValue::Fixed32((number()? as f32).to_bits()),
+            FieldType::Double => RawValue::Fixed64(number()?.to_bits()),
+            FieldType::Bool => match value {
+                ProtoConstant::Boolean(value) => RawValue::Varint(u64::from(*value)),
+                _ => {
+                    return Err(format!(
+                        "Value must be \"true\" or \"false\" for boolean option \"{}\".",
+                        field.full_name
+                    ))
+                }
+            },
+            FieldType::String | FieldType::Bytes => string()?,
+            FieldType::Enum => self.enum_value(field, value)?,
+            FieldType::Message | FieldType::Group => match (value, &field.type_node) {
+                (ProtoConstant::Aggregate(fields), Some(node)) => {
+                    RawValue::LengthDelimited(self.aggregate(node, fields)?)
+                }
+                (ProtoConstant::Aggregate(_), None) => {
+                    return Err(format!(
+                        "Map field \"{}\" can't be set in an option.",
+                        field.full_name
+                    ))
+                }
+                _ => {
+                    return Err(format!(
+                        "Option \"{}\" is a message. To set the entire message, use syntax like \"{} = {{ <proto text format> }}\". To set fields within it, use syntax like \"{}.foo = value\".",
+                        field.full_name, field.full_name, field.full_name
+                    ))
+                }
+            },
+        })
+    }
+
+    fn enum_value(
+        &self,
+        field: &ValueField<'a>,
+        value: &ProtoConstant,
+    ) -> Result<RawValue, String> {
+        let identifier = match value {
+            ProtoConstant::Identifier(identifier) => identifier,
+            _ => {
+                return Err(format!(
+                    "Value must be identifier for enum-valued option \"{}\".",
+                    field.full_name
+                ))
+            }
+        };
+
+        let node = field
+            .type_node
+            .as_ref()
+            .expect("expected enum fields to have a type");
+        let node = node.borrow();
+
+        let number = match node.proto_type.as_deref() {
+            Some(ProtoType::Enum(enumeration)) => enumeration
+                .values
+                .iter()
+                .find(|enum_value| &enum_value.name == identifier)
+                .map(|enum_value| enum_value.position),
+            _ => None,
+        };
+
+        number
+            .map(|number| RawValue::Varint(i64::from(number) as u64))
+            .ok_or_else(|| {
+                format!(
+                    "Enum type \"{}\" has no value named \"{}\" for option \"{}\".",
+                    node.full_name, identifier, field.full_name
+                )
+            })
+    }
+
+    // Encodes a message written in the text format, e.g. `{ min_len: 1 tags: ["a", "b"] }`.
+    fn aggregate(
+        &self,
+        node: &TypeNode<'a>,
+        fields: &[ProtoAggregateField],
+    ) -> Result<Vec<u8>, String> {
+        let mut encoder = Encoder::new();
+
+        for aggregate_field in fields {
+            let field = self
+                .message_field(node, &aggregate_field.name)?
+                .ok_or_else(|| {
+                    format!(
+                        "Message type \"{}\" has no field named \"{}\".",
+                        node.borrow().full_name,
+                        aggregate_field.name
+                    )
+                })?;
+
+            let values = match &aggregate_field.value {
+                ProtoAggregateValue::Single(value) => std::slice::from_ref(value),
+                ProtoAggregateValue::List(values) => values.as_slice(),
+            };
+            for value in values {
+                encoder.raw(field.number, &self.value(&field, value)?);
+            }
+        }
+
+        Ok(encoder.into_bytes())
+    }
+
+    // The field of a message with the given name, if there is one.
+    fn message_field(
+        &self,
+        node: &TypeNode<'a>,
+        name: &str,
+    ) -> Result<Option<ValueField<'a>>, String> {
+        self.find_message_field(node, |field| field.name == name)
+    }
+
+    fn find_message_field(
+        &self,
+        node: &TypeNode<'a>,
+        predicate: impl Fn(&ProtoMessageField) -> bool,
+    ) -> Result<Option<ValueField<'a>>, String> {
+        let proto_type = node.borrow().proto_type.clone();
+        let message = match proto_type.as_deref() {
+            Some(ProtoType::Message(message)) => message,
+            _ => return Ok(None),
+        };
+
+        let field = message
+            .fields
+            .iter()
+            .chain(message.oneofs.iter().flat_map(|oneof| oneof.fields.iter()))
+            .find(|field| predicate(field));
+
+        match field {
+            Some(field) => {
+                // The field's type is resolved from the message it's declared in.
+                let env = GeneratorEnvironment::for_type_node(
+                    self.program,
+                    self.type_hierarchy.clone(),
+                    node.clone(),
+                );
+                let field = Self::value_field(field, &env).map_err(|err| err.to_string())?;
+
+                Ok(Some(field))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn value_field(
+        field: &ProtoMessageField,
+        env: &GeneratorEnvironment<'a>,
+    ) -> Result<ValueField<'a>, Box<ResolutionError>> {
+        let (field_type, type_node) = match &field.field_type {
+            ProtoFieldType::Primitive(primitive) => (primitive_field_type(primitive), None),
+            ProtoFieldType::IdentifierPath(path) => {
+                let node = env.resolve_type_node(path, field.span)?;
+                let field_type = match node.borrow().proto_type.as_deref() {
+                    Some(ProtoType::Enum(_)) => FieldType::Enum,
+                    _ => FieldType::Message,
+                };
+
+                (field_type, Some(node))
+            }
+        };
+
+        let scope = env
+            .get_fully_qualified_identifier()
+            .or_else(|| env.program().package.clone());
+
+        Ok(ValueField {
+            full_name: match scope {
+                Some(scope) => format!("{}.{}", scope, field.name),
+                None => field.name.to_string(),
+            },
+            number: field.position,
+            repeated: field.modifier == Some(ProtoMessageFieldModifier::Repeated),
+            field_type,
+            type_node,
+        })
+    }
+}
+
+pub(super) fn unknown_option(name: &str) -> String {
+    format!(
+        "Option \"{}\" unknown. Ensure that your proto definition file imports the proto which defines the option.",
+        name
+    )
+}
+
+fn type_name(node: &TypeNode) -> String {
+    node.borrow()
+        .proto_type
+        .as_deref()
+        .map(|proto_type| proto_type.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn scalar_name(field_type: FieldType) -> &'static str {
+    match field_type {
+        FieldType::Double => "double",
+        FieldType::Float => "float",
+        FieldType::Int64 => "int64",
+        FieldType::UInt64 => "uint64",
+        FieldType::Int32 => "int32",
+        FieldType::Fixed64 => "fixed64",
+        FieldType::Fixed32 => "fixed32",
+        FieldType::Bool => "bool",
+        FieldType::String => "string",
+        FieldType::Group => "group",
+        FieldType::Message => "message",
+        FieldType::Bytes => "bytes",
+        FieldType::UInt32 => "uint32",
+        FieldType::Enum => "enum",
+        FieldType::SFixed32 => "sfixed32",
+        FieldType::SFixed64 => "sfixed64",
+        FieldType::SInt32 => "sint32",
+        FieldType::SInt64 => "sint64",
+    }
+}
@@ -0,0 +1,68 @@
+use super::DecodeError;
+use crate::code_gen::env::ResolutionError;
+use crate::compiler::CompileError;
+use crate::parser::Span;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DescriptorError {
+    // A file was requested that isn't part of the compilation.
+    UnknownFile {
+        file: String,
+    },
+
+    // A field, extension or rpc references a type that can't be resolved.
+    Resolution {
+        file: String,
+        error: Box<ResolutionError>,
+    },
+
+    // An option or default value has a value that doesn't fit its type.
+    InvalidOption {
+        file: String,
+        span: Option<Span>,
+        message: String,
+    },
+
+    // A descriptor set isn't a valid binary FileDescriptorSet.
+    Decode(DecodeError),
+
+    // A descriptor uses something the AST can't represent, e.g. a group field.
+    Unsupported {
+        file: String,
+        message: String,
+    },
+
+    // The files of a descriptor set don't form a valid compilation, e.g. two files declare the same symbol.
+    Compile(Box<CompileError>),
+}
+
+impl fmt::Display for DescriptorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescriptorError::UnknownFile { file } => {
+                write!(f, "'{}' is not part of the compilation", file)
+            }
+            // ResolutionError leads with the line and column when it has them.
+            DescriptorError::Resolution { file, error } => match error.span {
+                Some(_) => write!(f, "{}:{}", file, error),
+                None => write!(f, "{}: {}", file, error),
+            },
+            DescriptorError::InvalidOption {
+                file,
+                span,
+                message,
+            } => match span {
+                Some(span) => write!(
+                    f,
+                    "{}:{}:{}: {}",
+                    file, span.start_line, span.start_column, message
+                ),
+                None => write!(f, "{}: {}", file, message),
+            },
+            DescriptorError::Decode(error) => write!(f, "{}", error),
+            DescriptorError::Unsupported { file, message } => write!(f, "{}: {}", file, message),
+            DescriptorError::Compile(error) => write!(f, "{}", error),
+        }
+    }
+}
@@ -0,0 +1,970 @@
+use super::lower::json_name;
+use super::options::*;
+use super::types::*;
+use super::wire::{Decoder, FieldValue, WireType};
+use super::DescriptorError;
+use crate::parser::literals::unescape;
+use crate::parser::*;
+
+use std::collections::HashMap;
+
+// The exclusive end of a range that runs to "max".
+const MAX_FIELD_NUMBER_END: i32 = 536_870_912;
+
+// A message or enum declared in one of the files of a descriptor set.
+enum DeclaredType<'d> {
+    Message(&'d DescriptorProto),
+    Enum(&'d EnumDescriptorProto),
+}
+
+// Builds the AST for a file from its FileDescriptorProto, which is the reverse of lower_file. The Program owns its
+// names, so it outlives the descriptor, and has no source text. Spans aren't recovered, but comments are when the
+// descriptor has SourceCodeInfo. `files` are the other files of the set, which declare the
+// extensions that the file's custom options set.
+pub fn lift_file<'d>(
+    file: &'d FileDescriptorProto,
+    files: &'d [FileDescriptorProto],
+) -> Result<Program, DescriptorError> {
+    let lifting = FileLifting {
+        file,
+        files,
+        locations: file
+            .source_code_info
+            .iter()
+            .flat_map(|source_code_info| source_code_info.location.iter())
+            .map(|location| (location.path.as_slice(), location))
+            .collect(),
+    };
+
+    lifting.lift()
+}
+
+struct FileLifting<'d> {
+    file: &'d FileDescriptorProto,
+    files: &'d [FileDescriptorProto],
+
+    // SourceCodeInfo locations by path.
+    locations: HashMap<&'d [i32], &'d Location>,
+}
+
+impl<'d> FileLifting<'d> {
+    fn lift(&self) -> Result<Program, DescriptorError> {
+        let file = self.file;
+        let mut program = Program::new("");
+
+        // protoc leaves the syntax unset for proto2 files without a syntax statement, which the parser leaves unset too.
+        program.syntax = match file.syntax.as_deref() {
+            None => None,
+            Some("proto2") => Some(ProtoSyntax::Proto2),
+            Some("proto3") => Some(ProtoSyntax::Proto3),
+            Some(syntax) => {
+                return Err(self.unsupported(format!("Unsupported syntax '{}'", syntax)));
+            }
+        };
+        program.package = file.package.clone();
+        program.syntax_comments = self.comments(&[12]);
+        program.package_comments = self.comments(&[2]);
+
+        for (i, dependency) in file.dependency.iter().enumerate() {
+            let i = i as i32;
+
+            program.imports.push(ProtoImport {
+                path: dependency.clone(),
+                modifier: if file.public_dependency.contains(&i) {
+                    Some(ProtoImportModifier::Public)
+                } else if file.weak_dependency.contains(&i) {
+                    Some(ProtoImportModifier::Weak)
+                } else {
+                    None
+                },
+                comments: self.comments(&[3, i]),
+                span: None,
+                modifier_span: None,
+            });
+        }
+
+        program.options = self.lift_options(&file.options, FILE_OPTIONS, "FileOptions")?;
+
+        let mut types = vec![];
+        for (i, message) in file.message_type.iter().enumerate() {
+            let path = vec![4, i as i32];
+            types.push((
+                self.position(&path),
+                ProtoType::Message(self.lift_message(message, path)?),
+            ));
+        }
+        for (i, enumeration) in file.enum_type.iter().enumerate() {
+            let path = vec![5, i as i32];
+            types.push((
+                self.position(&path),
+                ProtoType::Enum(self.lift_enum(enumeration, path)?),
+            ));
+        }
+        program.types = Self::in_source_order(types);
+
+        for (i, service) in file.service.iter().enumerate() {
+            program
+                .services
+                .push(self.lift_service(service, vec![6, i as i32])?);
+        }
+
+        program.extends = self.lift_extensions(&file.extension, vec![7])?;
+
+        Ok(program)
+    }
+
+    fn lift_message(
+        &self,
+        message: &'d DescriptorProto,
+        path: Vec<i32>,
+    ) -> Result<ProtoMessage, DescriptorError> {
+        let mut result = ProtoMessage::new(&message.name, None);
+        result.comments = self.comments(&path);
+        result.options = self.lift_options(&message.options, MESSAGE_OPTIONS, "MessageOptions")?;
+
+        // proto3 optional fields are wrapped in synthetic oneofs that don't appear in the source.
+        let mut oneofs = message
+            .oneof_decl
+            .iter()
+            .enumerate()
+            .map(|(i, oneof)| {
+                let is_synthetic = message
+                    .field
+                    .iter()
+                    .any(|field| field.proto3_optional && field.oneof_index == Some(i as i32));
+
+                if is_synthetic {
+                    return Ok(None);
+                }
+
+                let mut result = ProtoOneof::new(&oneof.name, None);
+                result.comments = self.comments(&child_path(&path, 8, i));
+                result.options =
+                    self.lift_options(&oneof.options, ONEOF_OPTIONS, "OneofOptions")?;
+
+                Ok(Some(result))
+            })
+            .collect::<Result<Vec<Option<ProtoOneof>>, DescriptorError>>()?;
+
+        for (i, field) in message.field.iter().enumerate() {
+            let mut lifted = self.lift_field(field, Some(message), child_path(&path, 2, i))?;
+
+            match field.oneof_index {
+                Some(oneof_index) if !field.proto3_optional => {
+                    let oneof = oneofs
+                        .get_mut(oneof_index as usize)
+                        .and_then(Option::as_mut)
+                        .ok_or_else(|| {
+                            self.unsupported(format!(
+                                "Field '{}' has an invalid oneof index {}",
+                                field.name, oneof_index
+                            ))
+                        })?;
+
+                    lifted.modifier = None;
+                    lifted.explicit_presence = true;
+                    oneof.fields.push(lifted);
+                }
+                _ => result.fields.push(lifted),
+            }
+        }
+
+        result.oneofs = oneofs.into_iter().flatten().collect();
+
+        let mut types = vec![];
+        for (i, nested) in message.nested_type.iter().enumerate() {
+            // Map entries become the type of their map field instead.
+            if Self::is_map_entry(nested) {
+                continue;
+            }
+
+            let nested_path = child_path(&path, 3, i);
+            types.push((
+                self.position(&nested_path),
+                ProtoType::Message(self.lift_message(nested, nested_path)?),
+            ));
+        }
+        for (i, enumeration) in message.enum_type.iter().enumerate() {
+            let enum_path = child_path(&path, 4, i);
+            types.push((
+                self.position(&enum_path),
+                ProtoType::Enum(self.lift_enum(enumeration, enum_path)?),
+            ));
+        }
+        result.types = Self::in_source_order(types);
+
+        result.extension_ranges = message
+            .extension_range
+            .iter()
+            .map(|range| Self::lift_exclusive_range(range.start, range.end))
+            .collect();
+
+        let mut extension_path = path.clone();
+        extension_path.push(6);
+        result.extends = self.lift_extensions(&message.extension, extension_path)?;
+
+        for range in &message.reserved_range {
+            result
+                .reserved
+                .push(ProtoReserved::Range(Self::lift_exclusive_range(
+                    range.start,
+                    range.end,
+                )));
+        }
+        for name in &message.reserved_name {
+            result
+                .reserved
+                .push(ProtoReserved::Name(name.clone(), None));
+        }
+
+        Ok(result)
+    }
+
+    // `message` is the message the field is declared in, which holds the entry types of its map fields.
+    fn lift_field(
+        &self,
+        field: &'d FieldDescriptorProto,
+        message: Option<&'d DescriptorProto>,
+        path: Vec<i32>,
+    ) -> Result<ProtoMessageField, DescriptorError> {
+        let map_entry = match (message, field.label, &field.type_name) {
+            (Some(message), FieldLabel::Repeated, Some(type_name)) => {
+                message.nested_type.iter().find(|nested| {
+                    Self::is_map_entry(nested) && type_name.ends_with(&format!(".{}", nested.name))
+                })
+            }
+            _ => None,
+        };
+
+        let field_type = match map_entry {
+            Some(map_entry) => {
+                let entry_type = |number| {
+                    map_entry
+                        .field
+                        .iter()
+                        .find(|entry_field| entry_field.number == number)
+                        .ok_or_else(|| {
+                            self.unsupported(format!(
+                                "Map entry '{}' is missing its key or value",
+                                map_entry.name
+                            ))
+                        })
+                        .and_then(|entry_field| self.lift_field_type(entry_field))
+                };
+
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Map(
+                    Box::new(entry_type(1)?),
+                    Box::new(entry_type(2)?),
+                ))
+            }
+            None => self.lift_field_type(field)?,
+        };
+
+        let proto3 = self.file.syntax.as_deref() == Some("proto3");
+        let modifier = match field.label {
+            FieldLabel::Required => Some(ProtoMessageFieldModifier::Required),
+            FieldLabel::Repeated if map_entry.is_none() => {
+                Some(ProtoMessageFieldModifier::Repeated)
+            }
+            FieldLabel::Repeated => None,
+            FieldLabel::Optional if proto3 && !field.proto3_optional => None,
+            FieldLabel::Optional => Some(ProtoMessageFieldModifier::Optional),
+        };
+
+        let mut options = vec![];
+        if let Some(default_value) = &field.default_value {
+            options.push(self.option("default", self.lift_default_value(field, default_value)?));
+        }
+        if let Some(custom_json_name) = &field.json_name {
+            if *custom_json_name != json_name(&field.name) {
+                options
+                    .push(self.option("json_name", ProtoConstant::Str(custom_json_name.clone())));
+            }
+        }
+        options.extend(self.lift_options(&field.options, FIELD_OPTIONS, "FieldOptions")?);
+
+        Ok(ProtoMessageField {
+            explicit_presence: matches!(
+                modifier,
+                Some(ProtoMessageFieldModifier::Optional)
+                    | Some(ProtoMessageFieldModifier::Required)
+            ),
+            modifier,
+            field_type,
+            name: field.name.clone(),
+            options,
+            position: field.number as u32,
+            comments: self.comments(&path),
+            span: None,
+            modifier_span: None,
+            type_span: None,
+            name_span: None,
+            position_span: None,
+            options_span: None,
+        })
+    }
+
+    fn lift_field_type(
+        &self,
+        field: &'d FieldDescriptorProto,
+    ) -> Result<ProtoFieldType, DescriptorError> {
+        let primitive = match field.field_type {
+            FieldType::Double => ProtoPrimitiveType::Double,
+            FieldType::Float => ProtoPrimitiveType::Float,
+            FieldType::Int64 => ProtoPrimitiveType::Int64,
+            FieldType::UInt64 => ProtoPrimitiveType::UInt64,
+            FieldType::Int32 => ProtoPrimitiveType::Int32,
+            FieldType::Fixed64 => ProtoPrimitiveType::Fixed64,
+            FieldType::Fixed32 => ProtoPrimitiveType::Fixed32,
+            FieldType::Bool => ProtoPrimitiveType::Boolean,
+            FieldType::String => ProtoPrimitiveType::Str,
+            FieldType::Bytes => ProtoPrimitiveType::Bytes,
+            FieldType::UInt32 => ProtoPrimitiveType::UInt32,
+            FieldType::SFixed32 => ProtoPrimitiveType::SFixed32,
+            FieldType::SFixed64 => ProtoPrimitiveType::SFixed64,
+            FieldType::SInt32 => ProtoPrimitiveType::SInt32,
+            FieldType::SInt64 => ProtoPrimitiveType::SInt64,
+            FieldType::Message | FieldType::Enum => {
+                let type_name = field.type_name.as_deref().ok_or_else(|| {
+                    self.unsupported(format!("Field '{}' is missing its type name", field.name))
+                })?;
+
+                return Ok(ProtoFieldType::IdentifierPath(type_name.into()));
+            }
+            FieldType::Group => {
+                return Err(self.unsupported(format!(
+                    "Field '{}' is a group, which isn't supported",
+                    field.name
+                )));
+            }
+        };
+
+        Ok(ProtoFieldType::Primitive(primitive))
+    }
+
+    // The inverse of lower_default_value, using the field's type to decide how to read the text.
+    fn lift_default_value(
+        &self,
+        field: &FieldDescriptorProto,
+        default_value: &str,
+    ) -> Result<ProtoConstant, DescriptorError> {
+        let invalid = || {
+            self.invalid_option(format!(
+                "Invalid default value '{}' for field '{}'",
+                default_value, field.name
+            ))
+        };
+
+        match field.field_type {
+            FieldType::String => Ok(ProtoConstant::Str(default_value.to_string())),
+            FieldType::Bytes => unescape(default_value)
+                .map(ProtoConstant::from_bytes)
+                .map_err(|_| invalid()),
+            FieldType::Bool => match default_value {
+                "true" => Ok(ProtoConstant::Boolean(true)),
+                "false" => Ok(ProtoConstant::Boolean(false)),
+                _ => Err(invalid()),
+            },
+            FieldType::Enum => Ok(ProtoConstant::Identifier(default_value.to_string())),
+            FieldType::Float | FieldType::Double => match default_value {
+                "inf" => Ok(ProtoConstant::Float(f64::INFINITY)),
+                "-inf" => Ok(ProtoConstant::Float(f64::NEG_INFINITY)),
+                "nan" => Ok(ProtoConstant::Float(f64::NAN)),
+                _ => default_value
+                    .parse()
+                    .map(ProtoConstant::Float)
+                    .map_err(|_| invalid()),
+            },
+            // Like the parser, non-negative integers are always stored as a UInt.
+            _ if default_value.starts_with('-') => default_value
+                .parse()
+                .map(ProtoConstant::Int)
+                .map_err(|_| invalid()),
+            _ => default_value
+                .parse()
+                .map(ProtoConstant::UInt)
+                .map_err(|_| invalid()),
+        }
+    }
+
+    // Extensions are grouped back into extend blocks, one for each run of extensions of the same message.
+    fn lift_extensions(
+        &self,
+        extensions: &'d [FieldDescriptorProto],
+        path: Vec<i32>,
+    ) -> Result<Vec<ProtoExtend>, DescriptorError> {
+        let mut result: Vec<ProtoExtend> = vec![];
+
+        for (i, extension) in extensions.iter().enumerate() {
+            let extendee: ProtoIdentifierPath = extension
+                .extendee
+                .as_deref()
+                .map(Into::into)
+                .ok_or_else(|| {
+                    self.unsupported(format!(
+                        "Extension '{}' is missing its extendee",
+                        extension.name
+                    ))
+                })?;
+
+            let mut field_path = path.clone();
+            field_path.push(i as i32);
+            let field = self.lift_field(extension, None, field_path)?;
+
+            match result.last_mut() {
+                Some(extend) if extend.extendee == extendee => extend.fields.push(field),
+                _ => result.push(ProtoExtend {
+                    extendee,
+                    fields: vec![field],
+                    comments: ProtoComments::default(),
+                    span: None,
+                    extendee_span: None,
+                }),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn lift_enum(
+        &self,
+        enumeration: &'d EnumDescriptorProto,
+        path: Vec<i32>,
+    ) -> Result<ProtoEnum, DescriptorError> {
+        let mut result = ProtoEnum::new(&enumeration.name, None);
+        result.comments = self.comments(&path);
+        result.options = self.lift_options(&enumeration.options, ENUM_OPTIONS, "EnumOptions")?;
+
+        for (i, value) in enumeration.value.iter().enumerate() {
+            result.values.push(ProtoEnumValue {
+                name: value.name.clone(),
+                options: self.lift_options(
+                    &value.options,
+                    ENUM_VALUE_OPTIONS,
+                    "EnumValueOptions",
+                )?,
+                position: value.number,
+                comments: self.comments(&child_path(&path, 2, i)),
+                span: None,
+                name_span: None,
+                position_span: None,
+                options_span: None,
+            });
+        }
+
+        // Unlike messages, enum reserved ranges are inclusive.
+        for range in &enumeration.reserved_range {
+            result.reserved.push(ProtoReserved::Range(ProtoRange {
+                start: range.start,
+                end: match range.end {
+                    i32::MAX => ProtoRangeEnd::Max,
+                    end => ProtoRangeEnd::Inclusive(end),
+                },
+                span: None,
+                start_span: None,
+                end_span: None,
+            }));
+        }
+        for name in &enumeration.reserved_name {
+            result
+                .reserved
+                .push(ProtoReserved::Name(name.clone(), None));
+        }
+
+        Ok(result)
+    }
+
+    fn lift_service(
+        &self,
+        service: &'d ServiceDescriptorProto,
+        path: Vec<i32>,
+    ) -> Result<ProtoService, DescriptorError> {
+        let mut result = ProtoService::new(&service.name, None);
+        result.comments = self.comments(&path);
+        result.options = self.lift_options(&service.options, SERVICE_OPTIONS, "ServiceOptions")?;
+
+        for (i, method) in service.method.iter().enumerate() {
+            result.rpcs.push(ProtoRpc {
+                name: method.name.clone(),
+                request_type: method.input_type.as_str().into(),
+                request_stream: method.client_streaming,
+                response_type: method.output_type.as_str().into(),
+                response_stream: method.server_streaming,
+                options: self.lift_options(&method.options, METHOD_OPTIONS, "MethodOptions")?,
+                comments: self.comments(&child_path(&path, 2, i)),
+                span: None,
+                name_span: None,
+                request_stream_span: None,
+                request_type_span: None,
+                response_stream_span: None,
+                response_type_span: None,
+            });
+        }
+
+        Ok(result)
+    }
+
+    // `options_message` names the *Options message the options belong to, e.g. "FieldOptions".
+    fn lift_options(
+        &self,
+        options: &Option<Options>,
+        specs: &[OptionSpec],
+        options_message: &str,
+    ) -> Result<Vec<ProtoOption>, DescriptorError> {
+        let options = match options {
+            Some(options) => options,
+            None => return Ok(vec![]),
+        };
+
+        let mut result = vec![];
+
+        for field in &options.fields {
+            let spec = match specs.iter().find(|spec| spec.number == field.number) {
+                Some(spec) => spec,
+                None => continue,
+            };
+
+            let value = match (&field.value, spec.option_type) {
+                (OptionValue::Bool(value), _) => ProtoConstant::Boolean(*value),
+                (OptionValue::Str(value), _) => ProtoConstant::Str(value.clone()),
+                (OptionValue::Enum(number), OptionType::Enum(values)) => values
+                    .iter()
+                    .find(|(_, value_number)| value_number == number)
+                    .map(|(name, _)| ProtoConstant::Identifier(name.to_string()))
+                    .ok_or_else(|| {
+                        self.invalid_option(format!(
+                            "{} is not a valid value for option '{}'",
+                            number, spec.name
+                        ))
+                    })?,
+                (OptionValue::Enum(number), _) => ProtoConstant::UInt(*number as u64),
+            };
+
+            result.push(self.option(spec.name, value));
+        }
+
+        for uninterpreted_option in &options.uninterpreted_option {
+            result.push(self.lift_uninterpreted_option(uninterpreted_option)?);
+        }
+
+        for field in &options.unknown_fields {
+            result.push(self.lift_custom_option(field, options_message)?);
+        }
+
+        Ok(result)
+    }
+
+    // Custom options that protoc interpreted are extension fields of the options message. They're lifted back into
+    // options named by the extension's full name, e.g. `(.foo.my_option) = 1`, which means the extension has to be
+    // declared in one of the files of the set.
+    fn lift_custom_option(
+        &self,
+        field: &UnknownField,
+        options_message: &str,
+    ) -> Result<ProtoOption, DescriptorError> {
+        let extendee = format!(".google.protobuf.{}", options_message);
+        let (name, extension) = self
+            .files
+            .iter()
+            .find_map(|file| {
+                Self::find_extension(
+                    file.package.as_deref(),
+                    &file.extension,
+                    &file.message_type,
+                    &extendee,
+                    field.number,
+                )
+            })
+            .ok_or_else(|| {
+                self.invalid_option(format!(
+                    "Option field {} of {} isn't declared by any file in the set",
+                    field.number, options_message
+                ))
+            })?;
+
+        Ok(ProtoOption {
+            name: format!(".{}", name),
+            field_path: None,
+            extension: true,
+            value: self.lift_option_value(extension, field.value.as_field_value())?,
+            span: None,
+            value_span: None,
+            comments: ProtoComments::default(),
+        })
+    }
+
+    // The full name and declaration of the extension of `extendee` with the given number, if it's declared in the
+    // given scope.
+    fn find_extension(
+        scope: Option<&str>,
+        extensions: &'d [FieldDescriptorProto],
+        messages: &'d [DescriptorProto],
+        extendee: &str,
+        number: u32,
+    ) -> Option<(String, &'d FieldDescriptorProto)> {
+        let full_name = |name: &str| match scope {
+            Some(scope) => format!("{}.{}", scope, name),
+            None => name.to_string(),
+        };
+
+        if let Some(extension) = extensions.iter().find(|extension| {
+            extension.extendee.as_deref() == Some(extendee) && extension.number == number as i32
+        }) {
+            return Some((full_name(&extension.name), extension));
+        }
+
+        messages.iter().find_map(|message| {
+            Self::find_extension(
+                Some(&full_name(&message.name)),
+                &message.extension,
+                &message.nested_type,
+                extendee,
+                number,
+            )
+        })
+    }
+
+    fn lift_option_value(
+        &self,
+        field: &FieldDescriptorProto,
+        value: FieldValue,
+    ) -> Result<ProtoConstant, DescriptorError> {
+        // Like the parser, non-negative integers are UInts and negative ones are Ints.
+        let integer = |value: i64| match value {
+            value if value < 0 => ProtoConstant::Int(value),
+            value => ProtoConstant::UInt(value as u64),
+        };
+
+        Ok(match (field.field_type, value) {
+            (FieldType::Int32, FieldValue::Varint(value)) => integer(i64::from(value as i32)),
+            (FieldType::Int64, FieldValue::Varint(value)) => integer(value as i64),
+            (FieldType::UInt32 | FieldType::UInt64, FieldValue::Varint(value)) => {
+                ProtoConstant::UInt(value)
+            }
+            (FieldType::SInt32 | FieldType::SInt64, FieldValue::Varint(value)) => {
+                integer((value >> 1) as i64 ^ -((value & 1) as i64))
+            }
+            (FieldType::Bool, FieldValue::Varint(value)) => ProtoConstant::Boolean(value != 0),
+            (FieldType::Enum, FieldValue::Varint(value)) => {
+                self.lift_enum_constant(field, value as i32)?
+            }
+            (FieldType::Fixed32, FieldValue::Fixed32(value)) => {
+                ProtoConstant::UInt(u64::from(value))
+            }
+            (FieldType::SFixed32, FieldValue::Fixed32(value)) => integer(i64::from(value as i32)),
+            (FieldType::Float, FieldValue::Fixed32(value)) => {
+                ProtoConstant::Float(f64::from(f32::from_bits(value)))
+            }
+            (FieldType::Fixed64, FieldValue::Fixed64(value)) => ProtoConstant::UInt(value),
+            (FieldType::SFixed64, FieldValue::Fixed64(value)) => integer(value as i64),
+            (FieldType::Double, FieldValue::Fixed64(value)) => {
+                ProtoConstant::Float(f64::from_bits(value))
+            }
+            (FieldType::Bytes, FieldValue::LengthDelimited(bytes)) => {
+                ProtoConstant::from_bytes(bytes.to_vec())
+            }
+            (FieldType::String, FieldValue::LengthDelimited(bytes)) => {
+                ProtoConstant::Str(String::from_utf8(bytes.to_vec()).map_err(|_| {
+                    self.invalid_option(format!(
+                        "Option field '{}' has a value that isn't valid UTF-8",
+                        field.name
+                    ))
+                })?)
+            }
+            (FieldType::Message, FieldValue::LengthDelimited(bytes)) => {
+                ProtoConstant::Aggregate(self.lift_aggregate(field, bytes)?)
+            }
+            _ => {
+                return Err(self.invalid_option(format!(
+                    "Option field '{}' has a value that doesn't match its type",
+                    field.name
+                )))
+            }
+        })
+    }
+
+    fn lift_enum_constant(
+        &self,
+        field: &FieldDescriptorProto,
+        number: i32,
+    ) -> Result<ProtoConstant, DescriptorError> {
+        let value = match self.find_type(field.type_name.as_deref()) {
+            Some(DeclaredType::Enum(enumeration)) => enumeration
+                .value
+                .iter()
+                .find(|value| value.number == number),
+            _ => None,
+        };
+
+        value
+            .map(|value| ProtoConstant::Identifier(value.name.clone()))
+            .ok_or_else(|| {
+                self.invalid_option(format!(
+                    "Option field '{}' has the unknown enum value {}",
+                    field.name, number
+                ))
+            })
+    }
+
+    // Message values are lifted into aggregates that list their fields in the order they were written.
+    fn lift_aggregate(
+        &self,
+        field: &FieldDescriptorProto,
+        bytes: &[u8],
+    ) -> Result<Vec<ProtoAggregateField>, DescriptorError> {
+        let message = match self.find_type(field.type_name.as_deref()) {
+            Some(DeclaredType::Message(message)) => message,
+            _ => {
+                return Err(self.invalid_option(format!(
+                    "The type of option field '{}' isn't declared by any file in the set",
+                    field.name
+                )))
+            }
+        };
+
+        let mut result = vec![];
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((number, value)) = decoder.next_field().map_err(DescriptorError::Decode)? {
+            let subfield = message
+                .field
+                .iter()
+                .find(|subfield| subfield.number == number as i32)
+                .ok_or_else(|| {
+                    self.invalid_option(format!(
+                        "Option field '{}' sets field {}, which '{}' doesn't have",
+                        field.name, number, message.name
+                    ))
+                })?;
+
+            // Repeated scalars may be packed.
+            let value = match (Self::wire_type(subfield.field_type), value) {
+                (Some(wire_type), FieldValue::LengthDelimited(_))
+                    if wire_type != WireType::LengthDelimited =>
+                {
+                    ProtoAggregateValue::List(
+                        value
+                            .unpack(wire_type)
+                            .map_err(DescriptorError::Decode)?
+                            .into_iter()
+                            .map(|value| self.lift_option_value(subfield, value))
+                            .collect::<Result<Vec<ProtoConstant>, DescriptorError>>()?,
+                    )
+                }
+                (_, value) => ProtoAggregateValue::Single(self.lift_option_value(subfield, value)?),
+            };
+
+            result.push(ProtoAggregateField {
+                name: subfield.name.clone(),
+                value,
+            });
+        }
+
+        Ok(result)
+    }
+
+    fn wire_type(field_type: FieldType) -> Option<WireType> {
+        match field_type {
+            FieldType::Int32
+            | FieldType::Int64
+            | FieldType::UInt32
+            | FieldType::UInt64
+            | FieldType::SInt32
+            | FieldType::SInt64
+            | FieldType::Bool
+            | FieldType::Enum => Some(WireType::Varint),
+            FieldType::Fixed32 | FieldType::SFixed32 | FieldType::Float => Some(WireType::Fixed32),
+            FieldType::Fixed64 | FieldType::SFixed64 | FieldType::Double => Some(WireType::Fixed64),
+            FieldType::String | FieldType::Bytes | FieldType::Message => {
+                Some(WireType::LengthDelimited)
+            }
+            FieldType::Group => None,
+        }
+    }
+
+    // The message or enum of the set with the given full name, e.g. ".foo.Bar".
+    fn find_type(&self, type_name: Option<&str>) -> Option<DeclaredType<'d>> {
+        let type_name = type_name?;
+
+        self.files.iter().find_map(|file| {
+            let scope = match &file.package {
+                Some(package) => format!(".{}", package),
+                None => String::new(),
+            };
+
+            Self::find_type_in(&scope, &file.message_type, &file.enum_type, type_name)
+        })
+    }
+
+    fn find_type_in(
+        scope: &str,
+        messages: &'d [DescriptorProto],
+        enums: &'d [EnumDescriptorProto],
+        type_name: &str,
+    ) -> Option<DeclaredType<'d>> {
+        if let Some(enumeration) = enums
+            .iter()
+            .find(|enumeration| format!("{}.{}", scope, enumeration.name) == type_name)
+        {
+            return Some(DeclaredType::Enum(enumeration));
+        }
+
+        messages.iter().find_map(|message| {
+            let full_name = format!("{}.{}", scope, message.name);
+
+            if full_name == type_name {
+                Some(DeclaredType::Message(message))
+            } else if type_name.starts_with(&format!("{}.", full_name)) {
+                Self::find_type_in(
+                    &full_name,
+                    &message.nested_type,
+                    &message.enum_type,
+                    type_name,
+                )
+            } else {
+                None
+            }
+        })
+    }
+
+    fn lift_uninterpreted_option(
+        &self,
+        option: &UninterpretedOption,
+    ) -> Result<ProtoOption, DescriptorError> {
+        let (name, field_path) = match option.name.split_first() {
+            Some((name, field_path)) => (name, field_path),
+            None => return Err(self.invalid_option("Option is missing its name".to_string())),
+        };
+
+        let value = if let Some(identifier) = &option.identifier_value {
+            match identifier.as_str() {
+                "true" => ProtoConstant::Boolean(true),
+                "false" => ProtoConstant::Boolean(false),
+                _ => ProtoConstant::Identifier(identifier.clone()),
+            }
+        } else if let Some(value) = option.positive_int_value {
+            ProtoConstant::UInt(value)
+        } else if let Some(value) = option.negative_int_value {
+            ProtoConstant::Int(value)
+        } else if let Some(value) = option.double_value {
+            ProtoConstant::Float(value)
+        } else if let Some(value) = &option.string_value {
+            // Whether the value has to be text depends on the option's type, which isn't known here.
+            ProtoConstant::from_bytes(value.clone())
+        } else if let Some(value) = &option.aggregate_value {
+            ParserImpl::parse_aggregate_text(value).map_err(|err| {
+                self.invalid_option(format!(
+                    "Option '{}' has an invalid aggregate value: {}",
+                    name.name_part, err
+                ))
+            })?
+        } else {
+            return Err(
+                self.invalid_option(format!("Option '{}' is missing its value", name.name_part))
+            );
+        };
+
+        Ok(ProtoOption {
+            name: name.name_part.clone(),
+            field_path: match field_path {
+                [] => None,
+                _ => Some(
+                    field_path
+                        .iter()
+                        .map(|part| part.name_part.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("."),
+                ),
+            },
+            extension: name.is_extension,
+            value,
+            span: None,
+            value_span: None,
+            comments: ProtoComments::default(),
+        })
+    }
+
+    fn option(&self, name: &str, value: ProtoConstant) -> ProtoOption {
+        ProtoOption {
+            name: name.to_string(),
+            field_path: None,
+            extension: false,
+            value,
+            span: None,
+            value_span: None,
+            comments: ProtoComments::default(),
+        }
+    }
+
+    fn comments(&self, path: &[i32]) -> ProtoComments {
+        match self.locations.get(path) {
+            Some(location) => ProtoComments {
+                leading: location.leading_comments.clone(),
+                trailing: location.trailing_comments.clone(),
+                leading_detached: location.leading_detached_comments.clone(),
+            },
+            None => ProtoComments::default(),
+        }
+    }
+
+    // Where an element starts in the source, if the descriptor has SourceCodeInfo for it.
+    fn position(&self, path: &[i32]) -> Option<(i32, i32)> {
+        self.locations
+            .get(path)
+            .and_then(|location| match location.span[..] {
+                [line, column, ..] => Some((line, column)),
+                _ => None,
+            })
+    }
+
+    // Descriptors list messages and enums separately, so their original order can only be recovered from source
+    // positions; without them, messages come first.
+    fn in_source_order<T>(mut elements: Vec<(Option<(i32, i32)>, T)>) -> Vec<T> {
+        elements.sort_by_key(|(position, _)| *position);
+        elements.into_iter().map(|(_, element)| element).collect()
+    }
+
+    fn is_map_entry(message: &DescriptorProto) -> bool {
+        message.options.iter().any(|options| {
+            options.fields.iter().any(|field| {
+                field.number == MAP_ENTRY_OPTION && field.value == OptionValue::Bool(true)
+            })
+        })
+    }
+
+    fn lift_exclusive_range(start: i32, end: i32) -> ProtoRange {
+        ProtoRange {
+            start,
+            end: match end {
+                MAX_FIELD_NUMBER_END => ProtoRangeEnd::Max,
+                end => ProtoRangeEnd::Inclusive(end - 1),
+            },
+            span: None,
+            start_span: None,
+            end_span: None,
+        }
+    }
+
+    fn unsupported(&self, message: String) -> DescriptorError {
+        DescriptorError::Unsupported {
+            file: self.file.name.clone(),
+            message,
+        }
+    }
+
+    fn invalid_option(&self, message: String) -> DescriptorError {
+        DescriptorError::InvalidOption {
+            file: self.file.name.clone(),
+            span: None,
+            message,
+        }
+    }
+}
+
+fn child_path(path: &[i32], field: i32, index: usize) -> Vec<i32> {
+    let mut result = path.to_vec();
+    result.push(field);
+    result.push(index as i32);
+
+    result
+}
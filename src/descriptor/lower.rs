@@ -0,0 +1,1211 @@
+use super::custom_options::{unknown_option, CustomOptions};
+use super::options::*;
+use super::types::*;
+use super::DescriptorError;
+use crate::code_gen::env::*;
+use crate::compiler::Compilation;
+use crate::parser::*;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// The exclusive end of a range that runs to "max" (one past the largest field number).
+const MAX_FIELD_NUMBER_END: i32 = 536_870_912;
+
+// Paths of SourceCodeInfo locations are made of these field numbers from descriptor.proto. The options fields are
+// suffixed with _FIELD, since FILE_OPTIONS and the like are the specs of the options themselves.
+const FILE_PACKAGE: i32 = 2;
+const FILE_DEPENDENCY: i32 = 3;
+const FILE_MESSAGE_TYPE: i32 = 4;
+const FILE_ENUM_TYPE: i32 = 5;
+const FILE_SERVICE: i32 = 6;
+const FILE_EXTENSION: i32 = 7;
+const FILE_OPTIONS_FIELD: i32 = 8;
+const FILE_PUBLIC_DEPENDENCY: i32 = 10;
+const FILE_WEAK_DEPENDENCY: i32 = 11;
+const FILE_SYNTAX: i32 = 12;
+const MESSAGE_NAME: i32 = 1;
+const MESSAGE_FIELD: i32 = 2;
+const MESSAGE_NESTED_TYPE: i32 = 3;
+const MESSAGE_ENUM_TYPE: i32 = 4;
+const MESSAGE_EXTENSION_RANGE: i32 = 5;
+const MESSAGE_EXTENSION: i32 = 6;
+const MESSAGE_OPTIONS_FIELD: i32 = 7;
+const MESSAGE_ONEOF_DECL: i32 = 8;
+const MESSAGE_RESERVED_RANGE: i32 = 9;
+const MESSAGE_RESERVED_NAME: i32 = 10;
+const RANGE_START: i32 = 1;
+const RANGE_END: i32 = 2;
+const FIELD_NAME: i32 = 1;
+const FIELD_EXTENDEE: i32 = 2;
+const FIELD_NUMBER: i32 = 3;
+const FIELD_LABEL: i32 = 4;
+const FIELD_TYPE: i32 = 5;
+const FIELD_TYPE_NAME: i32 = 6;
+const FIELD_DEFAULT_VALUE: i32 = 7;
+const FIELD_OPTIONS_FIELD: i32 = 8;
+const FIELD_JSON_NAME: i32 = 10;
+const ONEOF_NAME: i32 = 1;
+const ONEOF_OPTIONS_FIELD: i32 = 2;
+const ENUM_NAME: i32 = 1;
+const ENUM_VALUE: i32 = 2;
+const ENUM_OPTIONS_FIELD: i32 = 3;
+const ENUM_RESERVED_RANGE: i32 = 4;
+const ENUM_RESERVED_NAME: i32 = 5;
+const ENUM_VALUE_NAME: i32 = 1;
+const ENUM_VALUE_NUMBER: i32 = 2;
+const ENUM_VALUE_OPTIONS_FIELD: i32 = 3;
+const SERVICE_NAME: i32 = 1;
+const SERVICE_METHOD: i32 = 2;
+const SERVICE_OPTIONS_FIELD: i32 = 3;
+const METHOD_NAME: i32 = 1;
+const METHOD_INPUT_TYPE: i32 = 2;
+const METHOD_OUTPUT_TYPE: i32 = 3;
+const METHOD_OPTIONS_FIELD: i32 = 4;
+const METHOD_CLIENT_STREAMING: i32 = 5;
+const METHOD_SERVER_STREAMING: i32 = 6;
+
+// protoc's tokenizer counts columns in bytes, with tabs advancing to the next multiple of 8.
+const TAB_WIDTH: i32 = 8;
+
+// Lowers one file of a compilation into the FileDescriptorProto protoc would produce for it.
+pub fn lower_file(
+    compilation: &Compilation,
+    name: &str,
+    include_source_info: bool,
+) -> Result<FileDescriptorProto, DescriptorError> {
+    let file = compilation
+        .file(name)
+        .ok_or_else(|| DescriptorError::UnknownFile {
+            file: name.to_string(),
+        })?;
+
+    let visible_files = compilation.visible_files(name);
+    let type_hierarchy = ProtoTypeHierarchy::from_programs(
+        &visible_files
+            .iter()
+            .map(|visible_file| &visible_file.program)
+            .collect::<Vec<&Program>>(),
+        full_name_qualifier(),
+    );
+
+    let mut env = GeneratorEnvironment::new(&file.program, Rc::new(type_hierarchy));
+
+    let mut lowering = FileLowering {
+        file: name,
+        program: &file.program,
+        custom_options: CustomOptions::new(compilation, name)?,
+        locations: match include_source_info {
+            true => Some(vec![]),
+            false => None,
+        },
+    };
+
+    lowering.lower(&mut env)
+}
+
+// Types are identified by their full protobuf name, e.g. "foo.bar.Baz".
+pub(super) fn full_name_qualifier() -> IdentifierQualifier {
+    IdentifierQualifier::new(Box::new(|proto_type, parent| {
+        match parent.borrow().full_name.as_str() {
+            "" => proto_type.get_name().to_string(),
+            parent_name => format!("{}.{}", parent_name, proto_type.get_name()),
+        }
+    }))
+}
+
+pub(super) fn primitive_field_type(primitive: &ProtoPrimitiveType) -> FieldType {
+    match primitive {
+        ProtoPrimitiveType::Double => FieldType::Double,
+        ProtoPrimitiveType::Float => FieldType::Float,
+        ProtoPrimitiveType::Int32 => FieldType::Int32,
+        ProtoPrimitiveType::Int64 => FieldType::Int64,
+        ProtoPrimitiveType::UInt32 => FieldType::UInt32,
+        ProtoPrimitiveType::UInt64 => FieldType::UInt64,
+        ProtoPrimitiveType::SInt32 => FieldType::SInt32,
+        ProtoPrimitiveType::SInt64 => FieldType::SInt64,
+        ProtoPrimitiveType::Fixed32 => FieldType::Fixed32,
+        ProtoPrimitiveType::Fixed64 => FieldType::Fixed64,
+        ProtoPrimitiveType::SFixed32 => FieldType::SFixed32,
+        ProtoPrimitiveType::SFixed64 => FieldType::SFixed64,
+        ProtoPrimitiveType::Boolean => FieldType::Bool,
+        ProtoPrimitiveType::Str => FieldType::String,
+        ProtoPrimitiveType::Bytes => FieldType::Bytes,
+        ProtoPrimitiveType::Map(_, _) => FieldType::Message,
+    }
+}
+
+// The zero-based column of a byte offset, as protoc counts them.
+fn column(src: &str, offset: usize) -> i32 {
+    let line_start = src[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+
+    src.as_bytes()[line_start..offset]
+        .iter()
+        .fold(0, |column, byte| match byte {
+            b'\t' => column + TAB_WIDTH - column % TAB_WIDTH,
+            _ => column + 1,
+        })
+}
+
+// protoc's default JSON name: underscores are dropped and the letter after each is capitalized.
+pub(super) fn json_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut capitalize_next = false;
+
+    for ch in name.chars() {
+        if ch == '_' {
+            capitalize_next = true;
+        } else if capitalize_next {
+            result.extend(ch.to_uppercase());
+            capitalize_next = false;
+        } else {
+            result.push(ch);
+        }
+    }
+
+    result
+}
+
+struct FileLowering<'f> {
+    file: &'f str,
+    program: &'f Program,
+    custom_options: CustomOptions<'f>,
+
+    // SourceCodeInfo locations, if they're being recorded, along with where they're ordered in the source.
+    locations: Option<Vec<(usize, Location)>>,
+}
+
+// A message nested in another message, which is either declared or synthesized for a map field.
+enum NestedMessage<'m> {
+    Declared(&'m ProtoType),
+    MapEntry(&'m ProtoMessageField),
+}
+
+impl<'f> FileLowering<'f> {
+    fn lower(
+        &mut self,
+        env: &mut GeneratorEnvironment,
+    ) -> Result<FileDescriptorProto, DescriptorError> {
+        let program = self.program;
+
+        // The file's location covers everything declared in it.
+        let spans = [program.syntax_span, program.package_span]
+            .iter()
+            .copied()
+            .chain(program.imports.iter().map(|import| import.span))
+            .chain(program.options.iter().map(|option| option.span))
+            .chain(program.types.iter().map(ProtoType::get_span))
+            .chain(program.extends.iter().map(|extend| extend.span))
+            .chain(program.services.iter().map(|service| service.span))
+            .flatten()
+            .collect::<Vec<Span>>();
+        if let (Some(first), Some(last)) = (
+            spans.iter().min_by_key(|span| span.start),
+            spans.iter().max_by_key(|span| span.end),
+        ) {
+            let span = Span {
+                start: first.start,
+                end: last.end,
+                start_line: first.start_line,
+                start_column: first.start_column,
+                end_line: last.end_line,
+                end_column: last.end_column,
+            };
+
+            self.record_location(vec![], Some(span), None);
+        }
+
+        self.record_location(
+            vec![FILE_SYNTAX],
+            program.syntax_span,
+            Some(&program.syntax_comments),
+        );
+        self.record_location(
+            vec![FILE_PACKAGE],
+            program.package_span,
+            Some(&program.package_comments),
+        );
+
+        let mut result = FileDescriptorProto {
+            name: self.file.to_string(),
+            package: program.package.clone(),
+            options: self.lower_options(
+                &program.options,
+                FILE_OPTIONS,
+                "FileOptions",
+                env,
+                vec![FILE_OPTIONS_FIELD],
+                true,
+            )?,
+            ..FileDescriptorProto::default()
+        };
+
+        for (i, import) in program.imports.iter().enumerate() {
+            self.record_location(
+                vec![FILE_DEPENDENCY, i as i32],
+                import.span,
+                Some(&import.comments),
+            );
+
+            result.dependency.push(import.path.clone());
+            let modifier_dependencies = match import.modifier {
+                Some(ProtoImportModifier::Public) => {
+                    Some((FILE_PUBLIC_DEPENDENCY, &mut result.public_dependency))
+                }
+                Some(ProtoImportModifier::Weak) => {
+                    Some((FILE_WEAK_DEPENDENCY, &mut result.weak_dependency))
+                }
+                None => None,
+            };
+            if let Some((field, dependencies)) = modifier_dependencies {
+                self.record_location(
+                    vec![field, dependencies.len() as i32],
+                    import.modifier_span,
+                    None,
+                );
+                dependencies.push(i as i32);
+            }
+        }
+
+        for proto_type in &program.types {
+            let child_env = env
+                .new_child(proto_type)
+                .map_err(|err| self.resolution_error(err))?;
+            let mut child_env = child_env.borrow_mut();
+
+            match proto_type {
+                ProtoType::Message(message) => {
+                    let path = vec![FILE_MESSAGE_TYPE, result.message_type.len() as i32];
+                    result
+                        .message_type
+                        .push(self.lower_message(message, &mut child_env, path)?);
+                }
+                ProtoType::Enum(enumeration) => {
+                    let path = vec![FILE_ENUM_TYPE, result.enum_type.len() as i32];
+                    result
+                        .enum_type
+                        .push(self.lower_enum(enumeration, env, path)?);
+                }
+            }
+        }
+
+        for (i, service) in program.services.iter().enumerate() {
+            result
+                .service
+                .push(self.lower_service(service, env, vec![FILE_SERVICE, i as i32])?);
+        }
+
+        result.extension = self.lower_extends(&program.extends, env, vec![FILE_EXTENSION])?;
+
+        if program.syntax == Some(ProtoSyntax::Proto3) {
+            result.syntax = Some("proto3".to_string());
+        }
+
+        // protoc records locations as it parses, so they're listed in source order (with parents before children).
+        result.source_code_info = self.locations.take().map(|mut locations| {
+            locations.sort_by_key(|(order, _)| *order);
+
+            SourceCodeInfo {
+                location: locations
+                    .into_iter()
+                    .map(|(_, location)| location)
+                    .collect(),
+            }
+        });
+
+        Ok(result)
+    }
+
+    fn lower_message(
+        &mut self,
+        message: &ProtoMessage,
+        env: &mut GeneratorEnvironment,
+        path: Vec<i32>,
+    ) -> Result<DescriptorProto, DescriptorError> {
+        self.record_location(path.clone(), message.span, Some(&message.comments));
+        self.record_location(
+            Self::member_path(&path, MESSAGE_NAME),
+            message.name_span,
+            None,
+        );
+
+        let mut result = DescriptorProto {
+            name: message.name.to_string(),
+            options: self.lower_options(
+                &message.options,
+                MESSAGE_OPTIONS,
+                "MessageOptions",
+                env,
+                Self::member_path(&path, MESSAGE_OPTIONS_FIELD),
+                true,
+            )?,
+            ..DescriptorProto::default()
+        };
+
+        // Fields are listed in declaration order, with oneof members in between the other fields.
+        let mut fields = message
+            .fields
+            .iter()
+            .map(|field| (field, None))
+            .chain(message.oneofs.iter().enumerate().flat_map(|(i, oneof)| {
+                oneof
+                    .fields
+                    .iter()
+                    .map(move |field| (field, Some(i as i32)))
+            }))
+            .collect::<Vec<(&ProtoMessageField, Option<i32>)>>();
+        fields.sort_by_key(|(field, _)| field.span.map(|span| span.start));
+
+        for (i, oneof) in message.oneofs.iter().enumerate() {
+            let oneof_path = Self::child_path(&path, MESSAGE_ONEOF_DECL, i);
+            self.record_location(oneof_path.clone(), oneof.span, Some(&oneof.comments));
+            self.record_location(
+                Self::member_path(&oneof_path, ONEOF_NAME),
+                oneof.name_span,
+                None,
+            );
+
+            result.oneof_decl.push(OneofDescriptorProto {
+                name: oneof.name.to_string(),
+                options: self.lower_options(
+                    &oneof.options,
+                    ONEOF_OPTIONS,
+                    "OneofOptions",
+                    env,
+                    Self::member_path(&oneof_path, ONEOF_OPTIONS_FIELD),
+                    true,
+                )?,
+            });
+        }
+
+        for (i, (field, oneof_index)) in fields.iter().enumerate() {
+            let field_path = Self::child_path(&path, MESSAGE_FIELD, i);
+            let mut lowered = self.lower_field(field, env, field_path, None)?;
+            lowered.oneof_index = *oneof_index;
+
+            // proto3 optional fields are each wrapped in a synthetic oneof, which are listed after the real ones.
+            if self.program.syntax == Some(ProtoSyntax::Proto3)
+                && field.modifier == Some(ProtoMessageFieldModifier::Optional)
+            {
+                let mut oneof_name = format!("_{}", field.name);
+                while fields.iter().any(|(other, _)| other.name == oneof_name)
+                    || result
+                        .oneof_decl
+                        .iter()
+                        .any(|oneof| oneof.name == oneof_name)
+                {
+                    oneof_name = format!("X{}", oneof_name);
+                }
+
+                lowered.proto3_optional = true;
+                lowered.oneof_index = Some(result.oneof_decl.len() as i32);
+                result.oneof_decl.push(OneofDescriptorProto {
+                    name: oneof_name,
+                    options: None,
+                });
+            }
+
+            result.field.push(lowered);
+        }
+
+        // Map entries take the place of their field among the nested messages.
+        let mut nested_messages = message
+            .types
+            .iter()
+            .filter(|proto_type| matches!(proto_type, ProtoType::Message(_)))
+            .map(|proto_type| (proto_type.get_span(), NestedMessage::Declared(proto_type)))
+            .chain(
+                fields
+                    .iter()
+                    .filter(|(field, _)| Self::is_map(field))
+                    .map(|(field, _)| (field.span, NestedMessage::MapEntry(field))),
+            )
+            .collect::<Vec<(Option<Span>, NestedMessage)>>();
+        nested_messages.sort_by_key(|(span, _)| span.map(|span| span.start));
+
+        for (i, (_, nested_message)) in nested_messages.iter().enumerate() {
+            let nested = match nested_message {
+                NestedMessage::Declared(proto_type @ ProtoType::Message(nested)) => {
+                    let child_env = env
+                        .new_child(proto_type)
+                        .map_err(|err| self.resolution_error(err))?;
+
+                    let nested = self.lower_message(
+                        nested,
+                        &mut child_env.borrow_mut(),
+                        Self::child_path(&path, MESSAGE_NESTED_TYPE, i),
+                    )?;
+
+                    nested
+                }
+                NestedMessage::Declared(ProtoType::Enum(_)) => continue,
+                NestedMessage::MapEntry(field) => self.lower_map_entry(field, env)?,
+            };
+
+            result.nested_type.push(nested);
+        }
+
+        for proto_type in &message.types {
+            if let ProtoType::Enum(enumeration) = proto_type {
+                let enum_path = Self::child_path(&path, MESSAGE_ENUM_TYPE, result.enum_type.len());
+                result
+                    .enum_type
+                    .push(self.lower_enum(enumeration, env, enum_path)?);
+            }
+        }
+
+        for statement in &message.extensions_statements {
+            self.record_location(
+                Self::member_path(&path, MESSAGE_EXTENSION_RANGE),
+                statement.span,
+                Some(&statement.comments),
+            );
+        }
+
+        for (i, range) in message.extension_ranges.iter().enumerate() {
+            self.record_range_location(Self::child_path(&path, MESSAGE_EXTENSION_RANGE, i), range);
+
+            result.extension_range.push(ExtensionRange {
+                start: range.start,
+                end: Self::exclusive_end(range),
+            });
+        }
+
+        result.extension = self.lower_extends(
+            &message.extends,
+            env,
+            Self::member_path(&path, MESSAGE_EXTENSION),
+        )?;
+
+        self.record_reserved_locations(
+            &message.reserved,
+            &message.reserved_statements,
+            &path,
+            (MESSAGE_RESERVED_RANGE, MESSAGE_RESERVED_NAME),
+        );
+
+        for reserved in &message.reserved {
+            match reserved {
+                ProtoReserved::Range(range) => result.reserved_range.push(ReservedRange {
+                    start: range.start,
+                    end: Self::exclusive_end(range),
+                }),
+                ProtoReserved::Name(name, _) => result.reserved_name.push(name.clone()),
+            }
+        }
+
+        Ok(result)
+    }
+
+    // `extendee_span` is where an extension field's extend block names the message it extends.
+    fn lower_field(
+        &mut self,
+        field: &ProtoMessageField,
+        env: &mut GeneratorEnvironment,
+        path: Vec<i32>,
+        extendee_span: Option<Span>,
+    ) -> Result<FieldDescriptorProto, DescriptorError> {
+        self.record_location(path.clone(), field.span, Some(&field.comments));
+
+        // protoc locates the extendee right after each of the block's fields, even though it comes first in the source.
+        if let (Some(field_span), Some(extendee_span)) = (field.span, extendee_span) {
+            self.push_location(
+                field_span.start,
+                Self::member_path(&path, FIELD_EXTENDEE),
+                extendee_span,
+                None,
+            );
+        }
+
+        let type_field = match field.field_type {
+            ProtoFieldType::Primitive(ProtoPrimitiveType::Map(_, _))
+            | ProtoFieldType::IdentifierPath(_) => FIELD_TYPE_NAME,
+            ProtoFieldType::Primitive(_) => FIELD_TYPE,
+        };
+        for (member, span) in [
+            (FIELD_LABEL, field.modifier_span),
+            (type_field, field.type_span),
+            (FIELD_NAME, field.name_span),
+            (FIELD_NUMBER, field.position_span),
+            (FIELD_OPTIONS_FIELD, field.options_span),
+        ] {
+            self.record_location(Self::member_path(&path, member), span, None);
+        }
+
+        let label = match field.modifier {
+            Some(ProtoMessageFieldModifier::Required) => FieldLabel::Required,
+            Some(ProtoMessageFieldModifier::Repeated) => FieldLabel::Repeated,
+            _ if Self::is_map(field) => FieldLabel::Repeated,
+            _ => FieldLabel::Optional,
+        };
+
+        let (field_type, type_name) = match &field.field_type {
+            ProtoFieldType::Primitive(ProtoPrimitiveType::Map(_, _)) => {
+                let message_name = env
+                    .get_fully_qualified_identifier()
+                    .expect("expected map fields to be declared in a message");
+
+                (
+                    FieldType::Message,
+                    Some(format!(
+                        ".{}.{}",
+                        message_name,
+                        Self::map_entry_name(&field.name)
+                    )),
+                )
+            }
+            field_type => self.lower_field_type(field_type, field.span, env)?,
+        };
+
+        let mut default_value = None;
+        let mut custom_json_name = None;
+        let mut options = vec![];
+        for option in &field.options {
+            match (option.name.as_str(), &option.field_path, option.extension) {
+                ("default", None, false) => {
+                    self.record_location(
+                        Self::member_path(&path, FIELD_DEFAULT_VALUE),
+                        option.value_span,
+                        None,
+                    );
+                    default_value = Some(self.lower_default_value(option, field_type)?)
+                }
+                ("json_name", None, false) => match &option.value {
+                    ProtoConstant::Str(value) => {
+                        // Both the whole option and its value.
+                        for span in [option.span, option.value_span] {
+                            self.record_location(
+                                Self::member_path(&path, FIELD_JSON_NAME),
+                                span,
+                                None,
+                            );
+                        }
+                        custom_json_name = Some(value.clone())
+                    }
+                    _ => {
+                        return Err(self.invalid_option(
+                            option.span,
+                            "Option 'json_name' expects a string".to_string(),
+                        ))
+                    }
+                },
+                _ => options.push(option.clone()),
+            }
+        }
+
+        Ok(FieldDescriptorProto {
+            name: field.name.to_string(),
+            extendee: None,
+            number: field.position as i32,
+            label,
+            field_type,
+            type_name,
+            default_value,
+            options: self.lower_options(
+                &options,
+                FIELD_OPTIONS,
+                "FieldOptions",
+                env,
+                Self::member_path(&path, FIELD_OPTIONS_FIELD),
+                false,
+            )?,
+            oneof_index: None,
+            json_name: Some(custom_json_name.unwrap_or_else(|| json_name(&field.name))),
+            proto3_optional: false,
+        })
+    }
+
+    fn lower_field_type(
+        &self,
+        field_type: &ProtoFieldType,
+        span: Option<Span>,
+        env: &GeneratorEnvironment,
+    ) -> Result<(FieldType, Option<String>), DescriptorError> {
+        let primitive = match field_type {
+            ProtoFieldType::Primitive(primitive) => primitive,
+            ProtoFieldType::IdentifierPath(path) => {
+                let node = env
+                    .resolve_type_node(path, span)
+                    .map_err(|err| self.resolution_error(err))?;
+                let node = node.borrow();
+
+                let field_type = match node.proto_type.as_deref() {
+                    Some(ProtoType::Enum(_)) => FieldType::Enum,
+                    _ => FieldType::Message,
+                };
+
+                return Ok((field_type, Some(format!(".{}", node.full_name))));
+            }
+        };
+
+        Ok((primitive_field_type(primitive), None))
+    }
+
+    // The message protoc synthesizes for a map field, e.g. `map<string, Foo> my_map = 1;` gets
+    // `message MyMapEntry { option map_entry = true; optional string key = 1; optional Foo value = 2; }`.
+    fn lower_map_entry(
+        &self,
+        field: &ProtoMessageField,
+        env: &GeneratorEnvironment,
+    ) -> Result<DescriptorProto, DescriptorError> {
+        let (key_type, value_type) = match &field.field_type {
+            ProtoFieldType::Primitive(ProtoPrimitiveType::Map(key_type, value_type)) => {
+                (key_type, value_type)
+            }
+            _ => unreachable!("expected a map field"),
+        };
+
+        let mut entry_fields = vec![];
+        for (number, name, entry_type) in [(1, "key", key_type), (2, "value", value_type)] {
+            let (field_type, type_name) = self.lower_field_type(entry_type, field.span, env)?;
+
+            entry_fields.push(FieldDescriptorProto {
+                name: name.to_string(),
+                extendee: None,
+                number,
+                label: FieldLabel::Optional,
+                field_type,
+                type_name,
+                default_value: None,
+                options: None,
+                oneof_index: None,
+                json_name: Some(name.to_string()),
+                proto3_optional: false,
+            });
+        }
+
+        Ok(DescriptorProto {
+            name: Self::map_entry_name(&field.name),
+            field: entry_fields,
+            options: Some(Options {
+                fields: vec![OptionField {
+                    number: MAP_ENTRY_OPTION,
+                    value: OptionValue::Bool(true),
+                }],
+                uninterpreted_option: vec![],
+                unknown_fields: vec![],
+            }),
+            ..DescriptorProto::default()
+        })
+    }
+
+    // Extensions from every extend block in a scope, which share one list. `path` is the path of that list.
+    fn lower_extends(
+        &mut self,
+        extends: &[ProtoExtend],
+        env: &mut GeneratorEnvironment,
+        path: Vec<i32>,
+    ) -> Result<Vec<FieldDescriptorProto>, DescriptorError> {
+        let mut result = vec![];
+
+        for extend in extends {
+            // Every extend block in the scope is located at the list's path.
+            self.record_location(path.clone(), extend.span, Some(&extend.comments));
+
+            let extendee = env
+                .resolve_type_node(&extend.extendee, extend.span)
+                .map_err(|err| self.resolution_error(err))?;
+            let extendee = format!(".{}", extendee.borrow().full_name);
+
+            for field in &extend.fields {
+                let mut field_path = path.clone();
+                field_path.push(result.len() as i32);
+
+                let mut lowered = self.lower_field(field, env, field_path, extend.extendee_span)?;
+                lowered.extendee = Some(extendee.clone());
+
+                result.push(lowered);
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn lower_enum(
+        &mut self,
+        enumeration: &ProtoEnum,
+        env: &GeneratorEnvironment,
+        path: Vec<i32>,
+    ) -> Result<EnumDescriptorProto, DescriptorError> {
+        self.record_location(path.clone(), enumeration.span, Some(&enumeration.comments));
+        self.record_location(
+            Self::member_path(&path, ENUM_NAME),
+            enumeration.name_span,
+            None,
+        );
+
+        let mut result = EnumDescriptorProto {
+            name: enumeration.name.to_string(),
+            options: self.lower_options(
+                &enumeration.options,
+                ENUM_OPTIONS,
+                "EnumOptions",
+                env,
+                Self::member_path(&path, ENUM_OPTIONS_FIELD),
+                true,
+            )?,
+            ..EnumDescriptorProto::default()
+        };
+
+        for (i, value) in enumeration.values.iter().enumerate() {
+            let value_path = Self::child_path(&path, ENUM_VALUE, i);
+            self.record_location(value_path.clone(), value.span, Some(&value.comments));
+            for (member, span) in [
+                (ENUM_VALUE_NAME, value.name_span),
+                (ENUM_VALUE_NUMBER, value.position_span),
+                (ENUM_VALUE_OPTIONS_FIELD, value.options_span),
+            ] {
+                self.record_location(Self::member_path(&value_path, member), span, None);
+            }
+
+            result.value.push(EnumValueDescriptorProto {
+                name: value.name.clone(),
+                number: value.position,
+                options: self.lower_options(
+                    &value.options,
+                    ENUM_VALUE_OPTIONS,
+                    "EnumValueOptions",
+                    env,
+                    Self::member_path(&value_path, ENUM_VALUE_OPTIONS_FIELD),
+                    false,
+                )?,
+            });
+        }
+
+        self.record_reserved_locations(
+            &enumeration.reserved,
+            &enumeration.reserved_statements,
+            &path,
+            (ENUM_RESERVED_RANGE, ENUM_RESERVED_NAME),
+        );
+
+        // Unlike messages, enum reserved ranges are inclusive.
+        for reserved in &enumeration.reserved {
+            match reserved {
+                ProtoReserved::Range(range) => result.reserved_range.push(ReservedRange {
+                    start: range.start,
+                    end: match range.end {
+                        ProtoRangeEnd::Inclusive(end) => end,
+                        ProtoRangeEnd::Max => i32::MAX,
+                    },
+                }),
+                ProtoReserved::Name(name, _) => result.reserved_name.push(name.clone()),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn lower_service(
+        &mut self,
+        service: &ProtoService,
+        env: &GeneratorEnvironment,
+        path: Vec<i32>,
+    ) -> Result<ServiceDescriptorProto, DescriptorError> {
+        self.record_location(path.clone(), service.span, Some(&service.comments));
+        self.record_location(
+            Self::member_path(&path, SERVICE_NAME),
+            service.name_span,
+            None,
+        );
+
+        let mut result = ServiceDescriptorProto {
+            name: service.name.to_string(),
+            options: self.lower_options(
+                &service.options,
+                SERVICE_OPTIONS,
+                "ServiceOptions",
+                env,
+                Self::member_path(&path, SERVICE_OPTIONS_FIELD),
+                true,
+            )?,
+            ..ServiceDescriptorProto::default()
+        };
+
+        for (i, rpc) in service.rpcs.iter().enumerate() {
+            let method_path = Self::child_path(&path, SERVICE_METHOD, i);
+            self.record_location(method_path.clone(), rpc.span, Some(&rpc.comments));
+            for (member, span) in [
+                (METHOD_NAME, rpc.name_span),
+                (METHOD_CLIENT_STREAMING, rpc.request_stream_span),
+                (METHOD_INPUT_TYPE, rpc.request_type_span),
+                (METHOD_SERVER_STREAMING, rpc.response_stream_span),
+                (METHOD_OUTPUT_TYPE, rpc.response_type_span),
+            ] {
+                self.record_location(Self::member_path(&method_path, member), span, None);
+            }
+
+            let resolve = |type_path| {
+                env.resolve_type_node(type_path, rpc.span)
+                    .map(|node| format!(".{}", node.borrow().full_name))
+                    .map_err(|err| self.resolution_error(err))
+            };
+
+            result.method.push(MethodDescriptorProto {
+                name: rpc.name.to_string(),
+                input_type: resolve(&rpc.request_type)?,
+                output_type: resolve(&rpc.response_type)?,
+                options: self.lower_options(
+                    &rpc.options,
+                    METHOD_OPTIONS,
+                    "MethodOptions",
+                    env,
+                    Self::member_path(&method_path, METHOD_OPTIONS_FIELD),
+                    true,
+                )?,
+                client_streaming: rpc.request_stream,
+                server_streaming: rpc.response_stream,
+            });
+        }
+
+        Ok(result)
+    }
+
+    // Standard options are stored in their fields, and custom options as the extension fields they set.
+    // `options_message` names the *Options message they belong to, e.g. "FieldOptions", and `path` is the path of the
+    // element's options field. Options set by `statements` each have a location at `path` too, while options in
+    // brackets share the brackets' location, which is left to the caller.
+    fn lower_options(
+        &mut self,
+        options: &[ProtoOption],
+        specs: &[OptionSpec],
+        options_message: &str,
+        env: &GeneratorEnvironment,
+        path: Vec<i32>,
+        statements: bool,
+    ) -> Result<Option<Options>, DescriptorError> {
+        let mut result = Options::default();
+
+        // Values of repeated options are located by their index, counted separately for each option.
+        let mut repeated_counts = HashMap::new();
+
+        // Option names are looked up from the innermost scope outwards.
+        let scope = env
+            .get_fully_qualified_identifier()
+            .or_else(|| self.program.package.clone());
+
+        for option in options {
+            if statements {
+                self.record_location(path.clone(), option.span, None);
+            }
+
+            if option.extension {
+                let (field, option_path) = self
+                    .custom_options
+                    .interpret(option, scope.as_deref(), options_message)
+                    .map_err(|message| self.invalid_option(option.span, message))?;
+
+                let mut location_path = path.clone();
+                location_path.extend(&option_path.numbers);
+                if option_path.repeated {
+                    let count = repeated_counts.entry(location_path.clone()).or_insert(0);
+                    location_path.push(*count);
+                    *count += 1;
+                }
+                self.record_location(location_path, option.span, Some(&option.comments));
+
+                result.unknown_fields.push(field);
+                continue;
+            }
+
+            let spec = find_option(specs, &option.name)
+                .ok_or_else(|| self.invalid_option(option.span, unknown_option(&option.name)))?;
+            if option.field_path.is_some() {
+                return Err(self.invalid_option(
+                    option.span,
+                    format!(
+                        "Option \"{}\" is an atomic type, not a message.",
+                        option.name
+                    ),
+                ));
+            }
+
+            let value = self.lower_option_value(option, spec)?;
+            self.record_location(
+                Self::member_path(&path, spec.number as i32),
+                option.span,
+                Some(&option.comments),
+            );
+
+            // Like any other singular field, the last value wins.
+            result.fields.retain(|field| field.number != spec.number);
+            result.fields.push(OptionField {
+                number: spec.number,
+                value,
+            });
+        }
+
+        result.unknown_fields = self
+            .custom_options
+            .canonicalize(result.unknown_fields, options_message)
+            .map_err(|message| self.invalid_option(None, message))?;
+
+        if result.fields.is_empty() && result.unknown_fields.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(result))
+        }
+    }
+
+    fn lower_option_value(
+        &self,
+        option: &ProtoOption,
+        spec: OptionSpec,
+    ) -> Result<OptionValue, DescriptorError> {
+        match (spec.option_type, &option.value) {
+            (OptionType::Bool, ProtoConstant::Boolean(value)) => Ok(OptionValue::Bool(*value)),
+            (OptionType::Bool, _) => Err(self.invalid_option(
+                option.span,
+                format!("Option '{}' expects a boolean", spec.name),
+            )),
+            (OptionType::Str, ProtoConstant::Str(value)) => Ok(OptionValue::Str(value.clone())),
+            (OptionType::Str, ProtoConstant::Bytes(_)) => Err(self.invalid_option(
+                option.span,
+                format!(
+                    "Option '{}' expects a string, which has to be valid UTF-8",
+                    spec.name
+                ),
+            )),
+            (OptionType::Str, _) => Err(self.invalid_option(
+                option.span,
+                format!("Option '{}' expects a string", spec.name),
+            )),
+            (OptionType::Enum(values), ProtoConstant::Identifier(identifier)) => values
+                .iter()
+                .find(|(name, _)| name == identifier)
+                .map(|(_, number)| OptionValue::Enum(*number))
+                .ok_or_else(|| {
+                    self.invalid_option(
+                        option.span,
+                        format!(
+                            "'{}' is not a valid value for option '{}'",
+                            identifier, spec.name
+                        ),
+                    )
+                }),
+            (OptionType::Enum(_), _) => Err(self.invalid_option(
+                option.span,
+                format!("Option '{}' expects an enum value", spec.name),
+            )),
+        }
+    }
+
+    // Default values are stored as text: strings as-is, bytes escaped, enums by value name and numbers in decimal.
+    fn lower_default_value(
+        &self,
+        option: &ProtoOption,
+        field_type: FieldType,
+    ) -> Result<String, DescriptorError> {
+        match (&option.value, field_type) {
+            (ProtoConstant::Str(value), FieldType::Bytes) => Ok(Self::c_escape(value.as_bytes())),
+            (ProtoConstant::Str(value), _) => Ok(value.clone()),
+            (ProtoConstant::Bytes(value), FieldType::Bytes) => Ok(Self::c_escape(value)),
+            (ProtoConstant::Bytes(_), _) => Err(self.invalid_option(
+                option.span,
+                "Default values that aren't valid UTF-8 are only allowed for bytes fields"
+                    .to_string(),
+            )),
+            (ProtoConstant::Int(value), _) => Ok(value.to_string()),
+            (ProtoConstant::UInt(value), _) => Ok(value.to_string()),
+            (ProtoConstant::Float(value), FieldType::Float) => {
+                Ok(Self::float_text(f64::from(*value as f32)))
+            }
+            (ProtoConstant::Float(value), _) => Ok(Self::float_text(*value)),
+            (ProtoConstant::Boolean(value), _) => Ok(value.to_string()),
+            (ProtoConstant::Identifier(value), _) => Ok(value.clone()),
+            (ProtoConstant::Aggregate(_), _) => {
+                Err(self
+                    .invalid_option(option.span, "Default values can't be messages".to_string()))
+            }
+        }
+    }
+
+    fn float_text(value: f64) -> String {
+        if value.is_nan() {
+            "nan".to_string()
+        } else {
+            // Infinities display as "inf" and "-inf", as protoc writes them.
+            value.to_string()
+        }
+    }
+
+    // Escapes bytes the way protoc's CEscape does.
+    fn c_escape(bytes: &[u8]) -> String {
+        let mut result = String::new();
+
+        for byte in bytes {
+            match byte {
+                b'\n' => result.push_str("\\n"),
+                b'\r' => result.push_str("\\r"),
+                b'\t' => result.push_str("\\t"),
+                b'"' => result.push_str("\\\""),
+                b'\'' => result.push_str("\\'"),
+                b'\\' => result.push_str("\\\\"),
+                0x20..=0x7e => result.push(*byte as char),
+                _ => result.push_str(&format!("\\{:03o}", byte)),
+            }
+        }
+
+        result
+    }
+
+    // The name of the entry message for a map field, e.g. "my_map" becomes "MyMapEntry".
+    fn map_entry_name(name: &str) -> String {
+        let mut result = String::new();
+        let mut capitalize_next = true;
+
+        for ch in name.chars() {
+            if ch == '_' {
+                capitalize_next = true;
+            } else if capitalize_next {
+                result.extend(ch.to_uppercase());
+                capitalize_next = false;
+            } else {
+                result.push(ch);
+            }
+        }
+
+        result + "Entry"
+    }
+
+    fn is_map(field: &ProtoMessageField) -> bool {
+        matches!(
+            field.field_type,
+            ProtoFieldType::Primitive(ProtoPrimitiveType::Map(_, _))
+        )
+    }
+
+    fn exclusive_end(range: &ProtoRange) -> i32 {
+        match range.end {
+            ProtoRangeEnd::Inclusive(end) => end + 1,
+            ProtoRangeEnd::Max => MAX_FIELD_NUMBER_END,
+        }
+    }
+
+    fn member_path(path: &[i32], field: i32) -> Vec<i32> {
+        let mut result = path.to_vec();
+        result.push(field);
+
+        result
+    }
+
+    fn child_path(path: &[i32], field: i32, index: usize) -> Vec<i32> {
+        let mut result = path.to_vec();
+        result.push(field);
+        result.push(index as i32);
+
+        result
+    }
+
+    fn record_range_location(&mut self, path: Vec<i32>, range: &ProtoRange) {
+        self.record_location(path.clone(), range.span, None);
+        self.record_location(
+            Self::member_path(&path, RANGE_START),
+            range.start_span,
+            None,
+        );
+        self.record_location(Self::member_path(&path, RANGE_END), range.end_span, None);
+    }
+
+    // Reserved ranges and names are listed in separate fields of the element at `path`, and each statement is located
+    // by the field of what it reserves.
+    fn record_reserved_locations(
+        &mut self,
+        reserved: &[ProtoReserved],
+        statements: &[ProtoStatement],
+        path: &[i32],
+        (ranges_field, names_field): (i32, i32),
+    ) {
+        let mut declared = 0;
+        for statement in statements {
+            let field = match reserved.get(declared) {
+                Some(ProtoReserved::Name(_, _)) => names_field,
+                _ => ranges_field,
+            };
+            self.record_location(
+                Self::member_path(path, field),
+                statement.span,
+                Some(&statement.comments),
+            );
+
+            declared += statement.len;
+        }
+
+        let (mut ranges, mut names) = (0, 0);
+        for reservation in reserved {
+            match reservation {
+                ProtoReserved::Range(range) => {
+                    self.record_range_location(Self::child_path(path, ranges_field, ranges), range);
+                    ranges += 1;
+                }
+                ProtoReserved::Name(_, span) => {
+                    self.record_location(Self::child_path(path, names_field, names), *span, None);
+                    names += 1;
+                }
+            }
+        }
+    }
+
+    fn record_location(
+        &mut self,
+        path: Vec<i32>,
+        span: Option<Span>,
+        comments: Option<&ProtoComments>,
+    ) {
+        if let Some(span) = span {
+            self.push_location(span.start, path, span, comments);
+        }
+    }
+
+    // Locations are listed by `order`, an offset into the source, and in the order they're pushed when that's the same.
+    fn push_location(
+        &mut self,
+        order: usize,
+        path: Vec<i32>,
+        span: Span,
+        comments: Option<&ProtoComments>,
+    ) {
+        let src = self.program.src.as_str();
+        let locations = match &mut self.locations {
+            Some(locations) => locations,
+            None => return,
+        };
+
+        // Locations are zero-based, and leave out the end line when it's the same as the start line.
+        let (start_line, start_column) = (span.start_line as i32 - 1, column(src, span.start));
+        let (end_line, end_column) = (span.end_line as i32 - 1, column(src, span.end));
+        let span = if start_line == end_line {
+            vec![start_line, start_column, end_column]
+        } else {
+            vec![start_line, start_column, end_line, end_column]
+        };
+
+        let comments = comments.cloned().unwrap_or_default();
+
+        locations.push((
+            order,
+            Location {
+                path,
+                span,
+                leading_comments: comments.leading,
+                trailing_comments: comments.trailing,
+                leading_detached_comments: comments.leading_detached,
+            },
+        ));
+    }
+
+    fn resolution_error(&self, error: Box<ResolutionError>) -> DescriptorError {
+        DescriptorError::Resolution {
+            file: self.file.to_string(),
+            error,
+        }
+    }
+
+    fn invalid_option(&self, span: Option<Span>, message: String) -> DescriptorError {
+        DescriptorError::InvalidOption {
+            file: self.file.to_string(),
+            span,
+            message,
+        }
+    }
+}
@@ -0,0 +1,705 @@
+mod custom_options;
+mod error;
+mod lift;
+mod lower;
+mod options;
+mod types;
+mod wire;
+
+pub use error::*;
+pub use lift::lift_file;
+pub use lower::lower_file;
+pub use options::*;
+pub use types::*;
+pub use wire::*;
+
+use crate::compiler::{Compilation, CompiledFile};
+
+// Which parts of a compilation to write, mirroring protoc's --include_imports and --include_source_info flags.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DescriptorSetOptions {
+    pub include_imports: bool,
+    pub include_source_info: bool,
+}
+
+// Builds the FileDescriptorSet protoc would write with --descriptor_set_out for the given files of a compilation.
+pub fn build_descriptor_set(
+    compilation: &Compilation,
+    names: &[&str],
+    options: DescriptorSetOptions,
+) -> Result<FileDescriptorSet, DescriptorError> {
+    for name in names {
+        if compilation.file(name).is_none() {
+            return Err(DescriptorError::UnknownFile {
+                file: name.to_string(),
+            });
+        }
+    }
+
+    // The compilation lists files with their dependencies first, which is the order protoc writes them in.
+    let files = if options.include_imports {
+        let mut required = vec![];
+        for name in names {
+            add_dependencies(compilation, name, &mut required);
+        }
+
+        compilation
+            .files
+            .iter()
+            .map(|file| file.name.as_str())
+            .filter(|name| required.contains(name))
+            .collect::<Vec<&str>>()
+    } else {
+        names.to_vec()
+    };
+
+    let mut result = FileDescriptorSet::default();
+    for name in files {
+        result
+            .file
+            .push(lower_file(compilation, name, options.include_source_info)?);
+    }
+
+    Ok(result)
+}
+
+// Encodes the FileDescriptorSet for the given files of a compilation.
+pub fn encode_descriptor_set(
+    compilation: &Compilation,
+    names: &[&str],
+    options: DescriptorSetOptions,
+) -> Result<Vec<u8>, DescriptorError> {
+    Ok(build_descriptor_set(compilation, names, options)?.encode_to_vec())
+}
+
+// Decodes a binary FileDescriptorSet, e.g. one written by protoc with --descriptor_set_out.
+pub fn decode_descriptor_set(bytes: &[u8]) -> Result<FileDescriptorSet, DescriptorError> {
+    FileDescriptorSet::decode(bytes).map_err(DescriptorError::Decode)
+}
+
+// Builds a compilation from the files of a descriptor set, so that code can be generated without the original sources.
+// The set must list each file's dependencies ahead of it, which protoc does.
+pub fn compile_descriptor_set(set: &FileDescriptorSet) -> Result<Compilation, DescriptorError> {
+    compile_descriptors(&set.file)
+}
+
+// Builds a compilation from a list of files, e.g. the ones protoc sends to a plugin.
+pub fn compile_descriptors(files: &[FileDescriptorProto]) -> Result<Compilation, DescriptorError> {
+    let files = files
+        .iter()
+        .map(|file| {
+            Ok(CompiledFile {
+                name: file.name.clone(),
+                program: lift_file(file, files)?,
+            })
+        })
+        .collect::<Result<Vec<CompiledFile>, DescriptorError>>()?;
+
+    Compilation::from_files(files).map_err(|err| DescriptorError::Compile(Box::new(err)))
+}
+
+fn add_dependencies<'c>(compilation: &'c Compilation, name: &str, required: &mut Vec<&'c str>) {
+    // Missing weak imports won't have been loaded.
+    let file = match compilation.file(name) {
+        Some(file) => file,
+        None => return,
+    };
+
+    if required.contains(&file.name.as_str()) {
+        return;
+    }
+
+    required.push(&file.name);
+    for import in &file.program.imports {
+        add_dependencies(compilation, &import.path, required);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_gen::{generator_for, CodeGenerator, GeneratorOptions, Language};
+    use crate::compiler::{Compiler, SourceTree};
+    use crate::parser::tests::clear_program_spans;
+    use crate::parser::*;
+    use std::path::PathBuf;
+
+    fn compiler() -> Compiler {
+        Compiler::new(
+            Box::new(ParserImpl::default()),
+            SourceTree::new(vec![PathBuf::from("test_data/descriptors")]),
+        )
+    }
+
+    fn compile_and_encode(names: &[&str], options: DescriptorSetOptions) -> Vec<u8> {
+        let compiler = compiler();
+        let compilation = compiler.compile(names).expect("failed to compile");
+
+        encode_descriptor_set(&compilation, names, options).expect("failed to encode")
+    }
+
+    #[test]
+    fn test_descriptor_set_golden() {
+        let encoded = compile_and_encode(
+            &["shop.proto", "legacy.proto"],
+            DescriptorSetOptions {
+                include_imports: true,
+                include_source_info: false,
+            },
+        );
+
+        assert_eq!(
+            encoded,
+            include_bytes!("../../test_data/descriptors/descriptor_set.pb").to_vec()
+        );
+    }
+
+    #[test]
+    fn test_source_info_golden() {
+        let encoded = compile_and_encode(
+            &["shop.proto"],
+            DescriptorSetOptions {
+                include_imports: false,
+                include_source_info: true,
+            },
+        );
+
+        assert_eq!(
+            encoded,
+            include_bytes!("../../test_data/descriptors/shop_source_info.pb").to_vec()
+        );
+    }
+
+    #[test]
+    fn test_lowering() {
+        let compiler = compiler();
+        let compilation = compiler
+            .compile(&["shop.proto"])
+            .expect("failed to compile");
+
+        let set = build_descriptor_set(
+            &compilation,
+            &["shop.proto"],
+            DescriptorSetOptions::default(),
+        )
+        .expect("failed to build descriptor set");
+
+        assert_eq!(set.file.len(), 1);
+        let file = &set.file[0];
+        assert_eq!(file.dependency, vec!["common.proto".to_string()]);
+        assert_eq!(file.syntax, Some("proto3".to_string()));
+        assert_eq!(file.source_code_info, None);
+
+        let item = &file.message_type[0];
+
+        // Map fields reference a synthesized entry message, which comes before Dimensions since the field is
+        // declared first.
+        let stock = &item.field[3];
+        assert_eq!(stock.label, FieldLabel::Repeated);
+        assert_eq!(
+            stock.type_name,
+            Some(".shop.Item.StockByStoreEntry".to_string())
+        );
+        assert_eq!(
+            item.nested_type
+                .iter()
+                .map(|nested| nested.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["StockByStoreEntry", "Dimensions"]
+        );
+
+        // proto3 optional fields get a synthetic oneof after the real ones.
+        let display_name = &item.field[4];
+        assert!(display_name.proto3_optional);
+        assert_eq!(display_name.oneof_index, Some(1));
+        assert_eq!(item.oneof_decl[1].name, "_display_name");
+        assert_eq!(item.field[5].oneof_index, Some(0));
+
+        assert_eq!(
+            file.service[0].method[1].input_type,
+            ".shop.Item".to_string()
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut source_tree = SourceTree::new(vec![]);
+        source_tree.add_file(
+            "unresolved.proto",
+            "syntax = \"proto3\";\nmessage Foo {\n    Bar bar = 1;\n}".to_string(),
+        );
+        source_tree.add_file(
+            "bad_option.proto",
+            "syntax = \"proto3\";\noption optimize_for = FAST;".to_string(),
+        );
+
+        let compiler = Compiler::new(Box::new(ParserImpl::default()), source_tree);
+        let compilation = compiler
+            .compile(&["unresolved.proto", "bad_option.proto"])
+            .expect("failed to compile");
+
+        let error = |name| {
+            build_descriptor_set(&compilation, &[name], DescriptorSetOptions::default())
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            error("unresolved.proto"),
+            Err("unresolved.proto:3:5: Unable to resolve type 'Bar' from scope 'Foo'".to_string())
+        );
+        assert_eq!(
+            error("bad_option.proto"),
+            Err(
+                "bad_option.proto:2:1: 'FAST' is not a valid value for option 'optimize_for'"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            error("missing.proto"),
+            Err("'missing.proto' is not part of the compilation".to_string())
+        );
+    }
+
+    #[test]
+    fn test_custom_options() {
+        let compiler = Compiler::new(
+            Box::new(ParserImpl::default()),
+            SourceTree::new(vec![PathBuf::from("test_data/custom_options")]),
+        );
+        let compilation = compiler
+            .compile(&["uses.proto"])
+            .expect("failed to compile");
+
+        let set = build_descriptor_set(
+            &compilation,
+            &["uses.proto"],
+            DescriptorSetOptions {
+                include_imports: true,
+                include_source_info: false,
+            },
+        )
+        .expect("failed to build descriptor set");
+        let file = &set.file[2];
+
+        let unknown_fields = |options: &Option<Options>| {
+            options
+                .as_ref()
+                .map(|options| {
+                    options
+                        .unknown_fields
+                        .iter()
+                        .map(|field| (field.number, field.value.clone()))
+                        .collect::<Vec<(u32, RawValue)>>()
+                })
+                .unwrap_or_default()
+        };
+
+        assert_eq!(
+            unknown_fields(&file.options),
+            vec![(50000, RawValue::LengthDelimited(b"shop".to_vec()))]
+        );
+
+        // Names are looked up from the innermost scope outwards, or from the root with a leading dot.
+        let item = &file.message_type[0];
+        assert_eq!(
+            unknown_fields(&item.options),
+            vec![(50000, RawValue::Varint(1))]
+        );
+
+        // Field paths set a field of the option's message, and sint32s are zigzag encoded.
+        assert_eq!(
+            unknown_fields(&item.field[0].options),
+            vec![
+                (50000, RawValue::LengthDelimited(vec![0x08, 0x01])),
+                (50001, RawValue::Varint(3))
+            ]
+        );
+
+        // Aggregates are encoded like the message they spell out, and repeated options keep every value in order.
+        assert_eq!(
+            unknown_fields(&item.field[1].options),
+            vec![
+                (
+                    50000,
+                    RawValue::LengthDelimited(vec![
+                        0x12, 0x01, b'a', 0x12, 0x01, b'b', 0x18, 0x01, 0x22, 0x02, 0x08, 0x03
+                    ])
+                ),
+                (50002, RawValue::Fixed32(1.5f32.to_bits())),
+                (50002, RawValue::Fixed32(2f32.to_bits()))
+            ]
+        );
+
+        // Like protoc, options are ordered by number, and the fields set on the same option message are merged.
+        assert_eq!(
+            unknown_fields(&item.field[2].options),
+            vec![
+                (
+                    50000,
+                    RawValue::LengthDelimited(vec![
+                        0x08, 0x02, 0x22, 0x05, 0x08, 0x01, 0x12, 0x01, b'x'
+                    ])
+                ),
+                (50002, RawValue::Fixed32(3f32.to_bits()))
+            ]
+        );
+
+        assert_eq!(
+            unknown_fields(&file.enum_type[0].value[0].options),
+            vec![(50000, RawValue::Varint(1))]
+        );
+
+        // Decoding keeps the options as they were written, and lifting names them by their extensions, so lowering
+        // the lifted file gives the same options back.
+        let encoded = set.encode_to_vec();
+        let decoded = decode_descriptor_set(&encoded).expect("failed to decode");
+        assert_eq!(decoded.encode_to_vec(), encoded);
+
+        let lifted = compile_descriptor_set(&decoded).expect("failed to compile descriptors");
+        let program = &lifted.file("uses.proto").unwrap().program;
+        assert_eq!(
+            program
+                .options
+                .iter()
+                .map(|option| (option.name.as_str(), option.extension))
+                .collect::<Vec<(&str, bool)>>(),
+            vec![(".ext.owner", true)]
+        );
+
+        let relowered =
+            build_descriptor_set(&lifted, &["uses.proto"], DescriptorSetOptions::default())
+                .expect("failed to build descriptor set");
+        assert_eq!(&relowered.file[0], file);
+
+        // Without the file that declares them, the options can't be named, which is an error rather than a loss.
+        let without_imports = FileDescriptorSet {
+            file: vec![file.clone()],
+        };
+        assert_eq!(
+            compile_descriptor_set(&without_imports)
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+            Err("uses.proto: Option field 50000 of FileOptions isn't declared by any file in the set".to_string())
+        );
+    }
+
+    #[test]
+    fn test_custom_option_errors() {
+        let error = |body: &str| {
+            let mut source_tree = SourceTree::new(vec![PathBuf::from("test_data/custom_options")]);
+            source_tree.add_file(
+                "errors.proto",
+                format!("syntax = \"proto2\";\nimport \"options.proto\";\n{}", body),
+            );
+
+            let compiler = Compiler::new(Box::new(ParserImpl::default()), source_tree);
+            let compilation = compiler
+                .compile(&["errors.proto"])
+                .expect("failed to compile");
+
+            build_descriptor_set(
+                &compilation,
+                &["errors.proto"],
+                DescriptorSetOptions::default(),
+            )
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            error("option (ext.owner) = \"\\xff\";"),
+            Err(
+                "errors.proto:3:1: Value for string option \"ext.owner\" is not valid UTF-8."
+                    .to_string()
+            )
+        );
+        assert_eq!(error("option (ext.signature) = \"\\xff\";"), Ok(()));
+        assert_eq!(
+            error("option java_package = \"\\xff\";"),
+            Err("errors.proto:3:1: Option 'java_package' expects a string, which has to be valid UTF-8".to_string())
+        );
+        assert_eq!(
+            error("option java_pakage = \"x\";"),
+            Err("errors.proto:3:1: Option \"java_pakage\" unknown. Ensure that your proto definition file imports the proto which defines the option.".to_string())
+        );
+        assert_eq!(
+            error("option (Scoped.tracked) = true;"),
+            Err("errors.proto:3:1: Option \"(Scoped.tracked)\" unknown. Ensure that your proto definition file imports the proto which defines the option.".to_string())
+        );
+        assert_eq!(
+            error("option (ext.rules).min_len = 1;"),
+            Err("errors.proto:3:1: Option field \"(ext.rules)\" is not a field or extension of message \"FileOptions\".".to_string())
+        );
+        assert_eq!(
+            error("message M { optional int32 a = 1 [(ext.rules).max_len = 1]; }"),
+            Err("errors.proto:3:35: Option field \"(ext.rules).max_len\" is not a field or extension of message \"Rules\".".to_string())
+        );
+        assert_eq!(
+            error("message M { optional int32 a = 1 [(ext.offset) = \"x\"]; }"),
+            Err(
+                "errors.proto:3:35: Value must be integer for sint32 option \"ext.offset\"."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            error("message M { optional int32 a = 1 [deprecated.value = true]; }"),
+            Err(
+                "errors.proto:3:35: Option \"deprecated\" is an atomic type, not a message."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_bytes_defaults() {
+        let mut source_tree = SourceTree::new(vec![]);
+        source_tree.add_file(
+            "bytes.proto",
+            "syntax = \"proto2\";\nmessage Blob {\n    optional bytes data = 1 [default = \"\\xff\\0a\"];\n}"
+                .to_string(),
+        );
+
+        let compiler = Compiler::new(Box::new(ParserImpl::default()), source_tree);
+        let compilation = compiler
+            .compile(&["bytes.proto"])
+            .expect("failed to compile");
+
+        // Like protoc, bytes defaults are stored C-escaped.
+        let set = build_descriptor_set(
+            &compilation,
+            &["bytes.proto"],
+            DescriptorSetOptions::default(),
+        )
+        .expect("failed to build descriptor set");
+        assert_eq!(
+            set.file[0].message_type[0].field[0].default_value,
+            Some("\\377\\000a".to_string())
+        );
+
+        let lifted = compile_descriptor_set(&set).expect("failed to compile descriptors");
+        match &lifted.files[0].program.types[..] {
+            [ProtoType::Message(message)] => assert_eq!(
+                message.fields[0].default_value(),
+                Some(&ProtoConstant::Bytes(vec![0xff, 0x00, b'a']))
+            ),
+            types => panic!("expected a single message, got {:?}", types),
+        }
+    }
+
+    #[test]
+    fn test_descriptor_set_round_trip() {
+        let golden = include_bytes!("../../test_data/descriptors/descriptor_set.pb");
+        let set = decode_descriptor_set(golden).expect("failed to decode");
+        assert_eq!(set.encode_to_vec(), golden.to_vec());
+
+        // Without spans the AST can't say where oneof members and map entries were declared among the other fields
+        // and nested types, so lowering it again may list them in a different order, but nothing else changes.
+        let compilation = compile_descriptor_set(&set).expect("failed to compile descriptors");
+        let relowered = build_descriptor_set(
+            &compilation,
+            &["shop.proto", "legacy.proto"],
+            DescriptorSetOptions {
+                include_imports: true,
+                include_source_info: false,
+            },
+        )
+        .expect("failed to build descriptor set");
+        assert_eq!(relowered.file[0], set.file[0]);
+        assert_eq!(relowered.file[2], set.file[2]);
+
+        let recompiled = compile_descriptor_set(&relowered).expect("failed to compile descriptors");
+        for (file, refile) in compilation.files.iter().zip(&recompiled.files) {
+            assert_eq!(file.program, refile.program);
+        }
+    }
+
+    #[test]
+    fn test_lifting_parsed_file() {
+        let compiler = compiler();
+        let compilation = compiler
+            .compile(&["no_syntax.proto"])
+            .expect("failed to compile");
+
+        let set = build_descriptor_set(
+            &compilation,
+            &["no_syntax.proto"],
+            DescriptorSetOptions {
+                include_imports: true,
+                include_source_info: true,
+            },
+        )
+        .expect("failed to build descriptor set");
+        assert_eq!(set.file[1].syntax, None);
+
+        // Lifting gives back what was parsed, apart from the spans and the source text (the file spells out its type
+        // references in full, as descriptors do). The syntax is left unset since the file has no syntax statement, and
+        // the lifted files own their names, so they outlive the descriptors.
+        let lifted = compile_descriptor_set(&set).expect("failed to compile descriptors");
+        drop(set);
+
+        let mut parsed = compilation.file("no_syntax.proto").unwrap().program.clone();
+        clear_program_spans(&mut parsed);
+        parsed.src = String::new();
+
+        assert_eq!(parsed.syntax, None);
+        assert_eq!(lifted.file("no_syntax.proto").unwrap().program, parsed);
+    }
+
+    #[test]
+    fn test_lifting() {
+        let set = decode_descriptor_set(include_bytes!(
+            "../../test_data/descriptors/descriptor_set.pb"
+        ))
+        .expect("failed to decode");
+        let compilation = compile_descriptor_set(&set).expect("failed to compile descriptors");
+
+        let shop = &compilation.file("shop.proto").unwrap().program;
+        assert_eq!(shop.syntax, Some(ProtoSyntax::Proto3));
+        assert_eq!(shop.package.as_deref(), Some("shop"));
+
+        let item = match &shop.types[0] {
+            ProtoType::Message(item) => item,
+            _ => panic!("expected a message"),
+        };
+
+        // The map entry becomes the field's type and the synthetic oneof for display_name is dropped.
+        assert_eq!(
+            item.fields[3].field_type,
+            ProtoFieldType::Primitive(ProtoPrimitiveType::Map(
+                Box::new(ProtoFieldType::Primitive(ProtoPrimitiveType::Str)),
+                Box::new(ProtoFieldType::Primitive(ProtoPrimitiveType::Int32))
+            ))
+        );
+        assert_eq!(item.fields[3].modifier, None);
+        assert_eq!(
+            item.types
+                .iter()
+                .map(|nested| nested.get_name())
+                .collect::<Vec<&str>>(),
+            vec!["Dimensions"]
+        );
+        assert_eq!(
+            item.fields[4].modifier,
+            Some(ProtoMessageFieldModifier::Optional)
+        );
+        assert_eq!(item.oneofs.len(), 1);
+        assert_eq!(item.oneofs[0].fields[1].name, "amount_off");
+        assert_eq!(
+            item.oneofs[0].fields[1].field_type,
+            ProtoFieldType::IdentifierPath(ProtoIdentifierPath::Path(
+                ".shop.common.Money".to_string()
+            ))
+        );
+
+        let legacy = &compilation.file("legacy.proto").unwrap().program;
+        let record = match &legacy.types[0] {
+            ProtoType::Message(record) => record,
+            _ => panic!("expected a message"),
+        };
+
+        assert_eq!(
+            record
+                .fields
+                .iter()
+                .map(|field| field
+                    .options
+                    .iter()
+                    .map(|option| (option.name.as_str(), option.value.clone()))
+                    .collect::<Vec<(&str, ProtoConstant)>>())
+                .collect::<Vec<Vec<(&str, ProtoConstant)>>>(),
+            vec![
+                vec![],
+                vec![("default", ProtoConstant::Str("unnamed".to_string()))],
+                vec![("default", ProtoConstant::Float(-1.5))],
+                vec![("default", ProtoConstant::Identifier("SMALL".to_string()))],
+                vec![("default", ProtoConstant::Str("a\nb\u{1}".to_string()))],
+                vec![("json_name", ProtoConstant::Str("total".to_string()))],
+            ]
+        );
+        assert_eq!(
+            record.extension_ranges[1],
+            ProtoRange {
+                span: None,
+                start_span: None,
+                end_span: None,
+                start: 500,
+                end: ProtoRangeEnd::Max
+            }
+        );
+        assert_eq!(legacy.extends.len(), 1);
+        assert_eq!(
+            legacy.extends[0].extendee,
+            ProtoIdentifierPath::Path(".legacy.Record".to_string())
+        );
+    }
+
+    #[test]
+    fn test_gen_file_from_descriptors() {
+        let names = ["shop.proto", "legacy.proto"];
+        let compiler = compiler();
+        let compilation = compiler.compile(&names).expect("failed to compile");
+
+        let encoded = encode_descriptor_set(
+            &compilation,
+            &names,
+            DescriptorSetOptions {
+                include_imports: true,
+                include_source_info: true,
+            },
+        )
+        .expect("failed to encode");
+        let set = decode_descriptor_set(&encoded).expect("failed to decode");
+        let lifted = compile_descriptor_set(&set).expect("failed to compile descriptors");
+
+        // Source info carries the comments, so the generated code should match what the sources produce exactly.
+        let generator = generator_for(
+            Box::new(ParserImpl::default()),
+            Language::Dart,
+            &GeneratorOptions::default(),
+        )
+        .expect("failed to create generator");
+        for name in &["common.proto", "shop.proto", "legacy.proto"] {
+            let expected = generator.gen_file(&compilation, name);
+            assert!(expected.is_ok(), "{:?}", expected);
+            assert_eq!(generator.gen_file(&lifted, name), expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let error = |bytes: &[u8]| {
+            decode_descriptor_set(bytes)
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            error(&[0x0a, 0x05, 0x0a]),
+            Err("Invalid protobuf message: unexpected end of input".to_string())
+        );
+
+        // A file whose only field is a group.
+        let mut field = Encoder::new();
+        field.string(1, "g");
+        field.int32(3, 1);
+        field.int32(4, 1);
+        field.int32(5, 10);
+        let mut message = Encoder::new();
+        message.string(1, "M");
+        message.bytes(2, &field.into_bytes());
+        let mut file = Encoder::new();
+        file.string(1, "group.proto");
+        file.bytes(4, &message.into_bytes());
+        let mut set = Encoder::new();
+        set.bytes(1, &file.into_bytes());
+
+        let set = decode_descriptor_set(&set.into_bytes()).expect("failed to decode");
+        assert_eq!(
+            compile_descriptor_set(&set)
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+            Err("group.proto: Field 'g' is a group, which isn't supported".to_string())
+        );
+    }
+}
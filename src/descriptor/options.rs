@@ -0,0 +1,119 @@
+// The standard options of each kind of declaration, as defined by the *Options messages in descriptor.proto.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OptionType {
+    Bool,
+    Str,
+
+    // An enum option, with the names and numbers of its values.
+    Enum(&'static [(&'static str, i32)]),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub number: u32,
+    pub option_type: OptionType,
+}
+
+const fn spec(name: &'static str, number: u32, option_type: OptionType) -> OptionSpec {
+    OptionSpec {
+        name,
+        number,
+        option_type,
+    }
+}
+
+const OPTIMIZE_MODE: OptionType =
+    OptionType::Enum(&[("SPEED", 1), ("CODE_SIZE", 2), ("LITE_RUNTIME", 3)]);
+const C_TYPE: OptionType = OptionType::Enum(&[("STRING", 0), ("CORD", 1), ("STRING_PIECE", 2)]);
+const JS_TYPE: OptionType =
+    OptionType::Enum(&[("JS_NORMAL", 0), ("JS_STRING", 1), ("JS_NUMBER", 2)]);
+const IDEMPOTENCY_LEVEL: OptionType = OptionType::Enum(&[
+    ("IDEMPOTENCY_UNKNOWN", 0),
+    ("NO_SIDE_EFFECTS", 1),
+    ("IDEMPOTENT", 2),
+]);
+const OPTION_RETENTION: OptionType = OptionType::Enum(&[
+    ("RETENTION_UNKNOWN", 0),
+    ("RETENTION_RUNTIME", 1),
+    ("RETENTION_SOURCE", 2),
+]);
+
+pub const FILE_OPTIONS: &[OptionSpec] = &[
+    spec("java_package", 1, OptionType::Str),
+    spec("java_outer_classname", 8, OptionType::Str),
+    spec("optimize_for", 9, OPTIMIZE_MODE),
+    spec("java_multiple_files", 10, OptionType::Bool),
+    spec("go_package", 11, OptionType::Str),
+    spec("cc_generic_services", 16, OptionType::Bool),
+    spec("java_generic_services", 17, OptionType::Bool),
+    spec("py_generic_services", 18, OptionType::Bool),
+    spec("java_generate_equals_and_hash", 20, OptionType::Bool),
+    spec("deprecated", 23, OptionType::Bool),
+    spec("java_string_check_utf8", 27, OptionType::Bool),
+    spec("cc_enable_arenas", 31, OptionType::Bool),
+    spec("objc_class_prefix", 36, OptionType::Str),
+    spec("csharp_namespace", 37, OptionType::Str),
+    spec("swift_prefix", 39, OptionType::Str),
+    spec("php_class_prefix", 40, OptionType::Str),
+    spec("php_namespace", 41, OptionType::Str),
+    spec("php_generic_services", 42, OptionType::Bool),
+    spec("php_metadata_namespace", 44, OptionType::Str),
+    spec("ruby_package", 45, OptionType::Str),
+];
+
+pub const MESSAGE_OPTIONS: &[OptionSpec] = &[
+    spec("message_set_wire_format", 1, OptionType::Bool),
+    spec("no_standard_descriptor_accessor", 2, OptionType::Bool),
+    spec("deprecated", 3, OptionType::Bool),
+    spec("map_entry", 7, OptionType::Bool),
+    spec(
+        "deprecated_legacy_json_field_conflicts",
+        11,
+        OptionType::Bool,
+    ),
+];
+
+pub const FIELD_OPTIONS: &[OptionSpec] = &[
+    spec("ctype", 1, C_TYPE),
+    spec("packed", 2, OptionType::Bool),
+    spec("deprecated", 3, OptionType::Bool),
+    spec("lazy", 5, OptionType::Bool),
+    spec("jstype", 6, JS_TYPE),
+    spec("weak", 10, OptionType::Bool),
+    spec("unverified_lazy", 15, OptionType::Bool),
+    spec("debug_redact", 16, OptionType::Bool),
+    spec("retention", 17, OPTION_RETENTION),
+];
+
+pub const ONEOF_OPTIONS: &[OptionSpec] = &[];
+
+pub const ENUM_OPTIONS: &[OptionSpec] = &[
+    spec("allow_alias", 2, OptionType::Bool),
+    spec("deprecated", 3, OptionType::Bool),
+    spec(
+        "deprecated_legacy_json_field_conflicts",
+        6,
+        OptionType::Bool,
+    ),
+];
+
+pub const ENUM_VALUE_OPTIONS: &[OptionSpec] = &[
+    spec("deprecated", 1, OptionType::Bool),
+    spec("debug_redact", 3, OptionType::Bool),
+];
+
+pub const SERVICE_OPTIONS: &[OptionSpec] = &[spec("deprecated", 33, OptionType::Bool)];
+
+pub const METHOD_OPTIONS: &[OptionSpec] = &[
+    spec("deprecated", 33, OptionType::Bool),
+    spec("idempotency_level", 34, IDEMPOTENCY_LEVEL),
+];
+
+// The number of MessageOptions.map_entry, which protoc sets on the entry messages it synthesizes for map fields.
+pub const MAP_ENTRY_OPTION: u32 = 7;
+
+pub fn find_option(specs: &[OptionSpec], name: &str) -> Option<OptionSpec> {
+    specs.iter().find(|spec| spec.name == name).copied()
+}
@@ -0,0 +1,929 @@
+use super::options::*;
+use super::wire::{Decode, DecodeError, Decoder, Encode, Encoder, RawValue};
+
+// The messages of google/protobuf/descriptor.proto that describe .proto files. Field comments give the field numbers
+// they're encoded with.
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FileDescriptorSet {
+    // 1
+    pub file: Vec<FileDescriptorProto>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FileDescriptorProto {
+    // 1
+    pub name: String,
+    // 2
+    pub package: Option<String>,
+    // 3
+    pub dependency: Vec<String>,
+    // 4
+    pub message_type: Vec<DescriptorProto>,
+    // 5
+    pub enum_type: Vec<EnumDescriptorProto>,
+    // 6
+    pub service: Vec<ServiceDescriptorProto>,
+    // 7
+    pub extension: Vec<FieldDescriptorProto>,
+    // 8
+    pub options: Option<Options>,
+    // 9
+    pub source_code_info: Option<SourceCodeInfo>,
+    // 10
+    pub public_dependency: Vec<i32>,
+    // 11
+    pub weak_dependency: Vec<i32>,
+    // 12 (protoc leaves this unset for proto2 files)
+    pub syntax: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DescriptorProto {
+    // 1
+    pub name: String,
+    // 2
+    pub field: Vec<FieldDescriptorProto>,
+    // 3
+    pub nested_type: Vec<DescriptorProto>,
+    // 4
+    pub enum_type: Vec<EnumDescriptorProto>,
+    // 5
+    pub extension_range: Vec<ExtensionRange>,
+    // 6
+    pub extension: Vec<FieldDescriptorProto>,
+    // 7
+    pub options: Option<Options>,
+    // 8
+    pub oneof_decl: Vec<OneofDescriptorProto>,
+    // 9
+    pub reserved_range: Vec<ReservedRange>,
+    // 10
+    pub reserved_name: Vec<String>,
+}
+
+// A range of extension numbers, with an exclusive end.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ExtensionRange {
+    // 1
+    pub start: i32,
+    // 2
+    pub end: i32,
+}
+
+// A range of reserved numbers. The end is exclusive for messages and inclusive for enums.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ReservedRange {
+    // 1
+    pub start: i32,
+    // 2
+    pub end: i32,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum FieldLabel {
+    #[default]
+    Optional = 1,
+    Required = 2,
+    Repeated = 3,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum FieldType {
+    #[default]
+    Double = 1,
+    Float = 2,
+    Int64 = 3,
+    UInt64 = 4,
+    Int32 = 5,
+    Fixed64 = 6,
+    Fixed32 = 7,
+    Bool = 8,
+    String = 9,
+    Group = 10,
+    Message = 11,
+    Bytes = 12,
+    UInt32 = 13,
+    Enum = 14,
+    SFixed32 = 15,
+    SFixed64 = 16,
+    SInt32 = 17,
+    SInt64 = 18,
+}
+
+impl FieldLabel {
+    fn from_i32(value: i32) -> Result<Self, DecodeError> {
+        match value {
+            1 => Ok(FieldLabel::Optional),
+            2 => Ok(FieldLabel::Required),
+            3 => Ok(FieldLabel::Repeated),
+            _ => Err(DecodeError {
+                message: format!("unknown field label {}", value),
+            }),
+        }
+    }
+}
+
+impl FieldType {
+    fn from_i32(value: i32) -> Result<Self, DecodeError> {
+        const TYPES: [FieldType; 18] = [
+            FieldType::Double,
+            FieldType::Float,
+            FieldType::Int64,
+            FieldType::UInt64,
+            FieldType::Int32,
+            FieldType::Fixed64,
+            FieldType::Fixed32,
+            FieldType::Bool,
+            FieldType::String,
+            FieldType::Group,
+            FieldType::Message,
+            FieldType::Bytes,
+            FieldType::UInt32,
+            FieldType::Enum,
+            FieldType::SFixed32,
+            FieldType::SFixed64,
+            FieldType::SInt32,
+            FieldType::SInt64,
+        ];
+
+        TYPES
+            .iter()
+            .find(|field_type| **field_type as i32 == value)
+            .copied()
+            .ok_or_else(|| DecodeError {
+                message: format!("unknown field type {}", value),
+            })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FieldDescriptorProto {
+    // 1
+    pub name: String,
+    // 2
+    pub extendee: Option<String>,
+    // 3
+    pub number: i32,
+    // 4
+    pub label: FieldLabel,
+    // 5
+    pub field_type: FieldType,
+    // 6 (fully qualified with a leading dot for message and enum fields)
+    pub type_name: Option<String>,
+    // 7
+    pub default_value: Option<String>,
+    // 8
+    pub options: Option<Options>,
+    // 9
+    pub oneof_index: Option<i32>,
+    // 10
+    pub json_name: Option<String>,
+    // 17
+    pub proto3_optional: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct OneofDescriptorProto {
+    // 1
+    pub name: String,
+    // 2
+    pub options: Option<Options>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct EnumDescriptorProto {
+    // 1
+    pub name: String,
+    // 2
+    pub value: Vec<EnumValueDescriptorProto>,
+    // 3
+    pub options: Option<Options>,
+    // 4
+    pub reserved_range: Vec<ReservedRange>,
+    // 5
+    pub reserved_name: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct EnumValueDescriptorProto {
+    // 1
+    pub name: String,
+    // 2
+    pub number: i32,
+    // 3
+    pub options: Option<Options>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ServiceDescriptorProto {
+    // 1
+    pub name: String,
+    // 2
+    pub method: Vec<MethodDescriptorProto>,
+    // 3
+    pub options: Option<Options>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MethodDescriptorProto {
+    // 1
+    pub name: String,
+    // 2
+    pub input_type: String,
+    // 3
+    pub output_type: String,
+    // 4
+    pub options: Option<Options>,
+    // 5
+    pub client_streaming: bool,
+    // 6
+    pub server_streaming: bool,
+}
+
+// Any of the *Options messages. Standard options are stored by field number, and custom options as the extension
+// fields protoc interprets them into.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Options {
+    pub fields: Vec<OptionField>,
+    // 999
+    pub uninterpreted_option: Vec<UninterpretedOption>,
+
+    // Fields that aren't standard options, in the order they were set. Like protoc, they're written after the rest.
+    pub unknown_fields: Vec<UnknownField>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct OptionField {
+    pub number: u32,
+    pub value: OptionValue,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnknownField {
+    pub number: u32,
+    pub value: RawValue,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum OptionValue {
+    Bool(bool),
+    Enum(i32),
+    Str(String),
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct UninterpretedOption {
+    // 2
+    pub name: Vec<NamePart>,
+    // 3
+    pub identifier_value: Option<String>,
+    // 4
+    pub positive_int_value: Option<u64>,
+    // 5
+    pub negative_int_value: Option<i64>,
+    // 6
+    pub double_value: Option<f64>,
+    // 7
+    pub string_value: Option<Vec<u8>>,
+    // 8
+    pub aggregate_value: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct NamePart {
+    // 1
+    pub name_part: String,
+    // 2
+    pub is_extension: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SourceCodeInfo {
+    // 1
+    pub location: Vec<Location>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Location {
+    // 1 (the field numbers and indices leading to the element, e.g. [4, 0, 2, 1] for the second field of the first
+    // message)
+    pub path: Vec<i32>,
+    // 2 (zero-based [start line, start column, end line, end column], with the end line left out if it's the same as
+    // the start line)
+    pub span: Vec<i32>,
+    // 3
+    pub leading_comments: Option<String>,
+    // 4
+    pub trailing_comments: Option<String>,
+    // 6
+    pub leading_detached_comments: Vec<String>,
+}
+
+impl Encode for FileDescriptorSet {
+    fn encode(&self, encoder: &mut Encoder) {
+        for file in &self.file {
+            encoder.message(1, file);
+        }
+    }
+}
+
+impl Encode for FileDescriptorProto {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name);
+        if let Some(package) = &self.package {
+            encoder.string(2, package);
+        }
+        for dependency in &self.dependency {
+            encoder.string(3, dependency);
+        }
+        for message_type in &self.message_type {
+            encoder.message(4, message_type);
+        }
+        for enum_type in &self.enum_type {
+            encoder.message(5, enum_type);
+        }
+        for service in &self.service {
+            encoder.message(6, service);
+        }
+        for extension in &self.extension {
+            encoder.message(7, extension);
+        }
+        if let Some(options) = &self.options {
+            encoder.message(8, options);
+        }
+        if let Some(source_code_info) = &self.source_code_info {
+            encoder.message(9, source_code_info);
+        }
+        // Unpacked, since these are proto2 repeated fields.
+        for public_dependency in &self.public_dependency {
+            encoder.int32(10, *public_dependency);
+        }
+        for weak_dependency in &self.weak_dependency {
+            encoder.int32(11, *weak_dependency);
+        }
+        if let Some(syntax) = &self.syntax {
+            encoder.string(12, syntax);
+        }
+    }
+}
+
+impl Encode for DescriptorProto {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name);
+        for field in &self.field {
+            encoder.message(2, field);
+        }
+        for nested_type in &self.nested_type {
+            encoder.message(3, nested_type);
+        }
+        for enum_type in &self.enum_type {
+            encoder.message(4, enum_type);
+        }
+        for extension_range in &self.extension_range {
+            encoder.message(5, extension_range);
+        }
+        for extension in &self.extension {
+            encoder.message(6, extension);
+        }
+        if let Some(options) = &self.options {
+            encoder.message(7, options);
+        }
+        for oneof_decl in &self.oneof_decl {
+            encoder.message(8, oneof_decl);
+        }
+        for reserved_range in &self.reserved_range {
+            encoder.message(9, reserved_range);
+        }
+        for reserved_name in &self.reserved_name {
+            encoder.string(10, reserved_name);
+        }
+    }
+}
+
+impl Encode for ExtensionRange {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.int32(1, self.start);
+        encoder.int32(2, self.end);
+    }
+}
+
+impl Encode for ReservedRange {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.int32(1, self.start);
+        encoder.int32(2, self.end);
+    }
+}
+
+impl Encode for FieldDescriptorProto {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name);
+        if let Some(extendee) = &self.extendee {
+            encoder.string(2, extendee);
+        }
+        encoder.int32(3, self.number);
+        encoder.int32(4, self.label as i32);
+        encoder.int32(5, self.field_type as i32);
+        if let Some(type_name) = &self.type_name {
+            encoder.string(6, type_name);
+        }
+        if let Some(default_value) = &self.default_value {
+            encoder.string(7, default_value);
+        }
+        if let Some(options) = &self.options {
+            encoder.message(8, options);
+        }
+        if let Some(oneof_index) = self.oneof_index {
+            encoder.int32(9, oneof_index);
+        }
+        if let Some(json_name) = &self.json_name {
+            encoder.string(10, json_name);
+        }
+        if self.proto3_optional {
+            encoder.bool(17, true);
+        }
+    }
+}
+
+impl Encode for OneofDescriptorProto {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name);
+        if let Some(options) = &self.options {
+            encoder.message(2, options);
+        }
+    }
+}
+
+impl Encode for EnumDescriptorProto {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name);
+        for value in &self.value {
+            encoder.message(2, value);
+        }
+        if let Some(options) = &self.options {
+            encoder.message(3, options);
+        }
+        for reserved_range in &self.reserved_range {
+            encoder.message(4, reserved_range);
+        }
+        for reserved_name in &self.reserved_name {
+            encoder.string(5, reserved_name);
+        }
+    }
+}
+
+impl Encode for EnumValueDescriptorProto {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name);
+        encoder.int32(2, self.number);
+        if let Some(options) = &self.options {
+            encoder.message(3, options);
+        }
+    }
+}
+
+impl Encode for ServiceDescriptorProto {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name);
+        for method in &self.method {
+            encoder.message(2, method);
+        }
+        if let Some(options) = &self.options {
+            encoder.message(3, options);
+        }
+    }
+}
+
+impl Encode for MethodDescriptorProto {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name);
+        encoder.string(2, &self.input_type);
+        encoder.string(3, &self.output_type);
+        if let Some(options) = &self.options {
+            encoder.message(4, options);
+        }
+        if self.client_streaming {
+            encoder.bool(5, true);
+        }
+        if self.server_streaming {
+            encoder.bool(6, true);
+        }
+    }
+}
+
+impl Encode for Options {
+    fn encode(&self, encoder: &mut Encoder) {
+        let mut fields = self.fields.iter().collect::<Vec<&OptionField>>();
+        fields.sort_by_key(|field| field.number);
+
+        for field in fields {
+            match &field.value {
+                OptionValue::Bool(value) => encoder.bool(field.number, *value),
+                OptionValue::Enum(value) => encoder.int32(field.number, *value),
+                OptionValue::Str(value) => encoder.string(field.number, value),
+            }
+        }
+
+        for uninterpreted_option in &self.uninterpreted_option {
+            encoder.message(999, uninterpreted_option);
+        }
+
+        for field in &self.unknown_fields {
+            encoder.raw(field.number, &field.value);
+        }
+    }
+}
+
+impl Encode for UninterpretedOption {
+    fn encode(&self, encoder: &mut Encoder) {
+        for name in &self.name {
+            encoder.message(2, name);
+        }
+        if let Some(identifier_value) = &self.identifier_value {
+            encoder.string(3, identifier_value);
+        }
+        if let Some(positive_int_value) = self.positive_int_value {
+            encoder.uint64(4, positive_int_value);
+        }
+        if let Some(negative_int_value) = self.negative_int_value {
+            encoder.int64(5, negative_int_value);
+        }
+        if let Some(double_value) = self.double_value {
+            encoder.double(6, double_value);
+        }
+        if let Some(string_value) = &self.string_value {
+            encoder.bytes(7, string_value);
+        }
+        if let Some(aggregate_value) = &self.aggregate_value {
+            encoder.string(8, aggregate_value);
+        }
+    }
+}
+
+impl Encode for NamePart {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name_part);
+        encoder.bool(2, self.is_extension);
+    }
+}
+
+impl Encode for SourceCodeInfo {
+    fn encode(&self, encoder: &mut Encoder) {
+        for location in &self.location {
+            encoder.message(1, location);
+        }
+    }
+}
+
+impl Encode for Location {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.packed_int32(1, &self.path);
+        encoder.packed_int32(2, &self.span);
+        if let Some(leading_comments) = &self.leading_comments {
+            encoder.string(3, leading_comments);
+        }
+        if let Some(trailing_comments) = &self.trailing_comments {
+            encoder.string(4, trailing_comments);
+        }
+        for leading_detached_comments in &self.leading_detached_comments {
+            encoder.string(6, leading_detached_comments);
+        }
+    }
+}
+
+impl Decode for FileDescriptorSet {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = FileDescriptorSet::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            if field == 1 {
+                result.file.push(value.message()?);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for FileDescriptorProto {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = FileDescriptorProto::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name = value.string()?,
+                2 => result.package = Some(value.string()?),
+                3 => result.dependency.push(value.string()?),
+                4 => result.message_type.push(value.message()?),
+                5 => result.enum_type.push(value.message()?),
+                6 => result.service.push(value.message()?),
+                7 => result.extension.push(value.message()?),
+                8 => result.options = Some(Options::decode(value.bytes()?, FILE_OPTIONS)?),
+                9 => result.source_code_info = Some(value.message()?),
+                10 => value.int32s(&mut result.public_dependency)?,
+                11 => value.int32s(&mut result.weak_dependency)?,
+                12 => result.syntax = Some(value.string()?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for DescriptorProto {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = DescriptorProto::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name = value.string()?,
+                2 => result.field.push(value.message()?),
+                3 => result.nested_type.push(value.message()?),
+                4 => result.enum_type.push(value.message()?),
+                5 => result.extension_range.push(value.message()?),
+                6 => result.extension.push(value.message()?),
+                7 => result.options = Some(Options::decode(value.bytes()?, MESSAGE_OPTIONS)?),
+                8 => result.oneof_decl.push(value.message()?),
+                9 => result.reserved_range.push(value.message()?),
+                10 => result.reserved_name.push(value.string()?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for ExtensionRange {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = ExtensionRange::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.start = value.int32()?,
+                2 => result.end = value.int32()?,
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for ReservedRange {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = ReservedRange::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.start = value.int32()?,
+                2 => result.end = value.int32()?,
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for FieldDescriptorProto {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = FieldDescriptorProto::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name = value.string()?,
+                2 => result.extendee = Some(value.string()?),
+                3 => result.number = value.int32()?,
+                4 => result.label = FieldLabel::from_i32(value.int32()?)?,
+                5 => result.field_type = FieldType::from_i32(value.int32()?)?,
+                6 => result.type_name = Some(value.string()?),
+                7 => result.default_value = Some(value.string()?),
+                8 => result.options = Some(Options::decode(value.bytes()?, FIELD_OPTIONS)?),
+                9 => result.oneof_index = Some(value.int32()?),
+                10 => result.json_name = Some(value.string()?),
+                17 => result.proto3_optional = value.bool()?,
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for OneofDescriptorProto {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = OneofDescriptorProto::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name = value.string()?,
+                2 => result.options = Some(Options::decode(value.bytes()?, ONEOF_OPTIONS)?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for EnumDescriptorProto {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = EnumDescriptorProto::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name = value.string()?,
+                2 => result.value.push(value.message()?),
+                3 => result.options = Some(Options::decode(value.bytes()?, ENUM_OPTIONS)?),
+                4 => result.reserved_range.push(value.message()?),
+                5 => result.reserved_name.push(value.string()?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for EnumValueDescriptorProto {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = EnumValueDescriptorProto::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name = value.string()?,
+                2 => result.number = value.int32()?,
+                3 => result.options = Some(Options::decode(value.bytes()?, ENUM_VALUE_OPTIONS)?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for ServiceDescriptorProto {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = ServiceDescriptorProto::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name = value.string()?,
+                2 => result.method.push(value.message()?),
+                3 => result.options = Some(Options::decode(value.bytes()?, SERVICE_OPTIONS)?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for MethodDescriptorProto {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = MethodDescriptorProto::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name = value.string()?,
+                2 => result.input_type = value.string()?,
+                3 => result.output_type = value.string()?,
+                4 => result.options = Some(Options::decode(value.bytes()?, METHOD_OPTIONS)?),
+                5 => result.client_streaming = value.bool()?,
+                6 => result.server_streaming = value.bool()?,
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Options {
+    // Options messages are decoded against the standard options of their kind. Fields that aren't standard options
+    // (i.e. custom options that protoc has already interpreted) can't be named without their definitions, so they're
+    // kept as they were written.
+    pub fn decode(bytes: &[u8], specs: &[OptionSpec]) -> Result<Self, DecodeError> {
+        let mut result = Options::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            if field == 999 {
+                result.uninterpreted_option.push(value.message()?);
+                continue;
+            }
+
+            let spec = match specs.iter().find(|spec| spec.number == field) {
+                Some(spec) => spec,
+                None => {
+                    result.unknown_fields.push(UnknownField {
+                        number: field,
+                        value: value.into(),
+                    });
+                    continue;
+                }
+            };
+
+            let value = match spec.option_type {
+                OptionType::Bool => OptionValue::Bool(value.bool()?),
+                OptionType::Str => OptionValue::Str(value.string()?),
+                OptionType::Enum(_) => OptionValue::Enum(value.int32()?),
+            };
+
+            result.fields.retain(|field| field.number != spec.number);
+            result.fields.push(OptionField {
+                number: spec.number,
+                value,
+            });
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for UninterpretedOption {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = UninterpretedOption::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                2 => result.name.push(value.message()?),
+                3 => result.identifier_value = Some(value.string()?),
+                4 => result.positive_int_value = Some(value.uint64()?),
+                5 => result.negative_int_value = Some(value.int64()?),
+                6 => result.double_value = Some(value.double()?),
+                7 => result.string_value = Some(value.bytes()?.to_vec()),
+                8 => result.aggregate_value = Some(value.string()?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for NamePart {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = NamePart::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name_part = value.string()?,
+                2 => result.is_extension = value.bool()?,
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for SourceCodeInfo {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = SourceCodeInfo::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            if field == 1 {
+                result.location.push(value.message()?);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for Location {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = Location::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => value.int32s(&mut result.path)?,
+                2 => value.int32s(&mut result.span)?,
+                3 => result.leading_comments = Some(value.string()?),
+                4 => result.trailing_comments = Some(value.string()?),
+                6 => result.leading_detached_comments.push(value.string()?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,458 @@
+// The protobuf binary wire format: https://protobuf.dev/programming-guides/encoding/
+
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WireType {
+    Varint = 0,
+    Fixed64 = 1,
+    LengthDelimited = 2,
+    Fixed32 = 5,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecodeError {
+    pub message: String,
+}
+
+impl DecodeError {
+    fn new(message: &str) -> Self {
+        DecodeError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid protobuf message: {}", self.message)
+    }
+}
+
+// A message that can be written in the wire format.
+pub trait Encode {
+    fn encode(&self, encoder: &mut Encoder);
+
+    fn encode_to_vec(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        self.encode(&mut encoder);
+
+        encoder.into_bytes()
+    }
+}
+
+// A message that can be read from the wire format. Unknown fields are skipped.
+pub trait Decode: Sized {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError>;
+}
+
+// Writes the fields of a single message. Fields are written in the order they're added, so callers write them in
+// field number order to match the output of protoc.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Encoder::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn uint64(&mut self, field: u32, value: u64) {
+        self.key(field, WireType::Varint);
+        self.varint(value);
+    }
+
+    // Negative values are sign extended to 64 bits, so they always take ten bytes.
+    pub fn int32(&mut self, field: u32, value: i32) {
+        self.int64(field, i64::from(value));
+    }
+
+    pub fn int64(&mut self, field: u32, value: i64) {
+        self.uint64(field, value as u64);
+    }
+
+    pub fn bool(&mut self, field: u32, value: bool) {
+        self.uint64(field, u64::from(value));
+    }
+
+    pub fn double(&mut self, field: u32, value: f64) {
+        self.raw(field, &RawValue::Fixed64(value.to_bits()));
+    }
+
+    pub fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    pub fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, WireType::LengthDelimited);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    pub fn message(&mut self, field: u32, message: &impl Encode) {
+        self.bytes(field, &message.encode_to_vec());
+    }
+
+    pub fn raw(&mut self, field: u32, value: &RawValue) {
+        match value {
+            RawValue::Varint(value) => self.uint64(field, *value),
+            RawValue::Fixed64(value) => {
+                self.key(field, WireType::Fixed64);
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            }
+            RawValue::LengthDelimited(bytes) => self.bytes(field, bytes),
+            RawValue::Fixed32(value) => {
+                self.key(field, WireType::Fixed32);
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+
+    // Repeated scalars are written as a single length-delimited run of varints (or not at all when empty).
+    pub fn packed_int32(&mut self, field: u32, values: &[i32]) {
+        if values.is_empty() {
+            return;
+        }
+
+        let mut packed = Encoder::new();
+        for value in values {
+            packed.varint(i64::from(*value) as u64);
+        }
+
+        self.bytes(field, &packed.into_bytes());
+    }
+
+    fn key(&mut self, field: u32, wire_type: WireType) {
+        self.varint(u64::from(field) << 3 | wire_type as u64);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+
+        self.buf.push(value as u8);
+    }
+}
+
+// Reads the fields of a single message, in the order they were written.
+pub struct Decoder<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+// The value of a field, as far as it can be interpreted without knowing the field's type.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FieldValue<'b> {
+    Varint(u64),
+    Fixed64(u64),
+    LengthDelimited(&'b [u8]),
+    Fixed32(u32),
+}
+
+// A field value that's stored without being interpreted, e.g. a custom option.
+#[derive(Debug, PartialEq, Clone)]
+pub enum RawValue {
+    Varint(u64),
+    Fixed64(u64),
+    LengthDelimited(Vec<u8>),
+    Fixed32(u32),
+}
+
+impl RawValue {
+    pub fn as_field_value(&self) -> FieldValue<'_> {
+        match self {
+            RawValue::Varint(value) => FieldValue::Varint(*value),
+            RawValue::Fixed64(value) => FieldValue::Fixed64(*value),
+            RawValue::LengthDelimited(bytes) => FieldValue::LengthDelimited(bytes),
+            RawValue::Fixed32(value) => FieldValue::Fixed32(*value),
+        }
+    }
+}
+
+impl From<FieldValue<'_>> for RawValue {
+    fn from(value: FieldValue) -> Self {
+        match value {
+            FieldValue::Varint(value) => RawValue::Varint(value),
+            FieldValue::Fixed64(value) => RawValue::Fixed64(value),
+            FieldValue::LengthDelimited(bytes) => RawValue::LengthDelimited(bytes.to_vec()),
+            FieldValue::Fixed32(value) => RawValue::Fixed32(value),
+        }
+    }
+}
+
+impl<'b> Decoder<'b> {
+    pub fn new(bytes: &'b [u8]) -> Self {
+        Decoder { bytes, pos: 0 }
+    }
+
+    pub fn next_field(&mut self) -> Result<Option<(u32, FieldValue<'b>)>, DecodeError> {
+        if self.pos == self.bytes.len() {
+            return Ok(None);
+        }
+
+        let key = self.varint()?;
+        let field = u32::try_from(key >> 3)
+            .ok()
+            .filter(|field| *field > 0)
+            .ok_or_else(|| DecodeError::new("invalid field number"))?;
+
+        let value = match key & 0x7 {
+            0 => FieldValue::Varint(self.varint()?),
+            1 => FieldValue::Fixed64(u64::from_le_bytes(self.fixed()?)),
+            2 => {
+                let len = usize::try_from(self.varint()?)
+                    .map_err(|_| DecodeError::new("length out of range"))?;
+
+                FieldValue::LengthDelimited(self.take(len)?)
+            }
+            5 => FieldValue::Fixed32(u32::from_le_bytes(self.fixed()?)),
+            3 | 4 => return Err(DecodeError::new("groups are not supported")),
+            _ => return Err(DecodeError::new("invalid wire type")),
+        };
+
+        Ok(Some((field, value)))
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut result = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            result |= u64::from(byte & 0x7f) << shift;
+
+            if byte < 0x80 {
+                return Ok(result);
+            }
+        }
+
+        Err(DecodeError::new("varint is too long"))
+    }
+
+    fn fixed<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut result = [0; N];
+        result.copy_from_slice(self.take(N)?);
+
+        Ok(result)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'b [u8], DecodeError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| DecodeError::new("unexpected end of input"))?;
+
+        let result = &self.bytes[self.pos..end];
+        self.pos = end;
+
+        Ok(result)
+    }
+}
+
+impl<'b> FieldValue<'b> {
+    pub fn uint64(self) -> Result<u64, DecodeError> {
+        match self {
+            FieldValue::Varint(value) => Ok(value),
+            _ => Err(DecodeError::new("expected a varint")),
+        }
+    }
+
+    // int32 values are written sign extended to 64 bits, so they're truncated back down.
+    pub fn int32(self) -> Result<i32, DecodeError> {
+        Ok(self.uint64()? as i32)
+    }
+
+    pub fn int64(self) -> Result<i64, DecodeError> {
+        Ok(self.uint64()? as i64)
+    }
+
+    pub fn bool(self) -> Result<bool, DecodeError> {
+        Ok(self.uint64()? != 0)
+    }
+
+    pub fn double(self) -> Result<f64, DecodeError> {
+        match self {
+            FieldValue::Fixed64(value) => Ok(f64::from_bits(value)),
+            _ => Err(DecodeError::new("expected a 64-bit value")),
+        }
+    }
+
+    pub fn bytes(self) -> Result<&'b [u8], DecodeError> {
+        match self {
+            FieldValue::LengthDelimited(bytes) => Ok(bytes),
+            _ => Err(DecodeError::new("expected a length-delimited value")),
+        }
+    }
+
+    pub fn string(self) -> Result<String, DecodeError> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .map_err(|_| DecodeError::new("string is not valid UTF-8"))
+    }
+
+    pub fn message<T: Decode>(self) -> Result<T, DecodeError> {
+        T::decode(self.bytes()?)
+    }
+
+    // Splits a packed run of repeated scalars into its values, which are all of the given wire type.
+    pub fn unpack(self, wire_type: WireType) -> Result<Vec<FieldValue<'b>>, DecodeError> {
+        let bytes = self.bytes()?;
+        let mut packed = Decoder::new(bytes);
+        let mut values = vec![];
+
+        while packed.pos < bytes.len() {
+            values.push(match wire_type {
+                WireType::Varint => FieldValue::Varint(packed.varint()?),
+                WireType::Fixed64 => FieldValue::Fixed64(u64::from_le_bytes(packed.fixed()?)),
+                WireType::Fixed32 => FieldValue::Fixed32(u32::from_le_bytes(packed.fixed()?)),
+                WireType::LengthDelimited => {
+                    return Err(DecodeError::new("length-delimited values can't be packed"))
+                }
+            });
+        }
+
+        Ok(values)
+    }
+
+    // Repeated scalars can be written either packed or one value per field, and parsers have to accept both.
+    pub fn int32s(self, values: &mut Vec<i32>) -> Result<(), DecodeError> {
+        match self {
+            FieldValue::LengthDelimited(bytes) => {
+                let mut packed = Decoder::new(bytes);
+                while packed.pos < bytes.len() {
+                    values.push(packed.varint()? as i32);
+                }
+            }
+            value => values.push(value.int32()?),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(encode: impl FnOnce(&mut Encoder)) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encode(&mut encoder);
+
+        encoder.into_bytes()
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(encoded(|e| e.uint64(1, 150)), vec![0x08, 0x96, 0x01]);
+        assert_eq!(encoded(|e| e.int32(2, 0)), vec![0x10, 0x00]);
+        assert_eq!(
+            encoded(|e| e.int32(1, -1)),
+            vec![0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+        assert_eq!(encoded(|e| e.bool(16, true)), vec![0x80, 0x01, 0x01]);
+        assert_eq!(
+            encoded(|e| e.string(2, "testing")),
+            vec![0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g']
+        );
+        assert_eq!(
+            encoded(|e| e.double(6, 1.5)),
+            vec![0x31, 0, 0, 0, 0, 0, 0, 0xf8, 0x3f]
+        );
+        assert_eq!(
+            encoded(|e| e.packed_int32(4, &[3, 270, 86942])),
+            vec![0x22, 0x06, 0x03, 0x8e, 0x02, 0x9e, 0xa7, 0x05]
+        );
+        assert_eq!(encoded(|e| e.packed_int32(4, &[])), vec![]);
+        assert_eq!(
+            encoded(|e| e.raw(5, &RawValue::Fixed32(1.5f32.to_bits()))),
+            vec![0x2d, 0, 0, 0xc0, 0x3f]
+        );
+    }
+
+    #[test]
+    fn test_decoding() {
+        let bytes = encoded(|e| {
+            e.uint64(1, 150);
+            e.int32(2, -1);
+            e.string(3, "testing");
+            e.double(4, 1.5);
+            e.packed_int32(5, &[3, 270, -2]);
+            e.int32(5, 7);
+        });
+
+        let mut decoder = Decoder::new(&bytes);
+        let mut next = || {
+            decoder
+                .next_field()
+                .expect("failed to decode")
+                .expect("missing field")
+        };
+
+        assert_eq!(next(), (1, FieldValue::Varint(150)));
+        assert_eq!(next().1.int32(), Ok(-1));
+        assert_eq!(next().1.string(), Ok("testing".to_string()));
+        assert_eq!(next().1.double(), Ok(1.5));
+
+        let mut values = vec![];
+        next()
+            .1
+            .int32s(&mut values)
+            .expect("failed to decode packed values");
+        next()
+            .1
+            .int32s(&mut values)
+            .expect("failed to decode unpacked value");
+        assert_eq!(values, vec![3, 270, -2, 7]);
+
+        let packed = encoded(|e| e.packed_int32(1, &[1, 300]));
+        assert_eq!(
+            FieldValue::LengthDelimited(&packed[2..]).unpack(WireType::Varint),
+            Ok(vec![FieldValue::Varint(1), FieldValue::Varint(300)])
+        );
+        assert_eq!(
+            FieldValue::LengthDelimited(&[0, 0, 0xc0, 0x3f]).unpack(WireType::Fixed32),
+            Ok(vec![FieldValue::Fixed32(1.5f32.to_bits())])
+        );
+
+        assert_eq!(decoder.next_field(), Ok(None));
+    }
+
+    #[test]
+    fn test_decoding_errors() {
+        let error = |bytes: &[u8]| {
+            let mut decoder = Decoder::new(bytes);
+            loop {
+                match decoder.next_field() {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => return None,
+                    Err(err) => return Some(err.to_string()),
+                }
+            }
+        };
+
+        assert_eq!(
+            error(&[0x12, 0x05, b'a']),
+            Some("Invalid protobuf message: unexpected end of input".to_string())
+        );
+        assert_eq!(
+            error(&[0x08, 0x80]),
+            Some("Invalid protobuf message: unexpected end of input".to_string())
+        );
+        assert_eq!(
+            error(&[0x00, 0x01]),
+            Some("Invalid protobuf message: invalid field number".to_string())
+        );
+        assert_eq!(
+            error(&[0x0b]),
+            Some("Invalid protobuf message: groups are not supported".to_string())
+        );
+        assert_eq!(error(&[0x08, 0x01]), None);
+    }
+}
@@ -2,8 +2,66 @@ extern crate pest;
 #[macro_use]
 extern crate pest_derive;
 
+pub mod cli;
 pub mod code_gen;
+pub mod compiler;
+pub mod descriptor;
 pub mod parser;
+pub mod plugin;
 pub mod utils;
+pub mod validator;
 
-fn main() {}
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+
+fn main() {
+    let args = std::env::args().collect::<Vec<String>>();
+
+    // Installed (or linked) as protoc-gen-dart-rs, the binary runs as a protoc plugin.
+    let program_name = args
+        .first()
+        .and_then(|program| Path::new(program).file_stem())
+        .and_then(|stem| stem.to_str());
+
+    if program_name == Some(plugin::PLUGIN_NAME) {
+        process::exit(run_plugin());
+    }
+
+    process::exit(cli::run(
+        args.get(1..).unwrap_or_default(),
+        &mut io::stdout(),
+        &mut io::stderr(),
+    ));
+}
+
+fn run_plugin() -> i32 {
+    let mut request = vec![];
+    if let Err(err) = io::stdin().read_to_end(&mut request) {
+        eprintln!(
+            "{}: failed to read the request: {}",
+            plugin::PLUGIN_NAME,
+            err
+        );
+        return 1;
+    }
+
+    let response = match plugin::run(&request) {
+        Ok(response) => response,
+        Err(err) => {
+            eprintln!("{}: {}", plugin::PLUGIN_NAME, err);
+            return 1;
+        }
+    };
+
+    if let Err(err) = io::stdout().write_all(&response) {
+        eprintln!(
+            "{}: failed to write the response: {}",
+            plugin::PLUGIN_NAME,
+            err
+        );
+        return 1;
+    }
+
+    0
+}
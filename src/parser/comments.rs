@@ -0,0 +1,320 @@
+use super::types::*;
+
+#[derive(Debug, PartialEq)]
+enum CommentKind {
+    Line,
+    Block,
+}
+
+struct Comment {
+    kind: CommentKind,
+    text: String,
+    start: usize,
+    end: usize,
+
+    // Whether there is code before the comment on the line it starts on.
+    follows_code: bool,
+}
+
+// Attaches comments to the declarations they document, following the rules protoc uses for SourceCodeInfo:
+//   * a comment directly above a declaration (or before it on the same line) is its leading comment,
+//   * a comment after a declaration on the line it ends on (or after the opening brace of a block) is its trailing comment,
+//     as is a comment on the following line that is then followed by a blank line, another comment or the end of the block,
+//   * any other comments between the previous declaration and the leading comment are detached comments.
+pub fn attach_comments(src: &str, program: &mut Program) {
+    let comments = scan_comments(src);
+    if comments.is_empty() {
+        return;
+    }
+
+    let attacher = CommentAttacher { src, comments };
+
+    if let Some(span) = program.syntax_span {
+        program.syntax_comments = attacher.comments_for_statement(&span);
+    }
+    if let Some(span) = program.package_span {
+        program.package_comments = attacher.comments_for_statement(&span);
+    }
+    for import in &mut program.imports {
+        if let Some(span) = import.span {
+            import.comments = attacher.comments_for_statement(&span);
+        }
+    }
+
+    attacher.attach_to_options(&mut program.options);
+    attacher.attach_to_types(&mut program.types);
+    attacher.attach_to_extends(&mut program.extends);
+
+    for service in &mut program.services {
+        if let Some(span) = service.span {
+            service.comments = attacher.comments_for_block(&span);
+        }
+        attacher.attach_to_options(&mut service.options);
+
+        for rpc in &mut service.rpcs {
+            if let Some(span) = rpc.span {
+                rpc.comments = attacher.comments_for_block(&span);
+            }
+            attacher.attach_to_options(&mut rpc.options);
+        }
+    }
+}
+
+struct CommentAttacher<'s> {
+    src: &'s str,
+    comments: Vec<Comment>,
+}
+
+impl<'s> CommentAttacher<'s> {
+    fn attach_to_types(&self, types: &mut [ProtoType]) {
+        for proto_type in types {
+            match proto_type {
+                ProtoType::Message(message) => {
+                    if let Some(span) = message.span {
+                        message.comments = self.comments_for_block(&span);
+                    }
+
+                    self.attach_to_options(&mut message.options);
+                    self.attach_to_statements(&mut message.reserved_statements);
+                    self.attach_to_statements(&mut message.extensions_statements);
+
+                    self.attach_to_fields(&mut message.fields);
+                    for oneof in &mut message.oneofs {
+                        if let Some(span) = oneof.span {
+                            oneof.comments = self.comments_for_block(&span);
+                        }
+                        self.attach_to_options(&mut oneof.options);
+                        self.attach_to_fields(&mut oneof.fields);
+                    }
+
+                    self.attach_to_types(&mut message.types);
+                    self.attach_to_extends(&mut message.extends);
+                }
+                ProtoType::Enum(enumeration) => {
+                    if let Some(span) = enumeration.span {
+                        enumeration.comments = self.comments_for_block(&span);
+                    }
+                    self.attach_to_options(&mut enumeration.options);
+                    self.attach_to_statements(&mut enumeration.reserved_statements);
+
+                    for value in &mut enumeration.values {
+                        if let Some(span) = value.span {
+                            value.comments = self.comments_for_statement(&span);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn attach_to_extends(&self, extends: &mut [ProtoExtend]) {
+        for extend in extends {
+            if let Some(span) = extend.span {
+                extend.comments = self.comments_for_block(&span);
+            }
+            self.attach_to_fields(&mut extend.fields);
+        }
+    }
+
+    // Only option statements are given comments, the options in brackets after fields and enum values aren't.
+    fn attach_to_options(&self, options: &mut [ProtoOption]) {
+        for option in options {
+            if let Some(span) = option.span {
+                option.comments = self.comments_for_statement(&span);
+            }
+        }
+    }
+
+    fn attach_to_statements(&self, statements: &mut [ProtoStatement]) {
+        for statement in statements {
+            if let Some(span) = statement.span {
+                statement.comments = self.comments_for_statement(&span);
+            }
+        }
+    }
+
+    fn attach_to_fields(&self, fields: &mut [ProtoMessageField]) {
+        for field in fields {
+            if let Some(span) = field.span {
+                field.comments = self.comments_for_statement(&span);
+            }
+        }
+    }
+
+    // Blocks (messages, enums, services, ...) take their trailing comment from after the opening brace.
+    fn comments_for_block(&self, span: &Span) -> ProtoComments {
+        let header_end = self.src[span.start..span.end]
+            .find('{')
+            .map(|brace| span.start + brace + 1)
+            .unwrap_or(span.end);
+
+        self.comments_for(span, header_end)
+    }
+
+    fn comments_for_statement(&self, span: &Span) -> ProtoComments {
+        self.comments_for(span, span.end)
+    }
+
+    fn comments_for(&self, span: &Span, header_end: usize) -> ProtoComments {
+        let mut result = ProtoComments::default();
+
+        // Walk backwards from the declaration through comments that are only separated by whitespace.
+        let mut cursor = span.start;
+        let preceding = self
+            .comments
+            .iter()
+            .rev()
+            .filter(|comment| comment.end <= span.start);
+
+        for comment in preceding {
+            let gap = &self.src[comment.end..cursor];
+            if !gap.trim().is_empty() || comment.follows_code || self.trails_previous(comment) {
+                break;
+            }
+
+            if cursor == span.start && gap.matches('\n').count() <= 1 {
+                result.leading = Some(comment.text.clone());
+            } else {
+                result.leading_detached.insert(0, comment.text.clone());
+            }
+
+            cursor = comment.start;
+        }
+
+        let following = self
+            .comments
+            .iter()
+            .find(|comment| comment.start >= header_end);
+
+        if let Some(comment) = following {
+            let gap = &self.src[header_end..comment.start];
+            if gap.trim().is_empty() && (!gap.contains('\n') || self.trails_previous(comment)) {
+                result.trailing = Some(comment.text.clone());
+            }
+        }
+
+        result
+    }
+
+    // Whether a comment on its own line belongs to the code before it rather than to the next declaration. Like protoc, that's
+    // the case when it starts on the line right after the code, and is followed by a blank line, another comment, the end of
+    // the enclosing block or the end of the file.
+    fn trails_previous(&self, comment: &Comment) -> bool {
+        let code_end = self.src[..comment.start].trim_end().len();
+        let after_comment = self
+            .comments
+            .iter()
+            .any(|other| other.start < code_end && code_end <= other.end);
+
+        if comment.follows_code
+            || code_end == 0
+            || after_comment
+            || self.src[code_end..comment.start].matches('\n').count() != 1
+        {
+            return false;
+        }
+
+        let rest = &self.src[comment.end..];
+        let next = rest.trim_start();
+
+        next.is_empty()
+            || next.starts_with('}')
+            || next.starts_with("//")
+            || next.starts_with("/*")
+            || rest[..rest.len() - next.len()].matches('\n').count() > 1
+    }
+}
+
+fn scan_comments(src: &str) -> Vec<Comment> {
+    let bytes = src.as_bytes();
+    let mut comments: Vec<Comment> = vec![];
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            // Skip over string literals so that "//" inside of them isn't mistaken for a comment.
+            (quote @ b'"', _) | (quote @ b'\'', _) => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote && bytes[i] != b'\n' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+
+                    i += 1;
+                }
+
+                i += 1;
+            }
+            (b'/', Some(b'/')) => {
+                let end = src[i..].find('\n').map_or(src.len(), |newline| i + newline);
+                let text = format!("{}\n", src[i + 2..end].trim_end_matches('\r'));
+
+                push_comment(src, &mut comments, CommentKind::Line, text, i, end);
+                i = end;
+            }
+            (b'/', Some(b'*')) => {
+                let (text_end, end) = match src[i + 2..].find("*/") {
+                    Some(close) => (i + 2 + close, i + 2 + close + 2),
+                    None => (src.len(), src.len()),
+                };
+                let text = strip_block_comment(&src[i + 2..text_end]);
+
+                push_comment(src, &mut comments, CommentKind::Block, text, i, end);
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    comments
+}
+
+// Consecutive line comments that sit alone on their lines are merged into a single comment.
+fn push_comment(
+    src: &str,
+    comments: &mut Vec<Comment>,
+    kind: CommentKind,
+    text: String,
+    start: usize,
+    end: usize,
+) {
+    let line_start = src[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let follows_code = !src[line_start..start].trim().is_empty();
+
+    if let Some(previous) = comments.last_mut() {
+        let continues_previous = kind == CommentKind::Line
+            && previous.kind == CommentKind::Line
+            && !follows_code
+            && !previous.follows_code
+            && src[previous.end..start].matches('\n').count() == 1;
+
+        if continues_previous {
+            previous.text.push_str(&text);
+            previous.end = end;
+            return;
+        }
+    }
+
+    comments.push(Comment {
+        kind,
+        text,
+        start,
+        end,
+        follows_code,
+    });
+}
+
+// Strips the leading whitespace and '*' that conventionally start each line of a block comment after the first.
+fn strip_block_comment(text: &str) -> String {
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => line,
+            _ => {
+                let trimmed = line.trim_start();
+                trimmed.strip_prefix('*').unwrap_or(trimmed)
+            }
+        })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
@@ -0,0 +1,173 @@
+use super::{Rule, Span};
+use pest::error::{Error, ErrorVariant, InputLocation, LineColLocation};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError {
+    // The input doesn't match the grammar; `expected` lists the rules that would have been accepted.
+    Syntax {
+        span: Span,
+        expected: Vec<Rule>,
+        message: String,
+    },
+
+    // A rule showed up (or was missing) somewhere the parser doesn't know how to handle it.
+    UnexpectedRule {
+        span: Span,
+        rule: Rule,
+        message: String,
+    },
+
+    // A rule matched the grammar but its value is invalid (e.g. an out of range number).
+    InvalidValue {
+        span: Span,
+        rule: Rule,
+        message: String,
+    },
+
+    // The file parsed, but breaks one of protobuf's rules (e.g. it uses a reserved field number).
+    Validation {
+        span: Option<Span>,
+        message: String,
+    },
+}
+
+impl ParseError {
+    pub fn unexpected_rule(span: pest::Span, rule: Rule, message: String) -> Self {
+        ParseError::UnexpectedRule {
+            span: span.into(),
+            rule,
+            message,
+        }
+    }
+
+    pub fn invalid_value(span: pest::Span, rule: Rule, message: String) -> Self {
+        ParseError::InvalidValue {
+            span: span.into(),
+            rule,
+            message,
+        }
+    }
+
+    pub fn validation(span: Option<Span>, message: String) -> Self {
+        ParseError::Validation { span, message }
+    }
+
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            ParseError::Syntax { span, .. }
+            | ParseError::UnexpectedRule { span, .. }
+            | ParseError::InvalidValue { span, .. } => Some(span),
+            ParseError::Validation { span, .. } => span.as_ref(),
+        }
+    }
+
+    pub fn rule(&self) -> Option<Rule> {
+        match self {
+            ParseError::UnexpectedRule { rule, .. } | ParseError::InvalidValue { rule, .. } => {
+                Some(*rule)
+            }
+            ParseError::Syntax { .. } | ParseError::Validation { .. } => None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ParseError::Syntax { message, .. }
+            | ParseError::UnexpectedRule { message, .. }
+            | ParseError::InvalidValue { message, .. }
+            | ParseError::Validation { message, .. } => message,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span() {
+            Some(span) => write!(
+                f,
+                "{}:{}: {}",
+                span.start_line,
+                span.start_column,
+                self.message()
+            ),
+            None => f.write_str(self.message()),
+        }
+    }
+}
+
+impl From<Error<Rule>> for ParseError {
+    fn from(err: Error<Rule>) -> Self {
+        let (start, end) = match err.location {
+            InputLocation::Pos(pos) => (pos, pos),
+            InputLocation::Span(span) => span,
+        };
+
+        let ((start_line, start_column), (end_line, end_column)) = match err.line_col {
+            LineColLocation::Pos(pos) => (pos, pos),
+            LineColLocation::Span(start, end) => (start, end),
+        };
+
+        let span = Span {
+            start,
+            end,
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        };
+
+        match err.variant {
+            ErrorVariant::ParsingError {
+                positives,
+                negatives,
+            } => {
+                let message = match (positives.len(), negatives.len()) {
+                    (0, 0) => "unknown parsing error".to_string(),
+                    (0, _) => format!("unexpected {}", list_rules(&negatives)),
+                    _ => format!("expected {}", list_rules(&positives)),
+                };
+
+                ParseError::Syntax {
+                    span,
+                    expected: positives,
+                    message,
+                }
+            }
+            ErrorVariant::CustomError { message } => ParseError::Syntax {
+                span,
+                expected: vec![],
+                message,
+            },
+        }
+    }
+}
+
+impl<'a> From<pest::Span<'a>> for Span {
+    fn from(span: pest::Span<'a>) -> Self {
+        let (start_line, start_column) = span.start_pos().line_col();
+        let (end_line, end_column) = span.end_pos().line_col();
+
+        Span {
+            start: span.start(),
+            end: span.end(),
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        }
+    }
+}
+
+fn list_rules(rules: &[Rule]) -> String {
+    let rules = rules
+        .iter()
+        .map(|rule| format!("{:?}", rule))
+        .collect::<Vec<String>>();
+
+    match rules.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
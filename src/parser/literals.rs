@@ -0,0 +1,103 @@
+// Decodes the contents of a string literal (without its quotes), following protobuf's escaping rules. The bytes don't
+// have to be UTF-8, since string literals are also used for bytes values.
+pub fn unescape(contents: &str) -> Result<Vec<u8>, String> {
+    let mut bytes: Vec<u8> = vec![];
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+
+        let escaped = chars
+            .next()
+            .ok_or_else(|| "String literal ends with an incomplete escape sequence".to_string())?;
+
+        match escaped {
+            'a' => bytes.push(0x07),
+            'b' => bytes.push(0x08),
+            'f' => bytes.push(0x0c),
+            'n' => bytes.push(b'\n'),
+            'r' => bytes.push(b'\r'),
+            't' => bytes.push(b'\t'),
+            'v' => bytes.push(0x0b),
+            '?' | '\\' | '\'' | '"' => bytes.push(escaped as u8),
+            'x' | 'X' => {
+                let digits = take_digits(&mut chars, 16, 2);
+                bytes.push(parse_digits(&escaped.to_string(), &digits, 16)? as u8);
+            }
+            '0'..='7' => {
+                let mut digits = escaped.to_string();
+                digits.push_str(&take_digits(&mut chars, 8, 2));
+
+                let value = parse_digits("", &digits, 8)?;
+                if value > 0xff {
+                    return Err(format!("Octal escape '\\{}' is out of range", digits));
+                }
+
+                bytes.push(value as u8);
+            }
+            'u' | 'U' => {
+                let len = if escaped == 'u' { 4 } else { 8 };
+                let digits = take_digits(&mut chars, 16, len);
+                if digits.len() != len {
+                    return Err(format!(
+                        "Unicode escape '\\{}' must have exactly {} hex digits",
+                        escaped, len
+                    ));
+                }
+
+                let code_point = parse_digits(&escaped.to_string(), &digits, 16)?;
+                let c = std::char::from_u32(code_point).ok_or_else(|| {
+                    format!(
+                        "Unicode escape '\\{}{}' is not a valid code point",
+                        escaped, digits
+                    )
+                })?;
+
+                let mut buffer = [0; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            }
+            other => return Err(format!("Invalid escape sequence '\\{}'", other)),
+        }
+    }
+
+    Ok(bytes)
+}
+
+// Splits an integer literal into its digits and radix, e.g. "0x1F" is ("1F", 16) and "017" is ("17", 8).
+pub fn int_lit_radix(text: &str) -> (&str, u32) {
+    if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        (digits, 16)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (&text[1..], 8)
+    } else {
+        (text, 10)
+    }
+}
+
+fn take_digits<I>(chars: &mut std::iter::Peekable<I>, radix: u32, max_len: usize) -> String
+where
+    I: Iterator<Item = char>,
+{
+    let mut digits = String::new();
+
+    while digits.len() < max_len {
+        match chars.peek() {
+            Some(c) if c.is_digit(radix) => {
+                digits.push(*c);
+                chars.next();
+            }
+            _ => break,
+        }
+    }
+
+    digits
+}
+
+fn parse_digits(prefix: &str, digits: &str, radix: u32) -> Result<u32, String> {
+    u32::from_str_radix(digits, radix)
+        .map_err(|_| format!("Invalid escape sequence '\\{}{}'", prefix, digits))
+}
@@ -1,7 +1,13 @@
+mod comments;
+mod error;
+pub mod literals;
 pub mod types;
+mod validation;
 
+pub use error::*;
 use pest::iterators::{Pair, Pairs};
 use pest::Parser as PestParser;
+use std::num::ParseIntError;
 pub use types::*;
 
 #[derive(Parser)]
@@ -9,304 +15,964 @@ pub use types::*;
 struct PestProtoParser;
 
 pub trait Parser {
-    fn parse<'a>(&self, input: &'a str) -> Result<Program<'a>, String>;
+    fn parse(&self, input: &str) -> Result<Program, ParseError>;
 }
 
 pub fn new_parser() -> impl Parser {
     ParserImpl::default()
 }
 
+// The inner pairs of a rule, which reports a ParseError (rather than panicking) when an expected pair is missing.
+struct Parts<'a> {
+    rule: Rule,
+    span: pest::Span<'a>,
+    pairs: Pairs<'a, Rule>,
+}
+
+impl<'a> Parts<'a> {
+    fn of(pair: Pair<'a, Rule>) -> Self {
+        Parts {
+            rule: pair.as_rule(),
+            span: pair.as_span(),
+            pairs: pair.into_inner(),
+        }
+    }
+
+    fn next(&mut self) -> Result<Pair<'a, Rule>, ParseError> {
+        let (rule, span) = (self.rule, self.span.clone());
+
+        self.pairs.next().ok_or_else(|| {
+            ParseError::unexpected_rule(span, rule, format!("Unexpected end of {:?}", rule))
+        })
+    }
+
+    fn next_if(&mut self, rule: Rule) -> Option<Pair<'a, Rule>> {
+        match self.pairs.peek() {
+            Some(ref pair) if pair.as_rule() == rule => self.pairs.next(),
+            _ => None,
+        }
+    }
+
+    fn rest(self) -> Pairs<'a, Rule> {
+        self.pairs
+    }
+}
+
+fn span_of(pair: &Pair<Rule>) -> Option<Span> {
+    Some(pair.as_span().into())
+}
+
+fn unexpected_rule(pair: &Pair<Rule>, context: &str) -> ParseError {
+    ParseError::unexpected_rule(
+        pair.as_span(),
+        pair.as_rule(),
+        format!(
+            "Unexpected rule {:?} when parsing {}",
+            pair.as_rule(),
+            context
+        ),
+    )
+}
+
 #[derive(Default)]
 pub struct ParserImpl {}
 
 impl ParserImpl {
-    fn parse_pest(prog: &str) -> Result<Pairs<Rule>, pest::error::Error<Rule>> {
-        PestProtoParser::parse(Rule::program, prog)
+    fn parse_pest(prog: &str) -> Result<Pairs<'_, Rule>, ParseError> {
+        PestProtoParser::parse(Rule::program, prog).map_err(ParseError::from)
     }
 
-    fn do_parse<'a>(src: &'a str, mut parse_root: Pairs<'a, Rule>) -> Result<Program<'a>, String> {
+    fn do_parse<'a>(src: &'a str, mut parse_root: Pairs<'a, Rule>) -> Result<Program, ParseError> {
         let mut prog = Program::new(src);
 
-        let top_level_stmts = parse_root.next().unwrap().into_inner();
+        let top_level_stmts = match parse_root.next() {
+            Some(program) => program.into_inner(),
+            None => return Ok(prog),
+        };
+
         for stmt in top_level_stmts {
             match stmt.as_rule() {
-                Rule::syntax => prog.syntax = Some(Self::parse_syntax(stmt)?),
-                Rule::package => prog.package = Some(Self::parse_package(stmt)?),
+                Rule::syntax => {
+                    prog.syntax_span = span_of(&stmt);
+                    prog.syntax = Some(Self::parse_syntax(stmt)?);
+                }
+                Rule::package => {
+                    prog.package_span = span_of(&stmt);
+                    prog.package = Some(Self::parse_package(stmt)?);
+                }
                 Rule::import => prog.imports.push(Self::parse_import(stmt)?),
                 Rule::option => prog.options.push(Self::parse_option(stmt)?),
                 Rule::enum_def => prog.types.push(Self::parse_enum(stmt)?),
                 Rule::message_def => prog.types.push(Self::parse_message(stmt)?),
-                err => {
-                    return Err(format!(
-                        "Unexpected rule '{:?}' found at top level of file.",
-                        err
-                    ));
-                }
+                Rule::service_def => prog.services.push(Self::parse_service(stmt)?),
+                Rule::extend_def => prog.extends.push(Self::parse_extend(stmt)?),
+                Rule::EOI => {}
+                _ => return Err(unexpected_rule(&stmt, "top level of file")),
             }
         }
 
+        comments::attach_comments(src, &mut prog);
+        validation::validate(&prog)?;
+
         Ok(prog)
     }
 
-    fn parse_enum(statement: Pair<Rule>) -> Result<ProtoType, String> {
-        let mut enum_def_parts = statement.into_inner();
+    fn parse_enum(statement: Pair<Rule>) -> Result<ProtoType, ParseError> {
+        let span = span_of(&statement);
+        let mut enum_def_parts = Parts::of(statement);
 
-        let name = enum_def_parts.next().unwrap().as_str();
-        let mut result = ProtoEnum::new(name);
+        let name_pair = enum_def_parts.next()?;
+        let name = name_pair.as_str();
+        let mut result = ProtoEnum::new(name, span);
+        result.name_span = span_of(&name_pair);
 
-        let body_parts = enum_def_parts.next().unwrap().into_inner();
+        let body_parts = enum_def_parts.next()?.into_inner();
         for part in body_parts {
             match part.as_rule() {
                 Rule::option => result.options.push(Self::parse_option(part)?),
-                Rule::enum_value => result.values.push(Self::parse_enum_value(part)?),
-                err => {
-                    return Err(format!(
-                        "Unexpected rule found when parsing enum body: {:?}",
-                        err
-                    ));
+                Rule::reserved => {
+                    let span = span_of(&part);
+                    let reserved = Self::parse_reserved(part)?;
+
+                    result
+                        .reserved_statements
+                        .push(Self::statement(reserved.len(), span));
+                    result.reserved.extend(reserved);
                 }
+                Rule::enum_value => result.values.push(Self::parse_enum_value(part)?),
+                _ => return Err(unexpected_rule(&part, "enum body")),
             }
         }
 
+        for value in &result.values {
+            Self::check_not_reserved(
+                &result.reserved,
+                &value.name,
+                value.position.into(),
+                value.span,
+                || format!("Enum value '{}' in enum '{}'", value.name, name),
+            )?;
+        }
+
         Ok(ProtoType::Enum(result))
     }
 
-    fn parse_enum_value(value: Pair<Rule>) -> Result<ProtoEnumValue, String> {
-        let mut value_parts = value.into_inner();
-        let name = value_parts.next().unwrap().as_str().to_string();
-        let position = value_parts.next().unwrap().as_str().parse::<u32>().unwrap();
+    fn parse_enum_value(value: Pair<Rule>) -> Result<ProtoEnumValue, ParseError> {
+        let span = span_of(&value);
+        let mut value_parts = Parts::of(value);
+        let name_pair = value_parts.next()?;
+        let position_pair = value_parts.next()?;
+        let position =
+            Self::parse_integer(position_pair.clone(), "enum value", i32::from_str_radix)?;
 
-        let options = Self::parse_field_options(&mut value_parts)?;
+        let (options, options_span) = Self::parse_field_options(value_parts.rest())?;
 
         Ok(ProtoEnumValue {
-            name,
+            name: name_pair.as_str().to_string(),
             position,
             options,
+            comments: ProtoComments::default(),
+            span,
+            name_span: span_of(&name_pair),
+            position_span: span_of(&position_pair),
+            options_span,
         })
     }
 
-    fn parse_message(statement: Pair<Rule>) -> Result<ProtoType, String> {
-        let mut message_def_parts = statement.into_inner();
+    fn parse_message(statement: Pair<Rule>) -> Result<ProtoType, ParseError> {
+        let span = span_of(&statement);
+        let mut message_def_parts = Parts::of(statement);
 
-        let name = message_def_parts.next().unwrap().as_str();
-        let mut result = ProtoMessage::new(name);
+        let name_pair = message_def_parts.next()?;
+        let name = name_pair.as_str();
+        let mut result = ProtoMessage::new(name, span);
+        result.name_span = span_of(&name_pair);
 
-        let body = message_def_parts.next().unwrap();
+        let body = message_def_parts.next()?;
 
         let body_parts = body.into_inner();
         for part in body_parts {
             match part.as_rule() {
                 Rule::option => result.options.push(Self::parse_option(part)?),
+                Rule::reserved => {
+                    let span = span_of(&part);
+                    let reserved = Self::parse_reserved(part)?;
+
+                    result
+                        .reserved_statements
+                        .push(Self::statement(reserved.len(), span));
+                    result.reserved.extend(reserved);
+                }
+                Rule::extensions => {
+                    let span = span_of(&part);
+                    let ranges = Self::parse_extensions(part)?;
+
+                    result
+                        .extensions_statements
+                        .push(Self::statement(ranges.len(), span));
+                    result.extension_ranges.extend(ranges);
+                }
                 Rule::message_def => result.types.push(Self::parse_message(part)?),
+                Rule::extend_def => result.extends.push(Self::parse_extend(part)?),
                 Rule::enum_def => result.types.push(Self::parse_enum(part)?),
+                Rule::oneof_def => result.oneofs.push(Self::parse_oneof(part)?),
                 Rule::message_field => result.fields.push(Self::parse_message_field(part)?),
-                err => {
-                    return Err(format!(
-                        "Unexpected rule {:?} when parsing message body",
-                        err
-                    ));
-                }
+                _ => return Err(unexpected_rule(&part, "message body")),
             }
         }
 
+        let oneof_fields = result.oneofs.iter().flat_map(|oneof| oneof.fields.iter());
+        for field in result.fields.iter().chain(oneof_fields) {
+            Self::check_not_reserved(
+                &result.reserved,
+                &field.name,
+                field.position.into(),
+                field.span,
+                || format!("Field '{}' in message '{}'", field.name, name),
+            )?;
+        }
+
         Ok(ProtoType::Message(result))
     }
 
-    fn parse_message_field(field: Pair<Rule>) -> Result<ProtoMessageField, String> {
-        let mut field_parts = field.into_inner();
+    fn statement(len: usize, span: Option<Span>) -> ProtoStatement {
+        ProtoStatement {
+            len,
+            comments: ProtoComments::default(),
+            span,
+        }
+    }
+
+    fn parse_extensions(statement: Pair<Rule>) -> Result<Vec<ProtoRange>, ParseError> {
+        statement.into_inner().map(Self::parse_range).collect()
+    }
+
+    fn parse_extend(statement: Pair<Rule>) -> Result<ProtoExtend, ParseError> {
+        let span = span_of(&statement);
+        let mut extend_def_parts = Parts::of(statement);
+
+        let extendee_pair = extend_def_parts.next()?;
+        let extendee_span = span_of(&extendee_pair);
+        let extendee = extendee_pair.as_str().into();
+        let fields = extend_def_parts
+            .next()?
+            .into_inner()
+            .map(Self::parse_message_field)
+            .collect::<Result<Vec<ProtoMessageField>, ParseError>>()?;
+
+        Ok(ProtoExtend {
+            extendee,
+            fields,
+            comments: ProtoComments::default(),
+            span,
+            extendee_span,
+        })
+    }
+
+    fn parse_reserved(statement: Pair<Rule>) -> Result<Vec<ProtoReserved>, ParseError> {
+        let reservations = Parts::of(statement).next()?;
+
+        match reservations.as_rule() {
+            Rule::reserved_ranges => reservations
+                .into_inner()
+                .map(|range| Ok(ProtoReserved::Range(Self::parse_range(range)?)))
+                .collect(),
+            Rule::reserved_names => reservations
+                .into_inner()
+                .map(|name| {
+                    let span = span_of(&name);
+                    Ok(ProtoReserved::Name(Self::parse_string(name)?, span))
+                })
+                .collect(),
+            _ => Err(unexpected_rule(&reservations, "reserved statement")),
+        }
+    }
+
+    fn parse_range(range: Pair<Rule>) -> Result<ProtoRange, ParseError> {
+        let span = range.as_span();
+        let mut range_parts = Parts::of(range);
+
+        let start_pair = range_parts.next()?;
+        let start_span = span_of(&start_pair);
+        let start = Self::parse_integer(start_pair, "range", i32::from_str_radix)?;
+        let (end, end_span) = match range_parts.rest().next() {
+            Some(end) => {
+                let end_span = span_of(&end);
+                match end.as_rule() {
+                    Rule::range_max => (ProtoRangeEnd::Max, end_span),
+                    _ => (
+                        ProtoRangeEnd::Inclusive(Self::parse_integer(
+                            end,
+                            "range",
+                            i32::from_str_radix,
+                        )?),
+                        end_span,
+                    ),
+                }
+            }
+            None => (ProtoRangeEnd::Inclusive(start), start_span),
+        };
+
+        if let ProtoRangeEnd::Inclusive(end) = end {
+            if end < start {
+                return Err(ParseError::invalid_value(
+                    span,
+                    Rule::range,
+                    format!("Invalid range '{} to {}': end is before start", start, end),
+                ));
+            }
+        }
+
+        Ok(ProtoRange {
+            start,
+            end,
+            span: Some(span.into()),
+            start_span,
+            end_span,
+        })
+    }
+
+    fn parse_position(position: Pair<Rule>) -> Result<u32, ParseError> {
+        Self::parse_integer(position, "position", u32::from_str_radix)
+    }
+
+    // Parses a numeric that has to be an integer, using `from_str_radix` to bound it to the target type.
+    fn parse_integer<T>(
+        numeric: Pair<Rule>,
+        context: &str,
+        from_str_radix: fn(&str, u32) -> Result<T, ParseIntError>,
+    ) -> Result<T, ParseError> {
+        let invalid = |message: String| {
+            ParseError::invalid_value(
+                numeric.as_span(),
+                numeric.as_rule(),
+                format!("Invalid {} '{}': {}", context, numeric.as_str(), message),
+            )
+        };
+
+        let (sign, literal) = Self::parse_numeric_parts(numeric.clone())?;
+        if literal.as_rule() != Rule::int_lit {
+            return Err(invalid("expected an integer".to_string()));
+        }
+
+        let (digits, radix) = literals::int_lit_radix(literal.as_str());
+        from_str_radix(&format!("{}{}", sign, digits), radix)
+            .map_err(|err| invalid(err.to_string()))
+    }
+
+    // Splits a numeric into its sign ("-" or "") and its int_lit or float_lit.
+    fn parse_numeric_parts(
+        numeric: Pair<'_, Rule>,
+    ) -> Result<(&'static str, Pair<'_, Rule>), ParseError> {
+        let mut numeric_parts = Parts::of(numeric);
+
+        let sign = match numeric_parts.next_if(Rule::numeric_sign) {
+            Some(sign) if sign.as_str() == "-" => "-",
+            _ => "",
+        };
+
+        Ok((sign, numeric_parts.next()?))
+    }
+
+    fn check_not_reserved<F>(
+        reserved: &[ProtoReserved],
+        name: &str,
+        position: i64,
+        span: Option<Span>,
+        describe: F,
+    ) -> Result<(), ParseError>
+    where
+        F: Fn() -> String,
+    {
+        let violation = reserved.iter().find(|reservation| match reservation {
+            ProtoReserved::Range(range) => range.contains(position),
+            ProtoReserved::Name(reserved_name, _) => reserved_name == name,
+        });
+
+        match violation {
+            Some(reservation) => Err(ParseError::validation(
+                span,
+                format!(
+                    "{} (= {}) violates reservation 'reserved {};'",
+                    describe(),
+                    position,
+                    reservation
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_oneof(statement: Pair<Rule>) -> Result<ProtoOneof, ParseError> {
+        let span = span_of(&statement);
+        let mut oneof_def_parts = Parts::of(statement);
+
+        let name_pair = oneof_def_parts.next()?;
+        let mut result = ProtoOneof::new(name_pair.as_str(), span);
+        result.name_span = span_of(&name_pair);
+
+        let body_parts = oneof_def_parts.next()?.into_inner();
+        for part in body_parts {
+            match part.as_rule() {
+                Rule::option => result.options.push(Self::parse_option(part)?),
+                Rule::oneof_field => {
+                    let mut field = Self::parse_message_field(part)?;
+                    field.explicit_presence = true;
+
+                    result.fields.push(field);
+                }
+                _ => return Err(unexpected_rule(&part, "oneof body")),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn parse_message_field(field: Pair<Rule>) -> Result<ProtoMessageField, ParseError> {
+        let span = span_of(&field);
+        let mut field_parts = Parts::of(field);
 
-        let modifier = match field_parts.peek().unwrap().as_rule() {
-            Rule::message_field_modifier => match field_parts.next().unwrap().as_str() {
+        let modifier_pair = field_parts.next_if(Rule::message_field_modifier);
+        let modifier_span = modifier_pair.as_ref().and_then(span_of);
+        let modifier = match modifier_pair {
+            Some(modifier) => match modifier.as_str() {
                 "required" => Some(ProtoMessageFieldModifier::Required),
                 "optional" => Some(ProtoMessageFieldModifier::Optional),
                 "repeated" => Some(ProtoMessageFieldModifier::Repeated),
-                modifier => return Err(format!("Unkown modifier {}", modifier)),
+                err => {
+                    return Err(ParseError::invalid_value(
+                        modifier.as_span(),
+                        modifier.as_rule(),
+                        format!("Unknown modifier '{}'", err),
+                    ));
+                }
             },
-            _ => None,
+            None => None,
         };
 
-        let field_type = Self::parse_field_type(field_parts.next().unwrap())?;
-        let name = field_parts.next().unwrap().as_str();
-        let position = field_parts.next().unwrap().as_str().parse::<u32>().unwrap();
+        let type_pair = field_parts.next()?;
+        let type_span = span_of(&type_pair);
+        let field_type = Self::parse_field_type(type_pair)?;
+        let name_pair = field_parts.next()?;
+        let position_pair = field_parts.next()?;
+        let position_span = span_of(&position_pair);
+        let position = Self::parse_position(position_pair)?;
 
-        let options = match Self::parse_field_options(&mut field_parts) {
-            Ok(opts) => opts,
-            Err(err) => return Err(err),
-        };
+        let (options, options_span) = Self::parse_field_options(field_parts.rest())?;
+
+        // Labelled singular fields track presence; proto3 fields without a label only have implicit presence.
+        let explicit_presence = matches!(
+            modifier,
+            Some(ProtoMessageFieldModifier::Optional) | Some(ProtoMessageFieldModifier::Required)
+        );
 
         Ok(ProtoMessageField {
             modifier,
-            name,
+            explicit_presence,
+            name: name_pair.as_str().to_string(),
             field_type,
             options,
             position,
+            comments: ProtoComments::default(),
+            span,
+            modifier_span,
+            type_span,
+            name_span: span_of(&name_pair),
+            position_span,
+            options_span,
         })
     }
 
-    fn parse_field_type(type_pair: Pair<Rule>) -> Result<ProtoFieldType, String> {
+    fn parse_service(statement: Pair<Rule>) -> Result<ProtoService, ParseError> {
+        let span = span_of(&statement);
+        let mut service_def_parts = Parts::of(statement);
+
+        let name_pair = service_def_parts.next()?;
+        let mut result = ProtoService::new(name_pair.as_str(), span);
+        result.name_span = span_of(&name_pair);
+
+        let body_parts = service_def_parts.next()?.into_inner();
+        for part in body_parts {
+            match part.as_rule() {
+                Rule::option => result.options.push(Self::parse_option(part)?),
+                Rule::rpc_def => result.rpcs.push(Self::parse_rpc(part)?),
+                _ => return Err(unexpected_rule(&part, "service body")),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn parse_rpc(rpc: Pair<Rule>) -> Result<ProtoRpc, ParseError> {
+        let span = span_of(&rpc);
+        let mut rpc_parts = Parts::of(rpc);
+
+        let name_pair = rpc_parts.next()?;
+        let (request_stream_span, request_type, request_type_span) =
+            Self::parse_rpc_type(rpc_parts.next()?)?;
+        let (response_stream_span, response_type, response_type_span) =
+            Self::parse_rpc_type(rpc_parts.next()?)?;
+
+        let mut options = vec![];
+        if let Some(body) = rpc_parts.next_if(Rule::rpc_body) {
+            for part in body.into_inner() {
+                match part.as_rule() {
+                    Rule::option => options.push(Self::parse_option(part)?),
+                    _ => return Err(unexpected_rule(&part, "rpc body")),
+                }
+            }
+        }
+
+        Ok(ProtoRpc {
+            name: name_pair.as_str().to_string(),
+            request_type,
+            request_stream: request_stream_span.is_some(),
+            response_type,
+            response_stream: response_stream_span.is_some(),
+            options,
+            comments: ProtoComments::default(),
+            span,
+            name_span: span_of(&name_pair),
+            request_stream_span,
+            request_type_span,
+            response_stream_span,
+            response_type_span,
+        })
+    }
+
+    // The span of the `stream` keyword if there is one, and the type along with its span.
+    fn parse_rpc_type(
+        rpc_type: Pair<Rule>,
+    ) -> Result<(Option<Span>, ProtoIdentifierPath, Option<Span>), ParseError> {
+        let mut rpc_type_parts = Parts::of(rpc_type);
+
+        let stream_span = rpc_type_parts
+            .next_if(Rule::rpc_stream)
+            .as_ref()
+            .and_then(span_of);
+        let path = rpc_type_parts.next()?;
+
+        Ok((stream_span, path.as_str().into(), span_of(&path)))
+    }
+
+    fn parse_field_type(type_pair: Pair<Rule>) -> Result<ProtoFieldType, ParseError> {
         match type_pair.as_rule() {
             Rule::primitive => match type_pair.as_str() {
+                "double" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Double)),
+                "float" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Float)),
                 "int32" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Int32)),
                 "int64" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Int64)),
+                "uint32" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::UInt32)),
+                "uint64" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::UInt64)),
+                "sint32" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::SInt32)),
+                "sint64" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::SInt64)),
+                "fixed32" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Fixed32)),
+                "fixed64" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Fixed64)),
+                "sfixed32" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::SFixed32)),
+                "sfixed64" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::SFixed64)),
+                "bool" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Boolean)),
                 "string" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Str)),
-                "boolean" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Boolean)),
+                "bytes" => Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Bytes)),
                 _ => {
-                    let next = type_pair.into_inner().next();
-                    match next {
-                        Some(next) => match next.as_rule() {
-                            Rule::map => {
-                                let mut map_parts = next.into_inner();
-                                let key = map_parts.next().unwrap();
-                                let value = map_parts.next().unwrap();
-
-                                Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Map(
-                                    Box::new(Self::parse_field_type(key)?),
-                                    Box::new(Self::parse_field_type(value)?),
-                                )))
-                            }
-                            err => Err(format!("Unknown primitive type found while parsing field type: {:?} (expected map<T,U>)", err)) 
-                        },
-                        None => Err("Unexpected end of input while parsing primitve field value type".to_string())
+                    let next = Parts::of(type_pair).next()?;
+                    match next.as_rule() {
+                        Rule::map => {
+                            let mut map_parts = Parts::of(next);
+                            let key = map_parts.next()?;
+                            let value = map_parts.next()?;
+
+                            Ok(ProtoFieldType::Primitive(ProtoPrimitiveType::Map(
+                                Box::new(Self::parse_field_type(key)?),
+                                Box::new(Self::parse_field_type(value)?),
+                            )))
+                        }
+                        _ => Err(unexpected_rule(
+                            &next,
+                            "primitive field type (expected map<T,U>)",
+                        )),
                     }
                 }
             },
-            Rule::path => Ok(ProtoFieldType::IdentifierPath(type_pair.as_str().into())),
-            err => Err(format!(
-                "Unknown type found while parsing field type: {:?}",
-                err
-            )),
+            Rule::type_path => Ok(ProtoFieldType::IdentifierPath(type_pair.as_str().into())),
+            _ => Err(unexpected_rule(&type_pair, "field type")),
         }
     }
 
-    fn parse_option(option: Pair<Rule>) -> Result<ProtoOption, String> {
-        let option_body_pair = option.into_inner().next().unwrap();
-        Self::parse_option_body(option_body_pair)
+    fn parse_option(option: Pair<Rule>) -> Result<ProtoOption, ParseError> {
+        let span = span_of(&option);
+        let option_body_pair = Parts::of(option).next()?;
+        Self::parse_option_body(option_body_pair, span)
     }
 
-    fn parse_field_options(next_pairs: &mut Pairs<Rule>) -> Result<Vec<ProtoOption>, String> {
+    // The options in brackets after a field or enum value, along with the span of the brackets.
+    fn parse_field_options(
+        next_pairs: Pairs<Rule>,
+    ) -> Result<(Vec<ProtoOption>, Option<Span>), ParseError> {
         let mut options = vec![];
+        let mut options_span = None;
         for next in next_pairs {
             match next.as_rule() {
-                Rule::field_option => options.push(Self::parse_field_option(next)?),
-                err => {
-                    return Err(format!(
-                        "Unknown token encountered while parsing field options: {:?}",
-                        err
-                    ));
+                Rule::field_options => {
+                    options_span = span_of(&next);
+                    for option_body in next.into_inner() {
+                        let span = span_of(&option_body);
+                        options.push(Self::parse_option_body(option_body, span)?);
+                    }
                 }
+                _ => return Err(unexpected_rule(&next, "field options")),
             }
         }
 
-        Ok(options)
-    }
-
-    fn parse_field_option(option: Pair<Rule>) -> Result<ProtoOption, String> {
-        let option_body_pair = option.into_inner().next().unwrap();
-        Self::parse_option_body(option_body_pair)
+        Ok((options, options_span))
     }
 
-    fn parse_option_body(option_body_pair: Pair<Rule>) -> Result<ProtoOption, String> {
-        let mut option_body_inner = option_body_pair.into_inner();
-        let mut option_identifier_pairs = option_body_inner.next().unwrap().into_inner();
+    fn parse_option_body(
+        option_body_pair: Pair<Rule>,
+        span: Option<Span>,
+    ) -> Result<ProtoOption, ParseError> {
+        let mut option_body_inner = Parts::of(option_body_pair);
+        let mut option_identifier_pairs = Parts::of(option_body_inner.next()?);
 
-        let name = option_identifier_pairs.next().unwrap().as_str().to_string();
-        let field_path = match option_identifier_pairs.peek() {
-            Some(_) => Some(
-                option_identifier_pairs
-                    .map(|pair| pair.as_str())
-                    .collect::<Vec<&str>>()
-                    .join("."),
-            ),
-            None => None,
+        let name_pair = option_identifier_pairs.next()?;
+        let extension = name_pair.as_rule() == Rule::type_path;
+        let name = name_pair.as_str().to_string();
+        let field_path = option_identifier_pairs
+            .rest()
+            .map(|pair| pair.as_str())
+            .collect::<Vec<&str>>();
+        let field_path = match field_path.len() {
+            0 => None,
+            _ => Some(field_path.join(".")),
         };
 
-        let value = Self::parse_constant(option_body_inner.next().unwrap())?;
+        let value_pair = option_body_inner.next()?;
+        let value_span = span_of(&value_pair);
+        let value = Self::parse_constant(value_pair)?;
 
         Ok(ProtoOption {
             name,
             field_path,
+            extension,
             value,
+            span,
+            value_span,
+            comments: ProtoComments::default(),
         })
     }
 
-    fn parse_constant(constant_pair: Pair<Rule>) -> Result<ProtoConstant, String> {
+    fn parse_constant(constant_pair: Pair<Rule>) -> Result<ProtoConstant, ParseError> {
         match constant_pair.as_rule() {
-            Rule::numeric => match constant_pair.as_str().parse() {
-                Ok(numeric) => Ok(ProtoConstant::Numeric(numeric)),
-                Err(err) => Err(format!("{}", err)),
-            },
-            Rule::string => Ok(ProtoConstant::Str(
-                constant_pair
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .as_str()
-                    .to_string(),
-            )),
+            Rule::numeric => Self::parse_numeric(constant_pair),
+            Rule::string => Ok(ProtoConstant::from_bytes(Self::parse_string_bytes(
+                constant_pair,
+            )?)),
             Rule::boolean => match constant_pair.as_str() {
                 "true" => Ok(ProtoConstant::Boolean(true)),
                 "false" => Ok(ProtoConstant::Boolean(false)),
-                _ => Err(format!(
-                    "Invalid boolean value '{}'",
-                    constant_pair.as_str().to_string()
+                _ => Err(ParseError::invalid_value(
+                    constant_pair.as_span(),
+                    constant_pair.as_rule(),
+                    format!("Invalid boolean value '{}'", constant_pair.as_str()),
                 )),
             },
-            err => Err(format!(
-                "Unknown value type encountered while parsing constant: '{:?}'",
-                err
+            Rule::path => Ok(ProtoConstant::Identifier(
+                constant_pair.as_str().to_string(),
             )),
+            Rule::aggregate => Ok(ProtoConstant::Aggregate(Self::parse_aggregate(
+                constant_pair,
+            )?)),
+            _ => Err(unexpected_rule(&constant_pair, "constant")),
+        }
+    }
+
+    // Parses the fields of an aggregate option value without their enclosing braces, which is how descriptors store
+    // them, e.g. "foo: 1 bar { baz: true }".
+    pub fn parse_aggregate_text(text: &str) -> Result<ProtoConstant, ParseError> {
+        let wrapped = format!("{{{}}}", text);
+        let aggregate = PestProtoParser::parse(Rule::aggregate, &wrapped)
+            .map_err(ParseError::from)?
+            .next()
+            .ok_or_else(|| ParseError::validation(None, "Expected an aggregate".to_string()))?;
+
+        Ok(ProtoConstant::Aggregate(Self::parse_aggregate(aggregate)?))
+    }
+
+    fn parse_aggregate(aggregate: Pair<Rule>) -> Result<Vec<ProtoAggregateField>, ParseError> {
+        aggregate
+            .into_inner()
+            .map(Self::parse_aggregate_field)
+            .collect()
+    }
+
+    fn parse_aggregate_field(field: Pair<Rule>) -> Result<ProtoAggregateField, ParseError> {
+        let mut field_parts = Parts::of(field);
+
+        let name = field_parts.next()?.as_str().to_string();
+        let value_pair = field_parts.next()?;
+
+        let value = match value_pair.as_rule() {
+            Rule::aggregate_list => ProtoAggregateValue::List(
+                value_pair
+                    .into_inner()
+                    .map(Self::parse_constant)
+                    .collect::<Result<Vec<ProtoConstant>, ParseError>>()?,
+            ),
+            _ => ProtoAggregateValue::Single(Self::parse_constant(value_pair)?),
+        };
+
+        Ok(ProtoAggregateField { name, value })
+    }
+
+    fn parse_numeric(numeric: Pair<Rule>) -> Result<ProtoConstant, ParseError> {
+        let (span, rule) = (numeric.as_span(), numeric.as_rule());
+        let (sign, literal) = Self::parse_numeric_parts(numeric)?;
+
+        let invalid = |message: String| {
+            ParseError::invalid_value(
+                span.clone(),
+                rule,
+                format!("Invalid numeric constant '{}': {}", span.as_str(), message),
+            )
+        };
+
+        match literal.as_rule() {
+            Rule::float_lit => match literal.as_str().parse::<f64>() {
+                Ok(float) if sign == "-" => Ok(ProtoConstant::Float(-float)),
+                Ok(float) => Ok(ProtoConstant::Float(float)),
+                Err(err) => Err(invalid(err.to_string())),
+            },
+            Rule::int_lit => {
+                let (digits, radix) = literals::int_lit_radix(literal.as_str());
+
+                match sign {
+                    "-" => i64::from_str_radix(&format!("-{}", digits), radix)
+                        .map(ProtoConstant::Int)
+                        .map_err(|err| invalid(err.to_string())),
+                    _ => u64::from_str_radix(digits, radix)
+                        .map(ProtoConstant::UInt)
+                        .map_err(|err| invalid(err.to_string())),
+                }
+            }
+            _ => Err(unexpected_rule(&literal, "numeric")),
+        }
+    }
+
+    // A string literal where only text makes sense, e.g. an import path, which has to be valid UTF-8.
+    fn parse_string(string_pair: Pair<Rule>) -> Result<String, ParseError> {
+        let (span, rule) = (string_pair.as_span(), string_pair.as_rule());
+
+        String::from_utf8(Self::parse_string_bytes(string_pair)?).map_err(|_| {
+            ParseError::invalid_value(
+                span,
+                rule,
+                "String literal does not decode to valid UTF-8".to_string(),
+            )
+        })
+    }
+
+    // Adjacent literals are concatenated, e.g. "a" "b" is "ab".
+    fn parse_string_bytes(string_pair: Pair<Rule>) -> Result<Vec<u8>, ParseError> {
+        let mut result = vec![];
+
+        for literal in string_pair.into_inner() {
+            let contents = Parts::of(literal).next()?;
+            let unescaped = literals::unescape(contents.as_str()).map_err(|message| {
+                ParseError::invalid_value(contents.as_span(), contents.as_rule(), message)
+            })?;
+
+            result.extend(unescaped);
         }
+
+        Ok(result)
     }
 
-    fn parse_syntax(statement: Pair<Rule>) -> Result<ProtoSyntax, String> {
-        match statement.into_inner().next().unwrap().as_str() {
+    fn parse_syntax(statement: Pair<Rule>) -> Result<ProtoSyntax, ParseError> {
+        let syntax = Parts::of(statement).next()?;
+        let (span, rule) = (syntax.as_span(), syntax.as_rule());
+
+        match Self::parse_string(syntax)?.as_str() {
             "proto2" => Ok(ProtoSyntax::Proto2),
             "proto3" => Ok(ProtoSyntax::Proto3),
-            syntax => Err(format!("Unknown proto syntax '{}'", syntax)),
+            err => Err(ParseError::invalid_value(
+                span,
+                rule,
+                format!("Unknown proto syntax '{}'", err),
+            )),
         }
     }
 
-    fn parse_package(statement: Pair<Rule>) -> Result<&str, String> {
-        Ok(statement.into_inner().next().unwrap().as_str())
+    fn parse_package(statement: Pair<'_, Rule>) -> Result<String, ParseError> {
+        Ok(Parts::of(statement).next()?.as_str().to_string())
     }
 
-    fn parse_import(statement: Pair<Rule>) -> Result<ProtoImport, String> {
-        let mut import_parts = statement.into_inner();
+    fn parse_import(statement: Pair<Rule>) -> Result<ProtoImport, ParseError> {
+        let span = span_of(&statement);
+        let mut import_parts = Parts::of(statement);
 
-        let modifier = match import_parts.peek().unwrap().as_rule() {
-            Rule::import_modifier => match import_parts.next().unwrap().as_str() {
+        let modifier_pair = import_parts.next_if(Rule::import_modifier);
+        let modifier_span = modifier_pair.as_ref().and_then(span_of);
+        let modifier = match modifier_pair {
+            Some(modifier) => match modifier.as_str() {
                 "public" => Some(ProtoImportModifier::Public),
-                err => return Err(format!("Unknown import modifier '{}'", err)),
+                "weak" => Some(ProtoImportModifier::Weak),
+                err => {
+                    return Err(ParseError::invalid_value(
+                        modifier.as_span(),
+                        modifier.as_rule(),
+                        format!("Unknown import modifier '{}'", err),
+                    ));
+                }
             },
-            _ => None,
+            None => None,
         };
 
-        let path = import_parts.next().unwrap().as_str().to_string();
+        let path = Self::parse_string(import_parts.next()?)?;
 
-        Ok(ProtoImport { modifier, path })
+        Ok(ProtoImport {
+            modifier,
+            path,
+            comments: ProtoComments::default(),
+            span,
+            modifier_span,
+        })
     }
 }
 
 impl Parser for ParserImpl {
-    fn parse<'a>(&self, input: &'a str) -> Result<Program<'a>, String> {
-        match Self::parse_pest(input) {
-            Err(err) => Err(format!("{}", err)),
-            Ok(parse_root) => Self::do_parse(input, parse_root),
-        }
+    fn parse(&self, input: &str) -> Result<Program, ParseError> {
+        let parse_root = Self::parse_pest(input)?;
+        Self::do_parse(input, parse_root)
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     macro_rules! parse_test {
         ($test_path: expr) => {{
             let parser = ParserImpl::default();
-            parser
+            let mut program = parser
                 .parse(include_str!($test_path))
-                .expect(&format!("failed to parse {}", $test_path))
+                .expect(&format!("failed to parse {}", $test_path));
+
+            clear_program_spans(&mut program);
+            program
         }};
     }
 
+    // Spans are checked separately in test_spans so the structural tests don't have to spell out offsets.
+    pub(crate) fn clear_program_spans(program: &mut Program) {
+        program.syntax_span = None;
+        program.package_span = None;
+        for import in &mut program.imports {
+            import.span = None;
+            import.modifier_span = None;
+        }
+        clear_option_spans(&mut program.options);
+        clear_type_spans(&mut program.types);
+        clear_extend_spans(&mut program.extends);
+
+        for service in &mut program.services {
+            service.span = None;
+            service.name_span = None;
+            clear_option_spans(&mut service.options);
+
+            for rpc in &mut service.rpcs {
+                rpc.span = None;
+                rpc.name_span = None;
+                rpc.request_stream_span = None;
+                rpc.request_type_span = None;
+                rpc.response_stream_span = None;
+                rpc.response_type_span = None;
+                clear_option_spans(&mut rpc.options);
+            }
+        }
+    }
+
+    fn clear_type_spans(types: &mut [ProtoType]) {
+        for proto_type in types {
+            match proto_type {
+                ProtoType::Message(message) => {
+                    message.span = None;
+                    message.name_span = None;
+                    message.reserved_statements.clear();
+                    message.extensions_statements.clear();
+                    clear_option_spans(&mut message.options);
+                    clear_reserved_spans(&mut message.reserved);
+                    clear_range_spans(&mut message.extension_ranges);
+                    clear_field_spans(&mut message.fields);
+                    clear_type_spans(&mut message.types);
+                    clear_extend_spans(&mut message.extends);
+
+                    for oneof in &mut message.oneofs {
+                        oneof.span = None;
+                        oneof.name_span = None;
+                        clear_option_spans(&mut oneof.options);
+                        clear_field_spans(&mut oneof.fields);
+                    }
+                }
+                ProtoType::Enum(enumeration) => {
+                    enumeration.span = None;
+                    enumeration.name_span = None;
+                    enumeration.reserved_statements.clear();
+                    clear_option_spans(&mut enumeration.options);
+                    clear_reserved_spans(&mut enumeration.reserved);
+
+                    for value in &mut enumeration.values {
+                        value.span = None;
+                        value.name_span = None;
+                        value.position_span = None;
+                        value.options_span = None;
+                        clear_option_spans(&mut value.options);
+                    }
+                }
+            }
+        }
+    }
+
+    fn clear_extend_spans(extends: &mut [ProtoExtend]) {
+        for extend in extends {
+            extend.span = None;
+            extend.extendee_span = None;
+            clear_field_spans(&mut extend.fields);
+        }
+    }
+
+    fn clear_field_spans(fields: &mut [ProtoMessageField]) {
+        for field in fields {
+            field.span = None;
+            field.modifier_span = None;
+            field.type_span = None;
+            field.name_span = None;
+            field.position_span = None;
+            field.options_span = None;
+            clear_option_spans(&mut field.options);
+        }
+    }
+
+    fn clear_option_spans(options: &mut [ProtoOption]) {
+        for option in options {
+            option.span = None;
+            option.value_span = None;
+        }
+    }
+
+    fn clear_reserved_spans(reserved: &mut [ProtoReserved]) {
+        for reservation in reserved {
+            match reservation {
+                ProtoReserved::Range(range) => clear_range_spans(std::slice::from_mut(range)),
+                ProtoReserved::Name(_, span) => *span = None,
+            }
+        }
+    }
+
+    fn clear_range_spans(ranges: &mut [ProtoRange]) {
+        for range in ranges {
+            range.span = None;
+            range.start_span = None;
+            range.end_span = None;
+        }
+    }
+
     use super::*;
 
     #[test]
@@ -316,42 +982,81 @@ mod tests {
         assert_eq!(
             program,
             Program {
-                src: program.src,
+                syntax_comments: ProtoComments::default(),
+                package_comments: ProtoComments::default(),
+                src: program.src.clone(),
                 syntax: Some(ProtoSyntax::Proto3),
                 imports: vec![ProtoImport {
+                    comments: ProtoComments::default(),
+                    modifier_span: None,
+                    span: None,
                     path: "other.proto".to_string(),
                     modifier: Some(ProtoImportModifier::Public)
                 }],
                 package: None,
+                syntax_span: None,
+                package_span: None,
                 options: vec![ProtoOption {
+                    comments: ProtoComments::default(),
+                    value_span: None,
+                    span: None,
                     name: "java_package".to_string(),
                     field_path: None,
+                    extension: false,
                     value: ProtoConstant::Str("com.example.foo".to_string())
                 }],
                 types: vec![
                     ProtoType::Enum(ProtoEnum {
-                        name: "EnumAllowingAlias",
+                        reserved_statements: vec![],
+                        name_span: None,
+                        span: None,
+                        comments: ProtoComments::default(),
+                        name: "EnumAllowingAlias".to_string(),
+                        reserved: vec![],
                         options: vec![ProtoOption {
+                            comments: ProtoComments::default(),
+                            value_span: None,
+                            span: None,
                             name: "allow_alias".to_string(),
                             field_path: None,
+                            extension: false,
                             value: ProtoConstant::Boolean(true)
                         }],
                         values: vec![
                             ProtoEnumValue {
+                                name_span: None,
+                                position_span: None,
+                                options_span: None,
+                                span: None,
+                                comments: ProtoComments::default(),
                                 name: "UNKNOWN".to_string(),
                                 options: vec![],
                                 position: 0
                             },
                             ProtoEnumValue {
+                                name_span: None,
+                                position_span: None,
+                                options_span: None,
+                                span: None,
+                                comments: ProtoComments::default(),
                                 name: "STARTED".to_string(),
                                 options: vec![],
                                 position: 1
                             },
                             ProtoEnumValue {
+                                name_span: None,
+                                position_span: None,
+                                options_span: None,
+                                span: None,
+                                comments: ProtoComments::default(),
                                 name: "RUNNING".to_string(),
                                 options: vec![ProtoOption {
+                                    comments: ProtoComments::default(),
+                                    value_span: None,
+                                    span: None,
                                     name: "custom_option".to_string(),
                                     field_path: None,
+                                    extension: true,
                                     value: ProtoConstant::Str("hello world".to_string())
                                 }],
                                 position: 2
@@ -359,34 +1064,82 @@ mod tests {
                         ]
                     }),
                     ProtoType::Message(ProtoMessage {
-                        name: "outer",
+                        reserved_statements: vec![],
+                        extensions_statements: vec![],
+                        name_span: None,
+                        span: None,
+                        comments: ProtoComments::default(),
+                        name: "outer".to_string(),
+                        reserved: vec![],
+                        extension_ranges: vec![],
+                        extends: vec![],
                         options: vec![ProtoOption {
+                            comments: ProtoComments::default(),
+                            value_span: None,
+                            span: None,
                             name: "my_option".to_string(),
                             field_path: Some("a".to_string()),
+                            extension: true,
                             value: ProtoConstant::Boolean(true)
                         }],
                         types: vec![ProtoType::Message(ProtoMessage {
-                            name: "inner",
+                            reserved_statements: vec![],
+                            extensions_statements: vec![],
+                            name_span: None,
+                            span: None,
+                            comments: ProtoComments {
+                                trailing: Some(" Level 2\n".to_string()),
+                                ..ProtoComments::default()
+                            },
+                            name: "inner".to_string(),
+                            reserved: vec![],
+                            extension_ranges: vec![],
+                            extends: vec![],
                             options: vec![],
                             types: vec![],
                             fields: vec![ProtoMessageField {
-                                name: "ival",
+                                modifier_span: None,
+                                type_span: None,
+                                name_span: None,
+                                position_span: None,
+                                options_span: None,
+                                span: None,
+                                comments: ProtoComments::default(),
+                                explicit_presence: false,
+                                name: "ival".to_string(),
                                 modifier: None,
                                 field_type: ProtoFieldType::Primitive(ProtoPrimitiveType::Int64),
                                 options: vec![],
                                 position: 1
-                            }]
+                            }],
+                            oneofs: vec![]
                         })],
                         fields: vec![
                             ProtoMessageField {
-                                name: "inner_message",
+                                modifier_span: None,
+                                type_span: None,
+                                name_span: None,
+                                position_span: None,
+                                options_span: None,
+                                span: None,
+                                comments: ProtoComments::default(),
+                                explicit_presence: false,
+                                name: "inner_message".to_string(),
                                 field_type: ProtoFieldType::IdentifierPath("inner".into()),
                                 modifier: Some(ProtoMessageFieldModifier::Repeated),
                                 options: vec![],
                                 position: 2
                             },
                             ProtoMessageField {
-                                name: "enum_field",
+                                modifier_span: None,
+                                type_span: None,
+                                name_span: None,
+                                position_span: None,
+                                options_span: None,
+                                span: None,
+                                comments: ProtoComments::default(),
+                                explicit_presence: false,
+                                name: "enum_field".to_string(),
                                 field_type: ProtoFieldType::IdentifierPath(
                                     "EnumAllowingAlias".into()
                                 ),
@@ -395,7 +1148,15 @@ mod tests {
                                 position: 3
                             },
                             ProtoMessageField {
-                                name: "my_map",
+                                modifier_span: None,
+                                type_span: None,
+                                name_span: None,
+                                position_span: None,
+                                options_span: None,
+                                span: None,
+                                comments: ProtoComments::default(),
+                                explicit_presence: false,
+                                name: "my_map".to_string(),
                                 field_type: ProtoFieldType::Primitive(ProtoPrimitiveType::Map(
                                     Box::new(ProtoFieldType::Primitive(ProtoPrimitiveType::Int32)),
                                     Box::new(ProtoFieldType::Primitive(ProtoPrimitiveType::Str))
@@ -404,9 +1165,12 @@ mod tests {
                                 options: vec![],
                                 position: 4
                             },
-                        ]
+                        ],
+                        oneofs: vec![]
                     })
                 ],
+                extends: vec![],
+                services: vec![],
             }
         )
     }
@@ -418,16 +1182,26 @@ mod tests {
         assert_eq!(
             program,
             Program {
-                src: program.src,
+                syntax_comments: ProtoComments::default(),
+                package_comments: ProtoComments::default(),
+                src: program.src.clone(),
                 syntax: Some(ProtoSyntax::Proto3),
-                package: Some("foo.bar.baz"),
+                package: Some("foo.bar.baz".to_string()),
+                syntax_span: None,
+                package_span: None,
                 imports: vec![],
                 options: vec![ProtoOption {
+                    comments: ProtoComments::default(),
+                    value_span: None,
+                    span: None,
                     name: "java_package".to_string(),
                     field_path: None,
+                    extension: false,
                     value: ProtoConstant::Str("com.rsproto.toplevelconcepts".to_string())
                 }],
                 types: vec![],
+                extends: vec![],
+                services: vec![],
             }
         )
     }
@@ -440,39 +1214,78 @@ mod tests {
         assert_eq!(
             program,
             Program {
-                src: program.src,
+                syntax_comments: ProtoComments::default(),
+                package_comments: ProtoComments::default(),
+                src: program.src.clone(),
                 syntax: None,
                 package: None,
+                syntax_span: None,
+                package_span: None,
                 imports: vec![],
                 options: vec![],
                 types: vec![ProtoType::Message(ProtoMessage {
-                    name: "Person",
+                    reserved_statements: vec![],
+                    extensions_statements: vec![],
+                    name_span: None,
+                    span: None,
+                    comments: ProtoComments::default(),
+                    name: "Person".to_string(),
+                    reserved: vec![],
+                    extension_ranges: vec![],
+                    extends: vec![],
                     options: vec![],
                     types: vec![],
                     fields: vec![
                         ProtoMessageField {
+                            modifier_span: None,
+                            type_span: None,
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
+                            explicit_presence: true,
                             field_type: ProtoFieldType::Primitive(ProtoPrimitiveType::Str),
-                            name: "first_name",
-                            modifier: None,
+                            name: "first_name".to_string(),
+                            modifier: Some(ProtoMessageFieldModifier::Optional),
                             options: vec![],
                             position: 1
                         },
                         ProtoMessageField {
+                            modifier_span: None,
+                            type_span: None,
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
+                            explicit_presence: true,
                             field_type: ProtoFieldType::Primitive(ProtoPrimitiveType::Str),
-                            name: "last_name",
-                            modifier: None,
+                            name: "last_name".to_string(),
+                            modifier: Some(ProtoMessageFieldModifier::Optional),
                             options: vec![],
                             position: 2
                         },
                         ProtoMessageField {
+                            modifier_span: None,
+                            type_span: None,
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
+                            explicit_presence: true,
                             field_type: ProtoFieldType::Primitive(ProtoPrimitiveType::Int64),
-                            name: "date_of_birth_unix_epoch",
-                            modifier: None,
+                            name: "date_of_birth_unix_epoch".to_string(),
+                            modifier: Some(ProtoMessageFieldModifier::Optional),
                             options: vec![],
                             position: 3
                         }
-                    ]
-                })]
+                    ],
+                    oneofs: vec![]
+                })],
+                extends: vec![],
+                services: vec![],
             }
         );
     }
@@ -485,48 +1298,1139 @@ mod tests {
         assert_eq!(
             program,
             Program {
-                src: program.src,
+                syntax_comments: ProtoComments::default(),
+                package_comments: ProtoComments::default(),
+                src: program.src.clone(),
                 syntax: None,
                 package: None,
+                syntax_span: None,
+                package_span: None,
                 imports: vec![],
                 options: vec![],
                 types: vec![ProtoType::Enum(ProtoEnum {
-                    name: "RelationshipType",
+                    reserved_statements: vec![],
+                    name_span: None,
+                    span: None,
+                    comments: ProtoComments::default(),
+                    name: "RelationshipType".to_string(),
+                    reserved: vec![],
                     options: vec![],
                     values: vec![
                         ProtoEnumValue {
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
                             name: "UNKNOWN_VALUE".to_string(),
                             options: vec![],
                             position: 0
                         },
                         ProtoEnumValue {
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
                             name: "PARENT".to_string(),
                             options: vec![],
                             position: 1
                         },
                         ProtoEnumValue {
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
                             name: "SIBLING".to_string(),
                             options: vec![],
                             position: 2
                         },
                         ProtoEnumValue {
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
                             name: "CHILD".to_string(),
                             options: vec![],
                             position: 3
                         },
                         ProtoEnumValue {
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
                             name: "ANCESTOR".to_string(),
                             options: vec![],
                             position: 4
                         },
                         ProtoEnumValue {
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
                             name: "DESCENDANT".to_string(),
                             options: vec![],
                             position: 5
                         },
                     ]
-                })]
+                })],
+                extends: vec![],
+                services: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_service() {
+        let program = parse_test!("../../test_data/service.proto");
+
+        assert_eq!(program.types.len(), 2);
+        assert_eq!(
+            program.services,
+            vec![ProtoService {
+                name_span: None,
+                span: None,
+                comments: ProtoComments::default(),
+                name: "RouteGuide".to_string(),
+                options: vec![ProtoOption {
+                    comments: ProtoComments::default(),
+                    value_span: None,
+                    span: None,
+                    name: "deprecated".to_string(),
+                    field_path: None,
+                    extension: false,
+                    value: ProtoConstant::Boolean(true)
+                }],
+                rpcs: vec![
+                    ProtoRpc {
+                        name_span: None,
+                        request_stream_span: None,
+                        request_type_span: None,
+                        response_stream_span: None,
+                        response_type_span: None,
+                        span: None,
+                        comments: ProtoComments::default(),
+                        name: "Unary".to_string(),
+                        request_type: "Request".into(),
+                        request_stream: false,
+                        response_type: "Response".into(),
+                        response_stream: false,
+                        options: vec![]
+                    },
+                    ProtoRpc {
+                        name_span: None,
+                        request_stream_span: None,
+                        request_type_span: None,
+                        response_stream_span: None,
+                        response_type_span: None,
+                        span: None,
+                        comments: ProtoComments::default(),
+                        name: "ServerStreaming".to_string(),
+                        request_type: "Request".into(),
+                        request_stream: false,
+                        response_type: "Response".into(),
+                        response_stream: true,
+                        options: vec![]
+                    },
+                    ProtoRpc {
+                        name_span: None,
+                        request_stream_span: None,
+                        request_type_span: None,
+                        response_stream_span: None,
+                        response_type_span: None,
+                        span: None,
+                        comments: ProtoComments::default(),
+                        name: "ClientStreaming".to_string(),
+                        request_type: "Request".into(),
+                        request_stream: true,
+                        response_type: "Response".into(),
+                        response_stream: false,
+                        options: vec![]
+                    },
+                    ProtoRpc {
+                        name_span: None,
+                        request_stream_span: None,
+                        request_type_span: None,
+                        response_stream_span: None,
+                        response_type_span: None,
+                        span: None,
+                        comments: ProtoComments::default(),
+                        name: "BidiStreaming".to_string(),
+                        request_type: "Request".into(),
+                        request_stream: true,
+                        response_type: "Response".into(),
+                        response_stream: true,
+                        options: vec![ProtoOption {
+                            comments: ProtoComments::default(),
+                            value_span: None,
+                            span: None,
+                            name: "idempotency_level".to_string(),
+                            field_path: None,
+                            extension: false,
+                            value: ProtoConstant::Str("IDEMPOTENT".to_string())
+                        }]
+                    },
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_oneof() {
+        let program = parse_test!("../../test_data/oneof.proto");
+
+        assert_eq!(
+            program.types,
+            vec![ProtoType::Message(ProtoMessage {
+                reserved_statements: vec![],
+                extensions_statements: vec![],
+                name_span: None,
+                span: None,
+                comments: ProtoComments::default(),
+                name: "Sample".to_string(),
+                reserved: vec![],
+                extension_ranges: vec![],
+                extends: vec![],
+                options: vec![],
+                types: vec![],
+                fields: vec![ProtoMessageField {
+                    modifier_span: None,
+                    type_span: None,
+                    name_span: None,
+                    position_span: None,
+                    options_span: None,
+                    span: None,
+                    comments: ProtoComments::default(),
+                    explicit_presence: true,
+                    field_type: ProtoFieldType::Primitive(ProtoPrimitiveType::Int32),
+                    name: "id".to_string(),
+                    modifier: Some(ProtoMessageFieldModifier::Optional),
+                    options: vec![],
+                    position: 1
+                }],
+                oneofs: vec![ProtoOneof {
+                    comments: ProtoComments::default(),
+                    name_span: None,
+                    span: None,
+                    name: "payload".to_string(),
+                    options: vec![],
+                    fields: vec![
+                        ProtoMessageField {
+                            modifier_span: None,
+                            type_span: None,
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
+                            explicit_presence: true,
+                            field_type: ProtoFieldType::Primitive(ProtoPrimitiveType::Str),
+                            name: "text".to_string(),
+                            modifier: None,
+                            options: vec![],
+                            position: 2
+                        },
+                        ProtoMessageField {
+                            modifier_span: None,
+                            type_span: None,
+                            name_span: None,
+                            position_span: None,
+                            options_span: None,
+                            span: None,
+                            comments: ProtoComments::default(),
+                            explicit_presence: true,
+                            field_type: ProtoFieldType::Primitive(ProtoPrimitiveType::Int64),
+                            name: "big_number".to_string(),
+                            modifier: None,
+                            options: vec![],
+                            position: 3
+                        }
+                    ]
+                }]
+            })]
+        );
+    }
+
+    #[test]
+    fn test_scalars() {
+        let program = parse_test!("../../test_data/scalars.proto");
+
+        let message = match &program.types[0] {
+            ProtoType::Message(message) => message,
+            proto_type => panic!("expected message, got {:?}", proto_type),
+        };
+
+        assert_eq!(
+            message
+                .fields
+                .iter()
+                .map(|field| field.field_type.clone())
+                .collect::<Vec<ProtoFieldType>>(),
+            vec![
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Double),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Float),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Int32),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Int64),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::UInt32),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::UInt64),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::SInt32),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::SInt64),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Fixed32),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Fixed64),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::SFixed32),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::SFixed64),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Boolean),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Str),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Bytes),
+                ProtoFieldType::Primitive(ProtoPrimitiveType::Map(
+                    Box::new(ProtoFieldType::Primitive(ProtoPrimitiveType::Str)),
+                    Box::new(ProtoFieldType::Primitive(ProtoPrimitiveType::Bytes))
+                )),
+                ProtoFieldType::IdentifierPath("stringy".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reserved() {
+        let program = parse_test!("../../test_data/reserved.proto");
+
+        let (message, enumeration) = match &program.types[..] {
+            [ProtoType::Message(message), ProtoType::Enum(enumeration)] => (message, enumeration),
+            types => panic!("expected message and enum, got {:?}", types),
+        };
+
+        assert_eq!(
+            message.reserved,
+            vec![
+                ProtoReserved::Range(ProtoRange {
+                    span: None,
+                    start_span: None,
+                    end_span: None,
+                    start: 2,
+                    end: ProtoRangeEnd::Inclusive(2)
+                }),
+                ProtoReserved::Range(ProtoRange {
+                    span: None,
+                    start_span: None,
+                    end_span: None,
+                    start: 15,
+                    end: ProtoRangeEnd::Inclusive(15)
+                }),
+                ProtoReserved::Range(ProtoRange {
+                    span: None,
+                    start_span: None,
+                    end_span: None,
+                    start: 9,
+                    end: ProtoRangeEnd::Inclusive(11)
+                }),
+                ProtoReserved::Range(ProtoRange {
+                    span: None,
+                    start_span: None,
+                    end_span: None,
+                    start: 40,
+                    end: ProtoRangeEnd::Max
+                }),
+                ProtoReserved::Name("foo".to_string(), None),
+                ProtoReserved::Name("bar".to_string(), None),
+            ]
+        );
+
+        assert_eq!(
+            enumeration.reserved,
+            vec![
+                ProtoReserved::Range(ProtoRange {
+                    span: None,
+                    start_span: None,
+                    end_span: None,
+                    start: 1,
+                    end: ProtoRangeEnd::Inclusive(3)
+                }),
+                ProtoReserved::Name("BAZ".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reserved_violations() {
+        let parser = ParserImpl::default();
+
+        assert_eq!(
+            parser
+                .parse("message Foo {\n    reserved 9 to 11;\n    string name = 10;\n}")
+                .map_err(|err| err.to_string()),
+            Err(
+                "3:5: Field 'name' in message 'Foo' (= 10) violates reservation 'reserved 9 to 11;'"
+                    .to_string()
+            )
+        );
+
+        assert_eq!(
+            parser
+                .parse("message Foo {\n    string name = 1;\n    reserved \"name\";\n}")
+                .map_err(|err| err.to_string()),
+            Err(
+                "2:5: Field 'name' in message 'Foo' (= 1) violates reservation 'reserved \"name\";'"
+                    .to_string()
+            )
+        );
+
+        assert_eq!(
+            parser
+                .parse("enum Bar {\n    reserved 5 to max;\n    BAZ = 7;\n}")
+                .map_err(|err| err.to_string()),
+            Err(
+                "3:5: Enum value 'BAZ' in enum 'Bar' (= 7) violates reservation 'reserved 5 to max;'"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_extensions() {
+        let program = parse_test!("../../test_data/extensions.proto");
+
+        let (foo, baz) = match &program.types[..] {
+            [ProtoType::Message(foo), ProtoType::Message(baz)] => (foo, baz),
+            types => panic!("expected two messages, got {:?}", types),
+        };
+
+        assert_eq!(
+            foo.extension_ranges,
+            vec![
+                ProtoRange {
+                    span: None,
+                    start_span: None,
+                    end_span: None,
+                    start: 100,
+                    end: ProtoRangeEnd::Inclusive(199)
+                },
+                ProtoRange {
+                    span: None,
+                    start_span: None,
+                    end_span: None,
+                    start: 500,
+                    end: ProtoRangeEnd::Inclusive(500)
+                },
+                ProtoRange {
+                    span: None,
+                    start_span: None,
+                    end_span: None,
+                    start: 1000,
+                    end: ProtoRangeEnd::Max
+                },
+            ]
+        );
+
+        assert_eq!(
+            program.extends,
+            vec![ProtoExtend {
+                comments: ProtoComments::default(),
+                extendee_span: None,
+                span: None,
+                extendee: "Foo".into(),
+                fields: vec![ProtoMessageField {
+                    modifier_span: None,
+                    type_span: None,
+                    name_span: None,
+                    position_span: None,
+                    options_span: None,
+                    span: None,
+                    comments: ProtoComments::default(),
+                    explicit_presence: true,
+                    field_type: ProtoFieldType::Primitive(ProtoPrimitiveType::Int32),
+                    name: "bar".to_string(),
+                    modifier: Some(ProtoMessageFieldModifier::Optional),
+                    options: vec![],
+                    position: 126
+                }]
+            }]
+        );
+
+        assert_eq!(
+            baz.extends
+                .iter()
+                .map(|extend| extend.extendee.clone())
+                .collect::<Vec<ProtoIdentifierPath>>(),
+            vec![
+                ".foo.bar.Foo".into(),
+                "google.protobuf.MessageOptions".into()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let program = parse_test!("../../test_data/labels.proto");
+
+        let message = match &program.types[0] {
+            ProtoType::Message(message) => message,
+            proto_type => panic!("expected message, got {:?}", proto_type),
+        };
+
+        assert_eq!(
+            message
+                .fields
+                .iter()
+                .map(|field| (
+                    field.name.as_str(),
+                    field.modifier.clone(),
+                    field.explicit_presence
+                ))
+                .collect::<Vec<(&str, Option<ProtoMessageFieldModifier>, bool)>>(),
+            vec![
+                ("name", Some(ProtoMessageFieldModifier::Required), true),
+                ("age", Some(ProtoMessageFieldModifier::Optional), true),
+                ("emails", Some(ProtoMessageFieldModifier::Repeated), false),
+                ("attributes", None, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_proto3_optional() {
+        let program = parse_test!("../../test_data/proto3_optional.proto");
+
+        let message = match &program.types[0] {
+            ProtoType::Message(message) => message,
+            proto_type => panic!("expected message, got {:?}", proto_type),
+        };
+
+        assert_eq!(
+            message
+                .fields
+                .iter()
+                .map(|field| (field.name.as_str(), field.explicit_presence))
+                .collect::<Vec<(&str, bool)>>(),
+            vec![("name", false), ("age", true)]
+        );
+    }
+
+    #[test]
+    fn test_label_violations() {
+        let parser = ParserImpl::default();
+
+        assert_eq!(
+            parser.parse("syntax = \"proto3\";\nmessage Foo {\n    required string name = 1;\n}").map_err(|err| err.to_string()),
+            Err("3:5: Field 'name' in message 'Foo' is marked 'required', which is not allowed in proto3".to_string())
+        );
+
+        assert_eq!(
+            parser.parse("syntax = \"proto2\";\nmessage Foo {\n    string name = 1;\n}").map_err(|err| err.to_string()),
+            Err("3:5: Field 'name' in message 'Foo' has no label, but proto2 fields must be 'optional', 'required' or 'repeated'".to_string())
+        );
+
+        // Files without a syntax statement are proto2.
+        assert_eq!(
+            parser.parse("message Foo {\n    string name = 1;\n}").map_err(|err| err.to_string()),
+            Err("2:5: Field 'name' in message 'Foo' has no label, but proto2 fields must be 'optional', 'required' or 'repeated'".to_string())
+        );
+
+        assert_eq!(
+            parser.parse("syntax = \"proto2\";\nmessage Foo {\n    extensions 10 to 20;\n}\nextend Foo {\n    int32 bar = 10;\n}").map_err(|err| err.to_string()),
+            Err("6:5: Field 'bar' in extend 'Foo' has no label, but proto2 fields must be 'optional', 'required' or 'repeated'".to_string())
+        );
+
+        assert_eq!(
+            parser.parse(
+                "syntax = \"proto3\";\nmessage Foo {\n    repeated map<string, string> bar = 1;\n}"
+            ).map_err(|err| err.to_string()),
+            Err("3:5: Field 'bar' in message 'Foo' is a map field and cannot have a label".to_string())
+        );
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let parser = ParserImpl::default();
+
+        let err = parser
+            .parse("message Foo {\n    string name = ;\n}")
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            ParseError::Syntax {
+                span: Span {
+                    start: 32,
+                    end: 32,
+                    start_line: 2,
+                    start_column: 19,
+                    end_line: 2,
+                    end_column: 19
+                },
+                expected: vec![Rule::numeric],
+                message: "expected numeric".to_string()
+            }
+        );
+        assert_eq!(err.to_string(), "2:19: expected numeric");
+    }
+
+    #[test]
+    fn test_trailing_input_error() {
+        let parser = ParserImpl::default();
+
+        let err = parser.parse("message Foo {}\nbogus").unwrap_err();
+
+        assert_eq!(
+            err.span().map(|span| (span.start_line, span.start_column)),
+            Some((2, 1))
+        );
+    }
+
+    #[test]
+    fn test_invalid_value_error() {
+        let parser = ParserImpl::default();
+
+        let err = parser
+            .parse("message Foo {\n    string name = 99999999999;\n}")
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            ParseError::InvalidValue {
+                span: Span {
+                    start: 32,
+                    end: 43,
+                    start_line: 2,
+                    start_column: 19,
+                    end_line: 2,
+                    end_column: 30
+                },
+                rule: Rule::numeric,
+                message: "Invalid position '99999999999': number too large to fit in target type"
+                    .to_string()
             }
         );
     }
+
+    #[test]
+    fn test_spans() {
+        let parser = ParserImpl::default();
+        let program = parser
+            .parse(include_str!("../../test_data/reference_example.proto"))
+            .expect("failed to parse reference_example.proto");
+
+        assert_eq!(
+            program.syntax_span,
+            Some(Span {
+                start: 0,
+                end: 18,
+                start_line: 1,
+                start_column: 1,
+                end_line: 1,
+                end_column: 19
+            })
+        );
+
+        assert_eq!(
+            program.imports[0].span,
+            Some(Span {
+                start: 20,
+                end: 48,
+                start_line: 3,
+                start_column: 1,
+                end_line: 3,
+                end_column: 29
+            })
+        );
+
+        let (enumeration, message) = match &program.types[..] {
+            [ProtoType::Enum(enumeration), ProtoType::Message(message)] => (enumeration, message),
+            types => panic!("expected enum and message, got {:?}", types),
+        };
+
+        let running = &enumeration.values[2];
+        assert_eq!(
+            running.span,
+            Some(Span {
+                start: 178,
+                end: 224,
+                start_line: 11,
+                start_column: 3,
+                end_line: 11,
+                end_column: 49
+            })
+        );
+        assert_eq!(
+            running.options[0].span,
+            Some(Span {
+                start: 191,
+                end: 222,
+                start_line: 11,
+                start_column: 16,
+                end_line: 11,
+                end_column: 47
+            })
+        );
+
+        assert_eq!(
+            message.span,
+            Some(Span {
+                start: 228,
+                end: 437,
+                start_line: 14,
+                start_column: 1,
+                end_line: 23,
+                end_column: 2
+            })
+        );
+        assert_eq!(
+            message.fields[0].span,
+            Some(Span {
+                start: 333,
+                end: 366,
+                start_line: 20,
+                start_column: 3,
+                end_line: 20,
+                end_column: 36
+            })
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let program = parse_test!("../../test_data/comments.proto");
+
+        let (person, relationship) = match &program.types[..] {
+            [ProtoType::Message(person), ProtoType::Enum(relationship)] => (person, relationship),
+            types => panic!("expected message and enum, got {:?}", types),
+        };
+
+        assert_eq!(
+            person.comments,
+            ProtoComments {
+                leading: Some(" Someone we know.\n Spans two lines.\n".to_string()),
+                trailing: Some(" Trailing after the brace.\n".to_string()),
+                leading_detached: vec![" Detached from Person.\n".to_string()]
+            }
+        );
+
+        assert_eq!(
+            person
+                .fields
+                .iter()
+                .map(|field| field.comments.clone())
+                .collect::<Vec<ProtoComments>>(),
+            vec![
+                ProtoComments {
+                    leading: Some(" The name they go by. ".to_string()),
+                    trailing: Some(" Trailing after the field.\n".to_string()),
+                    leading_detached: vec![]
+                },
+                ProtoComments {
+                    leading: None,
+                    trailing: Some(" Trailing on the line after age.\n".to_string()),
+                    leading_detached: vec!["\n Detached from age.\n".to_string()]
+                },
+                ProtoComments {
+                    leading: None,
+                    trailing: Some(" Has \"//\" in it: \"http://example.com\"\n".to_string()),
+                    leading_detached: vec![]
+                },
+            ]
+        );
+
+        assert_eq!(
+            relationship.comments.leading,
+            Some(" Relationship kinds. ".to_string())
+        );
+        assert_eq!(
+            relationship.values[0].comments.leading,
+            Some(" The default.\n".to_string())
+        );
+        assert_eq!(
+            relationship.values[1].comments,
+            ProtoComments {
+                leading: None,
+                trailing: Some(" Trailing before the closing brace.\n".to_string()),
+                leading_detached: vec![]
+            }
+        );
+
+        assert_eq!(
+            program.services[0].comments.leading,
+            Some(" Finds people.\n".to_string())
+        );
+        assert_eq!(
+            program.services[0].rpcs[0].comments.leading,
+            Some(" Looks a person up.\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let program = parse_test!("../../test_data/strings.proto");
+
+        assert_eq!(program.syntax, Some(ProtoSyntax::Proto2));
+        assert_eq!(
+            program
+                .imports
+                .iter()
+                .map(|import| import.path.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["google/protobuf/descriptor.proto"]
+        );
+
+        assert_eq!(
+            program
+                .options
+                .iter()
+                .map(|option| option.value.clone())
+                .collect::<Vec<ProtoConstant>>(),
+            vec![
+                ProtoConstant::Str("com.rsproto.strings".to_string()),
+                ProtoConstant::Str("quotes: \" ' \\ and \"nested\" quotes".to_string()),
+                ProtoConstant::Str("\x07\x08\x0c\n\r\t\x0b?".to_string()),
+                ProtoConstant::Str("hex AJ \x07 octal A\0 unicode \u{e9} \u{1F600}".to_string()),
+                ProtoConstant::Str("split across lines".to_string()),
+            ]
+        );
+
+        match &program.types[..] {
+            [ProtoType::Message(message)] => assert_eq!(
+                message.reserved,
+                vec![
+                    ProtoReserved::Name("foo".to_string(), None),
+                    ProtoReserved::Name("bar".to_string(), None)
+                ]
+            ),
+            types => panic!("expected a single message, got {:?}", types),
+        }
+    }
+
+    #[test]
+    fn test_invalid_escape_error() {
+        let parser = ParserImpl::default();
+
+        let result = parser
+            .parse("option java_package = \"\\uD800\";")
+            .map_err(|err| err.to_string());
+
+        assert_eq!(
+            result,
+            Err("1:24: Unicode escape '\\uD800' is not a valid code point".to_string())
+        );
+
+        // Constants can hold any bytes, but names and paths have to be text.
+        let result = parser
+            .parse("import \"\\xff\";")
+            .map_err(|err| err.to_string());
+
+        assert_eq!(
+            result,
+            Err("1:8: String literal does not decode to valid UTF-8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_bytes_literals() {
+        let program = ParserImpl::default()
+            .parse("syntax = \"proto2\";\n\nmessage Bytes {\n    optional bytes hex = 1 [default = \"\\xff\\x00\"];\n    optional bytes octal = 2 [default = \"\\377\\0\" \"\\1\"];\n    optional bytes text = 3 [default = \"\\303\\251\"];\n}")
+            .expect("failed to parse");
+
+        match &program.types[..] {
+            [ProtoType::Message(message)] => assert_eq!(
+                message
+                    .fields
+                    .iter()
+                    .map(|field| field.default_value().cloned())
+                    .collect::<Vec<Option<ProtoConstant>>>(),
+                vec![
+                    Some(ProtoConstant::Bytes(vec![0xff, 0x00])),
+                    Some(ProtoConstant::Bytes(vec![0xff, 0x00, 0x01])),
+                    Some(ProtoConstant::Str("\u{e9}".to_string())),
+                ]
+            ),
+            types => panic!("expected a single message, got {:?}", types),
+        }
+    }
+
+    #[test]
+    fn test_numerics() {
+        let program = parse_test!("../../test_data/numerics.proto");
+
+        let values = program
+            .options
+            .iter()
+            .map(|option| option.value.clone())
+            .collect::<Vec<ProtoConstant>>();
+
+        assert_eq!(
+            values[..13].to_vec(),
+            vec![
+                ProtoConstant::UInt(42),
+                ProtoConstant::UInt(31),
+                ProtoConstant::UInt(15),
+                ProtoConstant::UInt(0),
+                ProtoConstant::Int(i64::MIN),
+                ProtoConstant::UInt(u64::MAX),
+                ProtoConstant::UInt(7),
+                ProtoConstant::Float(1500.0),
+                ProtoConstant::Float(0.25),
+                ProtoConstant::Float(2.0),
+                ProtoConstant::Float(-0.005),
+                ProtoConstant::Float(f64::INFINITY),
+                ProtoConstant::Float(f64::NEG_INFINITY),
+            ]
+        );
+        assert!(matches!(values[13], ProtoConstant::Float(nan) if nan.is_nan()));
+
+        let (sign, numbers) = match &program.types[..] {
+            [ProtoType::Enum(sign), ProtoType::Message(numbers)] => (sign, numbers),
+            types => panic!("expected an enum and a message, got {:?}", types),
+        };
+
+        assert_eq!(
+            sign.reserved,
+            vec![ProtoReserved::Range(ProtoRange {
+                span: None,
+                start_span: None,
+                end_span: None,
+                start: -10,
+                end: ProtoRangeEnd::Inclusive(-5)
+            })]
+        );
+        assert_eq!(
+            sign.values
+                .iter()
+                .map(|value| value.position)
+                .collect::<Vec<i32>>(),
+            vec![-1, 0, 1]
+        );
+
+        assert_eq!(
+            numbers.reserved,
+            vec![ProtoReserved::Range(ProtoRange {
+                span: None,
+                start_span: None,
+                end_span: None,
+                start: 16,
+                end: ProtoRangeEnd::Inclusive(31)
+            })]
+        );
+        assert_eq!(numbers.fields[0].position, 8);
+        assert_eq!(
+            numbers.fields[0].options[0].value,
+            ProtoConstant::Int(i64::MIN)
+        );
+    }
+
+    #[test]
+    fn test_invalid_numeric_errors() {
+        let parser = ParserImpl::default();
+
+        let errors = vec![
+            "message Foo {\n    string name = 1.5;\n}",
+            "message Foo {\n    string name = -1;\n}",
+            "enum Foo {\n    BAR = 2147483648;\n}",
+            "option foo = 18446744073709551616;",
+            "option foo = -9223372036854775809;",
+        ]
+        .into_iter()
+        .map(|src| parser.parse(src).unwrap_err().to_string())
+        .collect::<Vec<String>>();
+
+        assert_eq!(
+            errors,
+            vec![
+                "2:19: Invalid position '1.5': expected an integer",
+                "2:19: Invalid position '-1': invalid digit found in string",
+                "2:11: Invalid enum value '2147483648': number too large to fit in target type",
+                "1:14: Invalid numeric constant '18446744073709551616': number too large to fit in target type",
+                "1:14: Invalid numeric constant '-9223372036854775809': number too small to fit in target type",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let program = parse_test!("../../test_data/aggregates.proto");
+
+        let field = |name: &str, value: ProtoAggregateValue| ProtoAggregateField {
+            name: name.to_string(),
+            value,
+        };
+        let single = ProtoAggregateValue::Single;
+
+        assert_eq!(
+            program
+                .options
+                .iter()
+                .map(|option| (option.name.as_str(), option.value.clone()))
+                .collect::<Vec<(&str, ProtoConstant)>>(),
+            vec![
+                (
+                    "my.opt",
+                    ProtoConstant::Aggregate(vec![
+                        field("name", single(ProtoConstant::Str("x".to_string()))),
+                        field(
+                            "nested",
+                            single(ProtoConstant::Aggregate(vec![field(
+                                "a",
+                                single(ProtoConstant::UInt(1))
+                            )]))
+                        ),
+                        field(
+                            "list",
+                            ProtoAggregateValue::List(vec![
+                                ProtoConstant::UInt(1),
+                                ProtoConstant::UInt(2)
+                            ])
+                        ),
+                    ])
+                ),
+                (
+                    ".validate.rules",
+                    ProtoConstant::Aggregate(vec![
+                        field(
+                            "string",
+                            single(ProtoConstant::Aggregate(vec![
+                                field("min_len", single(ProtoConstant::UInt(1))),
+                                field("max_len", single(ProtoConstant::UInt(10))),
+                            ]))
+                        ),
+                        field(
+                            "[ext.field]",
+                            single(ProtoConstant::Identifier("SPEED".to_string()))
+                        ),
+                        field(
+                            "messages",
+                            ProtoAggregateValue::List(vec![
+                                ProtoConstant::Aggregate(vec![field(
+                                    "a",
+                                    single(ProtoConstant::Float(-1.5))
+                                )]),
+                                ProtoConstant::Aggregate(vec![field(
+                                    "a",
+                                    single(ProtoConstant::Float(f64::INFINITY))
+                                )]),
+                            ])
+                        ),
+                        field("empty", single(ProtoConstant::Aggregate(vec![]))),
+                    ])
+                ),
+                (
+                    "optimize_for",
+                    ProtoConstant::Identifier("SPEED".to_string())
+                ),
+            ]
+        );
+
+        match &program.types[..] {
+            [ProtoType::Message(message)] => {
+                let option = &message.fields[0].options[0];
+
+                assert_eq!(option.name, "validate.rules");
+                assert_eq!(option.field_path, Some("string".to_string()));
+                assert_eq!(
+                    option.value,
+                    ProtoConstant::Aggregate(vec![field(
+                        "uuid",
+                        single(ProtoConstant::Boolean(true))
+                    )])
+                );
+            }
+            types => panic!("expected a single message, got {:?}", types),
+        }
+    }
+
+    #[test]
+    fn test_field_options() {
+        let program = parse_test!("../../test_data/field_options.proto");
+
+        let (message, enumeration) = match &program.types[..] {
+            [ProtoType::Message(message), ProtoType::Enum(enumeration)] => (message, enumeration),
+            types => panic!("expected a message and an enum, got {:?}", types),
+        };
+
+        assert_eq!(
+            message
+                .fields
+                .iter()
+                .map(|field| (
+                    field.deprecated(),
+                    field.packed(),
+                    field.json_name(),
+                    field.default_value().cloned(),
+                    field.lazy()
+                ))
+                .collect::<Vec<(
+                    bool,
+                    Option<bool>,
+                    Option<&str>,
+                    Option<ProtoConstant>,
+                    bool
+                )>>(),
+            vec![
+                (true, None, Some("Old"), None, false),
+                (false, Some(false), None, None, false),
+                (
+                    false,
+                    None,
+                    None,
+                    Some(ProtoConstant::Str("unnamed".to_string())),
+                    false
+                ),
+                (false, None, None, None, true),
+                (false, None, None, None, false),
+            ]
+        );
+
+        // Custom options that share a builtin's name don't change what the accessors return.
+        assert!(message.fields[4]
+            .options
+            .iter()
+            .all(|option| option.extension));
+
+        assert_eq!(
+            message.fields[2]
+                .options
+                .iter()
+                .map(|option| (option.name.as_str(), option.field_path.as_deref()))
+                .collect::<Vec<(&str, Option<&str>)>>(),
+            vec![
+                ("default", None),
+                ("custom", None),
+                ("validate.rules", Some("string.min_len"))
+            ]
+        );
+
+        let oneof_field = &message.oneofs[0].fields[0];
+        assert!(oneof_field.deprecated());
+        assert_eq!(oneof_field.json_name(), Some("theA"));
+
+        assert_eq!(
+            enumeration.values[0].options,
+            vec![
+                ProtoOption {
+                    comments: ProtoComments::default(),
+                    value_span: None,
+                    span: None,
+                    name: "deprecated".to_string(),
+                    field_path: None,
+                    extension: false,
+                    value: ProtoConstant::Boolean(true)
+                },
+                ProtoOption {
+                    comments: ProtoComments::default(),
+                    value_span: None,
+                    span: None,
+                    name: "custom".to_string(),
+                    field_path: None,
+                    extension: true,
+                    value: ProtoConstant::Str("x".to_string())
+                }
+            ]
+        );
+
+        // Each field takes a single bracketed list of options.
+        let parser = ParserImpl::default();
+        assert!(parser
+            .parse("message Foo {\n    int32 a = 1 [deprecated = true][packed = true];\n}")
+            .is_err());
+    }
 }
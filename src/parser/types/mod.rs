@@ -1,81 +1,264 @@
+use std::fmt;
+
+// A region of the source file, as byte offsets along with the (1-based) lines and columns they fall on.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+// Comments attached to a declaration, following protoc's SourceCodeInfo conventions. Each comment keeps its original
+// text minus the comment markers, so line comments end in a newline.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ProtoComments {
+    pub leading: Option<String>,
+    pub trailing: Option<String>,
+    pub leading_detached: Vec<String>,
+}
+
 #[derive(Debug, PartialEq, Clone)]
-pub enum ProtoType<'a> {
-    Message(ProtoMessage<'a>),
-    Enum(ProtoEnum<'a>),
+pub enum ProtoType {
+    Message(ProtoMessage),
+    Enum(ProtoEnum),
 }
 
-impl<'a> ProtoType<'a> {
+impl ProtoType {
     pub fn get_name(&self) -> &str {
         match self {
             ProtoType::Message(message) => &message.name,
             ProtoType::Enum(enumeration) => &enumeration.name,
         }
     }
+
+    pub fn get_span(&self) -> Option<Span> {
+        match self {
+            ProtoType::Message(message) => message.span,
+            ProtoType::Enum(enumeration) => enumeration.span,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum ProtoFieldType<'a> {
-    Primitive(ProtoPrimitiveType<'a>),
-    IdentifierPath(ProtoIdentifierPath<'a>),
+pub enum ProtoFieldType {
+    Primitive(ProtoPrimitiveType),
+    IdentifierPath(ProtoIdentifierPath),
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum ProtoIdentifierPath<'a> {
-    Path(&'a str),
+pub enum ProtoIdentifierPath {
+    Path(String),
 }
 
-impl<'a> ProtoIdentifierPath<'a> {
+impl ProtoIdentifierPath {
+    // The components of the path, without the leading dot of an absolute path.
     pub fn get_path_parts(&self) -> Vec<&str> {
         match self {
-            ProtoIdentifierPath::Path(path) => path.split('.').collect(),
+            ProtoIdentifierPath::Path(path) => {
+                path.strip_prefix('.').unwrap_or(path).split('.').collect()
+            }
+        }
+    }
+
+    // Absolute paths start with a dot and are resolved from the root scope.
+    pub fn is_absolute(&self) -> bool {
+        match self {
+            ProtoIdentifierPath::Path(path) => path.starts_with('.'),
         }
     }
 }
 
-impl<'a> From<&'a str> for ProtoIdentifierPath<'a> {
-    fn from(string: &'a str) -> Self {
-        ProtoIdentifierPath::Path(string)
+impl From<&str> for ProtoIdentifierPath {
+    fn from(string: &str) -> Self {
+        ProtoIdentifierPath::Path(string.to_string())
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum ProtoPrimitiveType<'a> {
+pub enum ProtoPrimitiveType {
+    Double,
+    Float,
     Int32,
     Int64,
-    Str,
+    UInt32,
+    UInt64,
+    SInt32,
+    SInt64,
+    Fixed32,
+    Fixed64,
+    SFixed32,
+    SFixed64,
     Boolean,
-    Map(Box<ProtoFieldType<'a>>, Box<ProtoFieldType<'a>>),
+    Str,
+    Bytes,
+    Map(Box<ProtoFieldType>, Box<ProtoFieldType>),
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct ProtoOption {
     pub name: String,
     pub field_path: Option<String>,
+
+    // Whether the name was parenthesized, making it a custom option defined by an extension.
+    pub extension: bool,
     pub value: ProtoConstant,
+    pub span: Option<Span>,
+    pub value_span: Option<Span>,
+
+    // Only options set by an option statement have comments; ones in brackets don't.
+    pub comments: ProtoComments,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum ProtoConstant {
-    Numeric(f32),
+    // Like protoc, non-negative integers are always stored as a UInt and negative integers as an Int.
+    Int(i64),
+    UInt(u64),
+    Float(f64),
     Str(String),
+    // A string literal that isn't valid UTF-8, which can only be used as a bytes value.
+    Bytes(Vec<u8>),
     Boolean(bool),
+
+    // A bare identifier, e.g. an enum value name like SPEED.
+    Identifier(String),
+
+    // A message literal written in the text format.
+    Aggregate(Vec<ProtoAggregateField>),
+}
+
+impl ProtoConstant {
+    // The constant for a decoded string literal, which is only a Str if it's valid UTF-8.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(string) => ProtoConstant::Str(string),
+            Err(err) => ProtoConstant::Bytes(err.into_bytes()),
+        }
+    }
+
+    // The value of a string literal as bytes, whether or not it's valid UTF-8.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            ProtoConstant::Str(string) => Some(string.as_bytes()),
+            ProtoConstant::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProtoAggregateField {
+    // Extension and Any fields keep their brackets, e.g. "[foo.bar]".
+    pub name: String,
+    pub value: ProtoAggregateValue,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ProtoAggregateValue {
+    Single(ProtoConstant),
+    List(Vec<ProtoConstant>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ProtoRangeEnd {
+    Inclusive(i32),
+    Max,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProtoRange {
+    pub start: i32,
+    pub end: ProtoRangeEnd,
+    pub span: Option<Span>,
+
+    // A range of a single number has that number as both its start and its end.
+    pub start_span: Option<Span>,
+    pub end_span: Option<Span>,
+}
+
+impl ProtoRange {
+    // Takes an i64 so that both field numbers (u32) and enum values (i32) can be checked.
+    pub fn contains(&self, position: i64) -> bool {
+        match self.end {
+            ProtoRangeEnd::Inclusive(end) => {
+                i64::from(self.start) <= position && position <= i64::from(end)
+            }
+            ProtoRangeEnd::Max => i64::from(self.start) <= position,
+        }
+    }
+}
+
+impl fmt::Display for ProtoRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            ProtoRangeEnd::Inclusive(end) if end == self.start => write!(f, "{}", self.start),
+            ProtoRangeEnd::Inclusive(end) => write!(f, "{} to {}", self.start, end),
+            ProtoRangeEnd::Max => write!(f, "{} to max", self.start),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ProtoReserved {
+    Range(ProtoRange),
+    Name(String, Option<Span>),
+}
+
+impl fmt::Display for ProtoReserved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtoReserved::Range(range) => write!(f, "{}", range),
+            ProtoReserved::Name(name, _) => write!(f, "\"{}\"", name),
+        }
+    }
 }
 
+// A statement that declares several things at once, like `reserved 1, 5 to 7;`, whose ranges or names are listed
+// alongside the ones from the other statements of its kind.
 #[derive(Debug, PartialEq, Clone)]
-pub struct ProtoMessage<'a> {
-    pub name: &'a str,
+pub struct ProtoStatement {
+    // How many of the ranges or names the statement declares.
+    pub len: usize,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProtoMessage {
+    pub name: String,
     pub options: Vec<ProtoOption>,
-    pub types: Vec<ProtoType<'a>>,
-    pub fields: Vec<ProtoMessageField<'a>>,
+    pub reserved: Vec<ProtoReserved>,
+    pub reserved_statements: Vec<ProtoStatement>,
+    pub extension_ranges: Vec<ProtoRange>,
+    pub extensions_statements: Vec<ProtoStatement>,
+    pub types: Vec<ProtoType>,
+    pub extends: Vec<ProtoExtend>,
+    pub fields: Vec<ProtoMessageField>,
+    pub oneofs: Vec<ProtoOneof>,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+    pub name_span: Option<Span>,
 }
 
-impl<'a> ProtoMessage<'a> {
-    pub fn new(name: &'a str) -> Self {
+impl ProtoMessage {
+    pub fn new(name: &str, span: Option<Span>) -> Self {
         ProtoMessage {
-            name,
+            name: name.to_string(),
             options: vec![],
+            reserved: vec![],
+            reserved_statements: vec![],
+            extension_ranges: vec![],
+            extensions_statements: vec![],
             types: vec![],
+            extends: vec![],
             fields: vec![],
+            oneofs: vec![],
+            comments: ProtoComments::default(),
+            span,
+            name_span: None,
         }
     }
 }
@@ -88,27 +271,120 @@ pub enum ProtoMessageFieldModifier {
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct ProtoMessageField<'a> {
+pub struct ProtoMessageField {
     pub modifier: Option<ProtoMessageFieldModifier>,
-    pub field_type: ProtoFieldType<'a>,
-    pub name: &'a str,
+    pub explicit_presence: bool,
+    pub field_type: ProtoFieldType,
+    pub name: String,
     pub options: Vec<ProtoOption>,
     pub position: u32,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+    pub modifier_span: Option<Span>,
+    pub type_span: Option<Span>,
+    pub name_span: Option<Span>,
+    pub position_span: Option<Span>,
+
+    // The brackets around the options.
+    pub options_span: Option<Span>,
+}
+
+// Typed access to the well-known field options. Options with a value of the wrong type are treated as unset.
+impl ProtoMessageField {
+    pub fn deprecated(&self) -> bool {
+        matches!(
+            self.option("deprecated"),
+            Some(ProtoConstant::Boolean(true))
+        )
+    }
+
+    pub fn packed(&self) -> Option<bool> {
+        match self.option("packed") {
+            Some(ProtoConstant::Boolean(packed)) => Some(*packed),
+            _ => None,
+        }
+    }
+
+    pub fn json_name(&self) -> Option<&str> {
+        match self.option("json_name") {
+            Some(ProtoConstant::Str(json_name)) => Some(json_name),
+            _ => None,
+        }
+    }
+
+    pub fn default_value(&self) -> Option<&ProtoConstant> {
+        self.option("default")
+    }
+
+    pub fn lazy(&self) -> bool {
+        matches!(self.option("lazy"), Some(ProtoConstant::Boolean(true)))
+    }
+
+    // The last occurrence wins if an option is set more than once. Custom options that share a builtin's name don't count.
+    fn option(&self, name: &str) -> Option<&ProtoConstant> {
+        self.options
+            .iter()
+            .rev()
+            .find(|option| option.name == name && !option.extension && option.field_path.is_none())
+            .map(|option| &option.value)
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct ProtoEnum<'a> {
-    pub name: &'a str,
+pub struct ProtoExtend {
+    pub extendee: ProtoIdentifierPath,
+    pub fields: Vec<ProtoMessageField>,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+    pub extendee_span: Option<Span>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProtoOneof {
+    pub name: String,
     pub options: Vec<ProtoOption>,
+    pub fields: Vec<ProtoMessageField>,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+    pub name_span: Option<Span>,
+}
+
+impl ProtoOneof {
+    pub fn new(name: &str, span: Option<Span>) -> Self {
+        ProtoOneof {
+            name: name.to_string(),
+            options: vec![],
+            fields: vec![],
+            comments: ProtoComments::default(),
+            span,
+            name_span: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProtoEnum {
+    pub name: String,
+    pub options: Vec<ProtoOption>,
+    pub reserved: Vec<ProtoReserved>,
+    pub reserved_statements: Vec<ProtoStatement>,
     pub values: Vec<ProtoEnumValue>,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+    pub name_span: Option<Span>,
 }
 
-impl<'a> ProtoEnum<'a> {
-    pub fn new(name: &'a str) -> Self {
+impl ProtoEnum {
+    pub fn new(name: &str, span: Option<Span>) -> Self {
         ProtoEnum {
-            name,
+            name: name.to_string(),
             options: vec![],
+            reserved: vec![],
+            reserved_statements: vec![],
             values: vec![],
+            comments: ProtoComments::default(),
+            span,
+            name_span: None,
         }
     }
 }
@@ -117,7 +393,54 @@ impl<'a> ProtoEnum<'a> {
 pub struct ProtoEnumValue {
     pub name: String,
     pub options: Vec<ProtoOption>,
-    pub position: u32,
+    pub position: i32,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+    pub name_span: Option<Span>,
+    pub position_span: Option<Span>,
+    pub options_span: Option<Span>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProtoService {
+    pub name: String,
+    pub options: Vec<ProtoOption>,
+    pub rpcs: Vec<ProtoRpc>,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+    pub name_span: Option<Span>,
+}
+
+impl ProtoService {
+    pub fn new(name: &str, span: Option<Span>) -> Self {
+        ProtoService {
+            name: name.to_string(),
+            options: vec![],
+            rpcs: vec![],
+            comments: ProtoComments::default(),
+            span,
+            name_span: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProtoRpc {
+    pub name: String,
+    pub request_type: ProtoIdentifierPath,
+    pub request_stream: bool,
+    pub response_type: ProtoIdentifierPath,
+    pub response_stream: bool,
+    pub options: Vec<ProtoOption>,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+    pub name_span: Option<Span>,
+
+    // The `stream` keywords, if there are any, and the types.
+    pub request_stream_span: Option<Span>,
+    pub request_type_span: Option<Span>,
+    pub response_stream_span: Option<Span>,
+    pub response_type_span: Option<Span>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -129,33 +452,49 @@ pub enum ProtoSyntax {
 #[derive(Debug, PartialEq, Clone)]
 pub enum ProtoImportModifier {
     Public,
+    Weak,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct ProtoImport {
     pub path: String,
     pub modifier: Option<ProtoImportModifier>,
+    pub comments: ProtoComments,
+    pub span: Option<Span>,
+    pub modifier_span: Option<Span>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct Program<'a> {
-    pub src: &'a str,
+pub struct Program {
+    pub src: String,
     pub syntax: Option<ProtoSyntax>,
-    pub package: Option<&'a str>,
+    pub package: Option<String>,
+    pub syntax_span: Option<Span>,
+    pub package_span: Option<Span>,
+    pub syntax_comments: ProtoComments,
+    pub package_comments: ProtoComments,
     pub imports: Vec<ProtoImport>,
     pub options: Vec<ProtoOption>,
-    pub types: Vec<ProtoType<'a>>,
+    pub types: Vec<ProtoType>,
+    pub extends: Vec<ProtoExtend>,
+    pub services: Vec<ProtoService>,
 }
 
-impl<'a> Program<'a> {
-    pub fn new(src: &'a str) -> Program {
+impl Program {
+    pub fn new(src: &str) -> Program {
         Program {
-            src,
+            src: src.to_string(),
             syntax: None,
             package: None,
+            syntax_span: None,
+            package_span: None,
+            syntax_comments: ProtoComments::default(),
+            package_comments: ProtoComments::default(),
             imports: vec![],
             options: vec![],
             types: vec![],
+            extends: vec![],
+            services: vec![],
         }
     }
 }
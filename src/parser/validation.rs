@@ -0,0 +1,78 @@
+use super::types::*;
+use super::ParseError;
+
+// Checks that can only be made once the whole file has been parsed.
+pub fn validate(program: &Program) -> Result<(), ParseError> {
+    // Like protoc, files without a syntax statement are proto2.
+    let syntax = program.syntax.as_ref().unwrap_or(&ProtoSyntax::Proto2);
+
+    validate_labels(syntax, &program.types, &program.extends)
+}
+
+fn validate_labels(
+    syntax: &ProtoSyntax,
+    types: &[ProtoType],
+    extends: &[ProtoExtend],
+) -> Result<(), ParseError> {
+    for extend in extends {
+        let ProtoIdentifierPath::Path(extendee) = &extend.extendee;
+
+        for field in &extend.fields {
+            validate_label(syntax, field, || {
+                format!("Field '{}' in extend '{}'", field.name, extendee)
+            })?;
+        }
+    }
+
+    for proto_type in types {
+        if let ProtoType::Message(message) = proto_type {
+            for field in &message.fields {
+                validate_label(syntax, field, || {
+                    format!("Field '{}' in message '{}'", field.name, message.name)
+                })?;
+            }
+
+            validate_labels(syntax, &message.types, &message.extends)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_label<F>(
+    syntax: &ProtoSyntax,
+    field: &ProtoMessageField,
+    describe: F,
+) -> Result<(), ParseError>
+where
+    F: Fn() -> String,
+{
+    let is_map = matches!(
+        field.field_type,
+        ProtoFieldType::Primitive(ProtoPrimitiveType::Map(_, _))
+    );
+
+    match (syntax, &field.modifier) {
+        (ProtoSyntax::Proto3, Some(ProtoMessageFieldModifier::Required)) => {
+            Err(ParseError::validation(
+                field.span,
+                format!(
+                    "{} is marked 'required', which is not allowed in proto3",
+                    describe()
+                ),
+            ))
+        }
+        (ProtoSyntax::Proto2, None) if !is_map => Err(ParseError::validation(
+            field.span,
+            format!(
+                "{} has no label, but proto2 fields must be 'optional', 'required' or 'repeated'",
+                describe()
+            ),
+        )),
+        (_, Some(_)) if is_map => Err(ParseError::validation(
+            field.span,
+            format!("{} is a map field and cannot have a label", describe()),
+        )),
+        _ => Ok(()),
+    }
+}
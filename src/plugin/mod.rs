@@ -0,0 +1,185 @@
+mod types;
+
+pub use types::*;
+
+use crate::code_gen::{generator_for, CodeGenerator, GeneratorOptions, Language};
+use crate::descriptor::{compile_descriptors, Decode, DescriptorError, Encode};
+use crate::parser::ParserImpl;
+
+// protoc finds plugins by name, so `protoc --dart-rs_out=gen` runs the protoc-gen-dart-rs on the PATH.
+pub const PLUGIN_NAME: &str = "protoc-gen-dart-rs";
+
+// CodeGeneratorResponse.Feature.FEATURE_PROTO3_OPTIONAL, since proto3 optional fields are lifted like any other.
+const FEATURE_PROTO3_OPTIONAL: u64 = 1;
+
+// Handles an encoded CodeGeneratorRequest and returns the encoded CodeGeneratorResponse. Only a request that can't be
+// decoded is an error here; problems with the .proto files themselves are reported to protoc in the response.
+pub fn run(request: &[u8]) -> Result<Vec<u8>, DescriptorError> {
+    let request = CodeGeneratorRequest::decode(request).map_err(DescriptorError::Decode)?;
+
+    Ok(generate(&request).encode_to_vec())
+}
+
+pub fn generate(request: &CodeGeneratorRequest) -> CodeGeneratorResponse {
+    let mut response = CodeGeneratorResponse {
+        supported_features: FEATURE_PROTO3_OPTIONAL,
+        ..CodeGeneratorResponse::default()
+    };
+
+    match generate_files(request) {
+        Ok(files) => response.file = files,
+        Err(error) => response.error = Some(error),
+    }
+
+    response
+}
+
+fn generate_files(request: &CodeGeneratorRequest) -> Result<Vec<GeneratedFile>, String> {
+    let options = GeneratorOptions::parse(request.parameter.as_deref().unwrap_or(""));
+    let generator = generator_for(Box::new(ParserImpl::default()), Language::Dart, &options)?;
+
+    let compilation = compile_descriptors(&request.proto_file).map_err(|err| err.to_string())?;
+
+    // Report every file that fails rather than stopping at the first one.
+    let mut files = vec![];
+    let mut errors = vec![];
+    for name in &request.file_to_generate {
+        let file = match compilation.file(name) {
+            Some(file) => file,
+            None => {
+                errors.push(format!("{}: File not found in the request", name));
+                continue;
+            }
+        };
+
+        match generator.gen_file(&compilation, name) {
+            Ok(content) => files.push(GeneratedFile {
+                name: generator.output_file_name(file),
+                insertion_point: None,
+                content,
+            }),
+            Err(error) => errors.push(error.in_file(name)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+
+    files.extend(
+        generator
+            .runtime_files()
+            .into_iter()
+            .map(|(name, content)| GeneratedFile {
+                name,
+                insertion_point: None,
+                content,
+            }),
+    );
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{Compiler, SourceTree};
+    use crate::descriptor::{build_descriptor_set, DescriptorSetOptions};
+    use std::path::PathBuf;
+
+    fn request(names: &[&str], parameter: Option<&str>) -> CodeGeneratorRequest {
+        let compiler = Compiler::new(
+            Box::new(ParserImpl::default()),
+            SourceTree::new(vec![PathBuf::from("test_data/descriptors")]),
+        );
+        let compilation = compiler.compile(names).expect("failed to compile");
+        let set = build_descriptor_set(
+            &compilation,
+            names,
+            DescriptorSetOptions {
+                include_imports: true,
+                include_source_info: true,
+            },
+        )
+        .expect("failed to build descriptor set");
+
+        CodeGeneratorRequest {
+            file_to_generate: names.iter().map(|name| name.to_string()).collect(),
+            parameter: parameter.map(str::to_string),
+            compiler_version: Some(Version {
+                major: 3,
+                minor: 21,
+                patch: 12,
+                suffix: None,
+            }),
+            proto_file: set.file,
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let request = request(&["shop.proto"], None);
+        let response = run(&request.encode_to_vec()).expect("failed to run");
+        let response = CodeGeneratorResponse::decode(&response).expect("failed to decode");
+
+        assert_eq!(response.error, None);
+        assert_eq!(response.supported_features, FEATURE_PROTO3_OPTIONAL);
+
+        // Only the requested files are generated, not their imports, along with the runtime they rely on.
+        assert_eq!(
+            response
+                .file
+                .iter()
+                .map(|file| file.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["shop/shop.pb.dart", "rs_proto.dart"]
+        );
+        assert!(response.file[0].content.starts_with(
+            "import '../rs_proto.dart';\nimport 'common/common.pb.dart';\n\n\
+             /// An item that can be ordered.\nclass Item extends ProtobufMessage {\n"
+        ));
+
+        assert_eq!(
+            run(&[0x0a]).map_err(|err| err.to_string()),
+            Err("Invalid protobuf message: unexpected end of input".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parameter() {
+        let response = generate(&request(&["shop.proto"], Some("omit_comments")));
+        assert_eq!(response.error, None);
+        assert!(!response.file[0].content.contains("///"));
+
+        let response = generate(&request(&["shop.proto"], Some("omit_comments,fast=true")));
+        assert_eq!(
+            response.error,
+            Some("Unknown option 'fast' for the Dart generator".to_string())
+        );
+        assert!(response.file.is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut request = request(&["shop.proto", "legacy.proto"], None);
+
+        // Give two fields of each file the same number.
+        for file in &mut request.proto_file {
+            if file.name != "common.proto" {
+                let fields = &mut file.message_type[0].field;
+                fields[1].number = fields[0].number;
+            }
+        }
+
+        let response = generate(&request);
+        assert_eq!(
+            response.error,
+            Some(
+                "shop.proto: error: Field number 1 has already been used in \"shop.Item\" by field \"sku\".\n\
+                 legacy.proto: error: Field number 1 has already been used in \"legacy.Record\" by field \"id\"."
+                    .to_string()
+            )
+        );
+        assert!(response.file.is_empty());
+    }
+}
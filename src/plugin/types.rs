@@ -0,0 +1,175 @@
+use crate::descriptor::{Decode, DecodeError, Decoder, Encode, Encoder, FileDescriptorProto};
+
+// The messages of google/protobuf/compiler/plugin.proto that protoc exchanges with plugins. Field comments give the field
+// numbers they're encoded with.
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CodeGeneratorRequest {
+    // 1 (the files named on the command line, which are the ones to generate code for)
+    pub file_to_generate: Vec<String>,
+    // 2
+    pub parameter: Option<String>,
+    // 3
+    pub compiler_version: Option<Version>,
+    // 15 (every file in file_to_generate and everything they import, with each file's dependencies ahead of it)
+    pub proto_file: Vec<FileDescriptorProto>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Version {
+    // 1
+    pub major: i32,
+    // 2
+    pub minor: i32,
+    // 3
+    pub patch: i32,
+    // 4
+    pub suffix: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CodeGeneratorResponse {
+    // 1 (set when the .proto files can't be generated, as opposed to when the plugin itself fails)
+    pub error: Option<String>,
+    // 2
+    pub supported_features: u64,
+    // 15
+    pub file: Vec<GeneratedFile>,
+}
+
+// CodeGeneratorResponse.File
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct GeneratedFile {
+    // 1
+    pub name: String,
+    // 2
+    pub insertion_point: Option<String>,
+    // 15
+    pub content: String,
+}
+
+impl Encode for CodeGeneratorRequest {
+    fn encode(&self, encoder: &mut Encoder) {
+        for file_to_generate in &self.file_to_generate {
+            encoder.string(1, file_to_generate);
+        }
+        if let Some(parameter) = &self.parameter {
+            encoder.string(2, parameter);
+        }
+        if let Some(compiler_version) = &self.compiler_version {
+            encoder.message(3, compiler_version);
+        }
+        for proto_file in &self.proto_file {
+            encoder.message(15, proto_file);
+        }
+    }
+}
+
+impl Encode for Version {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.int32(1, self.major);
+        encoder.int32(2, self.minor);
+        encoder.int32(3, self.patch);
+        if let Some(suffix) = &self.suffix {
+            encoder.string(4, suffix);
+        }
+    }
+}
+
+impl Encode for CodeGeneratorResponse {
+    fn encode(&self, encoder: &mut Encoder) {
+        if let Some(error) = &self.error {
+            encoder.string(1, error);
+        }
+        if self.supported_features != 0 {
+            encoder.uint64(2, self.supported_features);
+        }
+        for file in &self.file {
+            encoder.message(15, file);
+        }
+    }
+}
+
+impl Encode for GeneratedFile {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.string(1, &self.name);
+        if let Some(insertion_point) = &self.insertion_point {
+            encoder.string(2, insertion_point);
+        }
+        encoder.string(15, &self.content);
+    }
+}
+
+impl Decode for CodeGeneratorRequest {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = CodeGeneratorRequest::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.file_to_generate.push(value.string()?),
+                2 => result.parameter = Some(value.string()?),
+                3 => result.compiler_version = Some(value.message()?),
+                15 => result.proto_file.push(value.message()?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for Version {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = Version::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.major = value.int32()?,
+                2 => result.minor = value.int32()?,
+                3 => result.patch = value.int32()?,
+                4 => result.suffix = Some(value.string()?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for CodeGeneratorResponse {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = CodeGeneratorResponse::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.error = Some(value.string()?),
+                2 => result.supported_features = value.uint64()?,
+                15 => result.file.push(value.message()?),
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Decode for GeneratedFile {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut result = GeneratedFile::default();
+
+        let mut decoder = Decoder::new(bytes);
+        while let Some((field, value)) = decoder.next_field()? {
+            match field {
+                1 => result.name = value.string()?,
+                2 => result.insertion_point = Some(value.string()?),
+                15 => result.content = value.string()?,
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
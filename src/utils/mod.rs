@@ -26,3 +26,38 @@ pub fn camel_case(string: CasedString) -> String {
         }
     }
 }
+
+pub fn pascal_case(string: CasedString) -> String {
+    let camel_cased_string = camel_case(string);
+
+    let mut chars = camel_cased_string.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// The Levenshtein distance between two strings, i.e. the number of single character insertions, deletions and
+// substitutions needed to turn one into the other.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+
+    let mut previous_row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, a_ch) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+
+        for (j, b_ch) in b.iter().enumerate() {
+            let substitution_cost = if a_ch == *b_ch { 0 } else { 1 };
+
+            row.push(
+                (previous_row[j] + substitution_cost)
+                    .min(previous_row[j + 1] + 1)
+                    .min(row[j] + 1),
+            );
+        }
+
+        previous_row = row;
+    }
+
+    previous_row[b.len()]
+}
@@ -0,0 +1,67 @@
+use crate::parser::Span;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Option<Span>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(span: Option<Span>, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            span,
+            message,
+        }
+    }
+
+    pub fn warning(span: Option<Span>, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            span,
+            message,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    // The diagnostic prefixed with the file it's for, e.g. "a.proto:3:5: error: ...".
+    pub fn in_file(&self, file: &str) -> String {
+        match self.span {
+            Some(_) => format!("{}:{}", file, self),
+            None => format!("{}: {}", file, self),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(
+                f,
+                "{}:{}: {}: {}",
+                span.start_line, span.start_column, self.severity, self.message
+            ),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
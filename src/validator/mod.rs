@@ -0,0 +1,968 @@
+mod diagnostic;
+
+pub use diagnostic::*;
+
+use crate::code_gen::env::{
+    GeneratorEnvironment, IdentifierQualifier, ProtoTypeHierarchy, ResolutionError,
+};
+use crate::parser::*;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const MAX_FIELD_NUMBER: i64 = 536_870_911;
+const FIRST_RESERVED_FIELD_NUMBER: u32 = 19_000;
+const LAST_RESERVED_FIELD_NUMBER: u32 = 19_999;
+
+// Checks a parsed Program against protobuf's semantic rules (the ones protoc enforces once a file has parsed), and
+// reports every problem it finds rather than stopping at the first one. The dependencies are the other files whose types
+// the program can reference.
+pub fn validate<'a>(program: &'a Program, dependencies: &[&'a Program]) -> Vec<Diagnostic> {
+    let mut files = vec![program];
+    files.extend(dependencies);
+
+    // Type references are resolved the same way the generators resolve them.
+    let type_hierarchy = ProtoTypeHierarchy::from_programs(
+        &files,
+        IdentifierQualifier::new(Box::new(|proto_type, _| proto_type.get_name().to_string())),
+    );
+    let mut env = GeneratorEnvironment::new(program, Rc::new(type_hierarchy));
+
+    let mut validator = Validator {
+        program,
+        files,
+        diagnostics: vec![],
+    };
+
+    validator.validate_file(&mut env);
+    validator.diagnostics
+}
+
+struct Validator<'p> {
+    program: &'p Program,
+
+    // The program followed by its dependencies.
+    files: Vec<&'p Program>,
+
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'p> Validator<'p> {
+    fn validate_file(&mut self, env: &mut GeneratorEnvironment) {
+        if self.program.syntax.is_none() {
+            self.diagnostics.push(Diagnostic::warning(
+                None,
+                "No syntax specified, so proto2 is assumed. Please use 'syntax = \"proto2\";' or 'syntax = \"proto3\";'".to_string(),
+            ));
+        }
+
+        let package = self.program.package.as_deref().unwrap_or("");
+
+        let names = self
+            .program
+            .types
+            .iter()
+            .map(|proto_type| (proto_type.get_name(), Self::type_span(proto_type)))
+            .chain(
+                self.program
+                    .services
+                    .iter()
+                    .map(|service| (service.name.as_str(), service.span)),
+            )
+            .collect::<Vec<(&str, Option<Span>)>>();
+        self.check_unique_names(package, names.iter().copied());
+        self.check_enum_value_names(package, &self.program.types, &names);
+
+        self.validate_types(package, &self.program.types, env);
+
+        for extend in &self.program.extends {
+            self.validate_extend(extend, env);
+        }
+
+        for service in &self.program.services {
+            self.validate_service(service, env);
+        }
+    }
+
+    fn validate_types(&mut self, scope: &str, types: &[ProtoType], env: &mut GeneratorEnvironment) {
+        for proto_type in types {
+            let full_name = Self::join_name(scope, proto_type.get_name());
+
+            match proto_type {
+                ProtoType::Message(message) => match env.new_child(proto_type) {
+                    Ok(child_env) => {
+                        self.validate_message(&full_name, message, &mut child_env.borrow_mut())
+                    }
+                    Err(err) => self.resolution_error(*err),
+                },
+                ProtoType::Enum(enumeration) => self.validate_enum(&full_name, enumeration),
+            }
+        }
+    }
+
+    fn validate_message(
+        &mut self,
+        full_name: &str,
+        message: &ProtoMessage,
+        env: &mut GeneratorEnvironment,
+    ) {
+        let all_fields = message
+            .fields
+            .iter()
+            .chain(message.oneofs.iter().flat_map(|oneof| oneof.fields.iter()))
+            .collect::<Vec<&ProtoMessageField>>();
+
+        // Fields, oneofs and nested types all share the message's scope.
+        let names = all_fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.span))
+            .chain(
+                message
+                    .oneofs
+                    .iter()
+                    .map(|oneof| (oneof.name.as_str(), oneof.span)),
+            )
+            .chain(
+                message
+                    .types
+                    .iter()
+                    .map(|proto_type| (proto_type.get_name(), Self::type_span(proto_type))),
+            )
+            .collect::<Vec<(&str, Option<Span>)>>();
+        self.check_unique_names(full_name, names.iter().copied());
+        self.check_enum_value_names(full_name, &message.types, &names);
+
+        let mut numbers: HashMap<u32, &str> = HashMap::new();
+        for field in &all_fields {
+            self.check_field_number(field);
+
+            match numbers.get(&field.position) {
+                Some(previous) => self.error(
+                    field.span,
+                    format!(
+                        "Field number {} has already been used in \"{}\" by field \"{}\".",
+                        field.position, full_name, previous
+                    ),
+                ),
+                None => {
+                    numbers.insert(field.position, &field.name);
+                }
+            }
+
+            self.validate_field(field);
+
+            if self.is_proto3() {
+                self.check_open_enum(full_name, field, env);
+            }
+        }
+
+        for oneof in &message.oneofs {
+            if oneof.fields.is_empty() {
+                self.error(
+                    oneof.span,
+                    format!("Oneof \"{}\" must have at least one field.", oneof.name),
+                );
+            }
+        }
+
+        let reserved_ranges = Self::reserved_ranges(&message.reserved);
+        self.check_overlapping_ranges(
+            full_name,
+            message.span,
+            "Reserved",
+            &reserved_ranges,
+            MAX_FIELD_NUMBER,
+        );
+        self.check_overlapping_ranges(
+            full_name,
+            message.span,
+            "Extension",
+            &message
+                .extension_ranges
+                .iter()
+                .collect::<Vec<&ProtoRange>>(),
+            MAX_FIELD_NUMBER,
+        );
+
+        for extension_range in &message.extension_ranges {
+            for reserved_range in &reserved_ranges {
+                if Self::ranges_overlap(extension_range, reserved_range, MAX_FIELD_NUMBER) {
+                    self.error(
+                        message.span,
+                        format!(
+                            "Extension range {} overlaps with reserved range {} in \"{}\".",
+                            extension_range, reserved_range, full_name
+                        ),
+                    );
+                }
+            }
+
+            for field in &all_fields {
+                if extension_range.contains(field.position.into()) {
+                    self.error(
+                        field.span,
+                        format!(
+                            "Extension range {} includes field \"{}\" ({}).",
+                            extension_range, field.name, field.position
+                        ),
+                    );
+                }
+            }
+        }
+
+        if self.is_proto3() && !message.extension_ranges.is_empty() {
+            self.error(
+                message.span,
+                format!(
+                    "Extension ranges are not allowed in proto3 (\"{}\").",
+                    full_name
+                ),
+            );
+        }
+
+        self.check_json_names(full_name, &all_fields);
+
+        self.validate_types(full_name, &message.types, env);
+
+        for extend in &message.extends {
+            self.validate_extend(extend, env);
+        }
+    }
+
+    fn validate_field(&mut self, field: &ProtoMessageField) {
+        if self.is_proto3() && field.default_value().is_some() {
+            self.error(
+                field.span,
+                format!(
+                    "Field \"{}\" has an explicit default value, which isn't allowed in proto3.",
+                    field.name
+                ),
+            );
+        }
+
+        // String literals can hold any bytes, but only bytes fields can take ones that aren't UTF-8.
+        if field.field_type == ProtoFieldType::Primitive(ProtoPrimitiveType::Str)
+            && matches!(field.default_value(), Some(ProtoConstant::Bytes(_)))
+        {
+            self.error(
+                field.span,
+                format!(
+                    "Default value of string field \"{}\" is not valid UTF-8.",
+                    field.name
+                ),
+            );
+        }
+
+        if let ProtoFieldType::Primitive(ProtoPrimitiveType::Map(key, _)) = &field.field_type {
+            let valid_key = matches!(
+                **key,
+                ProtoFieldType::Primitive(
+                    ProtoPrimitiveType::Int32
+                        | ProtoPrimitiveType::Int64
+                        | ProtoPrimitiveType::UInt32
+                        | ProtoPrimitiveType::UInt64
+                        | ProtoPrimitiveType::SInt32
+                        | ProtoPrimitiveType::SInt64
+                        | ProtoPrimitiveType::Fixed32
+                        | ProtoPrimitiveType::Fixed64
+                        | ProtoPrimitiveType::SFixed32
+                        | ProtoPrimitiveType::SFixed64
+                        | ProtoPrimitiveType::Boolean
+                        | ProtoPrimitiveType::Str
+                )
+            );
+
+            if !valid_key {
+                self.error(
+                    field.span,
+                    format!(
+                        "Key in map field \"{}\" cannot be a float, double, bytes, map or message type.",
+                        field.name
+                    ),
+                );
+            }
+        }
+
+        if field.packed() == Some(true) {
+            let repeated = field.modifier == Some(ProtoMessageFieldModifier::Repeated);
+
+            // Message and enum references can't be told apart without resolving them, so only scalars are checked.
+            let packable = !matches!(
+                field.field_type,
+                ProtoFieldType::Primitive(
+                    ProtoPrimitiveType::Str
+                        | ProtoPrimitiveType::Bytes
+                        | ProtoPrimitiveType::Map(_, _)
+                )
+            );
+
+            if !repeated || !packable {
+                self.error(
+                    field.span,
+                    format!(
+                        "Field \"{}\" sets [packed = true], which can only be specified for repeated primitive fields.",
+                        field.name
+                    ),
+                );
+            }
+        }
+    }
+
+    fn validate_extend(&mut self, extend: &ProtoExtend, env: &GeneratorEnvironment) {
+        for field in &extend.fields {
+            self.check_field_number(field);
+            self.validate_field(field);
+        }
+
+        // Extendees that live in other files (e.g. google.protobuf.FieldOptions) can't be checked here.
+        let extendee = match env.resolve_proto_type(&extend.extendee) {
+            Some(extendee) => extendee,
+            None => return,
+        };
+        let extendee = extendee.borrow();
+        let extendee = match extendee.proto_type.as_deref() {
+            Some(ProtoType::Message(extendee)) => extendee,
+            _ => return,
+        };
+
+        for field in &extend.fields {
+            let in_range = extendee
+                .extension_ranges
+                .iter()
+                .any(|range| range.contains(field.position.into()));
+
+            if !in_range {
+                let declared_ranges = match extendee.extension_ranges.len() {
+                    0 => "none declared".to_string(),
+                    _ => extendee
+                        .extension_ranges
+                        .iter()
+                        .map(|range| range.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                };
+
+                self.error(
+                    field.span,
+                    format!(
+                        "Extension field \"{}\" (= {}) is outside of the extension ranges of \"{}\" ({}).",
+                        field.name, field.position, extendee.name, declared_ranges
+                    ),
+                );
+            }
+        }
+    }
+
+    fn validate_enum(&mut self, full_name: &str, enumeration: &ProtoEnum) {
+        let first_value = match enumeration.values.first() {
+            Some(first_value) => first_value,
+            None => {
+                self.error(
+                    enumeration.span,
+                    format!("Enum \"{}\" must contain at least one value.", full_name),
+                );
+                return;
+            }
+        };
+
+        if self.is_proto3() && first_value.position != 0 {
+            self.error(
+                first_value.span,
+                format!(
+                    "The first enum value of \"{}\" must be zero in proto3.",
+                    full_name
+                ),
+            );
+        }
+
+        // Clashes with names outside of the enum are checked along with the rest of the enum's scope.
+        let names = enumeration
+            .values
+            .iter()
+            .map(|value| (value.name.as_str(), value.span));
+        self.check_unique_names(full_name, names);
+
+        let allow_alias = enumeration.options.iter().any(|option| {
+            option.name == "allow_alias" && option.value == ProtoConstant::Boolean(true)
+        });
+
+        let mut has_aliases = false;
+        let mut numbers: HashMap<i32, &str> = HashMap::new();
+        for value in &enumeration.values {
+            match numbers.get(&value.position) {
+                Some(previous) => {
+                    has_aliases = true;
+
+                    if !allow_alias {
+                        self.error(
+                            value.span,
+                            format!(
+                                "\"{}\" uses the same enum value as \"{}\". If this is intended, set 'option allow_alias = true;' on \"{}\".",
+                                value.name, previous, full_name
+                            ),
+                        );
+                    }
+                }
+                None => {
+                    numbers.insert(value.position, &value.name);
+                }
+            }
+        }
+
+        if allow_alias && !has_aliases {
+            self.error(
+                enumeration.span,
+                format!(
+                    "\"{}\" declares 'option allow_alias = true;', but does not have any aliases.",
+                    full_name
+                ),
+            );
+        }
+
+        self.check_overlapping_ranges(
+            full_name,
+            enumeration.span,
+            "Reserved",
+            &Self::reserved_ranges(&enumeration.reserved),
+            i32::MAX.into(),
+        );
+    }
+
+    fn validate_service(&mut self, service: &ProtoService, env: &GeneratorEnvironment) {
+        let full_name =
+            Self::join_name(self.program.package.as_deref().unwrap_or(""), &service.name);
+        let names = service.rpcs.iter().map(|rpc| (rpc.name.as_str(), rpc.span));
+
+        self.check_unique_names(&full_name, names);
+
+        for rpc in &service.rpcs {
+            for path in &[&rpc.request_type, &rpc.response_type] {
+                self.check_rpc_type(rpc, path, env);
+            }
+        }
+    }
+
+    // An rpc's request and response have to be messages.
+    fn check_rpc_type(
+        &mut self,
+        rpc: &ProtoRpc,
+        path: &ProtoIdentifierPath,
+        env: &GeneratorEnvironment,
+    ) {
+        let node = match env.resolve_type_node(path, rpc.span) {
+            Ok(node) => node,
+            Err(err) => return self.resolution_error(*err),
+        };
+
+        if !matches!(
+            node.borrow().proto_type.as_deref(),
+            Some(ProtoType::Message(_))
+        ) {
+            let ProtoIdentifierPath::Path(written_path) = path;
+
+            self.error(
+                rpc.span,
+                format!("\"{}\" is not a message type.", written_path),
+            );
+        }
+    }
+
+    fn check_field_number(&mut self, field: &ProtoMessageField) {
+        let position = field.position;
+
+        if position == 0 {
+            self.error(
+                field.span,
+                format!(
+                    "Field \"{}\" has number 0, but field numbers must be positive integers.",
+                    field.name
+                ),
+            );
+        } else if i64::from(position) > MAX_FIELD_NUMBER {
+            self.error(
+                field.span,
+                format!(
+                    "Field \"{}\" has number {}, but field numbers cannot be greater than {}.",
+                    field.name, position, MAX_FIELD_NUMBER
+                ),
+            );
+        } else if (FIRST_RESERVED_FIELD_NUMBER..=LAST_RESERVED_FIELD_NUMBER).contains(&position) {
+            self.error(
+                field.span,
+                format!(
+                    "Field \"{}\" has number {}, but field numbers {} through {} are reserved for the protocol buffer library implementation.",
+                    field.name, position, FIRST_RESERVED_FIELD_NUMBER, LAST_RESERVED_FIELD_NUMBER
+                ),
+            );
+        }
+    }
+
+    fn check_unique_names<'n, I>(&mut self, scope: &str, names: I)
+    where
+        I: Iterator<Item = (&'n str, Option<Span>)>,
+    {
+        let mut seen = vec![];
+
+        for (name, span) in names {
+            if seen.contains(&name) {
+                let full_name = Self::join_name(scope, name);
+                let message = match scope {
+                    "" => format!("\"{}\" is already defined.", full_name),
+                    _ => format!("\"{}\" is already defined in \"{}\".", full_name, scope),
+                };
+
+                self.error(span, message);
+            } else {
+                seen.push(name);
+            }
+        }
+    }
+
+    // Enum values are siblings of their enum rather than children of it, so they have to be unique across the enum's
+    // scope: among the values of every enum declared there, and among the scope's other names.
+    fn check_enum_value_names(
+        &mut self,
+        scope: &str,
+        types: &[ProtoType],
+        scope_names: &[(&str, Option<Span>)],
+    ) {
+        let mut seen = scope_names
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<&str>>();
+
+        for proto_type in types {
+            let enumeration = match proto_type {
+                ProtoType::Enum(enumeration) => enumeration,
+                ProtoType::Message(_) => continue,
+            };
+
+            // Clashes within the enum itself are reported by validate_enum.
+            let mut values = vec![];
+            for value in &enumeration.values {
+                if values.contains(&value.name.as_str()) {
+                    continue;
+                }
+
+                if seen.contains(&value.name.as_str()) {
+                    let (defined_in, unique_within) = match scope {
+                        "" => (String::new(), "the global scope".to_string()),
+                        _ => (format!(" in \"{}\"", scope), format!("\"{}\"", scope)),
+                    };
+
+                    self.error(
+                        value.span,
+                        format!(
+                            "\"{}\" is already defined{}. Note that enum values use C++ scoping rules, meaning that enum values are siblings of their type, not children of it. Therefore, \"{}\" must be unique within {}, not just within \"{}\".",
+                            Self::join_name(scope, &value.name), defined_in, value.name, unique_within, enumeration.name
+                        ),
+                    );
+                }
+
+                values.push(&value.name);
+            }
+
+            seen.extend(values);
+        }
+    }
+
+    // proto3 messages can only use open enums, so enums declared in proto2 files are off limits.
+    fn check_open_enum(
+        &mut self,
+        full_name: &str,
+        field: &ProtoMessageField,
+        env: &GeneratorEnvironment,
+    ) {
+        let path = match &field.field_type {
+            ProtoFieldType::IdentifierPath(path) => path,
+            ProtoFieldType::Primitive(ProtoPrimitiveType::Map(_, value)) => match &**value {
+                ProtoFieldType::IdentifierPath(path) => path,
+                ProtoFieldType::Primitive(_) => return,
+            },
+            ProtoFieldType::Primitive(_) => return,
+        };
+
+        // Unresolved references are reported by the generators.
+        let node = match env.resolve_proto_type(path) {
+            Some(node) => node,
+            None => return,
+        };
+        let node = node.borrow();
+        if !matches!(node.proto_type.as_deref(), Some(ProtoType::Enum(_))) {
+            return;
+        }
+
+        let closed = self
+            .files
+            .iter()
+            .find(|file| Self::declares(file, &node.full_name))
+            .is_some_and(|file| file.syntax != Some(ProtoSyntax::Proto3));
+
+        if closed {
+            self.error(
+                field.span,
+                format!(
+                    "Enum type \"{}\" is not a proto3 enum, but is used in \"{}\" which is a proto3 message type.",
+                    node.full_name, full_name
+                ),
+            );
+        }
+    }
+
+    // Whether a file declares the type with the given full name, e.g. "foo.Bar.Baz".
+    fn declares(file: &Program, full_name: &str) -> bool {
+        let mut types = &file.types;
+        let mut scope = file.package.as_deref().unwrap_or("").to_string();
+
+        loop {
+            let found = types.iter().find(|proto_type| {
+                let name = Self::join_name(&scope, proto_type.get_name());
+                full_name == name || full_name.starts_with(&format!("{}.", name))
+            });
+
+            match found {
+                Some(proto_type) => {
+                    scope = Self::join_name(&scope, proto_type.get_name());
+                    if scope == full_name {
+                        return true;
+                    }
+
+                    match proto_type {
+                        ProtoType::Message(message) => types = &message.types,
+                        ProtoType::Enum(_) => return false,
+                    }
+                }
+                None => return false,
+            }
+        }
+    }
+
+    fn check_overlapping_ranges(
+        &mut self,
+        full_name: &str,
+        span: Option<Span>,
+        kind: &str,
+        ranges: &[&ProtoRange],
+        max: i64,
+    ) {
+        for (i, range) in ranges.iter().enumerate() {
+            for previous in &ranges[..i] {
+                if Self::ranges_overlap(range, previous, max) {
+                    self.error(
+                        span,
+                        format!(
+                            "{} range {} overlaps with already-defined range {} in \"{}\".",
+                            kind, range, previous, full_name
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    // Protobuf allows field names that map to the same JSON name in proto2 (with a warning), but not in proto3.
+    fn check_json_names(&mut self, full_name: &str, fields: &[&ProtoMessageField]) {
+        let mut json_names: HashMap<String, &str> = HashMap::new();
+
+        for field in fields {
+            let json_name = field
+                .json_name()
+                .map(|json_name| json_name.to_string())
+                .unwrap_or_else(|| Self::default_json_name(&field.name));
+
+            match json_names.get(&json_name) {
+                // Fields with the same name have already been reported as duplicates.
+                Some(previous) if *previous == field.name => {}
+                Some(previous) => {
+                    let message = format!(
+                        "The JSON name of field \"{}\" (\"{}\") conflicts with field \"{}\" in \"{}\".",
+                        field.name, json_name, previous, full_name
+                    );
+
+                    if self.is_proto3() {
+                        self.error(field.span, message);
+                    } else {
+                        self.diagnostics
+                            .push(Diagnostic::warning(field.span, message));
+                    }
+                }
+                None => {
+                    json_names.insert(json_name, &field.name);
+                }
+            }
+        }
+    }
+
+    // Mirrors protoc's ToJsonName: underscores are dropped and the letter after each one is capitalized.
+    fn default_json_name(name: &str) -> String {
+        let mut result = String::new();
+        let mut capitalize_next = false;
+
+        for c in name.chars() {
+            match c {
+                '_' => capitalize_next = true,
+                c if capitalize_next => {
+                    result.extend(c.to_uppercase());
+                    capitalize_next = false;
+                }
+                c => result.push(c),
+            }
+        }
+
+        result
+    }
+
+    fn reserved_ranges(reserved: &[ProtoReserved]) -> Vec<&ProtoRange> {
+        reserved
+            .iter()
+            .filter_map(|reservation| match reservation {
+                ProtoReserved::Range(range) => Some(range),
+                ProtoReserved::Name(_, _) => None,
+            })
+            .collect()
+    }
+
+    fn ranges_overlap(a: &ProtoRange, b: &ProtoRange, max: i64) -> bool {
+        let end = |range: &ProtoRange| match range.end {
+            ProtoRangeEnd::Inclusive(end) => i64::from(end),
+            ProtoRangeEnd::Max => max,
+        };
+
+        i64::from(a.start) <= end(b) && i64::from(b.start) <= end(a)
+    }
+
+    fn type_span(proto_type: &ProtoType) -> Option<Span> {
+        match proto_type {
+            ProtoType::Message(message) => message.span,
+            ProtoType::Enum(enumeration) => enumeration.span,
+        }
+    }
+
+    fn is_proto3(&self) -> bool {
+        self.program.syntax == Some(ProtoSyntax::Proto3)
+    }
+
+    fn error(&mut self, span: Option<Span>, message: String) {
+        self.diagnostics.push(Diagnostic::error(span, message));
+    }
+
+    // The diagnostic carries the location, so it's left out of the message.
+    fn resolution_error(&mut self, err: ResolutionError) {
+        let span = err.span;
+        let message = ResolutionError { span: None, ..err }.to_string();
+
+        self.error(span, message);
+    }
+
+    fn join_name(scope: &str, name: &str) -> String {
+        match scope {
+            "" => name.to_string(),
+            _ => format!("{}.{}", scope, name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! validate_test {
+        ($test_path: expr) => {{
+            let parser = ParserImpl::default();
+            let program = parser
+                .parse(include_str!($test_path))
+                .expect("failed to parse test file");
+
+            validate(&program, &[])
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<String>>()
+        }};
+    }
+
+    fn validate_errors(src: &str, dependencies: &[&str]) -> Vec<String> {
+        let parser = ParserImpl::default();
+        let program = parser.parse(src).expect("failed to parse test source");
+        let dependencies = dependencies
+            .iter()
+            .map(|dependency| {
+                parser
+                    .parse(dependency)
+                    .expect("failed to parse test dependency")
+            })
+            .collect::<Vec<Program>>();
+
+        validate(&program, &dependencies.iter().collect::<Vec<&Program>>())
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .map(|diagnostic| diagnostic.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_errors() {
+        let diagnostics = validate_test!("../../test_data/invalid.proto");
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                "22:1: error: \"app.Dupes\" is already defined in \"app\".",
+                "8:5: error: \"app.Dupes.a\" is already defined in \"app.Dupes\".",
+                "5:5: error: Field \"zero\" has number 0, but field numbers must be positive integers.",
+                "7:5: error: Field number 1 has already been used in \"app.Dupes\" by field \"a\".",
+                "9:5: error: Field \"library\" has number 19000, but field numbers 19000 through 19999 are reserved for the protocol buffer library implementation.",
+                "10:5: error: Field \"too_big\" has number 536870912, but field numbers cannot be greater than 536870911.",
+                "11:5: error: Field \"with_default\" has an explicit default value, which isn't allowed in proto3.",
+                "12:5: error: Key in map field \"bad_key\" cannot be a float, double, bytes, map or message type.",
+                "13:5: error: Field \"packed_string\" sets [packed = true], which can only be specified for repeated primitive fields.",
+                "19:5: error: Oneof \"empty\" must have at least one field.",
+                "4:1: error: Reserved range 150 to 160 overlaps with already-defined range 100 to 200 in \"app.Dupes\".",
+                "15:5: error: The JSON name of field \"fooBar\" (\"fooBar\") conflicts with field \"foo_bar\" in \"app.Dupes\".",
+                "25:5: error: The first enum value of \"app.NotZero\" must be zero in proto3.",
+                "26:5: error: \"SECOND\" uses the same enum value as \"FIRST\". If this is intended, set 'option allow_alias = true;' on \"app.NotZero\".",
+                "29:1: error: \"app.Aliased\" declares 'option allow_alias = true;', but does not have any aliases.",
+                "35:1: error: Enum \"app.Empty\" must contain at least one value.",
+                "39:5: error: \"app.Service.Call\" is already defined in \"app.Service\".",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let diagnostics = validate_test!("../../test_data/warnings.proto");
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                "warning: No syntax specified, so proto2 is assumed. Please use 'syntax = \"proto2\";' or 'syntax = \"proto3\";'",
+                "3:5: warning: The JSON name of field \"fooBar\" (\"fooBar\") conflicts with field \"foo_bar\" in \"Warnings\".",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_valid() {
+        assert_eq!(
+            validate_test!("../../test_data/field_options.proto"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            validate_test!("../../test_data/extensions.proto"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_extension_out_of_range() {
+        assert_eq!(
+            validate_errors(
+                "message Foo {\n    extensions 100 to 199;\n}\n\nextend Foo {\n    optional int32 bar = 200;\n}",
+                &[]
+            ),
+            vec!["6:5: error: Extension field \"bar\" (= 200) is outside of the extension ranges of \"Foo\" (100 to 199)."]
+        );
+
+        // The extendee is looked up from the scope of the extend block.
+        assert_eq!(
+            validate_errors(
+                "message Foo {\n    message Bar {}\n\n    extend Bar {\n        optional int32 baz = 1;\n    }\n}",
+                &[]
+            ),
+            vec!["5:9: error: Extension field \"baz\" (= 1) is outside of the extension ranges of \"Bar\" (none declared)."]
+        );
+    }
+
+    #[test]
+    fn test_enum_value_scope() {
+        assert_eq!(
+            validate_errors(
+                "syntax = \"proto3\";\n\npackage app;\n\nenum Color {\n    RED = 0;\n    GREEN = 1;\n}\n\nenum Light {\n    OFF = 0;\n    GREEN = 1;\n}\n\nmessage Paint {\n    enum Finish {\n        MATTE = 0;\n    }\n\n    int32 MATTE = 1;\n}",
+                &[]
+            ),
+            vec![
+                "12:5: error: \"app.GREEN\" is already defined in \"app\". Note that enum values use C++ scoping rules, meaning that enum values are siblings of their type, not children of it. Therefore, \"GREEN\" must be unique within \"app\", not just within \"Light\".",
+                "17:9: error: \"app.Paint.MATTE\" is already defined in \"app.Paint\". Note that enum values use C++ scoping rules, meaning that enum values are siblings of their type, not children of it. Therefore, \"MATTE\" must be unique within \"app.Paint\", not just within \"Finish\".",
+            ]
+        );
+
+        assert_eq!(
+            validate_errors(
+                "syntax = \"proto3\";\n\nenum Color {\n    Shade = 0;\n}\n\nmessage Shade {}",
+                &[]
+            ),
+            vec!["4:5: error: \"Shade\" is already defined. Note that enum values use C++ scoping rules, meaning that enum values are siblings of their type, not children of it. Therefore, \"Shade\" must be unique within the global scope, not just within \"Color\"."]
+        );
+    }
+
+    #[test]
+    fn test_closed_enum_in_proto3() {
+        let legacy =
+            "syntax = \"proto2\";\n\npackage legacy;\n\nenum Mode {\n    MODE_UNKNOWN = 0;\n}";
+        let open = "syntax = \"proto3\";\n\npackage open;\n\nenum Mode {\n    MODE_UNKNOWN = 0;\n}";
+
+        assert_eq!(
+            validate_errors(
+                "syntax = \"proto3\";\n\nmessage Settings {\n    legacy.Mode mode = 1;\n    map<string, legacy.Mode> modes = 2;\n    open.Mode open_mode = 3;\n}",
+                &[legacy, open]
+            ),
+            vec![
+                "4:5: error: Enum type \"legacy.Mode\" is not a proto3 enum, but is used in \"Settings\" which is a proto3 message type.",
+                "5:5: error: Enum type \"legacy.Mode\" is not a proto3 enum, but is used in \"Settings\" which is a proto3 message type.",
+            ]
+        );
+
+        // proto2 messages can use either kind of enum.
+        assert_eq!(
+            validate_errors(
+                "syntax = \"proto2\";\n\nmessage Settings {\n    optional legacy.Mode mode = 1;\n    optional open.Mode open_mode = 2;\n}",
+                &[legacy, open]
+            ),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_nan_constants() {
+        // NaN isn't equal to itself, which used to keep messages holding one from being found in the hierarchy.
+        assert_eq!(
+            validate_errors(
+                "syntax = \"proto2\";\n\nmessage Limits {\n    message Inner {\n        optional double d = 1 [default = nan];\n    }\n\n    optional float f = 4 [default = nan];\n}",
+                &[]
+            ),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_non_utf8_defaults() {
+        assert_eq!(
+            validate_errors(
+                "syntax = \"proto2\";\n\nmessage Defaults {\n    optional string text = 1 [default = \"\\xff\"];\n    optional bytes data = 2 [default = \"\\xff\"];\n}",
+                &[]
+            ),
+            vec!["4:5: error: Default value of string field \"text\" is not valid UTF-8."]
+        );
+    }
+
+    #[test]
+    fn test_rpc_types() {
+        assert_eq!(
+            validate_errors(
+                "syntax = \"proto3\";\n\nenum E {\n    E_UNKNOWN = 0;\n}\n\nmessage Request {}\n\nservice Service {\n    rpc A(Missing) returns (E);\n    rpc B(Request) returns (Request);\n}",
+                &[]
+            ),
+            vec![
+                "10:5: error: Unable to resolve type 'Missing' from the root scope",
+                "10:5: error: \"E\" is not a message type.",
+            ]
+        );
+
+        // Types are resolved from the file's package, and can come from its dependencies.
+        assert_eq!(
+            validate_errors(
+                "syntax = \"proto3\";\n\npackage app;\n\nservice Service {\n    rpc A(shared.Request) returns (stream shared.Request);\n}",
+                &["syntax = \"proto3\";\n\npackage shared;\n\nmessage Request {}"]
+            ),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,553 @@
+import 'package:fixnum/fixnum.dart';
+import '../rs_proto.dart';
+
+/// Every kind of field, encoded in test/fixtures.
+class Everything extends ProtobufMessage {
+	double aDouble;
+	double aFloat;
+	int anInt32;
+	Int64 anInt64;
+	int aUint32;
+	Int64 aUint64;
+	int aSint32;
+	Int64 aSint64;
+	int aFixed32;
+	Int64 aFixed64;
+	int anSfixed32;
+	Int64 anSfixed64;
+	bool aBool;
+	String aString;
+	List<int> someBytes;
+	Everything_Color color;
+	Everything_Point? point;
+	int? maybe;
+	List<int> numbers = [];
+	List<Int64> deltas = [];
+	List<String> names = [];
+	List<Everything_Point> points = [];
+	List<Everything_Color> colors = [];
+	Map<String, int> counts = {};
+	Map<Int64, Everything_Point> pointsById = {};
+
+	Everything({
+		this.aDouble = 0.0,
+		this.aFloat = 0.0,
+		this.anInt32 = 0,
+		this.anInt64 = Int64.ZERO,
+		this.aUint32 = 0,
+		this.aUint64 = Int64.ZERO,
+		this.aSint32 = 0,
+		this.aSint64 = Int64.ZERO,
+		this.aFixed32 = 0,
+		this.aFixed64 = Int64.ZERO,
+		this.anSfixed32 = 0,
+		this.anSfixed64 = Int64.ZERO,
+		this.aBool = false,
+		this.aString = '',
+		this.someBytes = const <int>[],
+		this.color = Everything_Color.colorUnspecified,
+		this.point,
+		this.maybe,
+		List<int>? numbers,
+		List<Int64>? deltas,
+		List<String>? names,
+		List<Everything_Point>? points,
+		List<Everything_Color>? colors,
+		Map<String, int>? counts,
+		Map<Int64, Everything_Point>? pointsById,
+		String? label,
+		Everything_Point? origin,
+	}) {
+		if (numbers != null) {
+			this.numbers.addAll(numbers);
+		}
+		if (deltas != null) {
+			this.deltas.addAll(deltas);
+		}
+		if (names != null) {
+			this.names.addAll(names);
+		}
+		if (points != null) {
+			this.points.addAll(points);
+		}
+		if (colors != null) {
+			this.colors.addAll(colors);
+		}
+		if (counts != null) {
+			this.counts.addAll(counts);
+		}
+		if (pointsById != null) {
+			this.pointsById.addAll(pointsById);
+		}
+		if (label != null) {
+			this.label = label;
+		}
+		if (origin != null) {
+			this.origin = origin;
+		}
+	}
+
+	bool hasPoint() => point != null;
+	void clearPoint() => point = null;
+
+	bool hasMaybe() => maybe != null;
+	void clearMaybe() => maybe = null;
+
+	Everything_Choice _choiceCase = Everything_Choice.notSet;
+	String? _label;
+	Everything_Point? _origin;
+
+	Everything_Choice get choiceCase => _choiceCase;
+
+	String? get label => _label;
+	set label(String? value) {
+		clearChoice();
+		if (value != null) {
+			_label = value;
+			_choiceCase = Everything_Choice.label;
+		}
+	}
+	bool hasLabel() => _choiceCase == Everything_Choice.label;
+	void clearLabel() {
+		if (hasLabel()) {
+			clearChoice();
+		}
+	}
+
+	Everything_Point? get origin => _origin;
+	set origin(Everything_Point? value) {
+		clearChoice();
+		if (value != null) {
+			_origin = value;
+			_choiceCase = Everything_Choice.origin;
+		}
+	}
+	bool hasOrigin() => _choiceCase == Everything_Choice.origin;
+	void clearOrigin() {
+		if (hasOrigin()) {
+			clearChoice();
+		}
+	}
+
+	void clearChoice() {
+		_label = null;
+		_origin = null;
+		_choiceCase = Everything_Choice.notSet;
+	}
+
+	static Everything fromBuffer(List<int> bytes) => Everything()..mergeFromBuffer(bytes);
+
+	@override
+	void writeToWriter(ProtobufWriter writer) {
+		if (aDouble != 0 || aDouble.isNegative) {
+			writer..writeTag(9)..writeDouble(aDouble);
+		}
+		if (aFloat != 0 || aFloat.isNegative) {
+			writer..writeTag(21)..writeFloat(aFloat);
+		}
+		if (anInt32 != 0) {
+			writer..writeTag(24)..writeInt32(anInt32);
+		}
+		if (!anInt64.isZero) {
+			writer..writeTag(32)..writeInt64(anInt64);
+		}
+		if (aUint32 != 0) {
+			writer..writeTag(40)..writeUint32(aUint32);
+		}
+		if (!aUint64.isZero) {
+			writer..writeTag(48)..writeUint64(aUint64);
+		}
+		if (aSint32 != 0) {
+			writer..writeTag(56)..writeSint32(aSint32);
+		}
+		if (!aSint64.isZero) {
+			writer..writeTag(64)..writeSint64(aSint64);
+		}
+		if (aFixed32 != 0) {
+			writer..writeTag(77)..writeFixed32(aFixed32);
+		}
+		if (!aFixed64.isZero) {
+			writer..writeTag(81)..writeFixed64(aFixed64);
+		}
+		if (anSfixed32 != 0) {
+			writer..writeTag(93)..writeSfixed32(anSfixed32);
+		}
+		if (!anSfixed64.isZero) {
+			writer..writeTag(97)..writeSfixed64(anSfixed64);
+		}
+		if (aBool) {
+			writer..writeTag(104)..writeBool(aBool);
+		}
+		if (aString.isNotEmpty) {
+			writer..writeTag(114)..writeString(aString);
+		}
+		if (someBytes.isNotEmpty) {
+			writer..writeTag(122)..writeBytes(someBytes);
+		}
+		if (color.position != 0) {
+			writer..writeTag(128)..writeEnum(color);
+		}
+		if (point != null) {
+			writer..writeTag(138)..writeMessage(point!);
+		}
+		if (maybe != null) {
+			writer..writeTag(144)..writeInt32(maybe!);
+		}
+		if (numbers.isNotEmpty) {
+			writer..writeTag(162)..writePacked(numbers, (writer, value) => writer.writeInt32(value));
+		}
+		for (final value in deltas) {
+			writer..writeTag(168)..writeSint64(value);
+		}
+		for (final value in names) {
+			writer..writeTag(178)..writeString(value);
+		}
+		for (final value in points) {
+			writer..writeTag(186)..writeMessage(value);
+		}
+		if (colors.isNotEmpty) {
+			writer..writeTag(194)..writePacked(colors, (writer, value) => writer.writeEnum(value));
+		}
+		counts.forEach((key, value) {
+			writer..writeTag(202)..writeLengthDelimited((entry) => entry..writeTag(10)..writeString(key)..writeTag(16)..writeInt32(value));
+		});
+		pointsById.forEach((key, value) {
+			writer..writeTag(210)..writeLengthDelimited((entry) => entry..writeTag(8)..writeInt64(key)..writeTag(18)..writeMessage(value));
+		});
+		if (_choiceCase == Everything_Choice.label) {
+			writer..writeTag(218)..writeString(_label!);
+		}
+		if (_choiceCase == Everything_Choice.origin) {
+			writer..writeTag(226)..writeMessage(_origin!);
+		}
+	}
+
+	@override
+	void mergeFromReader(ProtobufReader reader) {
+		while (!reader.isAtEnd) {
+			final tag = reader.readTag();
+			switch (tag) {
+				case 9:
+					aDouble = reader.readDouble();
+					break;
+				case 21:
+					aFloat = reader.readFloat();
+					break;
+				case 24:
+					anInt32 = reader.readInt32();
+					break;
+				case 32:
+					anInt64 = reader.readInt64();
+					break;
+				case 40:
+					aUint32 = reader.readUint32();
+					break;
+				case 48:
+					aUint64 = reader.readUint64();
+					break;
+				case 56:
+					aSint32 = reader.readSint32();
+					break;
+				case 64:
+					aSint64 = reader.readSint64();
+					break;
+				case 77:
+					aFixed32 = reader.readFixed32();
+					break;
+				case 81:
+					aFixed64 = reader.readFixed64();
+					break;
+				case 93:
+					anSfixed32 = reader.readSfixed32();
+					break;
+				case 97:
+					anSfixed64 = reader.readSfixed64();
+					break;
+				case 104:
+					aBool = reader.readBool();
+					break;
+				case 114:
+					aString = reader.readString();
+					break;
+				case 122:
+					someBytes = reader.readBytes();
+					break;
+				case 128:
+					color = reader.readEnum(Everything_Color.valueOfOrUnknown);
+					break;
+				case 138:
+					reader.readMessage(point ??= Everything_Point());
+					break;
+				case 144:
+					maybe = reader.readInt32();
+					break;
+				case 160:
+					numbers.add(reader.readInt32());
+					break;
+				case 162:
+					reader.readPacked((reader) => numbers.add(reader.readInt32()));
+					break;
+				case 168:
+					deltas.add(reader.readSint64());
+					break;
+				case 170:
+					reader.readPacked((reader) => deltas.add(reader.readSint64()));
+					break;
+				case 178:
+					names.add(reader.readString());
+					break;
+				case 186:
+					points.add(reader.readMessage(Everything_Point()));
+					break;
+				case 192:
+					colors.add(reader.readEnum(Everything_Color.valueOfOrUnknown));
+					break;
+				case 194:
+					reader.readPacked((reader) => colors.add(reader.readEnum(Everything_Color.valueOfOrUnknown)));
+					break;
+				case 202:
+					reader.readMapEntry(counts, 10, (reader) => reader.readString(), '', 16, (reader) => reader.readInt32(), () => 0);
+					break;
+				case 210:
+					reader.readMapEntry(pointsById, 8, (reader) => reader.readInt64(), Int64.ZERO, 18, (reader) => reader.readMessage(Everything_Point()), () => Everything_Point());
+					break;
+				case 218:
+					label = reader.readString();
+					break;
+				case 226:
+					origin = reader.readMessage(_origin ?? Everything_Point());
+					break;
+				default:
+					reader.skipField(tag);
+			}
+		}
+	}
+
+	@override
+	bool operator ==(Object other) =>
+			other is Everything &&
+			protobufEquals(aDouble, other.aDouble) &&
+			protobufEquals(aFloat, other.aFloat) &&
+			protobufEquals(anInt32, other.anInt32) &&
+			protobufEquals(anInt64, other.anInt64) &&
+			protobufEquals(aUint32, other.aUint32) &&
+			protobufEquals(aUint64, other.aUint64) &&
+			protobufEquals(aSint32, other.aSint32) &&
+			protobufEquals(aSint64, other.aSint64) &&
+			protobufEquals(aFixed32, other.aFixed32) &&
+			protobufEquals(aFixed64, other.aFixed64) &&
+			protobufEquals(anSfixed32, other.anSfixed32) &&
+			protobufEquals(anSfixed64, other.anSfixed64) &&
+			protobufEquals(aBool, other.aBool) &&
+			protobufEquals(aString, other.aString) &&
+			protobufEquals(someBytes, other.someBytes) &&
+			protobufEquals(color, other.color) &&
+			protobufEquals(point, other.point) &&
+			protobufEquals(maybe, other.maybe) &&
+			protobufEquals(numbers, other.numbers) &&
+			protobufEquals(deltas, other.deltas) &&
+			protobufEquals(names, other.names) &&
+			protobufEquals(points, other.points) &&
+			protobufEquals(colors, other.colors) &&
+			protobufEquals(counts, other.counts) &&
+			protobufEquals(pointsById, other.pointsById) &&
+			protobufEquals(_choiceCase, other._choiceCase) &&
+			protobufEquals(_label, other._label) &&
+			protobufEquals(_origin, other._origin);
+
+	@override
+	int get hashCode => protobufHash([aDouble, aFloat, anInt32, anInt64, aUint32, aUint64, aSint32, aSint64, aFixed32, aFixed64, anSfixed32, anSfixed64, aBool, aString, someBytes, color, point, maybe, numbers, deltas, names, points, colors, counts, pointsById, _choiceCase, _label, _origin]);
+
+	Everything copyWith({
+		double? aDouble,
+		double? aFloat,
+		int? anInt32,
+		Int64? anInt64,
+		int? aUint32,
+		Int64? aUint64,
+		int? aSint32,
+		Int64? aSint64,
+		int? aFixed32,
+		Int64? aFixed64,
+		int? anSfixed32,
+		Int64? anSfixed64,
+		bool? aBool,
+		String? aString,
+		List<int>? someBytes,
+		Everything_Color? color,
+		Everything_Point? point,
+		int? maybe,
+		List<int>? numbers,
+		List<Int64>? deltas,
+		List<String>? names,
+		List<Everything_Point>? points,
+		List<Everything_Color>? colors,
+		Map<String, int>? counts,
+		Map<Int64, Everything_Point>? pointsById,
+		String? label,
+		Everything_Point? origin,
+	}) {
+		final copy = Everything(
+			aDouble: aDouble ?? this.aDouble,
+			aFloat: aFloat ?? this.aFloat,
+			anInt32: anInt32 ?? this.anInt32,
+			anInt64: anInt64 ?? this.anInt64,
+			aUint32: aUint32 ?? this.aUint32,
+			aUint64: aUint64 ?? this.aUint64,
+			aSint32: aSint32 ?? this.aSint32,
+			aSint64: aSint64 ?? this.aSint64,
+			aFixed32: aFixed32 ?? this.aFixed32,
+			aFixed64: aFixed64 ?? this.aFixed64,
+			anSfixed32: anSfixed32 ?? this.anSfixed32,
+			anSfixed64: anSfixed64 ?? this.anSfixed64,
+			aBool: aBool ?? this.aBool,
+			aString: aString ?? this.aString,
+			someBytes: someBytes ?? this.someBytes,
+			color: color ?? this.color,
+			point: point ?? this.point,
+			maybe: maybe ?? this.maybe,
+			numbers: numbers ?? this.numbers,
+			deltas: deltas ?? this.deltas,
+			names: names ?? this.names,
+			points: points ?? this.points,
+			colors: colors ?? this.colors,
+			counts: counts ?? this.counts,
+			pointsById: pointsById ?? this.pointsById,
+		);
+		copy
+			.._choiceCase = _choiceCase
+			.._label = _label
+			.._origin = _origin;
+		if (label != null) {
+			copy.label = label;
+		}
+		if (origin != null) {
+			copy.origin = origin;
+		}
+		return copy;
+	}
+
+	@override
+	String toString() =>
+			'Everything('
+			'aDouble: $aDouble, '
+			'aFloat: $aFloat, '
+			'anInt32: $anInt32, '
+			'anInt64: $anInt64, '
+			'aUint32: $aUint32, '
+			'aUint64: $aUint64, '
+			'aSint32: $aSint32, '
+			'aSint64: $aSint64, '
+			'aFixed32: $aFixed32, '
+			'aFixed64: $aFixed64, '
+			'anSfixed32: $anSfixed32, '
+			'anSfixed64: $anSfixed64, '
+			'aBool: $aBool, '
+			'aString: $aString, '
+			'someBytes: $someBytes, '
+			'color: $color, '
+			'point: $point, '
+			'maybe: $maybe, '
+			'numbers: $numbers, '
+			'deltas: $deltas, '
+			'names: $names, '
+			'points: $points, '
+			'colors: $colors, '
+			'counts: $counts, '
+			'pointsById: $pointsById, '
+			'label: $label, '
+			'origin: $origin'
+			')';
+}
+
+enum Everything_Choice {
+	label,
+	origin,
+	notSet
+}
+
+class Everything_Color extends ProtobufEnum {
+	static const Everything_Color colorUnspecified = Everything_Color._(0, "COLOR_UNSPECIFIED");
+	static const Everything_Color red = Everything_Color._(1, "RED");
+	static const Everything_Color green = Everything_Color._(2, "GREEN");
+
+	static const List<Everything_Color> values = [
+		colorUnspecified,
+		red,
+		green
+	];
+
+	static final Map<int, Everything_Color> _byPosition = ProtobufEnum.initByPosition(values);
+	static final Map<String, Everything_Color> _byName = ProtobufEnum.initByName(values);
+
+	static Everything_Color? valueOf(int position) => _byPosition[position];
+	static Everything_Color valueOfOrUnknown(int position) => _byPosition[position] ?? Everything_Color._(position, '$position');
+	static Everything_Color? valueOfName(String name) => _byName[name];
+
+	const Everything_Color._(int position, String name) : super(position, name);
+}
+
+class Everything_Point extends ProtobufMessage {
+	int x;
+	int y;
+
+	Everything_Point({
+		this.x = 0,
+		this.y = 0,
+	});
+
+	static Everything_Point fromBuffer(List<int> bytes) => Everything_Point()..mergeFromBuffer(bytes);
+
+	@override
+	void writeToWriter(ProtobufWriter writer) {
+		if (x != 0) {
+			writer..writeTag(8)..writeSint32(x);
+		}
+		if (y != 0) {
+			writer..writeTag(16)..writeSint32(y);
+		}
+	}
+
+	@override
+	void mergeFromReader(ProtobufReader reader) {
+		while (!reader.isAtEnd) {
+			final tag = reader.readTag();
+			switch (tag) {
+				case 8:
+					x = reader.readSint32();
+					break;
+				case 16:
+					y = reader.readSint32();
+					break;
+				default:
+					reader.skipField(tag);
+			}
+		}
+	}
+
+	@override
+	bool operator ==(Object other) =>
+			other is Everything_Point &&
+			protobufEquals(x, other.x) &&
+			protobufEquals(y, other.y);
+
+	@override
+	int get hashCode => protobufHash([x, y]);
+
+	Everything_Point copyWith({
+		int? x,
+		int? y,
+	}) {
+		return Everything_Point(
+			x: x ?? this.x,
+			y: y ?? this.y,
+		);
+	}
+
+	@override
+	String toString() =>
+			'Everything_Point('
+			'x: $x, '
+			'y: $y'
+			')';
+}
@@ -0,0 +1,463 @@
+// The runtime library for Dart code generated by rs-proto. Generated messages use it to read and write the protobuf
+// binary wire format. It's written to compile both with and without null safety, so it serves either dialect of the
+// generated code.
+
+import 'dart:convert';
+import 'dart:typed_data';
+
+import 'package:fixnum/fixnum.dart';
+
+// Wire types, the low three bits of a field's tag.
+const int _wireVarint = 0;
+const int _wireFixed64 = 1;
+const int _wireLengthDelimited = 2;
+const int _wireStartGroup = 3;
+const int _wireEndGroup = 4;
+const int _wireFixed32 = 5;
+
+/// The base class of generated messages.
+abstract class ProtobufMessage {
+  /// Writes the message's fields to [writer] in field number order.
+  void writeToWriter(ProtobufWriter writer);
+
+  /// Reads fields from [reader] into the message until it runs out of input.
+  ///
+  /// Singular fields are replaced, repeated and map fields are added to and message fields are merged, as
+  /// protobuf's merging rules require.
+  void mergeFromReader(ProtobufReader reader);
+
+  /// Encodes the message in the protobuf binary wire format.
+  Uint8List writeToBuffer() {
+    final writer = ProtobufWriter();
+    writeToWriter(writer);
+    return writer.toBuffer();
+  }
+
+  /// Decodes [bytes] in the protobuf binary wire format into the message.
+  void mergeFromBuffer(List<int> bytes) {
+    mergeFromReader(ProtobufReader(bytes));
+  }
+}
+
+/// The base class of generated enums.
+///
+/// Each value the enum declares is a const instance. Numbers it doesn't declare, like those read from a newer version
+/// of the schema, get an instance of their own so they survive being decoded and encoded again; compare [position]
+/// rather than the instances themselves when that matters.
+abstract class ProtobufEnum {
+  /// The value's number.
+  final int position;
+
+  /// The value's name in the .proto file, or its number for values the enum doesn't declare.
+  final String name;
+
+  const ProtobufEnum(this.position, this.name);
+
+  /// Indexes [values] by number for `valueOf`. The first of several aliases for a number wins.
+  static Map<int, T> initByPosition<T extends ProtobufEnum>(List<T> values) {
+    final byPosition = <int, T>{};
+    for (final value in values) {
+      byPosition.putIfAbsent(value.position, () => value);
+    }
+    return byPosition;
+  }
+
+  /// Indexes [values] by name for `valueOfName`.
+  static Map<String, T> initByName<T extends ProtobufEnum>(List<T> values) {
+    return {for (final value in values) value.name: value};
+  }
+
+  @override
+  String toString() => name;
+}
+
+/// Compares the values of two fields as generated messages' `==` does: lists and maps by their contents and enums by
+/// their numbers.
+bool protobufEquals(dynamic a, dynamic b) {
+  if (a is List && b is List) {
+    if (a.length != b.length) {
+      return false;
+    }
+    for (var i = 0; i < a.length; i++) {
+      if (!protobufEquals(a[i], b[i])) {
+        return false;
+      }
+    }
+    return true;
+  }
+  if (a is Map && b is Map) {
+    if (a.length != b.length) {
+      return false;
+    }
+    for (final key in a.keys) {
+      if (!b.containsKey(key) || !protobufEquals(a[key], b[key])) {
+        return false;
+      }
+    }
+    return true;
+  }
+  if (a is ProtobufEnum && b is ProtobufEnum) {
+    return a.runtimeType == b.runtimeType && a.position == b.position;
+  }
+  return a == b;
+}
+
+/// Hashes the values of a message's fields consistently with [protobufEquals].
+int protobufHash(List<dynamic> values) {
+  var hash = 0;
+  for (final value in values) {
+    hash = _combineHash(hash, _hashValue(value));
+  }
+  return _finishHash(hash);
+}
+
+int _hashValue(dynamic value) {
+  if (value is List) {
+    return protobufHash(value);
+  }
+  if (value is Map) {
+    // Maps are unordered, so their entries' hashes are combined in a way that doesn't depend on order.
+    var hash = 0;
+    value.forEach((key, value) {
+      hash = (hash + _combineHash(_hashValue(key), _hashValue(value))) % 0x20000000;
+    });
+    return _finishHash(hash);
+  }
+  if (value is ProtobufEnum) {
+    return value.position.hashCode;
+  }
+  return value.hashCode;
+}
+
+// Jenkins' one-at-a-time hash, kept to 29 bits so it stays a small integer when compiled to JavaScript.
+int _combineHash(int hash, int value) {
+  hash = 0x1fffffff & (hash + value);
+  hash = 0x1fffffff & (hash + ((0x0007ffff & hash) << 10));
+  return hash ^ (hash >> 6);
+}
+
+int _finishHash(int hash) {
+  hash = 0x1fffffff & (hash + ((0x03ffffff & hash) << 3));
+  hash = hash ^ (hash >> 11);
+  return 0x1fffffff & (hash + ((0x00003fff & hash) << 15));
+}
+
+/// Thrown when the input to a [ProtobufReader] isn't valid protobuf.
+class ProtobufException implements Exception {
+  final String message;
+
+  ProtobufException(this.message);
+
+  @override
+  String toString() => 'ProtobufException: $message';
+}
+
+/// Writes values in the protobuf binary wire format.
+///
+/// Fields are written as a tag followed by the field's value, e.g. `writer..writeTag(8)..writeInt32(150)`.
+class ProtobufWriter {
+  final List<int> _bytes = <int>[];
+
+  Uint8List toBuffer() => Uint8List.fromList(_bytes);
+
+  /// Writes a field's tag, which is `fieldNumber << 3 | wireType`.
+  void writeTag(int tag) => _writeVarint32(tag);
+
+  /// Negative values are sign extended to 64 bits, so they always take ten bytes.
+  void writeInt32(int value) {
+    if (value >= 0) {
+      _writeVarint32(value);
+    } else {
+      _writeVarint64(Int64(value));
+    }
+  }
+
+  void writeInt64(Int64 value) => _writeVarint64(value);
+
+  void writeUint32(int value) => _writeVarint32(value);
+
+  void writeUint64(Int64 value) => _writeVarint64(value);
+
+  /// ZigZag encodes the value so small negative numbers stay small.
+  void writeSint32(int value) => _writeVarint32(value >= 0 ? value * 2 : -value * 2 - 1);
+
+  void writeSint64(Int64 value) => _writeVarint64((value << 1) ^ (value >> 63));
+
+  void writeFixed32(int value) => _writeLittleEndian32(value);
+
+  void writeFixed64(Int64 value) => _bytes.addAll(value.toBytes());
+
+  void writeSfixed32(int value) => _writeLittleEndian32(value);
+
+  void writeSfixed64(Int64 value) => _bytes.addAll(value.toBytes());
+
+  void writeFloat(double value) {
+    final data = ByteData(4)..setFloat32(0, value, Endian.little);
+    _bytes.addAll(data.buffer.asUint8List());
+  }
+
+  void writeDouble(double value) {
+    final data = ByteData(8)..setFloat64(0, value, Endian.little);
+    _bytes.addAll(data.buffer.asUint8List());
+  }
+
+  void writeBool(bool value) => _bytes.add(value ? 1 : 0);
+
+  void writeEnum(ProtobufEnum value) => writeInt32(value.position);
+
+  void writeString(String value) => writeBytes(utf8.encode(value));
+
+  void writeBytes(List<int> value) {
+    _writeVarint32(value.length);
+    _bytes.addAll(value);
+  }
+
+  void writeMessage(ProtobufMessage value) => writeLengthDelimited(value.writeToWriter);
+
+  /// Writes [values] as a single length-delimited run, e.g. for a packed repeated field.
+  void writePacked<T>(List<T> values, void Function(ProtobufWriter writer, T value) writeValue) {
+    writeLengthDelimited((writer) {
+      for (final value in values) {
+        writeValue(writer, value);
+      }
+    });
+  }
+
+  /// Writes whatever [writeContents] writes, prefixed with its length.
+  void writeLengthDelimited(void Function(ProtobufWriter writer) writeContents) {
+    final contents = ProtobufWriter();
+    writeContents(contents);
+    writeBytes(contents._bytes);
+  }
+
+  // Arithmetic rather than bitwise operators, which only work on 32 bits when compiled to JavaScript.
+  void _writeVarint32(int value) {
+    while (value >= 0x80) {
+      _bytes.add(value % 0x80 + 0x80);
+      value = value ~/ 0x80;
+    }
+    _bytes.add(value);
+  }
+
+  void _writeVarint64(Int64 value) {
+    while (true) {
+      final low = (value & 0x7f).toInt();
+      value = value.shiftRightUnsigned(7);
+      if (value.isZero) {
+        _bytes.add(low);
+        return;
+      }
+      _bytes.add(low + 0x80);
+    }
+  }
+
+  void _writeLittleEndian32(int value) {
+    if (value < 0) {
+      value += 0x100000000;
+    }
+    for (var i = 0; i < 4; i++) {
+      _bytes.add(value % 0x100);
+      value = value ~/ 0x100;
+    }
+  }
+}
+
+/// Reads values in the protobuf binary wire format.
+class ProtobufReader {
+  final List<int> _bytes;
+  int _position;
+  final int _end;
+
+  ProtobufReader(List<int> bytes) : this._(bytes, 0, bytes.length);
+
+  ProtobufReader._(this._bytes, this._position, this._end);
+
+  bool get isAtEnd => _position >= _end;
+
+  /// Reads a field's tag, which is `fieldNumber << 3 | wireType`.
+  int readTag() {
+    final tag = _readVarint32();
+    if (tag ~/ 8 == 0) {
+      throw ProtobufException('Invalid field number 0');
+    }
+    return tag;
+  }
+
+  int readInt32() => _toSigned32(_readVarint32());
+
+  Int64 readInt64() => _readVarint64();
+
+  int readUint32() => _readVarint32();
+
+  Int64 readUint64() => _readVarint64();
+
+  int readSint32() {
+    final value = _readVarint32();
+    return value % 2 == 0 ? value ~/ 2 : -(value + 1) ~/ 2;
+  }
+
+  Int64 readSint64() {
+    final value = _readVarint64();
+    return value.shiftRightUnsigned(1) ^ -(value & 1);
+  }
+
+  int readFixed32() => _readLittleEndian32();
+
+  Int64 readFixed64() => Int64.fromBytes(_read(8));
+
+  int readSfixed32() => _toSigned32(_readLittleEndian32());
+
+  Int64 readSfixed64() => Int64.fromBytes(_read(8));
+
+  double readFloat() => ByteData.view(Uint8List.fromList(_read(4)).buffer).getFloat32(0, Endian.little);
+
+  double readDouble() => ByteData.view(Uint8List.fromList(_read(8)).buffer).getFloat64(0, Endian.little);
+
+  bool readBool() => !_readVarint64().isZero;
+
+  /// Reads an enum's number and looks it up with [valueOf], which has to handle numbers the enum doesn't know.
+  T readEnum<T extends ProtobufEnum>(T Function(int position) valueOf) => valueOf(readInt32());
+
+  String readString() => utf8.decode(readBytes());
+
+  List<int> readBytes() => _read(_readLength());
+
+  /// Merges a length-delimited message into [message] and returns it.
+  T readMessage<T extends ProtobufMessage>(T message) {
+    readLengthDelimited(message.mergeFromReader);
+    return message;
+  }
+
+  /// Reads a packed run of values, calling [readValue] until the run is used up.
+  void readPacked(void Function(ProtobufReader reader) readValue) {
+    readLengthDelimited((reader) {
+      while (!reader.isAtEnd) {
+        readValue(reader);
+      }
+    });
+  }
+
+  /// Calls [readContents] with a reader limited to a length-delimited value.
+  void readLengthDelimited(void Function(ProtobufReader reader) readContents) {
+    final end = _position + _readLength();
+    readContents(ProtobufReader._(_bytes, _position, end));
+    _position = end;
+  }
+
+  /// Reads a map entry into [map]. Entries are messages with the key as field 1 and the value as field 2, either of
+  /// which can be missing.
+  void readMapEntry<K, V>(Map<K, V> map, int keyTag, K Function(ProtobufReader reader) readKey, K defaultKey,
+      int valueTag, V Function(ProtobufReader reader) readValue, V Function() defaultValue) {
+    var key = defaultKey;
+    var value = defaultValue();
+    readLengthDelimited((entry) {
+      while (!entry.isAtEnd) {
+        final tag = entry.readTag();
+        if (tag == keyTag) {
+          key = readKey(entry);
+        } else if (tag == valueTag) {
+          value = readValue(entry);
+        } else {
+          entry.skipField(tag);
+        }
+      }
+    });
+    map[key] = value;
+  }
+
+  /// Skips the value of a field the message doesn't know about.
+  void skipField(int tag) {
+    switch (tag % 8) {
+      case _wireVarint:
+        _readVarint64();
+        break;
+      case _wireFixed64:
+        _read(8);
+        break;
+      case _wireLengthDelimited:
+        _read(_readLength());
+        break;
+      case _wireStartGroup:
+        _skipGroup(tag ~/ 8);
+        break;
+      case _wireFixed32:
+        _read(4);
+        break;
+      default:
+        throw ProtobufException('Invalid wire type ${tag % 8}');
+    }
+  }
+
+  void _skipGroup(int fieldNumber) {
+    while (true) {
+      final tag = readTag();
+      if (tag % 8 == _wireEndGroup) {
+        if (tag ~/ 8 != fieldNumber) {
+          throw ProtobufException('Mismatched end of group ${tag ~/ 8}');
+        }
+        return;
+      }
+      skipField(tag);
+    }
+  }
+
+  int _readLength() {
+    final length = _readVarint32();
+    if (length > _end - _position) {
+      throw ProtobufException('Truncated message');
+    }
+    return length;
+  }
+
+  // The low 32 bits of the varint, as an unsigned value.
+  int _readVarint32() {
+    var result = 0;
+    var multiplier = 1;
+    for (var i = 0; i < 10; i++) {
+      final byte = _readByte();
+      if (i < 5) {
+        result += (byte % 0x80) * multiplier;
+        multiplier *= 0x80;
+      }
+      if (byte < 0x80) {
+        return result % 0x100000000;
+      }
+    }
+    throw ProtobufException('Malformed varint');
+  }
+
+  Int64 _readVarint64() {
+    var result = Int64.ZERO;
+    for (var shift = 0; shift < 70; shift += 7) {
+      final byte = _readByte();
+      result |= Int64(byte % 0x80) << shift;
+      if (byte < 0x80) {
+        return result;
+      }
+    }
+    throw ProtobufException('Malformed varint');
+  }
+
+  int _readLittleEndian32() {
+    final bytes = _read(4);
+    return bytes[0] + bytes[1] * 0x100 + bytes[2] * 0x10000 + bytes[3] * 0x1000000;
+  }
+
+  int _readByte() {
+    if (_position >= _end) {
+      throw ProtobufException('Truncated message');
+    }
+    return _bytes[_position++];
+  }
+
+  List<int> _read(int length) {
+    if (length > _end - _position) {
+      throw ProtobufException('Truncated message');
+    }
+    final result = _bytes.sublist(_position, _position + length);
+    _position += length;
+    return result;
+  }
+
+  static int _toSigned32(int value) => value >= 0x80000000 ? value - 0x100000000 : value;
+}
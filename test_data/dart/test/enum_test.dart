@@ -0,0 +1,30 @@
+import 'package:rs_proto_fixtures/fixtures/wire.pb.dart';
+import 'package:test/test.dart';
+
+void main() {
+  test('looks values up by number and name', () {
+    expect(Everything_Color.values, [Everything_Color.colorUnspecified, Everything_Color.red, Everything_Color.green]);
+    expect(Everything_Color.valueOf(2), same(Everything_Color.green));
+    expect(Everything_Color.valueOf(3), isNull);
+    expect(Everything_Color.valueOfName('RED'), same(Everything_Color.red));
+    expect(Everything_Color.valueOfName('BLUE'), isNull);
+    expect(Everything_Color.valueOfOrUnknown(1), same(Everything_Color.red));
+  });
+
+  test('prints values by name', () {
+    expect(Everything_Color.green.toString(), 'GREEN');
+    expect('${Everything_Color.red}', 'RED');
+  });
+
+  test('keeps numbers from a newer version of the enum', () {
+    // color = 7 and colors = [1, 9], neither 7 nor 9 being a Color.
+    final bytes = [0x80, 0x01, 0x07, 0xc2, 0x01, 0x02, 0x01, 0x09];
+    final message = Everything.fromBuffer(bytes);
+
+    expect(message.color.position, 7);
+    expect(message.color.toString(), '7');
+    expect(message.colors.map((color) => color.position), [1, 9]);
+    expect(message.colors.first, same(Everything_Color.red));
+    expect(message.writeToBuffer(), bytes);
+  });
+}
@@ -0,0 +1,50 @@
+import 'package:rs_proto_fixtures/fixtures/wire.pb.dart';
+import 'package:test/test.dart';
+
+void main() {
+  test('tracks whether message fields and optional scalars are set', () {
+    final message = Everything();
+    expect(message.hasPoint(), isFalse);
+    expect(message.hasMaybe(), isFalse);
+
+    message
+      ..point = Everything_Point()
+      ..maybe = 0;
+    expect(message.hasPoint(), isTrue);
+    expect(message.hasMaybe(), isTrue);
+
+    message
+      ..clearPoint()
+      ..clearMaybe();
+    expect(message.point, isNull);
+    expect(message.maybe, isNull);
+    expect(message.writeToBuffer(), isEmpty);
+  });
+
+  test('reads fields with presence as set even when they hold their default value', () {
+    final message = Everything.fromBuffer([0x8a, 0x01, 0x00, 0x90, 0x01, 0x00]);
+
+    expect(message.hasPoint(), isTrue);
+    expect(message.point, Everything_Point());
+    expect(message.hasMaybe(), isTrue);
+    expect(message.maybe, 0);
+  });
+
+  test('tracks which oneof member is set', () {
+    final message = Everything(label: '');
+    expect(message.hasLabel(), isTrue);
+    expect(message.hasOrigin(), isFalse);
+
+    message.clearOrigin();
+    expect(message.hasLabel(), isTrue);
+
+    message.clearLabel();
+    expect(message.hasLabel(), isFalse);
+    expect(message.choiceCase, Everything_Choice.notSet);
+
+    message
+      ..origin = Everything_Point()
+      ..origin = null;
+    expect(message.choiceCase, Everything_Choice.notSet);
+  });
+}
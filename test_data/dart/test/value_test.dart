@@ -0,0 +1,67 @@
+import 'package:fixnum/fixnum.dart';
+import 'package:rs_proto_fixtures/fixtures/wire.pb.dart';
+import 'package:test/test.dart';
+
+void main() {
+  test('gives proto3 scalars their zero values', () {
+    final message = Everything();
+
+    expect(message.aDouble, 0.0);
+    expect(message.anInt32, 0);
+    expect(message.anInt64, Int64.ZERO);
+    expect(message.aBool, false);
+    expect(message.aString, '');
+    expect(message.someBytes, isEmpty);
+    expect(message.color, Everything_Color.colorUnspecified);
+    expect(message.point, isNull);
+    expect(message.maybe, isNull);
+    expect(message.numbers, isEmpty);
+    expect(message.counts, isEmpty);
+    expect(message.choiceCase, Everything_Choice.notSet);
+  });
+
+  test('takes fields as named parameters, copying collections', () {
+    final numbers = [1, 2];
+    final message = Everything(anInt32: 3, numbers: numbers, counts: {'a': 1}, origin: Everything_Point(x: 1));
+    numbers.add(3);
+
+    expect(message.anInt32, 3);
+    expect(message.numbers, [1, 2]);
+    expect(message.counts, {'a': 1});
+    expect(message.choiceCase, Everything_Choice.origin);
+    expect(message.origin?.x, 1);
+  });
+
+  test('compares and hashes by value', () {
+    Everything make() => Everything(
+        aString: 'a',
+        point: Everything_Point(y: 2),
+        numbers: [1, 2],
+        pointsById: {Int64(1): Everything_Point(x: 1)},
+        label: 'b');
+
+    expect(make(), make());
+    expect(make().hashCode, make().hashCode);
+    expect(make(), isNot(make()..numbers.add(3)));
+    expect(make(), isNot(make()..pointsById[Int64(1)]?.x = 2));
+    expect(make(), isNot(make()..origin = Everything_Point()));
+    expect(Everything(counts: {'a': 1, 'b': 2}).hashCode, Everything(counts: {'b': 2, 'a': 1}).hashCode);
+    expect(Everything()..maybe = 0, isNot(Everything()));
+  });
+
+  test('copies with some fields replaced', () {
+    final message = Everything(anInt32: 1, aString: 'a', label: 'b');
+
+    expect(message.copyWith(anInt32: 2), Everything(anInt32: 2, aString: 'a', label: 'b'));
+    expect(message.copyWith(origin: Everything_Point()).choiceCase, Everything_Choice.origin);
+    expect(message.copyWith(origin: Everything_Point()).label, isNull);
+    expect(message.copyWith(), message);
+    expect(identical(message.copyWith().numbers, message.numbers), isFalse);
+  });
+
+  test('prints its fields', () {
+    expect(Everything_Point(x: 1, y: -2).toString(), 'Everything_Point(x: 1, y: -2)');
+    expect('${Everything(color: Everything_Color.red, numbers: [1])}', contains('color: RED, '));
+    expect('${Everything(numbers: [1])}', contains('numbers: [1], '));
+  });
+}
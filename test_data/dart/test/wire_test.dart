@@ -0,0 +1,113 @@
+import 'dart:io';
+
+import 'package:fixnum/fixnum.dart';
+import 'package:rs_proto_fixtures/fixtures/wire.pb.dart';
+import 'package:rs_proto_fixtures/rs_proto.dart';
+import 'package:test/test.dart';
+
+List<int> fixture(String name) => File('test/fixtures/$name').readAsBytesSync();
+
+Everything_Point point(int x, int y) => Everything_Point()
+  ..x = x
+  ..y = y;
+
+// The message encoded in everything.bin.
+Everything everything() {
+  final message = Everything()
+    ..aDouble = 1.5
+    ..aFloat = -2.25
+    ..anInt32 = -1
+    ..anInt64 = Int64(1234567890123)
+    ..aUint32 = 4294967295
+    ..aUint64 = Int64(-1)
+    ..aSint32 = -3
+    ..aSint64 = Int64(-1234567890123)
+    ..aFixed32 = 4000000000
+    ..aFixed64 = Int64.parseInt('9007199254740993')
+    ..anSfixed32 = -5
+    ..anSfixed64 = Int64(-6)
+    ..aBool = true
+    ..aString = 'héllo ✓'
+    ..someBytes = [0, 1, 254, 255]
+    ..color = Everything_Color.green
+    ..point = point(-1, 2)
+    ..maybe = 0
+    ..label = 'pick';
+
+  message.numbers.addAll([1, -1, 300]);
+  message.deltas.addAll([Int64(-1), Int64(1)]);
+  message.names.addAll(['a', '']);
+  message.points.addAll([point(1, 0), Everything_Point()]);
+  message.colors.addAll([Everything_Color.red, Everything_Color.green]);
+  message.counts.addAll({'a': 1, 'b': 0});
+  message.pointsById[Int64(7)] = point(0, -2);
+
+  return message;
+}
+
+void main() {
+  test('writes every kind of field', () {
+    expect(everything().writeToBuffer(), fixture('everything.bin'));
+  });
+
+  test('reads every kind of field', () {
+    final message = Everything.fromBuffer(fixture('everything.bin'));
+
+    expect(message.aDouble, 1.5);
+    expect(message.aFloat, -2.25);
+    expect(message.anInt32, -1);
+    expect(message.anInt64, Int64(1234567890123));
+    expect(message.aUint32, 4294967295);
+    expect(message.aUint64, Int64(-1));
+    expect(message.aSint32, -3);
+    expect(message.aSint64, Int64(-1234567890123));
+    expect(message.aFixed32, 4000000000);
+    expect(message.aFixed64, Int64.parseInt('9007199254740993'));
+    expect(message.anSfixed32, -5);
+    expect(message.anSfixed64, Int64(-6));
+    expect(message.aBool, true);
+    expect(message.aString, 'héllo ✓');
+    expect(message.someBytes, [0, 1, 254, 255]);
+    expect(message.color, Everything_Color.green);
+    expect([message.point?.x, message.point?.y], [-1, 2]);
+    expect(message.maybe, 0);
+    expect(message.numbers, [1, -1, 300]);
+    expect(message.deltas, [Int64(-1), Int64(1)]);
+    expect(message.names, ['a', '']);
+    expect(message.points, [point(1, 0), Everything_Point()]);
+    expect(message.colors, [Everything_Color.red, Everything_Color.green]);
+    expect(message.counts, {'a': 1, 'b': 0});
+    expect(message.pointsById.keys, [Int64(7)]);
+    expect(message.pointsById[Int64(7)]?.y, -2);
+    expect(message.choiceCase, Everything_Choice.label);
+    expect(message.label, 'pick');
+
+    expect(message, everything());
+    expect(message.writeToBuffer(), fixture('everything.bin'));
+  });
+
+  test('leaves out fields that hold their default value unless they track presence', () {
+    expect(Everything().writeToBuffer(), isEmpty);
+    expect((Everything()..maybe = 0).writeToBuffer(), [0x90, 0x01, 0x00]);
+
+    // -0.0 == 0, but it isn't the default value.
+    expect((Everything()..aDouble = -0.0).writeToBuffer(), [0x09, 0, 0, 0, 0, 0, 0, 0, 0x80]);
+    expect((Everything()..aFloat = -0.0).writeToBuffer(), [0x15, 0, 0, 0, 0x80]);
+  });
+
+  test('accepts either repeated encoding, merges messages and skips unknown fields', () {
+    final message = Everything.fromBuffer(fixture('lenient.bin'));
+
+    expect(message.anInt32, 2);
+    expect(message.numbers, [1, 2]);
+    expect(message.deltas, [Int64(-1), Int64(1)]);
+    expect([message.point?.x, message.point?.y], [1, 2]);
+    expect(message.choiceCase, Everything_Choice.origin);
+    expect([message.origin?.x, message.origin?.y], [3, 4]);
+  });
+
+  test('rejects truncated input', () {
+    expect(() => Everything.fromBuffer([0x72, 0x05, 0x61]), throwsA(isA<ProtobufException>()));
+    expect(() => Everything.fromBuffer([0x18, 0x80]), throwsA(isA<ProtobufException>()));
+  });
+}
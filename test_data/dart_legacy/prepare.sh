@@ -0,0 +1,9 @@
+#!/bin/sh
+# Copies the runtime and the tests, which are written to work with and without null safety, from ../dart so that
+# `dart test` runs them against lib/fixtures/wire.pb.dart. Run it again after changing either.
+set -e
+
+cd "$(dirname "$0")"
+cp ../../src/code_gen/dart/runtime.dart lib/rs_proto.dart
+rm -rf test
+cp -R ../dart/test test